@@ -0,0 +1,361 @@
+//! Macros for implementing the [Acteur](https://docs.rs/acteur) traits without writing the
+//! `#[async_trait]` impl blocks by hand.
+//!
+//! This crate is not meant to be used directly. Enable the `derive` feature of acteur and use the
+//! macros from there:
+//!
+//! ```toml
+//! [dependencies]
+//! acteur = { version = "*", features = ["derive"] }
+//! ```
+//!
+//! The macros only generate implementations of the `Actor`, `Receive` and `Respond` traits, so
+//! anything generated can be written by hand too.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Error, FnArg, ImplItem, ImplItemFn, ItemImpl,
+    Member, Pat, ReturnType, Type,
+};
+
+/// Implements `Actor` for a struct that implements `Default`.
+///
+/// The field marked with `#[actor(id)]` is the Id of the actor. The generated `activate` creates
+/// the actor with `Default::default()` and sets the Id in that field. If no field is marked, the
+/// Id is `()`, so there is only one actor of that type.
+///
+/// ```rust,ignore
+/// use acteur::Actor;
+///
+/// #[derive(Debug, Default, Actor)]
+/// struct Employee {
+///     #[actor(id)]
+///     id: u32,
+///     salary: u32,
+/// }
+/// ```
+///
+/// For custom activations, deactivations or configurations, implement `Actor` by hand instead.
+#[proc_macro_derive(Actor, attributes(actor))]
+pub fn derive_actor(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand_actor(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Turns the methods marked with `#[receive]` or `#[respond]` of an `impl` block into
+/// implementations of `Receive` and `Respond`.
+///
+/// The marked methods must be `async` and take `&mut self`, the message and, optionally, the
+/// `&ActorAssistant<Self>`. The type of the message is the type of the second argument and, for
+/// `#[respond]`, the response is the return type. Methods without a mark stay in the `impl`
+/// block as they are.
+///
+/// ```rust,ignore
+/// use acteur::{handlers, ActorAssistant};
+///
+/// #[handlers]
+/// impl Employee {
+///     #[receive]
+///     async fn salary_changed(&mut self, message: SalaryChanged) {
+///         self.salary = message.0;
+///     }
+///
+///     #[respond]
+///     async fn get_salary(&mut self, _: GetSalary, assistant: &ActorAssistant<Self>) -> u32 {
+///         self.salary
+///     }
+/// }
+/// ```
+///
+/// is the same as:
+///
+/// ```rust,ignore
+/// #[async_trait]
+/// impl Receive<SalaryChanged> for Employee {
+///     async fn handle(&mut self, message: SalaryChanged, _: &ActorAssistant<Self>) {
+///         self.salary = message.0;
+///     }
+/// }
+///
+/// #[async_trait]
+/// impl Respond<GetSalary> for Employee {
+///     type Response = u32;
+///
+///     async fn handle(&mut self, _: GetSalary, assistant: &ActorAssistant<Self>) -> u32 {
+///         self.salary
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn handlers(arguments: TokenStream, input: TokenStream) -> TokenStream {
+    let arguments = TokenStream2::from(arguments);
+
+    if !arguments.is_empty() {
+        return Error::new(arguments.span(), "#[handlers] doesn't take arguments")
+            .into_compile_error()
+            .into();
+    }
+
+    let input = parse_macro_input!(input as ItemImpl);
+
+    expand_handlers(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_actor(input: DeriveInput) -> Result<TokenStream2, Error> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(Error::new(
+                input.ident.span(),
+                "#[derive(Actor)] is only supported for structs",
+            ))
+        }
+    };
+
+    let mut id_field = None;
+
+    for (index, field) in fields.iter().enumerate() {
+        for attribute in field.attrs.iter().filter(|a| a.path().is_ident("actor")) {
+            attribute.parse_nested_meta(|meta| {
+                if !meta.path.is_ident("id") {
+                    return Err(meta.error("expected #[actor(id)]"));
+                }
+
+                if id_field.is_some() {
+                    return Err(meta.error("only one field can be the id of the actor"));
+                }
+
+                let member = match &field.ident {
+                    Some(ident) => Member::Named(ident.clone()),
+                    None => Member::Unnamed(index.into()),
+                };
+
+                id_field = Some((member, field.ty.clone()));
+
+                Ok(())
+            })?;
+        }
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let (id_type, activation) = match id_field {
+        Some((member, id_type)) => (
+            quote!(#id_type),
+            quote! {
+                Self {
+                    #member: id,
+                    ..::core::default::Default::default()
+                }
+            },
+        ),
+        None => (quote!(()), quote!(::core::default::Default::default())),
+    };
+
+    Ok(quote! {
+        #[::acteur::__private::async_trait]
+        impl #impl_generics ::acteur::Actor for #name #ty_generics #where_clause {
+            type Id = #id_type;
+
+            #[allow(unused_variables)]
+            async fn activate(id: Self::Id, _: &::acteur::ActorAssistant<Self>) -> Self {
+                #activation
+            }
+        }
+    })
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum HandlerKind {
+    Receive,
+    Respond,
+}
+
+fn expand_handlers(mut input: ItemImpl) -> Result<TokenStream2, Error> {
+    if let Some((_, path, _)) = &input.trait_ {
+        return Err(Error::new(
+            path.span(),
+            "#[handlers] must be used in an impl block without trait",
+        ));
+    }
+
+    let mut handlers = Vec::new();
+    let mut items = Vec::new();
+
+    for item in std::mem::take(&mut input.items) {
+        match item {
+            ImplItem::Fn(mut method) => match take_handler_kind(&mut method.attrs)? {
+                Some(kind) => handlers.push(expand_handler(&input, kind, method)?),
+                None => items.push(ImplItem::Fn(method)),
+            },
+            item => items.push(item),
+        }
+    }
+
+    input.items = items;
+
+    // The block is kept only if something else than handlers was there.
+    let rest = if input.items.is_empty() {
+        None
+    } else {
+        Some(&input)
+    };
+
+    Ok(quote! {
+        #rest
+        #(#handlers)*
+    })
+}
+
+// Removes the #[receive] or #[respond] attribute of the method, if any.
+fn take_handler_kind(attributes: &mut Vec<Attribute>) -> Result<Option<HandlerKind>, Error> {
+    let mut kind = None;
+    let mut error = None;
+
+    attributes.retain(|attribute| {
+        let found = if attribute.path().is_ident("receive") {
+            HandlerKind::Receive
+        } else if attribute.path().is_ident("respond") {
+            HandlerKind::Respond
+        } else {
+            return true;
+        };
+
+        if kind.is_some() {
+            error = Some(Error::new(
+                attribute.span(),
+                "a method can only be marked once with #[receive] or #[respond]",
+            ));
+        } else if let Err(arguments_error) = attribute.meta.require_path_only() {
+            error = Some(arguments_error);
+        }
+
+        kind = Some(found);
+
+        false
+    });
+
+    match error {
+        Some(error) => Err(error),
+        None => Ok(kind),
+    }
+}
+
+fn expand_handler(
+    input: &ItemImpl,
+    kind: HandlerKind,
+    method: ImplItemFn,
+) -> Result<TokenStream2, Error> {
+    let signature = &method.sig;
+
+    if signature.asyncness.is_none() {
+        return Err(Error::new(
+            signature.fn_token.span(),
+            "handlers must be async methods",
+        ));
+    }
+
+    if !signature.generics.params.is_empty() {
+        return Err(Error::new(
+            signature.generics.span(),
+            "handlers cannot have generic parameters, make them generic in the impl block",
+        ));
+    }
+
+    let mut arguments = signature.inputs.iter();
+
+    match arguments.next() {
+        Some(FnArg::Receiver(receiver))
+            if receiver.reference.is_some() && receiver.mutability.is_some() => {}
+        _ => {
+            return Err(Error::new(
+                signature.ident.span(),
+                "the first argument of a handler must be `&mut self`",
+            ))
+        }
+    }
+
+    let (message_pattern, message_type) = match arguments.next() {
+        Some(FnArg::Typed(argument)) => (&argument.pat, &argument.ty),
+        _ => {
+            return Err(Error::new(
+                signature.ident.span(),
+                "the second argument of a handler must be the message",
+            ))
+        }
+    };
+
+    let (assistant_pattern, assistant_type): (Box<Pat>, Box<Type>) = match arguments.next() {
+        Some(FnArg::Typed(argument)) => (argument.pat.clone(), argument.ty.clone()),
+        Some(argument) => {
+            return Err(Error::new(
+                argument.span(),
+                "the third argument of a handler must be the assistant",
+            ))
+        }
+        None => (
+            Box::new(syn::parse_quote!(_)),
+            Box::new(syn::parse_quote!(&::acteur::ActorAssistant<Self>)),
+        ),
+    };
+
+    if let Some(argument) = arguments.next() {
+        return Err(Error::new(
+            argument.span(),
+            "handlers only take the message and the assistant",
+        ));
+    }
+
+    let response_type: Type = match (&signature.output, kind) {
+        (ReturnType::Default, _) => syn::parse_quote!(()),
+        (ReturnType::Type(_, response_type), HandlerKind::Respond) => (**response_type).clone(),
+        (ReturnType::Type(arrow, _), HandlerKind::Receive) => {
+            return Err(Error::new(
+                arrow.span(),
+                "#[receive] handlers cannot return anything, use #[respond] for responding",
+            ))
+        }
+    };
+
+    let self_type = &input.self_ty;
+    let (impl_generics, _, where_clause) = input.generics.split_for_impl();
+    let attributes = &method.attrs;
+    let body = &method.block;
+    let span = signature.ident.span();
+
+    Ok(match kind {
+        HandlerKind::Receive => quote_spanned! {span=>
+            #[::acteur::__private::async_trait]
+            impl #impl_generics ::acteur::Receive<#message_type> for #self_type #where_clause {
+                #(#attributes)*
+                async fn handle(
+                    &mut self,
+                    #message_pattern: #message_type,
+                    #assistant_pattern: #assistant_type,
+                ) #body
+            }
+        },
+        HandlerKind::Respond => quote_spanned! {span=>
+            #[::acteur::__private::async_trait]
+            impl #impl_generics ::acteur::Respond<#message_type> for #self_type #where_clause {
+                type Response = #response_type;
+
+                #(#attributes)*
+                async fn handle(
+                    &mut self,
+                    #message_pattern: #message_type,
+                    #assistant_pattern: #assistant_type,
+                ) -> #response_type #body
+            }
+        },
+    })
+}
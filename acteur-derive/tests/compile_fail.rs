@@ -0,0 +1,7 @@
+// The cases the macros refuse, with the errors they report. Regenerate the expected errors with
+// `TRYBUILD=overwrite cargo test -p acteur-derive --test compile_fail` after changing them.
+#[test]
+fn invalid_uses_of_the_macros_fail_to_compile() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
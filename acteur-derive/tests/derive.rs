@@ -0,0 +1,139 @@
+use acteur::{handlers, Acteur, Actor, ActorAssistant};
+use std::fmt::Debug;
+
+#[derive(Debug, Default, Actor)]
+struct Employee {
+    #[actor(id)]
+    id: u32,
+    salary: u32,
+}
+
+#[derive(Debug)]
+struct SalaryChanged(u32);
+
+#[derive(Debug)]
+struct GetSalary;
+
+#[derive(Debug)]
+struct GetId;
+
+#[derive(Debug)]
+struct Promote;
+
+#[handlers]
+impl Employee {
+    #[receive]
+    async fn salary_changed(&mut self, message: SalaryChanged) {
+        self.salary = message.0;
+    }
+
+    #[respond]
+    async fn get_salary(&mut self, _: GetSalary) -> u32 {
+        self.salary
+    }
+
+    #[respond]
+    async fn get_id(&mut self, _: GetId, assistant: &ActorAssistant<Self>) -> u32 {
+        *assistant.get_id().await
+    }
+
+    // The assistant can be used as in any handler. The next employee gets the raise.
+    #[receive]
+    async fn promote(&mut self, _: Promote, assistant: &ActorAssistant<Self>) {
+        let salary = self.raised_salary();
+
+        assistant
+            .send_to_actor::<Employee, SalaryChanged>(self.id + 1, SalaryChanged(salary))
+            .await;
+    }
+
+    // Methods without a mark stay in the impl block
+    fn raised_salary(&self) -> u32 {
+        self.salary + 100
+    }
+}
+
+// Without a field marked as id, there is only one actor of the type.
+#[derive(Debug, Default, Actor)]
+struct Office {
+    visits: u32,
+}
+
+#[derive(Debug)]
+struct Visit;
+
+#[handlers]
+impl Office {
+    #[respond]
+    async fn visit(&mut self, _: Visit) -> u32 {
+        self.visits += 1;
+        self.visits
+    }
+}
+
+// Tuple structs and generic actors work too.
+#[derive(Debug, Default, Actor)]
+struct Named<T: Default + Debug + Send + Sync + 'static>(#[actor(id)] String, T);
+
+#[derive(Debug)]
+struct GetName;
+
+#[handlers]
+impl<T: Default + Debug + Send + Sync + 'static> Named<T> {
+    #[respond]
+    async fn get_name(&mut self, _: GetName) -> String {
+        format!("{}: {:?}", self.0, self.1)
+    }
+}
+
+#[test]
+fn derived_actors_get_their_id_and_the_default_values() {
+    let sys = Acteur::builder().build().blocking();
+
+    assert_eq!(sys.call_actor::<Employee, GetId>(42, GetId), Ok(42));
+    assert_eq!(sys.call_actor::<Employee, GetSalary>(42, GetSalary), Ok(0));
+    assert_eq!(
+        sys.call_actor::<Named<u8>, GetName>("Ann".to_string(), GetName),
+        Ok("Ann: 0".to_string())
+    );
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn actors_without_id_are_unique() {
+    let sys = Acteur::builder().build().blocking();
+
+    assert_eq!(sys.call_actor::<Office, Visit>((), Visit), Ok(1));
+    assert_eq!(sys.call_actor::<Office, Visit>((), Visit), Ok(2));
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn marked_methods_handle_their_messages() {
+    let sys = Acteur::builder().build().blocking();
+
+    sys.send_to_actor::<Employee, SalaryChanged>(1, SalaryChanged(1000));
+    assert_eq!(
+        sys.call_actor::<Employee, GetSalary>(1, GetSalary),
+        Ok(1000)
+    );
+
+    // Once the first call responds, the message sent by the promotion is already enqueued
+    sys.send_to_actor::<Employee, Promote>(1, Promote);
+    assert_eq!(
+        sys.call_actor::<Employee, GetSalary>(1, GetSalary),
+        Ok(1000)
+    );
+    assert_eq!(
+        sys.call_actor::<Employee, GetSalary>(2, GetSalary),
+        Ok(1100)
+    );
+    assert_eq!(sys.call_actor::<Employee, GetSalary>(3, GetSalary), Ok(0));
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
@@ -0,0 +1,9 @@
+use acteur::Actor;
+
+#[derive(Debug, Actor)]
+enum Employee {
+    Hired,
+    Fired,
+}
+
+fn main() {}
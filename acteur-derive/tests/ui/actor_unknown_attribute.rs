@@ -0,0 +1,9 @@
+use acteur::Actor;
+
+#[derive(Debug, Default, Actor)]
+struct Employee {
+    #[actor(key)]
+    id: u32,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use acteur::Actor;
+
+#[derive(Debug, Default, Actor)]
+struct Employee {
+    #[actor(id)]
+    id: u32,
+    #[actor(id)]
+    code: u32,
+}
+
+fn main() {}
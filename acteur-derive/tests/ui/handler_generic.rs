@@ -0,0 +1,12 @@
+use acteur::{handlers, Actor};
+
+#[derive(Debug, Default, Actor)]
+struct Employee;
+
+#[handlers]
+impl Employee {
+    #[receive]
+    async fn hired<M: Send>(&mut self, _: M) {}
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+use acteur::{handlers, Actor};
+
+#[derive(Debug, Default, Actor)]
+struct Employee;
+
+#[derive(Debug)]
+struct Hired;
+
+#[handlers]
+impl Employee {
+    #[respond(u32)]
+    async fn hired(&mut self, _: Hired) -> u32 {
+        1
+    }
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use acteur::{handlers, Actor, ActorAssistant};
+
+#[derive(Debug, Default, Actor)]
+struct Employee;
+
+#[derive(Debug)]
+struct Hired;
+
+#[handlers]
+impl Employee {
+    #[receive]
+    async fn hired(&mut self, _: Hired, _: &ActorAssistant<Self>, salary: u32) {}
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use acteur::{handlers, Actor};
+
+#[derive(Debug, Default, Actor)]
+struct Employee;
+
+#[handlers]
+impl Employee {
+    #[receive]
+    async fn hired(&mut self) {}
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use acteur::{handlers, Actor};
+
+#[derive(Debug, Default, Actor)]
+struct Employee;
+
+#[handlers]
+impl Clone for Employee {
+    fn clone(&self) -> Self {
+        Employee
+    }
+}
+
+fn main() {}
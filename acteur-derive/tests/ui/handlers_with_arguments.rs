@@ -0,0 +1,15 @@
+use acteur::{handlers, Actor};
+
+#[derive(Debug, Default, Actor)]
+struct Employee;
+
+#[derive(Debug)]
+struct Hired;
+
+#[handlers(all)]
+impl Employee {
+    #[receive]
+    async fn hired(&mut self, _: Hired) {}
+}
+
+fn main() {}
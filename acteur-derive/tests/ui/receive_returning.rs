@@ -0,0 +1,17 @@
+use acteur::{handlers, Actor};
+
+#[derive(Debug, Default, Actor)]
+struct Employee;
+
+#[derive(Debug)]
+struct Hired;
+
+#[handlers]
+impl Employee {
+    #[receive]
+    async fn hired(&mut self, _: Hired) -> bool {
+        true
+    }
+}
+
+fn main() {}
@@ -10,7 +10,7 @@
 //
 // Until then, this is how to do a id-less Actor.
 
-use acteur::{Acteur, Actor, ActorAssistant, Receive};
+use acteur::{Actor, ActorAssistant, BlockingActeur, Receive};
 use async_trait::async_trait;
 
 #[derive(Debug)]
@@ -41,11 +41,11 @@ impl Receive<SalaryChanged> for Employee {
 }
 
 fn main() {
-    let sys = Acteur::new();
+    let sys = BlockingActeur::new();
 
     // As drawback, is that we need to write () each time.
     // That will be solved when services are implemented.
-    sys.send_to_actor_sync::<Employee, SalaryChanged>((), SalaryChanged(55000));
+    sys.send_to_actor::<Employee, SalaryChanged>((), SalaryChanged(55000));
 
     sys.wait_until_stopped();
 }
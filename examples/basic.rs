@@ -1,4 +1,4 @@
-use acteur::{Acteur, Actor, ActorAssistant, Receive};
+use acteur::{Actor, ActorAssistant, BlockingActeur, Receive};
 use async_trait::async_trait;
 
 #[derive(Debug)]
@@ -28,9 +28,9 @@ impl Receive<SalaryChanged> for Employee {
 }
 
 fn main() {
-    let sys = Acteur::new();
+    let sys = BlockingActeur::new();
 
-    sys.send_to_actor_sync::<Employee, SalaryChanged>(42, SalaryChanged(55000));
+    sys.send_to_actor::<Employee, SalaryChanged>(42, SalaryChanged(55000));
 
     sys.wait_until_stopped();
 }
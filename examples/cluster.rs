@@ -0,0 +1,104 @@
+// Three systems in the same process, connected as a cluster through localhost. In a real
+// deployment each node would run in its own process or machine, with a known address.
+//
+// Each account lives in only one of the nodes, but the messages sent to it from any node
+// reach that same instance. So the deposits made from different nodes add up.
+
+use acteur::{Acteur, Actor, ActorAssistant, BlockingActeur, Cluster, Receive, Respond};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug)]
+struct BankAccount {
+    balance: u64,
+}
+
+#[async_trait]
+impl Actor for BankAccount {
+    type Id = String;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        BankAccount { balance: 0 }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Deposit(u64);
+
+#[async_trait]
+impl Receive<Deposit> for BankAccount {
+    async fn handle(&mut self, message: Deposit, _: &ActorAssistant<BankAccount>) {
+        self.balance += message.0;
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GetBalance;
+
+#[async_trait]
+impl Respond<GetBalance> for BankAccount {
+    type Response = u64;
+
+    async fn handle(&mut self, _: GetBalance, _: &ActorAssistant<BankAccount>) -> u64 {
+        self.balance
+    }
+}
+
+fn main() -> std::io::Result<()> {
+    let names = ["a", "b", "c"];
+
+    let nodes = names
+        .iter()
+        .map(|name| Cluster::bind(name, "127.0.0.1:0"))
+        .collect::<std::io::Result<Vec<Cluster>>>()?;
+
+    let addresses: Vec<_> = nodes.iter().map(Cluster::local_addr).collect();
+
+    let systems: Vec<BlockingActeur> = nodes
+        .into_iter()
+        .map(|mut node| {
+            for (name, address) in names.iter().zip(&addresses) {
+                node = node.node(name, *address);
+            }
+
+            // Every node registers the same actors and messages, with the same names.
+            let node = node
+                .actor::<BankAccount>("bank_account")
+                .receive::<BankAccount, Deposit>("deposit")
+                .respond::<BankAccount, GetBalance>("get_balance");
+
+            Acteur::builder().cluster(node).build().blocking()
+        })
+        .collect();
+
+    for sys in &systems {
+        for account in &["alice", "bob", "carol"] {
+            sys.send_to_actor::<BankAccount, Deposit>(account.to_string(), Deposit(100));
+
+            // Messages sent from one node arrive in order, so once this call responds the deposit
+            // is done. Messages sent from different nodes have no order between them.
+            let _ = sys.call_actor::<BankAccount, GetBalance>(account.to_string(), GetBalance);
+        }
+    }
+
+    for account in &["alice", "bob", "carol"] {
+        for (name, sys) in names.iter().zip(&systems) {
+            let balance =
+                sys.call_actor::<BankAccount, GetBalance>(account.to_string(), GetBalance);
+            println!(
+                "Balance of {} seen from node {}: {:?}",
+                account, name, balance
+            );
+        }
+    }
+
+    for sys in &systems {
+        sys.stop();
+    }
+
+    for sys in &systems {
+        sys.wait_until_stopped();
+    }
+
+    Ok(())
+}
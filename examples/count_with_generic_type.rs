@@ -1,4 +1,4 @@
-use acteur::{Acteur, Actor, ActorAssistant, Receive};
+use acteur::{Actor, ActorAssistant, BlockingActeur, Receive};
 use async_trait::async_trait;
 use std::collections::HashMap;
 use std::fmt::Debug;
@@ -13,12 +13,12 @@ pub fn start() {
     let start = SystemTime::now();
     println!("Time to start: {:?}", start);
 
-    let sys = Acteur::new();
+    let sys = BlockingActeur::new();
 
     for i in 0..1u32 {
         let message = TestMessage { field: i };
 
-        sys.send_to_actor_sync::<TestActor<u32>, TestMessage>(43, message);
+        sys.send_to_actor::<TestActor<u32>, TestMessage>(43, message);
     }
 
     sys.wait_until_stopped();
@@ -52,7 +52,7 @@ impl<T: 'static + Send + Sync + Eq + Clone + Hash + Debug + Default> Actor for T
     }
 
     async fn deactivate(&mut self) {
-        println!("I'm dead! {:?}", self.id);
+        println!("I'm dead! {:?} ({} values)", self.id, self.values.len());
     }
 }
 
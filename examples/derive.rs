@@ -0,0 +1,62 @@
+use acteur::{handlers, Actor, ActorAssistant, BlockingActeur};
+
+// The Actor implementation is generated. The actor is created with `Default::default()`
+// and the field marked as id receives the id of the actor.
+#[derive(Debug, Default, Actor)]
+struct Employee {
+    #[actor(id)]
+    id: u32,
+    salary: u32,
+}
+
+#[derive(Debug)]
+struct SalaryChanged(u32);
+
+#[derive(Debug)]
+struct GetSalary;
+
+// Each marked method becomes an implementation of Receive or Respond for its message.
+#[handlers]
+impl Employee {
+    #[receive]
+    async fn salary_changed(&mut self, message: SalaryChanged) {
+        self.salary = message.0;
+    }
+
+    #[respond]
+    async fn get_salary(&mut self, _: GetSalary, assistant: &ActorAssistant<Self>) -> String {
+        // The assistant is optional, as in `salary_changed`
+        assistant
+            .send_to_actor::<Employee, SalaryChanged>(self.id + 1, SalaryChanged(self.salary))
+            .await;
+
+        if self.is_well_paid() {
+            format!("Employee {} is well paid", self.id)
+        } else {
+            format!("Employee {} earns {}", self.id, self.salary)
+        }
+    }
+
+    // Methods without a mark stay as they are
+    fn is_well_paid(&self) -> bool {
+        self.salary > 50000
+    }
+}
+
+fn main() {
+    let sys = BlockingActeur::new();
+
+    sys.send_to_actor::<Employee, SalaryChanged>(42, SalaryChanged(55000));
+
+    let response = sys.call_actor::<Employee, GetSalary>(42, GetSalary);
+
+    println!("Response is: {:?}", response); // Response is: Ok("Employee 42 is well paid")
+
+    let response = sys.call_actor::<Employee, GetSalary>(43, GetSalary);
+
+    println!("Response is: {:?}", response); // Response is: Ok("Employee 43 is well paid")
+
+    sys.stop();
+
+    sys.wait_until_stopped();
+}
@@ -0,0 +1,128 @@
+use acteur::{
+    Acteur, Actor, ActorAssistant, FileJournal, FileSnapshotStore, PersistentActor, Receive,
+    Respond, SnapshotActor, SnapshotPolicy,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ShoppingCart {
+    items: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum ShoppingCartEvent {
+    ItemAdded(String),
+    ItemRemoved(String),
+}
+
+#[async_trait]
+impl Actor for ShoppingCart {
+    type Id = u32;
+
+    async fn activate(id: Self::Id, assistant: &ActorAssistant<Self>) -> Self {
+        // The state is rebuilt from the last snapshot and the events stored after it in the journal
+        let cart = assistant
+            .recover_from_snapshot(ShoppingCart { items: Vec::new() })
+            .await
+            .expect("Cannot recover the shopping cart");
+
+        println!(
+            "Cart {} recovered with {} events: {:?}",
+            id,
+            assistant.last_sequence_nr(),
+            cart.items
+        );
+
+        cart
+    }
+}
+
+impl PersistentActor for ShoppingCart {
+    type Event = ShoppingCartEvent;
+
+    fn apply(&mut self, event: &ShoppingCartEvent) {
+        match event {
+            ShoppingCartEvent::ItemAdded(item) => self.items.push(item.clone()),
+            ShoppingCartEvent::ItemRemoved(item) => self.items.retain(|other| other != item),
+        }
+    }
+
+    fn persistence_id(id: &u32) -> String {
+        format!("shopping-cart-{}", id)
+    }
+}
+
+impl SnapshotActor for ShoppingCart {
+    fn snapshot_policy() -> SnapshotPolicy {
+        SnapshotPolicy::EveryMessagesAndOnDeactivate(100)
+    }
+}
+
+#[derive(Debug)]
+struct AddItem(String);
+
+#[async_trait]
+impl Receive<AddItem> for ShoppingCart {
+    async fn handle(&mut self, message: AddItem, assistant: &ActorAssistant<Self>) {
+        if let Err(error) = assistant
+            .persist(self, ShoppingCartEvent::ItemAdded(message.0))
+            .await
+        {
+            println!("The item couldn't be added: {}", error);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RemoveItem(String);
+
+#[async_trait]
+impl Receive<RemoveItem> for ShoppingCart {
+    async fn handle(&mut self, message: RemoveItem, assistant: &ActorAssistant<Self>) {
+        if let Err(error) = assistant
+            .persist(self, ShoppingCartEvent::ItemRemoved(message.0))
+            .await
+        {
+            println!("The item couldn't be removed: {}", error);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct GetItems;
+
+#[async_trait]
+impl Respond<GetItems> for ShoppingCart {
+    type Response = Vec<String>;
+
+    async fn handle(&mut self, _: GetItems, _: &ActorAssistant<Self>) -> Vec<String> {
+        self.items.clone()
+    }
+}
+
+fn main() {
+    let directory = std::env::temp_dir().join("acteur_persistent_actor_example");
+
+    // Run the example several times and the cart will keep growing
+    let sys = Acteur::builder()
+        .journal(FileJournal::new(directory.join("journal")).expect("Cannot create the journal"))
+        .snapshot_store(
+            FileSnapshotStore::new(directory.join("snapshots"))
+                .expect("Cannot create the snapshot store"),
+        )
+        .build()
+        .blocking();
+
+    sys.send_to_actor::<ShoppingCart, AddItem>(1, AddItem("Apples".to_string()));
+    sys.send_to_actor::<ShoppingCart, AddItem>(1, AddItem("Pears".to_string()));
+    sys.send_to_actor::<ShoppingCart, RemoveItem>(1, RemoveItem("Apples".to_string()));
+
+    let items = sys.call_actor::<ShoppingCart, GetItems>(1, GetItems);
+
+    println!("Items in the cart: {:?}", items);
+    println!("Journal and snapshots stored in {:?}", directory);
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
@@ -1,4 +1,4 @@
-use acteur::{Acteur, Listen, Service, ServiceAssistant, ServiceConfiguration};
+use acteur::{BlockingActeur, Listen, Service, ServiceAssistant, ServiceConfiguration};
 
 #[derive(Debug)]
 struct EmployeeTaxesCalculator {
@@ -31,16 +31,17 @@ impl Listen<EmployeeSalaryChange> for EmployeeTaxesCalculator {
         println!("Message received! {:?}", message);
 
         // Calculate the tax rate and send it to however you want
+        println!("Taxes to pay: {:?}", message.0 * self.tax_rate);
     }
 }
 
 fn main() {
-    let mut sys = Acteur::new();
+    let mut sys = BlockingActeur::new();
 
-    sys.preload_service_sync::<EmployeeTaxesCalculator>();
+    sys.preload_service::<EmployeeTaxesCalculator>();
 
     // You just need to send the message, whoever is subscribed will received.
-    sys.publish_sync(EmployeeSalaryChange(55000.0));
+    sys.publish(EmployeeSalaryChange(55000.0));
 
     sys.stop();
 
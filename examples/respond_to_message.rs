@@ -1,4 +1,4 @@
-use acteur::{Acteur, Actor, ActorAssistant, Receive, Respond};
+use acteur::{Actor, ActorAssistant, BlockingActeur, Receive, Respond};
 use async_trait::async_trait;
 
 #[derive(Debug)]
@@ -57,9 +57,9 @@ impl Receive<SalaryChanged> for Employee {
 }
 
 fn main() {
-    let sys = Acteur::new();
+    let sys = BlockingActeur::new();
 
-    let response = sys.call_actor_sync::<Employee, SalaryChanged>(42, SalaryChanged(55000));
+    let response = sys.call_actor::<Employee, SalaryChanged>(42, SalaryChanged(55000));
 
     println!("Response is: {:?}", response); // Response is: Thanks!
 
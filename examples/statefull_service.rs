@@ -1,4 +1,4 @@
-use acteur::{Acteur, Listen, Service, ServiceAssistant, ServiceConfiguration};
+use acteur::{BlockingActeur, Listen, Service, ServiceAssistant, ServiceConfiguration};
 use async_std::sync::Mutex;
 
 #[derive(Debug)]
@@ -34,8 +34,8 @@ impl Listen<EmployeeHired> for EmployeeExpensesCalculator {
     }
 }
 fn main() {
-    let sys = Acteur::new();
-    sys.send_to_service_sync::<EmployeeExpensesCalculator, _>(EmployeeHired(55000.0));
+    let sys = BlockingActeur::new();
+    sys.send_to_service::<EmployeeExpensesCalculator, _>(EmployeeHired(55000.0));
     sys.stop();
     sys.wait_until_stopped();
 }
@@ -1,4 +1,4 @@
-use acteur::{Acteur, Serve, Service, ServiceAssistant, ServiceConfiguration};
+use acteur::{BlockingActeur, Serve, Service, ServiceAssistant, ServiceConfiguration};
 
 #[derive(Debug)]
 struct EmployeeTaxesCalculator {
@@ -26,10 +26,10 @@ impl Serve<EmployeeSalaryChange> for EmployeeTaxesCalculator {
 }
 
 fn main() {
-    let sys = Acteur::new();
+    let sys = BlockingActeur::new();
 
     let taxes = sys
-        .call_service_sync::<EmployeeTaxesCalculator, _>(EmployeeSalaryChange(55000.0))
+        .call_service::<EmployeeTaxesCalculator, _>(EmployeeSalaryChange(55000.0))
         .unwrap();
 
     println!("Employee taxes are: {:?}", taxes);
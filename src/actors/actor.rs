@@ -1,7 +1,9 @@
 use crate::actors::assistant::ActorAssistant;
+use crate::supervision::SupervisionStrategy;
 use async_trait::async_trait;
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::time::Duration;
 
 /// The main Trait from this crate.
 ///
@@ -55,7 +57,167 @@ pub trait Actor: Sized + Debug + Send + Sync + 'static {
     async fn activate(id: Self::Id, assistant: &ActorAssistant<Self>) -> Self;
 
     /// This method will be called when the framework decided to unload the actor.
-    /// The concrete algorithms to decide that can change in the future.
-    /// As for now, this method is never called and actors are never unloaded.
+    /// When that happens depends on the [Passivation](./enum.Passivation.html) policy
+    /// returned by the `configuration` method, or when the actor or the system are stopped.
+    /// All the enqueued messages are processed before calling this method.
     async fn deactivate(&mut self) {}
+
+    /// Allows to configure how the framework manages this actor type. It is called once
+    /// per actor type and system.
+    ///
+    /// ```rust,no_run
+    /// use acteur::{Actor, ActorAssistant, ActorConfiguration, Passivation};
+    /// use async_trait::async_trait;
+    ///
+    /// #[derive(Debug)]
+    /// struct Country {
+    ///     id: String,
+    /// }
+    ///
+    /// #[async_trait]
+    /// impl Actor for Country {
+    ///     type Id = String;
+    ///
+    ///     async fn activate(id: Self::Id, _: &ActorAssistant<Self>) -> Self {
+    ///         Country { id }
+    ///     }
+    ///
+    ///     // There are not many countries, we can keep them in Ram forever.
+    ///     fn configuration() -> ActorConfiguration {
+    ///         ActorConfiguration {
+    ///             passivation: Passivation::Never,
+    ///             ..ActorConfiguration::default()
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    fn configuration() -> ActorConfiguration {
+        ActorConfiguration::default()
+    }
+
+    /// Estimates the memory used by the actor, in bytes, for the memory budget set with
+    /// `ActeurBuilder::actors_memory_budget`. It is called after activating the actor and after
+    /// each message it handles, only if there is a budget.
+    ///
+    /// By default, it is the size of the struct (`std::mem::size_of`), which doesn't count the
+    /// memory in the heap (Vec, String, HashMap, etc). Actors keeping data there should override it.
+    ///
+    /// ```rust,no_run
+    /// use acteur::{Actor, ActorAssistant};
+    /// use async_trait::async_trait;
+    ///
+    /// #[derive(Debug)]
+    /// struct Inbox {
+    ///     emails: Vec<String>,
+    /// }
+    ///
+    /// #[async_trait]
+    /// impl Actor for Inbox {
+    ///     type Id = u64;
+    ///
+    ///     async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+    ///         Inbox { emails: vec![] }
+    ///     }
+    ///
+    ///     fn estimated_memory(&self) -> usize {
+    ///         std::mem::size_of::<Self>()
+    ///             + self.emails.iter().map(|email| email.capacity()).sum::<usize>()
+    ///     }
+    /// }
+    /// ```
+    fn estimated_memory(&self) -> usize {
+        std::mem::size_of::<Self>()
+    }
+}
+
+/// Defines when an actor instance is deallocated (passivated). When that happens, the method
+/// `deactivate` is called and the actor is removed from Ram. The next message for that actor will
+/// activate it again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Passivation {
+    /// Uses the system default, which is deallocating the actor after some time without
+    /// receiving messages. The default time can be configured with the `ActeurBuilder`.
+    SystemDefault,
+    /// Deallocates the actor after the given time without receiving messages.
+    AfterInactivity(Duration),
+    /// Deallocates the actor after handling the given number of messages.
+    /// The actor won't be deallocated because of inactivity. `AfterMessages(0)` behaves as
+    /// `AfterMessages(1)`.
+    AfterMessages(usize),
+    /// The actor is never deallocated. It will only be stopped if you stop it
+    /// or when the system stops.
+    Never,
+}
+
+/// Defines what happens when a message is sent to an actor whose mailbox is full.
+///
+/// ```rust,no_run
+/// use acteur::{Actor, ActorAssistant, ActorConfiguration, MailboxOverflow};
+/// use async_trait::async_trait;
+///
+/// #[derive(Debug)]
+/// struct Sensor;
+///
+/// #[async_trait]
+/// impl Actor for Sensor {
+///     type Id = u32;
+///
+///     async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+///         Sensor
+///     }
+///
+///     // Only the latest readings matter, old ones can be discarded.
+///     fn configuration() -> ActorConfiguration {
+///         ActorConfiguration {
+///             mailbox_capacity: Some(100),
+///             mailbox_overflow: MailboxOverflow::DropOldest,
+///             ..ActorConfiguration::default()
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MailboxOverflow {
+    /// The sender waits until there is room in the mailbox (backpressure). Only that sender waits:
+    /// messages for other actors, even of the same type, are delivered meanwhile. An actor sending
+    /// messages to itself with a full mailbox waits forever, as it cannot take them meanwhile.
+    Await,
+    /// The new message is discarded.
+    DropNewest,
+    /// The oldest enqueued message is discarded in order to make room for the new one.
+    DropOldest,
+    /// The new message is discarded and the sender gets an error. Callers of `call_actor` get
+    /// `CallError::MailboxFull`, callers of `try_send_to_actor` get `TrySendError::MailboxFull`
+    /// and callers of `send_to_actor_checked` get `SendError::MailboxFull`.
+    Reject,
+}
+
+/// Defines the actor configuration: the passivation policy, what to do when a handler panics and
+/// the mailbox size.
+///
+/// This struct implements the Default trait. You can call it with `ActorConfiguration::default()` and
+/// it should work well for the most of the cases.
+#[derive(Debug, Clone)]
+pub struct ActorConfiguration {
+    pub passivation: Passivation,
+    pub supervision: SupervisionStrategy,
+    /// Maximum number of times an actor instance is restarted because of its failures while it
+    /// is loaded. By default, None (unlimited).
+    pub max_restarts: Option<usize>,
+    /// Maximum number of messages enqueued for each actor instance. By default, None (unlimited).
+    pub mailbox_capacity: Option<usize>,
+    /// What to do when the mailbox is full. By default, `MailboxOverflow::Await`.
+    pub mailbox_overflow: MailboxOverflow,
+}
+
+impl Default for ActorConfiguration {
+    fn default() -> ActorConfiguration {
+        ActorConfiguration {
+            passivation: Passivation::SystemDefault,
+            supervision: SupervisionStrategy::Restart,
+            max_restarts: None,
+            mailbox_capacity: None,
+            mailbox_overflow: MailboxOverflow::Await,
+        }
+    }
 }
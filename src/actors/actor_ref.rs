@@ -0,0 +1,180 @@
+use crate::actors::director::ActorsDirector;
+use crate::actors::manager::ManagerSender;
+use crate::error::{CallError, SendError, TrySendError};
+use crate::{Actor, Receive, Respond};
+use std::fmt::Debug;
+use std::time::Duration;
+
+/// A reference to the actor with an Id, obtained with [Acteur::actor_ref](./struct.Acteur.html#method.actor_ref).
+///
+/// Sending through a reference is the same as sending with `Acteur::send_to_actor` or
+/// `Acteur::call_actor`, but it skips looking for the actor type in the system for each message.
+/// References are cheap to clone, so they can be kept in the state of other actors, services or
+/// web handlers. They are valid until the system stops, even if the actor deactivates meanwhile:
+/// as usual, the next message activates it again.
+///
+/// Its methods are async. For sync code, [BlockingActeur::actor_ref](./struct.BlockingActeur.html#method.actor_ref)
+/// returns a [BlockingActorRef](./struct.BlockingActorRef.html) instead.
+///
+/// ```rust,no_run
+/// use acteur::{Acteur, Actor, ActorAssistant, Receive, Respond};
+/// use async_trait::async_trait;
+///
+/// #[derive(Debug)]
+/// struct Counter(u32);
+///
+/// #[async_trait]
+/// impl Actor for Counter {
+///     type Id = u32;
+///
+///     async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+///         Counter(0)
+///     }
+/// }
+///
+/// #[derive(Debug)]
+/// struct Increment;
+///
+/// #[async_trait]
+/// impl Receive<Increment> for Counter {
+///     async fn handle(&mut self, _: Increment, _: &ActorAssistant<Counter>) {
+///         self.0 += 1;
+///     }
+/// }
+///
+/// #[derive(Debug)]
+/// struct Get;
+///
+/// #[async_trait]
+/// impl Respond<Get> for Counter {
+///     type Response = u32;
+///
+///     async fn handle(&mut self, _: Get, _: &ActorAssistant<Counter>) -> u32 {
+///         self.0
+///     }
+/// }
+///
+/// # async fn example() {
+/// let sys = Acteur::new();
+///
+/// let counter = sys.actor_ref::<Counter>(42).await;
+///
+/// counter.send(Increment).await;
+/// counter.send(Increment).await;
+///
+/// assert_eq!(counter.call(Get).await, Ok(2));
+///
+/// sys.stop();
+/// sys.wait_until_stopped().await;
+/// # }
+/// ```
+pub struct ActorRef<A: Actor> {
+    actor_id: A::Id,
+    manager: ManagerSender<A>,
+    actors_director: ActorsDirector,
+}
+
+impl<A: Actor> ActorRef<A> {
+    pub(crate) fn new(
+        actor_id: A::Id,
+        manager: ManagerSender<A>,
+        actors_director: ActorsDirector,
+    ) -> ActorRef<A> {
+        ActorRef {
+            actor_id,
+            manager,
+            actors_director,
+        }
+    }
+
+    /// Returns the Id of the referenced actor.
+    pub fn id(&self) -> &A::Id {
+        &self.actor_id
+    }
+
+    /// Sends a message to the actor. Same as `Acteur::send_to_actor`.
+    pub async fn send<M: Debug + Send + 'static>(&self, message: M)
+    where
+        A: Receive<M>,
+    {
+        self.actors_director
+            .send_through::<A, M>(&self.manager, self.actor_id.clone(), message)
+            .await
+    }
+
+    /// Same as `send` but it reports if the message couldn't be enqueued. Same as
+    /// `Acteur::send_to_actor_checked`.
+    pub async fn send_checked<M: Debug + Send + 'static>(&self, message: M) -> Result<(), SendError>
+    where
+        A: Receive<M>,
+    {
+        self.actors_director
+            .send_checked_through::<A, M>(&self.manager, self.actor_id.clone(), message)
+            .await
+    }
+
+    /// Same as `send` but it never waits for room in the actor mailbox. Same as
+    /// `Acteur::try_send_to_actor`.
+    pub async fn try_send<M: Debug + Send + 'static>(&self, message: M) -> Result<(), TrySendError>
+    where
+        A: Receive<M>,
+    {
+        self.actors_director
+            .try_send_through::<A, M>(&self.manager, self.actor_id.clone(), message)
+            .await
+    }
+
+    /// Sends a message to the actor and waits for its response. Same as `Acteur::call_actor`.
+    pub async fn call<M: Debug + Send + 'static>(
+        &self,
+        message: M,
+    ) -> Result<<A as Respond<M>>::Response, CallError>
+    where
+        A: Respond<M>,
+    {
+        self.actors_director
+            .call_through::<A, M>(&self.manager, self.actor_id.clone(), message)
+            .await
+    }
+
+    /// Same as `call` but it returns `CallError::Timeout` if the response doesn't arrive on time.
+    /// Same as `Acteur::call_actor_with_timeout`.
+    pub async fn call_with_timeout<M: Debug + Send + 'static>(
+        &self,
+        message: M,
+        timeout: Duration,
+    ) -> Result<<A as Respond<M>>::Response, CallError>
+    where
+        A: Respond<M>,
+    {
+        self.actors_director
+            .call_with_timeout_through::<A, M>(
+                &self.manager,
+                self.actor_id.clone(),
+                message,
+                timeout,
+            )
+            .await
+    }
+}
+
+impl<A: Actor> Clone for ActorRef<A> {
+    fn clone(&self) -> Self {
+        ActorRef {
+            actor_id: self.actor_id.clone(),
+            manager: self.manager.clone(),
+            actors_director: self.actors_director.clone(),
+        }
+    }
+}
+
+impl<A: Actor> Debug for ActorRef<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ActorRef for {} ({:?})",
+            std::any::type_name::<A>(),
+            self.actor_id
+        )
+    }
+}
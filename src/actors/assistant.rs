@@ -1,10 +1,25 @@
 use crate::actors::director::ActorsDirector;
+#[cfg(feature = "persistence")]
+use crate::error::JournalError;
+use crate::error::{CallError, SendError, TrySendError};
+#[cfg(feature = "persistence")]
+use crate::persistence::{
+    journal::JournalEntry,
+    persistent_actor::{PersistentActor, SnapshotActor},
+    snapshot::Snapshot,
+    state::PersistenceState,
+};
+use crate::scheduler::ScheduleHandle;
 use crate::services::handle::{Listen, Serve};
 use crate::services::service::Service;
 use crate::system_director::SystemDirector;
+#[cfg(feature = "testkit")]
+use crate::testkit::outbox::Outbox;
 use crate::{Actor, Receive, Respond};
-use async_std::task;
+use std::any::TypeId;
+use std::collections::HashSet;
 use std::fmt::Debug;
+use std::sync::{Arc, Mutex, MutexGuard};
 
 /// This object is provided to the handle method in [Receive](./trait.Receive.html) and [Respond](./trait.Respond.html)
 /// traits for each message that an Actor receives.
@@ -12,7 +27,7 @@ use std::fmt::Debug;
 /// The Actor's assistant allows to send messages and to execute some task over the system.
 ///
 /// ```rust,no_run
-/// # use acteur::{Actor, Receive, ActorAssistant, Acteur};
+/// # use acteur::{Actor, Receive, ActorAssistant, BlockingActeur};
 /// # use async_trait::async_trait;
 /// #
 /// # #[derive(Debug)]
@@ -66,9 +81,9 @@ use std::fmt::Debug;
 /// }
 ///
 /// # fn main() {
-/// #     let sys = Acteur::new();
+/// #     let sys = BlockingActeur::new();
 /// #
-/// #     sys.send_to_actor_sync::<Employee, SalaryChanged>(42, SalaryChanged(55000));
+/// #     sys.send_to_actor::<Employee, SalaryChanged>(42, SalaryChanged(55000));
 /// #
 /// #     sys.wait_until_stopped();
 /// # }
@@ -77,8 +92,14 @@ use std::fmt::Debug;
 ///
 pub struct ActorAssistant<A: Actor> {
     system_director: SystemDirector,
+    #[cfg(feature = "testkit")]
+    outbox: Outbox,
     actors_director: ActorsDirector,
     actor_id: A::Id,
+    // The message types this instance subscribed to, so the deactivation only clears those
+    subscriptions: Arc<Mutex<HashSet<TypeId>>>,
+    #[cfg(feature = "persistence")]
+    persistence: Arc<PersistenceState<A>>,
 }
 
 impl<A: Actor> ActorAssistant<A> {
@@ -88,12 +109,35 @@ impl<A: Actor> ActorAssistant<A> {
         actor_id: A::Id,
     ) -> ActorAssistant<A> {
         ActorAssistant {
+            #[cfg(feature = "testkit")]
+            outbox: Outbox::System(system_director.clone()),
             system_director,
             actors_director,
             actor_id,
+            subscriptions: Arc::new(Mutex::new(HashSet::new())),
+            #[cfg(feature = "persistence")]
+            persistence: Arc::new(PersistenceState::new()),
         }
     }
 
+    // The `MockSystem` captures the outgoing messages instead of sending them
+    #[cfg(feature = "testkit")]
+    pub(crate) fn with_outbox(mut self, outbox: Outbox) -> ActorAssistant<A> {
+        self.outbox = outbox;
+        self
+    }
+
+    // Without the `testkit` feature, the messages go straight to the system
+    #[cfg(not(feature = "testkit"))]
+    fn outbox(&self) -> &SystemDirector {
+        &self.system_director
+    }
+
+    #[cfg(feature = "testkit")]
+    fn outbox(&self) -> &Outbox {
+        &self.outbox
+    }
+
     /// Sends a message to the Actor with the specified Id.
     /// If the Actor is not loaded, it will load the actor before, calling its method `activate`
     pub async fn send_to_actor<A2: Actor + Receive<M>, M: Debug + Send + 'static>(
@@ -101,7 +145,33 @@ impl<A: Actor> ActorAssistant<A> {
         actor_id: A2::Id,
         message: M,
     ) {
-        self.actors_director.send::<A2, M>(actor_id, message).await
+        self.outbox()
+            .send_to_actor::<A2, M>(actor_id, message)
+            .await
+    }
+
+    /// Same as `send_to_actor` but it returns a [SendError](./enum.SendError.html) if the message
+    /// couldn't be enqueued. It doesn't activate actors while the system is stopping.
+    pub async fn send_to_actor_checked<A2: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A2::Id,
+        message: M,
+    ) -> Result<(), SendError> {
+        self.outbox()
+            .send_to_actor_checked::<A2, M>(actor_id, message)
+            .await
+    }
+
+    /// Same as `send_to_actor` but it never waits for room in the actor mailbox. If the
+    /// mailbox is full, it returns `TrySendError::MailboxFull`.
+    pub async fn try_send_to_actor<A2: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A2::Id,
+        message: M,
+    ) -> Result<(), TrySendError> {
+        self.outbox()
+            .try_send_to_actor::<A2, M>(actor_id, message)
+            .await
     }
 
     /// Sends a message to all actors independently of the ID.
@@ -110,7 +180,7 @@ impl<A: Actor> ActorAssistant<A> {
         &self,
         message: M,
     ) {
-        self.actors_director.send_to_all::<A2, M>(message).await
+        self.outbox().send_to_all_actors::<A2, M>(message).await
     }
 
     /// Schedules to sends a message to the Actor with the specified Id.
@@ -120,10 +190,24 @@ impl<A: Actor> ActorAssistant<A> {
         actor_id: A2::Id,
         duration: std::time::Duration,
         message: M,
-    ) {
-        self.system_director
+    ) -> ScheduleHandle {
+        self.outbox()
             .schedule_send_to_actor::<A2, M>(actor_id, duration, message)
-            .await
+    }
+
+    /// Sends a copy of the message to the Actor with the specified Id every time the interval
+    /// passes, until the returned handle is cancelled.
+    pub async fn schedule_interval_to_actor<
+        A2: Actor + Receive<M>,
+        M: Debug + Send + Clone + 'static,
+    >(
+        &self,
+        actor_id: A2::Id,
+        interval: std::time::Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        self.outbox()
+            .schedule_interval_to_actor::<A2, M>(actor_id, interval, message)
     }
 
     /// Schedules to sends a message to all actors independently of the ID.
@@ -132,10 +216,23 @@ impl<A: Actor> ActorAssistant<A> {
         &self,
         duration: std::time::Duration,
         message: M,
-    ) {
-        self.system_director
+    ) -> ScheduleHandle {
+        self.outbox()
             .schedule_send_to_all_actors::<A2, M>(duration, message)
-            .await
+    }
+
+    /// Sends a copy of the message to all actors of a type every time the interval passes,
+    /// until the returned handle is cancelled.
+    pub async fn schedule_interval_to_all_actors<
+        A2: Actor + Receive<M>,
+        M: Debug + Send + Clone + 'static,
+    >(
+        &self,
+        interval: std::time::Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        self.outbox()
+            .schedule_interval_to_all_actors::<A2, M>(interval, message)
     }
 
     /// Sends a message to the Actor with the specified Id and waits the actor's response .
@@ -144,8 +241,21 @@ impl<A: Actor> ActorAssistant<A> {
         &self,
         actor_id: A2::Id,
         message: M,
-    ) -> Result<<A2 as Respond<M>>::Response, &str> {
-        self.actors_director.call::<A2, M>(actor_id, message).await
+    ) -> Result<<A2 as Respond<M>>::Response, CallError> {
+        self.outbox().call_actor::<A2, M>(actor_id, message).await
+    }
+
+    /// Same as `call_actor` but it returns `CallError::Timeout` if the response doesn't arrive on time.
+    /// If the actor didn't start handling the message yet, the message is skipped.
+    pub async fn call_actor_with_timeout<A2: Actor + Respond<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A2::Id,
+        message: M,
+        timeout: std::time::Duration,
+    ) -> Result<<A2 as Respond<M>>::Response, CallError> {
+        self.outbox()
+            .call_actor_with_timeout::<A2, M>(actor_id, message, timeout)
+            .await
     }
 
     /// Sends a message to a Service.
@@ -154,7 +264,41 @@ impl<A: Actor> ActorAssistant<A> {
         &self,
         message: M,
     ) {
-        self.system_director.send_to_service::<S, M>(message).await
+        self.outbox().send_to_service::<S, M>(message).await
+    }
+
+    /// Same as `send_to_service` but it returns a [SendError](./enum.SendError.html) if the
+    /// message couldn't be enqueued. It doesn't initialize services while the system is stopping.
+    pub async fn send_to_service_checked<S: Service + Listen<M>, M: Debug + Send + 'static>(
+        &self,
+        message: M,
+    ) -> Result<(), SendError> {
+        self.outbox().send_to_service_checked::<S, M>(message).await
+    }
+
+    /// Schedules to send a message to a Service.
+    /// If the Service is not loaded, it will load the service before, calling its method `initialize`
+    pub async fn schedule_send_to_service<S: Service + Listen<M>, M: Debug + Send + 'static>(
+        &self,
+        duration: std::time::Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        self.outbox()
+            .schedule_send_to_service::<S, M>(duration, message)
+    }
+
+    /// Sends a copy of the message to a Service every time the interval passes, until the
+    /// returned handle is cancelled.
+    pub async fn schedule_interval_to_service<
+        S: Service + Listen<M>,
+        M: Debug + Send + Clone + 'static,
+    >(
+        &self,
+        interval: std::time::Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        self.outbox()
+            .schedule_interval_to_service::<S, M>(interval, message)
     }
 
     /// Sends a message to a Service and waits for its response.
@@ -162,8 +306,146 @@ impl<A: Actor> ActorAssistant<A> {
     pub async fn call_service<S: Service + Serve<M>, M: Debug + Send + 'static>(
         &self,
         message: M,
-    ) -> Result<<S as Serve<M>>::Response, &str> {
-        self.system_director.call_service::<S, M>(message).await
+    ) -> Result<<S as Serve<M>>::Response, CallError> {
+        self.outbox().call_service::<S, M>(message).await
+    }
+
+    /// Same as `call_service` but it returns `CallError::Timeout` if the response doesn't arrive on time.
+    /// If the service didn't start handling the message yet, the message is skipped.
+    pub async fn call_service_with_timeout<S: Service + Serve<M>, M: Debug + Send + 'static>(
+        &self,
+        message: M,
+        timeout: std::time::Duration,
+    ) -> Result<<S as Serve<M>>::Response, CallError> {
+        self.outbox()
+            .call_service_with_timeout::<S, M>(message, timeout)
+            .await
+    }
+
+    /// Publishes a message for the Services and Actors subscribed to its type. See
+    /// [Acteur::publish](./struct.Acteur.html#method.publish).
+    pub async fn publish<M: Send + Clone + 'static>(&self, message: M) {
+        self.outbox().publish(None, message).await
+    }
+
+    /// Same as `publish` but the message is published in a topic. See
+    /// [Acteur::publish_to_topic](./struct.Acteur.html#method.publish_to_topic).
+    pub async fn publish_to_topic<M: Send + Clone + 'static>(&self, topic: &str, message: M) {
+        self.outbox().publish(Some(topic), message).await
+    }
+
+    /// Subscribes this actor instance to a message type. Every message of that type published with
+    /// `publish` or `publish_to_topic` will be sent to this actor, as `send_to_actor` does.
+    /// Subscribing twice has no effect.
+    ///
+    /// The subscription lasts until the actor deactivates, so it is usually done in the `activate`
+    /// method. Publishing a message never activates an actor by itself.
+    pub async fn subscribe<M: Sync + Send + Debug + 'static>(&self)
+    where
+        A: Receive<M>,
+    {
+        self.lock_subscriptions().insert(TypeId::of::<M>());
+        self.system_director
+            .subscribe_actor::<A, M>(self.actor_id.clone(), None);
+    }
+
+    /// Same as `subscribe` but the actor only receives the messages published with
+    /// `publish_to_topic` in a topic matching the pattern. Topics are words separated by dots,
+    /// like `orders.eu.created`. In the patterns, `*` matches exactly one word and `#` matches
+    /// zero or more words, so `orders.*.created` and `orders.#` match that topic.
+    pub async fn subscribe_to_topic<M: Sync + Send + Debug + 'static>(&self, topic_pattern: &str)
+    where
+        A: Receive<M>,
+    {
+        self.lock_subscriptions().insert(TypeId::of::<M>());
+        self.system_director
+            .subscribe_actor::<A, M>(self.actor_id.clone(), Some(topic_pattern));
+    }
+
+    /// Removes the subscription made with `subscribe`.
+    pub async fn unsubscribe<M: Sync + Send + Debug + 'static>(&self)
+    where
+        A: Receive<M>,
+    {
+        self.system_director
+            .unsubscribe_actor::<A, M>(self.actor_id.clone(), None);
+    }
+
+    /// Removes the subscription made with `subscribe_to_topic` with the same pattern.
+    pub async fn unsubscribe_from_topic<M: Sync + Send + Debug + 'static>(
+        &self,
+        topic_pattern: &str,
+    ) where
+        A: Receive<M>,
+    {
+        self.system_director
+            .unsubscribe_actor::<A, M>(self.actor_id.clone(), Some(topic_pattern));
+    }
+
+    /// Subscribes all the actors of a type to a message type. Every message of that type published
+    /// with `publish` will be sent to all the actors of that type already loaded in Ram, each of
+    /// them receiving a copy. Subscribing twice has no effect.
+    ///
+    /// Unlike `subscribe`, this subscription lasts until it is removed or the system stops.
+    pub async fn subscribe_all_actors<
+        A2: Actor + Receive<M>,
+        M: Sync + Send + Clone + Debug + 'static,
+    >(
+        &self,
+    ) {
+        self.system_director.subscribe_all_actors::<A2, M>(None);
+    }
+
+    /// Same as `subscribe_all_actors` but only for the messages published in a topic matching the
+    /// pattern. See `subscribe_to_topic`.
+    pub async fn subscribe_all_actors_to_topic<
+        A2: Actor + Receive<M>,
+        M: Sync + Send + Clone + Debug + 'static,
+    >(
+        &self,
+        topic_pattern: &str,
+    ) {
+        self.system_director
+            .subscribe_all_actors::<A2, M>(Some(topic_pattern));
+    }
+
+    /// Removes the subscription made with `subscribe_all_actors`.
+    pub async fn unsubscribe_all_actors<
+        A2: Actor + Receive<M>,
+        M: Sync + Send + Clone + Debug + 'static,
+    >(
+        &self,
+    ) {
+        self.system_director.unsubscribe_all_actors::<A2, M>(None);
+    }
+
+    /// Removes the subscription made with `subscribe_all_actors_to_topic` with the same pattern.
+    pub async fn unsubscribe_all_actors_from_topic<
+        A2: Actor + Receive<M>,
+        M: Sync + Send + Clone + Debug + 'static,
+    >(
+        &self,
+        topic_pattern: &str,
+    ) {
+        self.system_director
+            .unsubscribe_all_actors::<A2, M>(Some(topic_pattern));
+    }
+
+    /// Removes all the subscriptions of this actor instance. Called when the actor deactivates.
+    pub(crate) fn clear_subscriptions(&self) {
+        let message_types = std::mem::take(&mut *self.lock_subscriptions());
+
+        if !message_types.is_empty() {
+            self.system_director
+                .clear_actor_subscriptions::<A>(&self.actor_id, &message_types);
+        }
+    }
+
+    fn lock_subscriptions(&self) -> MutexGuard<'_, HashSet<TypeId>> {
+        match self.subscriptions.lock() {
+            Ok(subscriptions) => subscriptions,
+            Err(poisoned) => poisoned.into_inner(),
+        }
     }
 
     /// Enqueues a end command in the Actor messages queue. The actor will consume all mesages before ending.
@@ -184,18 +466,170 @@ impl<A: Actor> ActorAssistant<A> {
     pub fn stop_system(&self) {
         let system = self.system_director.clone();
 
-        task::spawn(async move {
+        self.system_director.get_executor().spawn(async move {
             system.stop().await;
         });
     }
 }
 
+#[cfg(feature = "persistence")]
+impl<A: PersistentActor> ActorAssistant<A> {
+    /// Rebuilds the state of the actor applying all its persisted events, in order, to the initial
+    /// state. It is meant to be called in the `activate` method. See
+    /// [PersistentActor](./trait.PersistentActor.html).
+    pub async fn recover(&self, initial_state: A) -> Result<A, JournalError> {
+        self.persistence.disable_snapshots();
+
+        self.replay_events(initial_state, 0).await
+    }
+
+    /// Applies to the actor the events persisted after the sequence number `after_sequence_nr`.
+    async fn replay_events(&self, mut actor: A, after_sequence_nr: u64) -> Result<A, JournalError> {
+        let entries = self
+            .actors_director
+            .get_journal()
+            .read(&A::persistence_id(&self.actor_id), after_sequence_nr + 1)
+            .await?;
+
+        let mut last_sequence_nr = after_sequence_nr;
+
+        for entry in entries {
+            let event: A::Event = serde_json::from_slice(&entry.payload)?;
+            actor.apply(&event);
+            last_sequence_nr = entry.sequence_nr;
+        }
+
+        self.persistence.set_last_sequence_nr(last_sequence_nr);
+
+        Ok(actor)
+    }
+
+    /// Appends the event to the journal and then applies it to the actor. If the journal fails,
+    /// the event is not applied.
+    pub async fn persist(&self, actor: &mut A, event: A::Event) -> Result<(), JournalError> {
+        self.persist_all(actor, vec![event]).await
+    }
+
+    /// Same as `persist` but for several events. Either all of them are stored and applied or
+    /// none of them is.
+    pub async fn persist_all(
+        &self,
+        actor: &mut A,
+        events: Vec<A::Event>,
+    ) -> Result<(), JournalError> {
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        let last_sequence_nr = self.persistence.last_sequence_nr();
+
+        let mut entries = Vec::with_capacity(events.len());
+
+        for (sequence_nr, event) in (last_sequence_nr + 1..).zip(events.iter()) {
+            entries.push(JournalEntry {
+                sequence_nr,
+                payload: serde_json::to_vec(event)?,
+            });
+        }
+
+        self.actors_director
+            .get_journal()
+            .append(
+                &A::persistence_id(&self.actor_id),
+                last_sequence_nr,
+                entries,
+            )
+            .await?;
+
+        for event in &events {
+            actor.apply(event);
+        }
+
+        self.persistence
+            .set_last_sequence_nr(last_sequence_nr + events.len() as u64);
+
+        Ok(())
+    }
+
+    /// Returns the sequence number of the last event persisted or recovered by this actor. It is
+    /// 0 if the actor has no events.
+    pub fn last_sequence_nr(&self) -> u64 {
+        self.persistence.last_sequence_nr()
+    }
+}
+
+#[cfg(feature = "persistence")]
+impl<A: SnapshotActor> ActorAssistant<A> {
+    /// Same as `recover` but it starts from the last snapshot of the actor, if any, and only
+    /// applies the events persisted after it. From now on, the framework saves snapshots as
+    /// `SnapshotActor::snapshot_policy` says. See [SnapshotActor](./trait.SnapshotActor.html).
+    pub async fn recover_from_snapshot(&self, initial_state: A) -> Result<A, JournalError> {
+        let snapshot = self
+            .actors_director
+            .get_snapshot_store()
+            .load(&A::persistence_id(&self.actor_id))
+            .await?;
+
+        let (actor, snapshot_sequence_nr) = match snapshot {
+            Some(snapshot) => (
+                serde_json::from_slice(&snapshot.payload)?,
+                snapshot.sequence_nr,
+            ),
+            None => (initial_state, 0),
+        };
+
+        let actor = self.replay_events(actor, snapshot_sequence_nr).await?;
+
+        self.persistence.enable_snapshots(
+            A::snapshot_policy(),
+            A::persistence_id(&self.actor_id),
+            |actor| serde_json::to_vec(actor),
+            snapshot_sequence_nr,
+        );
+
+        Ok(actor)
+    }
+}
+
+#[cfg(feature = "persistence")]
+impl<A: Actor> ActorAssistant<A> {
+    /// Saves a snapshot if the actor was recovered from a snapshot and its policy asks for one
+    /// after this message. Snapshots are an optimization, so errors are ignored: the events
+    /// are still in the journal.
+    pub(crate) async fn snapshot_after_message(&self, actor: &A) {
+        if let Some((persistence_id, snapshot)) = self.persistence.snapshot_after_message(actor) {
+            self.save_snapshot(&persistence_id, snapshot).await;
+        }
+    }
+
+    /// Same as `snapshot_after_message`, but for the deactivation.
+    pub(crate) async fn snapshot_before_deactivate(&self, actor: &A) {
+        if let Some((persistence_id, snapshot)) = self.persistence.snapshot_before_deactivate(actor)
+        {
+            self.save_snapshot(&persistence_id, snapshot).await;
+        }
+    }
+
+    async fn save_snapshot(&self, persistence_id: &str, snapshot: Snapshot) {
+        let _ = self
+            .actors_director
+            .get_snapshot_store()
+            .save(persistence_id, snapshot)
+            .await;
+    }
+}
+
 impl<A: Actor> Clone for ActorAssistant<A> {
     fn clone(&self) -> Self {
         ActorAssistant {
             actors_director: self.actors_director.clone(),
             actor_id: self.actor_id.clone(),
             system_director: self.system_director.clone(),
+            subscriptions: self.subscriptions.clone(),
+            #[cfg(feature = "testkit")]
+            outbox: self.outbox.clone(),
+            #[cfg(feature = "persistence")]
+            persistence: self.persistence.clone(),
         }
     }
 }
@@ -1,24 +1,60 @@
-use crate::actors::envelope::{ManagerLetter, ManagerLetterWithResponder};
-use crate::actors::manager::{ActorManagerProxyCommand, ActorsManager, Manager};
-use crate::actors::proxy::ActorReport;
-use crate::system_director::SystemDirector;
+use crate::actors::envelope::{ManagerLetter, ManagerLetterToAll, ManagerLetterWithResponder};
+use crate::actors::lru::ActorUsage;
+use crate::actors::manager::{ActorManagerProxyCommand, ActorsManager, Manager, ManagerSender};
+use crate::clock::Clock;
+#[cfg(feature = "cluster")]
+use crate::cluster::router::ClusterRouter;
+use crate::error::{CallError, SendError, TrySendError};
+use crate::metrics::Metrics;
+use crate::middleware::Middlewares;
+use crate::observer::Observer;
+#[cfg(feature = "persistence")]
+use crate::persistence::journal::Journal;
+#[cfg(feature = "persistence")]
+use crate::persistence::snapshot::SnapshotStore;
+use crate::report::ActorReport;
+use crate::runtime::Executor;
+use crate::shutdown::ShutdownState;
+use crate::supervision::Supervisor;
+use crate::system_director::WeakSystemDirector;
 use crate::{Actor, Receive, Respond};
-use async_channel::{bounded as channel, Sender};
-use async_std::sync::{Arc, Mutex};
+use async_channel::{bounded as channel, RecvError};
 use dashmap::{mapref::entry::Entry, DashMap};
+#[cfg(feature = "cluster")]
+use futures::future::BoxFuture;
 use futures::task::AtomicWaker;
-use std::sync::atomic::{AtomicBool, Ordering::Relaxed};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering::Relaxed};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::{
     any::TypeId,
     fmt::Debug,
     future::Future,
     pin::Pin,
     task::{Context, Poll},
+    time::{Instant, SystemTime},
 };
 
 #[derive(Debug)]
 pub(crate) struct ActorsDirectorConfiguration {
     pub(crate) innactivity_seconds_until_actor_end: std::time::Duration,
+    pub(crate) max_actors: Option<usize>,
+    pub(crate) max_actors_per_type: HashMap<TypeId, usize>,
+    pub(crate) actors_memory_budget: Option<usize>,
+    pub(crate) supervisor: Option<Arc<dyn Supervisor>>,
+    pub(crate) call_timeout: Option<std::time::Duration>,
+    pub(crate) metrics: Arc<Metrics>,
+    pub(crate) middlewares: Arc<Middlewares>,
+    pub(crate) clock: Clock,
+    pub(crate) executor: Executor,
+    pub(crate) observer: Observer,
+    pub(crate) shutdown: Arc<ShutdownState>,
+    #[cfg(feature = "persistence")]
+    pub(crate) journal: Arc<dyn Journal>,
+    #[cfg(feature = "persistence")]
+    pub(crate) snapshot_store: Arc<dyn SnapshotStore>,
+    #[cfg(feature = "cluster")]
+    pub(crate) cluster: Option<Arc<ClusterRouter>>,
 }
 
 #[derive(Debug, Clone)]
@@ -27,8 +63,11 @@ pub(crate) struct ActorsDirector {
     // TODO: Should be a WakerSet as there may be more than one thread that wants to wait
     waker: Arc<AtomicWaker>,
     is_stopping: Arc<AtomicBool>,
-    system: Arc<Mutex<Option<SystemDirector>>>,
+    system: Arc<Mutex<Option<WeakSystemDirector>>>,
     configuration: Arc<ActorsDirectorConfiguration>,
+    // Actors alive and not being evicted, and their estimated memory usage.
+    live_actors: Arc<AtomicUsize>,
+    live_actors_memory: Arc<AtomicUsize>,
 }
 
 impl ActorsDirector {
@@ -39,11 +78,13 @@ impl ActorsDirector {
             is_stopping: Arc::new(AtomicBool::new(false)),
             system: Arc::new(Mutex::new(None)),
             configuration: Arc::new(configuration),
+            live_actors: Arc::new(AtomicUsize::new(0)),
+            live_actors_memory: Arc::new(AtomicUsize::new(0)),
         }
     }
 
-    pub(crate) async fn set_system(&mut self, system_director: SystemDirector) {
-        let mut system = self.system.lock().await;
+    pub(crate) fn set_system(&mut self, system_director: WeakSystemDirector) {
+        let mut system = self.lock_system();
 
         if system.is_none() {
             system.replace(system_director);
@@ -52,8 +93,17 @@ impl ActorsDirector {
         }
     }
 
-    // Ensures that there is a manager for that type and returns a sender to it
-    async fn get_or_create_manager_sender<A: Actor>(&self) -> Sender<ActorManagerProxyCommand<A>> {
+    fn lock_system(&self) -> MutexGuard<'_, Option<WeakSystemDirector>> {
+        // The system is set only once, so it is consistent even if some thread panicked.
+        match self.system.lock() {
+            Ok(system) => system,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    // Ensures that there is a manager for that type and returns a sender to it. Managers are only
+    // removed when the system stops, so the sender can be kept, as `ActorRef` does.
+    pub(crate) async fn get_or_create_manager_sender<A: Actor>(&self) -> ManagerSender<A> {
         let type_id = TypeId::of::<A>();
 
         let managers_entry = self.managers.entry(type_id);
@@ -61,13 +111,24 @@ impl ActorsDirector {
         let any_sender = match managers_entry {
             Entry::Occupied(entry) => entry.into_ref(),
             Entry::Vacant(entry) => {
-                let manager = self.create_manager::<A>().await;
+                let manager = match self.create_manager::<A>().await {
+                    Some(manager) => manager,
+                    // Nothing can handle the message, as if the manager had already ended
+                    None => return ManagerSender::closed(),
+                };
+
+                // Actors activated while the system stops (for example, by a service that is
+                // still draining its messages) must stop too.
+                if self.is_stopping.load(Relaxed) {
+                    manager.mark_as_ending();
+                }
+
                 entry.insert(Box::new(manager))
             }
         }
         .get_sender_as_any();
 
-        match any_sender.downcast::<Sender<ActorManagerProxyCommand<A>>>() {
+        match any_sender.downcast::<ManagerSender<A>>() {
             Ok(sender) => *sender,
             // If type is not matching, crash as  we don't really want to
             // run the framework with a bug like that
@@ -80,24 +141,169 @@ impl ActorsDirector {
         actor_id: A::Id,
         message: M,
     ) {
+        let manager = self.get_or_create_manager_sender::<A>().await;
+
+        self.send_through::<A, M>(&manager, actor_id, message).await
+    }
+
+    /// Same as `send` but with the sender of the manager already known.
+    pub(crate) async fn send_through<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        manager: &ManagerSender<A>,
+        actor_id: A::Id,
+        message: M,
+    ) {
+        self.configuration.metrics.actor_message_sent::<A, M>();
+
+        if !manager.waits_for_room() {
+            let _ = manager
+                .send(ActorManagerProxyCommand::Dispatch(Box::new(
+                    ManagerLetter::new(actor_id, message),
+                )))
+                .await;
+
+            return;
+        }
+
+        // The message may wait for room in the actor mailbox, so we wait for it as well.
+        let (sender, receiver) = channel::<Result<(), SendError>>(1);
+
+        let letter = ManagerLetter::with_enqueue_report(actor_id, message, sender);
+
+        if manager
+            .send(ActorManagerProxyCommand::Dispatch(Box::new(letter)))
+            .await
+            .is_ok()
+        {
+            let _ = receiver.recv().await;
+        }
+    }
+
+    /// Returns true if the system is stopping and the actor type is not running anymore, so
+    /// checked sends, tries and calls don't start it again.
+    fn is_stopped<A: Actor>(&self) -> bool {
+        self.is_stopping.load(Relaxed) && !self.managers.contains_key(&TypeId::of::<A>())
+    }
+
+    /// Same as `send` but it reports if the message was enqueued. While the system stops, it
+    /// doesn't create managers nor actors.
+    pub(crate) async fn send_checked<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        message: M,
+    ) -> Result<(), SendError> {
+        if self.is_stopped::<A>() {
+            return Err(SendError::SystemStopped);
+        }
+
+        let manager = self.get_or_create_manager_sender::<A>().await;
+
+        self.send_checked_through::<A, M>(&manager, actor_id, message)
+            .await
+    }
+
+    /// Same as `send_checked` but with the sender of the manager already known.
+    pub(crate) async fn send_checked_through<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        manager: &ManagerSender<A>,
+        actor_id: A::Id,
+        message: M,
+    ) -> Result<(), SendError> {
+        self.configuration.metrics.actor_message_sent::<A, M>();
+
+        let (sender, receiver) = channel::<Result<(), SendError>>(1);
+
+        let letter = ManagerLetter::with_send_report(actor_id, message, sender);
+
+        if manager
+            .send(ActorManagerProxyCommand::Dispatch(Box::new(letter)))
+            .await
+            .is_err()
+        {
+            return Err(SendError::SystemStopped);
+        }
+
+        match receiver.recv().await {
+            Ok(result) => result,
+            // Same as with `try_send`, letters are only dropped when the system is stopping.
+            Err(_) => Err(SendError::SystemStopped),
+        }
+    }
+
+    pub(crate) async fn try_send<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        message: M,
+    ) -> Result<(), TrySendError> {
+        if self.is_stopped::<A>() {
+            return Err(TrySendError::SystemStopping);
+        }
+
+        let manager = self.get_or_create_manager_sender::<A>().await;
+
+        self.try_send_through::<A, M>(&manager, actor_id, message)
+            .await
+    }
+
+    /// Same as `try_send` but with the sender of the manager already known.
+    pub(crate) async fn try_send_through<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        manager: &ManagerSender<A>,
+        actor_id: A::Id,
+        message: M,
+    ) -> Result<(), TrySendError> {
+        self.configuration.metrics.actor_message_sent::<A, M>();
+
+        let (sender, receiver) = channel::<Result<(), TrySendError>>(1);
+
+        let letter = ManagerLetter::with_delivery_report(actor_id, message, sender);
+
+        // The manager channel is unbounded, so this doesn't wait.
+        if manager
+            .send(ActorManagerProxyCommand::Dispatch(Box::new(letter)))
+            .await
+            .is_err()
+        {
+            return Err(TrySendError::SystemStopping);
+        }
+
+        match receiver.recv().await {
+            Ok(result) => result,
+            // The manager only drops letters without delivering them when the system is stopping.
+            Err(_) => Err(TrySendError::SystemStopping),
+        }
+    }
+
+    pub(crate) async fn send_to_all<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        message: M,
+    ) {
+        self.configuration.metrics.actor_message_sent::<A, M>();
+
         let _ = self
             .get_or_create_manager_sender::<A>()
             .await
-            .send(ActorManagerProxyCommand::Dispatch(Box::new(
-                ManagerLetter::new(actor_id, message),
+            .send(ActorManagerProxyCommand::DispatchToAll(Box::new(
+                ManagerLetter::new(Default::default(), message),
             )))
             .await;
     }
 
-    pub(crate) async fn send_to_all<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+    /// Same as `send_to_all` but every actor receives a copy of the message.
+    pub(crate) async fn send_copy_to_all<
+        A: Actor + Receive<M>,
+        M: Debug + Send + Sync + Clone + 'static,
+    >(
         &self,
         message: M,
     ) {
+        self.configuration.metrics.actor_message_sent::<A, M>();
+
         let _ = self
             .get_or_create_manager_sender::<A>()
             .await
             .send(ActorManagerProxyCommand::DispatchToAll(Box::new(
-                ManagerLetter::new(Default::default(), message),
+                ManagerLetterToAll::new(message),
             )))
             .await;
     }
@@ -106,19 +312,165 @@ impl ActorsDirector {
         &self,
         actor_id: A::Id,
         message: M,
-        // TODO: Create a proper return type without &str
-    ) -> Result<<A as Respond<M>>::Response, &str> {
-        let (sender, receiver) = channel::<<A as Respond<M>>::Response>(1);
+    ) -> Result<<A as Respond<M>>::Response, CallError> {
+        if self.is_stopped::<A>() {
+            return Err(CallError::SystemStopping);
+        }
+
+        let manager = self.get_or_create_manager_sender::<A>().await;
+
+        self.call_through::<A, M>(&manager, actor_id, message).await
+    }
+
+    /// Same as `call` but with the sender of the manager already known.
+    pub(crate) async fn call_through<A: Actor + Respond<M>, M: Debug + Send + 'static>(
+        &self,
+        manager: &ManagerSender<A>,
+        actor_id: A::Id,
+        message: M,
+    ) -> Result<<A as Respond<M>>::Response, CallError> {
+        match self.configuration.call_timeout {
+            Some(timeout) => {
+                self.call_with_timeout_through::<A, M>(manager, actor_id, message, timeout)
+                    .await
+            }
+            None => {
+                self.call_without_timeout::<A, M>(manager, actor_id, message, None)
+                    .await
+            }
+        }
+    }
+
+    pub(crate) async fn call_with_timeout<A: Actor + Respond<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        message: M,
+        timeout: std::time::Duration,
+    ) -> Result<<A as Respond<M>>::Response, CallError> {
+        if self.is_stopped::<A>() {
+            return Err(CallError::SystemStopping);
+        }
+
+        let manager = self.get_or_create_manager_sender::<A>().await;
+
+        self.call_with_timeout_through::<A, M>(&manager, actor_id, message, timeout)
+            .await
+    }
+
+    /// Same as `call_with_timeout` but with the sender of the manager already known.
+    pub(crate) async fn call_with_timeout_through<
+        A: Actor + Respond<M>,
+        M: Debug + Send + 'static,
+    >(
+        &self,
+        manager: &ManagerSender<A>,
+        actor_id: A::Id,
+        message: M,
+        timeout: std::time::Duration,
+    ) -> Result<<A as Respond<M>>::Response, CallError> {
+        // When the timeout expires, the response receiver is dropped. If the message is still in
+        // the actor mailbox, it will be skipped instead of being handled.
+        let deadline = self.configuration.clock.now() + timeout;
+
+        let result = self
+            .configuration
+            .clock
+            .timeout(
+                timeout,
+                self.call_without_timeout::<A, M>(manager, actor_id, message, Some(deadline)),
+            )
+            .await;
+
+        match result {
+            Some(result) => result,
+            None => {
+                // If the actor lives in another node, its response is not waited for anymore
+                #[cfg(feature = "cluster")]
+                if let Some(cluster) = self.get_cluster() {
+                    cluster.discard_abandoned_calls();
+                }
+
+                Err(CallError::Timeout)
+            }
+        }
+    }
+
+    /// The deadline, if any, is only used by other nodes of the cluster. Here the caller stops
+    /// waiting with its own timeout.
+    async fn call_without_timeout<A: Actor + Respond<M>, M: Debug + Send + 'static>(
+        &self,
+        manager: &ManagerSender<A>,
+        actor_id: A::Id,
+        message: M,
+        deadline: Option<Instant>,
+    ) -> Result<<A as Respond<M>>::Response, CallError> {
+        self.configuration.metrics.actor_message_sent::<A, M>();
+
+        let (sender, receiver) = channel::<Result<<A as Respond<M>>::Response, CallError>>(1);
+
+        let _ = manager
+            .send(ActorManagerProxyCommand::Dispatch(Box::new(
+                ManagerLetterWithResponder::new(actor_id, message, sender, deadline),
+            )))
+            .await;
 
+        self.get_call_result(receiver.recv().await)
+    }
+
+    fn get_call_result<R>(
+        &self,
+        response: Result<Result<R, CallError>, RecvError>,
+    ) -> Result<R, CallError> {
+        match response {
+            Ok(Ok(response)) => Ok(response),
+            // If the actor stopped because the whole system is stopping, we report that instead
+            // as it is more meaningful for the caller (it doesn't make sense to retry).
+            Ok(Err(CallError::ActorStopped)) | Err(_) if self.is_stopping.load(Relaxed) => {
+                Err(CallError::SystemStopping)
+            }
+            Ok(Err(error)) => Err(error),
+            Err(_) => Err(CallError::ResponderDropped),
+        }
+    }
+
+    /// Same as `send` but for messages coming from another node of the cluster. They are
+    /// delivered in this node even if, for this node, the actor is owned by another one.
+    #[cfg(feature = "cluster")]
+    pub(crate) async fn send_from_cluster<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        message: M,
+    ) {
         let _ = self
             .get_or_create_manager_sender::<A>()
             .await
-            .send(ActorManagerProxyCommand::Dispatch(Box::new(
-                ManagerLetterWithResponder::new(actor_id, message, sender),
+            .send(ActorManagerProxyCommand::DispatchFromCluster(Box::new(
+                ManagerLetter::new(actor_id, message),
             )))
             .await;
+    }
 
-        receiver.recv().await.or(Err("Ups"))
+    /// Same as `send_from_cluster` but for calls. It returns once the message is enqueued, with
+    /// the response as a future.
+    #[cfg(feature = "cluster")]
+    pub(crate) async fn call_from_cluster<A: Actor + Respond<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        message: M,
+    ) -> BoxFuture<'static, Result<<A as Respond<M>>::Response, CallError>> {
+        let (sender, receiver) = channel::<Result<<A as Respond<M>>::Response, CallError>>(1);
+
+        let _ = self
+            .get_or_create_manager_sender::<A>()
+            .await
+            .send(ActorManagerProxyCommand::DispatchFromCluster(Box::new(
+                ManagerLetterWithResponder::new(actor_id, message, sender, None),
+            )))
+            .await;
+
+        let actors_director = self.clone();
+
+        Box::pin(async move { actors_director.get_call_result(receiver.recv().await) })
     }
 
     pub(crate) async fn stop_actor<A: Actor>(&self, actor_id: A::Id) {
@@ -133,19 +485,153 @@ impl ActorsDirector {
         ActorsDirectorStopAwaiter::new(self.clone()).await;
     }
 
-    pub(crate) async fn create_manager<A: Actor>(&self) -> ActorsManager<A> {
-        // We use unwrap here as we must guarantee that there is a system director in every other director
-        let system = if let Some(system) = &*self.system.lock().await {
-            system.clone()
-        } else {
-            unreachable!();
+    // Returns `None` if the system is already gone, which happens only once it is stopped.
+    pub(crate) async fn create_manager<A: Actor>(&self) -> Option<ActorsManager<A>> {
+        // The system is set as soon as it is created, before any manager is needed
+        let system = match &*self.lock_system() {
+            Some(system) => system.upgrade()?,
+            None => unreachable!(),
         };
 
-        ActorsManager::<A>::new(
+        let manager = ActorsManager::<A>::new(
             self.clone(),
             system,
             self.configuration.innactivity_seconds_until_actor_end,
-        )
+            self.configuration
+                .max_actors_per_type
+                .get(&TypeId::of::<A>())
+                .copied(),
+        );
+
+        Some(manager)
+    }
+
+    pub(crate) fn get_supervisor(&self) -> Option<Arc<dyn Supervisor>> {
+        self.configuration.supervisor.clone()
+    }
+
+    pub(crate) fn get_metrics(&self) -> &Metrics {
+        &self.configuration.metrics
+    }
+
+    pub(crate) fn get_middlewares(&self) -> &Middlewares {
+        &self.configuration.middlewares
+    }
+
+    pub(crate) fn get_clock(&self) -> &Clock {
+        &self.configuration.clock
+    }
+
+    pub(crate) fn get_executor(&self) -> &Executor {
+        &self.configuration.executor
+    }
+
+    pub(crate) fn get_observer(&self) -> &Observer {
+        &self.configuration.observer
+    }
+
+    #[cfg(feature = "cluster")]
+    pub(crate) fn get_cluster(&self) -> Option<&ClusterRouter> {
+        self.configuration.cluster.as_deref()
+    }
+
+    pub(crate) fn get_shutdown_state(&self) -> &ShutdownState {
+        &self.configuration.shutdown
+    }
+
+    /// Returns true if any manager or actor has messages waiting to be handled.
+    #[cfg(feature = "testkit")]
+    pub(crate) fn has_pending_messages(&self) -> bool {
+        self.managers
+            .iter()
+            .any(|manager| manager.has_pending_messages())
+    }
+
+    #[cfg(feature = "persistence")]
+    pub(crate) fn get_journal(&self) -> &dyn Journal {
+        &*self.configuration.journal
+    }
+
+    #[cfg(feature = "persistence")]
+    pub(crate) fn get_snapshot_store(&self) -> &dyn SnapshotStore {
+        &*self.configuration.snapshot_store
+    }
+
+    /// Starts counting the actor for the limits, with the size of its struct as memory until it
+    /// is activated.
+    pub(crate) fn signal_actor_added<A: Actor>(&self, usage: &ActorUsage) -> bool {
+        let is_added = usage.count(std::mem::size_of::<A>(), &self.live_actors_memory);
+
+        if is_added {
+            self.live_actors.fetch_add(1, Relaxed);
+        }
+
+        is_added
+    }
+
+    /// Stops counting the actor for the limits. Returns false if it was already forgotten.
+    pub(crate) fn signal_actor_forgotten(&self, usage: &ActorUsage) -> bool {
+        let is_forgotten = usage.uncount(&self.live_actors_memory);
+
+        if is_forgotten {
+            self.live_actors.fetch_sub(1, Relaxed);
+        }
+
+        is_forgotten
+    }
+
+    /// Updates the memory the actor counts for the memory budget, if there is a budget.
+    pub(crate) fn signal_actor_memory<A: Actor>(&self, usage: &ActorUsage, actor: &A) {
+        if self.configuration.actors_memory_budget.is_some() {
+            usage.set_memory(actor.estimated_memory(), &self.live_actors_memory);
+        }
+    }
+
+    /// Evicts the least recently used actors of any type until there is room for a new actor of type A.
+    pub(crate) async fn make_room_for_new_actor<A: Actor>(&self) {
+        while self.is_full_for::<A>() {
+            if !self.evict_least_recently_used() {
+                break;
+            }
+        }
+    }
+
+    // The new actor is not activated yet, so we can only count the size of its struct.
+    fn is_full_for<A: Actor>(&self) -> bool {
+        let is_count_full = match self.configuration.max_actors {
+            Some(max_actors) => self.live_actors.load(Relaxed) >= max_actors,
+            None => false,
+        };
+
+        let is_memory_full = match self.configuration.actors_memory_budget {
+            Some(budget) => {
+                self.live_actors_memory.load(Relaxed) + std::mem::size_of::<A>() > budget
+            }
+            None => false,
+        };
+
+        is_count_full || is_memory_full
+    }
+
+    fn evict_least_recently_used(&self) -> bool {
+        let mut oldest: Option<(SystemTime, TypeId)> = None;
+
+        for manager in self.managers.iter() {
+            if let Some(time) = manager.get_least_recently_used_time() {
+                match oldest {
+                    Some((oldest_time, _)) if oldest_time <= time => (),
+                    _ => oldest = Some((time, *manager.key())),
+                }
+            }
+        }
+
+        match oldest {
+            Some((_, type_id)) => match self.managers.get(&type_id) {
+                Some(manager) => manager.evict_least_recently_used(),
+                None => false,
+            },
+            None => false,
+        }
     }
 
     pub(crate) async fn signal_manager_removed(&self) {
@@ -165,15 +651,34 @@ impl ActorsDirector {
         }
     }
 
-    pub(crate) fn get_blocking_manager_entry(&self, id: TypeId) -> Entry<TypeId, Box<dyn Manager>> {
+    /// Discards the messages still enqueued for any actor. Returns how many were discarded
+    /// for each actor type.
+    pub(crate) fn discard_pending_messages(&self) -> HashMap<&'static str, usize> {
+        let mut discarded = HashMap::new();
+
+        for manager in self.managers.iter() {
+            let count = manager.discard_pending_messages();
+
+            if count > 0 {
+                discarded.insert(manager.get_type_name(), count);
+            }
+        }
+
+        discarded
+    }
+
+    pub(crate) fn get_blocking_manager_entry(
+        &self,
+        id: TypeId,
+    ) -> Entry<'_, TypeId, Box<dyn Manager>> {
         self.managers.entry(id)
     }
 
-    pub(crate) fn get_statistics(&self) -> Vec<(TypeId, Vec<ActorReport>)> {
-        let mut statistics = vec![];
+    pub(crate) fn get_statistics(&self) -> HashMap<&'static str, Vec<ActorReport>> {
+        let mut statistics = HashMap::new();
 
         for manager in self.managers.iter() {
-            statistics.push((manager.get_type_id(), manager.get_statistics()))
+            statistics.insert(manager.get_type_name(), manager.get_statistics());
         }
 
         statistics
@@ -1,22 +1,28 @@
 use crate::actors::handle::Respond;
 use crate::actors::proxy::ActorProxy;
+#[cfg(feature = "cluster")]
+use crate::cluster::registry::RemoteLetter;
+use crate::error::{CallError, SendError, TrySendError};
+use crate::trace::TraceContext;
 use crate::{Actor, ActorAssistant, Receive};
 use async_channel::Sender;
 use async_trait::async_trait;
+use std::any::{type_name, Any};
 use std::fmt::Debug;
 use std::marker::PhantomData;
+use std::time::Instant;
 
-/// This structures encapsulate the message and capture the Type through a PhantonType,
-/// making them safe to send via channels that may allow different types Letter<A> != Letter<B>
-/// at the same time that you are able to use the type later in the dispatch/deliver method.
-/// The way this works is that the Letter/Envelope will receive the Actor and it will be the
-/// one calling the method of the Actor A or B.
+// This structures encapsulate the message and capture the Type through a PhantonType,
+// making them safe to send via channels that may allow different types Letter<A> != Letter<B>
+// at the same time that you are able to use the type later in the dispatch/deliver method.
+// The way this works is that the Letter/Envelope will receive the Actor and it will be the
+// one calling the method of the Actor A or B.
 
-/// There are two variants of these structures. One is the Envelope/Letter pair, with executes the
-/// handle method from the Actor trait directly. The other is the ManagerEnvelope/ManagerLetter that
-/// sends the message to the ActorProxy inbox channel. The only difference between them both beyond
-/// the difference in the method calling is that the former contains the Actor::Id that should
-/// receive the message.
+// There are two variants of these structures. One is the Envelope/Letter pair, with executes the
+// handle method from the Actor trait directly. The other is the ManagerEnvelope/ManagerLetter that
+// sends the message to the ActorProxy inbox channel. The only difference between them both beyond
+// the difference in the method calling is that the former contains the Actor::Id that should
+// receive the message.
 
 /// Trait that represents a message directed to an actor instance.
 #[async_trait]
@@ -24,6 +30,26 @@ pub(crate) trait Envelope: Send + Debug {
     type Actor: Actor;
 
     async fn dispatch(&mut self, actor: &mut Self::Actor, assistant: &ActorAssistant<Self::Actor>);
+
+    /// Discards the message without handling it, letting the caller know why (if any).
+    fn discard(&mut self, _error: CallError) {}
+
+    /// Lets the sender know that the message is in the mailbox, if it is waiting for it.
+    fn enqueued(&mut self) {}
+
+    /// True if the caller is not waiting for the response anymore (for example, it timed out).
+    /// Then the message is skipped.
+    fn is_abandoned(&self) -> bool {
+        false
+    }
+
+    fn message_type_name(&self) -> &'static str;
+
+    /// The message, if it wasn't handled yet.
+    fn message_mut(&mut self) -> Option<&mut (dyn Any + Send)>;
+
+    /// Same as `message_mut` but only for printing it.
+    fn message_debug(&self) -> Option<&dyn Debug>;
 }
 
 /// This struct implements `Envelope` and stores the message and the Actors type. This is
@@ -33,6 +59,8 @@ pub(crate) trait Envelope: Send + Debug {
 pub(crate) struct Letter<A, M: Debug> {
     pub(crate) message: Option<M>,
     pub(crate) phantom: PhantomData<A>,
+    pub(crate) report: Option<SendReport>,
+    pub(crate) trace: TraceContext,
 }
 
 impl<A: Receive<M> + Actor, M: Debug> Letter<A, M> {
@@ -43,23 +71,73 @@ impl<A: Receive<M> + Actor, M: Debug> Letter<A, M> {
         Letter {
             message: Some(message),
             phantom: PhantomData,
+            report: None,
+            trace: TraceContext::current(),
+        }
+    }
+
+    /// Creates a letter that reports when the message is enqueued or why it was discarded.
+    pub fn with_report(message: M, report: SendReport) -> Self {
+        Letter {
+            message: Some(message),
+            phantom: PhantomData,
+            report: Some(report),
+            trace: TraceContext::current(),
         }
     }
 
     pub async fn dispatch(&mut self, actor: &mut A, assistant: &ActorAssistant<A>) {
         if let Some(message) = self.message.take() {
-            <A as Receive<M>>::handle(actor, message, assistant).await;
+            let handler = <A as Receive<M>>::handle(actor, message, assistant);
+
+            self.trace
+                .in_actor_handler::<A, _>(assistant.get_id().await, type_name::<M>(), handler)
+                .await;
         }
     }
 }
 
 #[async_trait]
-impl<A: Actor + Receive<M>, M: Send + Debug> Envelope for Letter<A, M> {
+impl<A: Actor + Receive<M>, M: Send + Debug + 'static> Envelope for Letter<A, M> {
     type Actor = A;
 
     async fn dispatch(&mut self, actor: &mut A, assistant: &ActorAssistant<A>) {
         Letter::<A, M>::dispatch(self, actor, assistant).await
     }
+
+    fn discard(&mut self, error: CallError) {
+        self.message.take();
+
+        if let Some(report) = self.report.take() {
+            let error = match error {
+                CallError::MailboxFull => SendError::MailboxFull,
+                CallError::SystemStopping => SendError::SystemStopped,
+                _ => SendError::MailboxClosed,
+            };
+
+            let _ = report.try_send(Err(error));
+        }
+    }
+
+    fn enqueued(&mut self) {
+        if let Some(report) = self.report.take() {
+            let _ = report.try_send(Ok(()));
+        }
+    }
+
+    fn message_type_name(&self) -> &'static str {
+        type_name::<M>()
+    }
+
+    fn message_mut(&mut self) -> Option<&mut (dyn Any + Send)> {
+        self.message
+            .as_mut()
+            .map(|message| message as &mut (dyn Any + Send))
+    }
+
+    fn message_debug(&self) -> Option<&dyn Debug> {
+        self.message.as_ref().map(|message| message as &dyn Debug)
+    }
 }
 
 /// Same as Envelope but for Actors Managers. Actors Managers control group of actors of the same type.
@@ -71,6 +149,22 @@ pub(crate) trait ManagerEnvelope: Send + Debug {
     async fn deliver(&mut self, manager: &mut ActorProxy<Self::Actor>);
 
     fn get_actor_id(&self) -> <<Self as ManagerEnvelope>::Actor as Actor>::Id;
+
+    /// Drops the message without delivering it, letting the caller know why, if any.
+    fn discard(&mut self, _error: CallError) {}
+
+    /// Drops the message without delivering it if the sender asked to know when the message cannot
+    /// be enqueued, letting it know why. Returns false if the message must be delivered anyway.
+    fn refuse(&mut self, _error: SendError) -> bool {
+        false
+    }
+
+    /// Takes the message out of the envelope for sending it to another node of the cluster.
+    /// Returns None if the message can only be delivered in this node.
+    #[cfg(feature = "cluster")]
+    fn take_remote(&mut self) -> Option<RemoteLetter> {
+        None
+    }
 }
 
 /// The struct that implements `ManagerEnvelope`. Same as Letter, but with the Actor::Id in it in order to route the message
@@ -79,8 +173,19 @@ pub(crate) struct ManagerLetter<A: Actor, M: Debug> {
     message: Option<M>,
     actor_id: A::Id,
     phantom: PhantomData<A>,
+    delivery_report: Option<DeliveryReport>,
+    send_report: Option<SendReport>,
+    // Only letters from `send_to_actor_checked` are refused when the actor is ending.
+    is_refusable: bool,
+    trace: TraceContext,
 }
 
+/// The channel used for letting know `try_send_to_actor` if the message was enqueued.
+pub(crate) type DeliveryReport = Sender<Result<(), TrySendError>>;
+
+/// The channel used for letting know `send_to_actor_checked` if the message was enqueued.
+pub(crate) type SendReport = Sender<Result<(), SendError>>;
+
 impl<A: Receive<M> + Actor, M: 'static + Send + Debug> ManagerLetter<A, M> {
     pub fn new(actor_id: A::Id, message: M) -> Self
     where
@@ -90,6 +195,52 @@ impl<A: Receive<M> + Actor, M: 'static + Send + Debug> ManagerLetter<A, M> {
             message: Some(message),
             actor_id,
             phantom: PhantomData,
+            delivery_report: None,
+            send_report: None,
+            is_refusable: false,
+            trace: TraceContext::current(),
+        }
+    }
+
+    /// Creates a letter that never waits for room in the actor mailbox and reports if the
+    /// message was enqueued.
+    pub fn with_delivery_report(actor_id: A::Id, message: M, report: DeliveryReport) -> Self {
+        ManagerLetter {
+            message: Some(message),
+            actor_id,
+            phantom: PhantomData,
+            delivery_report: Some(report),
+            send_report: None,
+            is_refusable: false,
+            trace: TraceContext::current(),
+        }
+    }
+
+    /// Creates a letter that reports if the message was enqueued. Unlike `with_delivery_report`,
+    /// it waits for room in the actor mailbox.
+    pub fn with_send_report(actor_id: A::Id, message: M, report: SendReport) -> Self {
+        ManagerLetter {
+            message: Some(message),
+            actor_id,
+            phantom: PhantomData,
+            delivery_report: None,
+            send_report: Some(report),
+            is_refusable: true,
+            trace: TraceContext::current(),
+        }
+    }
+
+    /// Same as `new` but it reports when the message is enqueued, for senders that wait for room
+    /// in the actor mailbox.
+    pub fn with_enqueue_report(actor_id: A::Id, message: M, report: SendReport) -> Self {
+        ManagerLetter {
+            message: Some(message),
+            actor_id,
+            phantom: PhantomData,
+            delivery_report: None,
+            send_report: Some(report),
+            is_refusable: false,
+            trace: TraceContext::current(),
         }
     }
 
@@ -99,7 +250,18 @@ impl<A: Receive<M> + Actor, M: 'static + Send + Debug> ManagerLetter<A, M> {
 
     pub async fn deliver(&mut self, manager: &mut ActorProxy<A>) {
         if let Some(message) = self.message.take() {
-            manager.send(message).await;
+            // The Letter created by the proxy captures the span of this one
+            if let Some(report) = self.delivery_report.take() {
+                let result = self.trace.in_scope(manager.try_send(message)).await;
+                let _ = report.try_send(result);
+            } else if let Some(report) = self.send_report.take() {
+                // The message may wait for room in the mailbox, so the proxy reports it later
+                self.trace
+                    .in_scope(manager.send_checked(message, report))
+                    .await
+            } else {
+                self.trace.in_scope(manager.send(message)).await
+            }
         }
     }
 }
@@ -115,44 +277,177 @@ impl<A: Actor + Receive<M>, M: 'static + Send + Debug> ManagerEnvelope for Manag
     fn get_actor_id(&self) -> A::Id {
         ManagerLetter::<A, M>::get_actor_id(self)
     }
+
+    fn refuse(&mut self, error: SendError) -> bool {
+        if !self.is_refusable {
+            return false;
+        }
+
+        match self.send_report.take() {
+            Some(report) => {
+                self.message.take();
+                let _ = report.try_send(Err(error));
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[cfg(feature = "cluster")]
+    fn take_remote(&mut self) -> Option<RemoteLetter> {
+        let letter = RemoteLetter::send(self.message.take()?);
+
+        // Once written to the connection, the message counts as enqueued for the sender
+        let delivery_report = self.delivery_report.take();
+        let send_report = self.send_report.take();
+
+        Some(letter.on_forwarded(move |result| {
+            if let Some(report) = delivery_report {
+                let _ = report.try_send(result.map_err(|error| match error {
+                    SendError::NodeUnreachable => TrySendError::NodeUnreachable,
+                    SendError::Serialization => TrySendError::Serialization,
+                    _ => TrySendError::SystemStopping,
+                }));
+            }
+
+            if let Some(report) = send_report {
+                let _ = report.try_send(result);
+            }
+        }))
+    }
+}
+
+/// Same as ManagerLetter, but it delivers a copy of the message to every actor proxy instead of
+/// delivering it only once. Used for the publications to a whole actor type.
+#[derive(Debug)]
+pub(crate) struct ManagerLetterToAll<A: Actor, M: Debug> {
+    message: M,
+    phantom: PhantomData<A>,
+    trace: TraceContext,
+}
+
+impl<A: Receive<M> + Actor, M: 'static + Send + Clone + Debug> ManagerLetterToAll<A, M> {
+    pub fn new(message: M) -> Self {
+        ManagerLetterToAll {
+            message,
+            phantom: PhantomData,
+            trace: TraceContext::current(),
+        }
+    }
+}
+
+#[async_trait]
+impl<A: Actor + Receive<M>, M: 'static + Send + Clone + Debug> ManagerEnvelope
+    for ManagerLetterToAll<A, M>
+{
+    type Actor = A;
+
+    async fn deliver(&mut self, manager: &mut ActorProxy<Self::Actor>) {
+        self.trace
+            .in_scope(manager.send(self.message.clone()))
+            .await
+    }
+
+    fn get_actor_id(&self) -> A::Id {
+        Default::default()
+    }
 }
 
 //////////////////////////////////////////
 
+/// The channel used for sending back the response of a `Respond` handler.
+pub(crate) type Responder<A, M> = Sender<Result<<A as Respond<M>>::Response, CallError>>;
+
 /// This struct behaves as the Letter struct but it contains a Sender for the response.
 #[derive(Debug)]
 pub(crate) struct LetterWithResponder<A: Respond<M>, M: Debug> {
     message: Option<M>,
     phantom_actor: PhantomData<A>,
     phantom_response: PhantomData<<A as Respond<M>>::Response>,
-    responder: Sender<<A as Respond<M>>::Response>,
+    responder: Option<Responder<A, M>>,
+    trace: TraceContext,
 }
 
 impl<A: Respond<M> + Actor, M: Debug> LetterWithResponder<A, M> {
-    pub fn new(message: M, responder: Sender<<A as Respond<M>>::Response>) -> Self {
+    pub fn new(message: M, responder: Responder<A, M>) -> Self {
         LetterWithResponder {
             message: Some(message),
             phantom_actor: PhantomData,
             phantom_response: PhantomData,
-            responder,
+            responder: Some(responder),
+            trace: TraceContext::current(),
         }
     }
 
     pub async fn dispatch(&mut self, actor: &mut A, assistant: &ActorAssistant<A>) {
         if let Some(message) = self.message.take() {
-            let response = <A as Respond<M>>::handle(actor, message, assistant).await;
-            let _ = self.responder.send(response).await;
+            let handler = <A as Respond<M>>::handle(actor, message, assistant);
+
+            let response = self
+                .trace
+                .in_actor_handler::<A, _>(assistant.get_id().await, type_name::<M>(), handler)
+                .await;
+
+            if let Some(responder) = self.responder.take() {
+                let _ = responder.send(Ok(response)).await;
+            }
+        }
+    }
+}
+
+// If the letter is dropped without responding, we let the caller know why. If the message is still
+// here the actor never got it (its queue was dropped). If not, the handler didn't finish (panicked).
+impl<A: Respond<M>, M: Debug> Drop for LetterWithResponder<A, M> {
+    fn drop(&mut self) {
+        if let Some(responder) = self.responder.take() {
+            let error = if self.message.is_some() {
+                CallError::ActorStopped
+            } else {
+                CallError::HandlerPanicked
+            };
+
+            // The channel has capacity for 1 message and nothing was sent yet, so it cannot be full.
+            let _ = responder.try_send(Err(error));
         }
     }
 }
 
 #[async_trait]
-impl<A: Actor + Respond<M>, M: Send + Debug> Envelope for LetterWithResponder<A, M> {
+impl<A: Actor + Respond<M>, M: Send + Debug + 'static> Envelope for LetterWithResponder<A, M> {
     type Actor = A;
 
     async fn dispatch(&mut self, actor: &mut A, assistant: &ActorAssistant<A>) {
         LetterWithResponder::<A, M>::dispatch(self, actor, assistant).await
     }
+
+    fn discard(&mut self, error: CallError) {
+        self.message.take();
+
+        if let Some(responder) = self.responder.take() {
+            let _ = responder.try_send(Err(error));
+        }
+    }
+
+    fn is_abandoned(&self) -> bool {
+        match &self.responder {
+            Some(responder) => responder.is_closed(),
+            None => false,
+        }
+    }
+
+    fn message_type_name(&self) -> &'static str {
+        type_name::<M>()
+    }
+
+    fn message_mut(&mut self) -> Option<&mut (dyn Any + Send)> {
+        self.message
+            .as_mut()
+            .map(|message| message as &mut (dyn Any + Send))
+    }
+
+    fn message_debug(&self) -> Option<&dyn Debug> {
+        self.message.as_ref().map(|message| message as &dyn Debug)
+    }
 }
 
 /// Same as ManagerLetter but with a response
@@ -162,11 +457,21 @@ pub(crate) struct ManagerLetterWithResponder<A: Actor + Respond<M>, M: Debug> {
     actor_id: A::Id,
     phantom_actor: PhantomData<<A as Respond<M>>::Response>,
     phantom_response: PhantomData<A>,
-    responder: Option<Sender<<A as Respond<M>>::Response>>,
+    responder: Option<Responder<A, M>>,
+    // When the caller stops waiting. Locally the closed responder tells it, but other nodes of
+    // the cluster need it for skipping the call once it expires.
+    #[cfg_attr(not(feature = "cluster"), allow(dead_code))]
+    deadline: Option<Instant>,
+    trace: TraceContext,
 }
 
 impl<A: Respond<M> + Actor, M: 'static + Send + Debug> ManagerLetterWithResponder<A, M> {
-    pub fn new(actor_id: A::Id, message: M, responder: Sender<<A as Respond<M>>::Response>) -> Self
+    pub fn new(
+        actor_id: A::Id,
+        message: M,
+        responder: Responder<A, M>,
+        deadline: Option<Instant>,
+    ) -> Self
     where
         A: Respond<M>,
     {
@@ -176,6 +481,8 @@ impl<A: Respond<M> + Actor, M: 'static + Send + Debug> ManagerLetterWithResponde
             phantom_actor: PhantomData,
             phantom_response: PhantomData,
             responder: Some(responder),
+            deadline,
+            trace: TraceContext::current(),
         }
     }
 
@@ -186,7 +493,7 @@ impl<A: Respond<M> + Actor, M: 'static + Send + Debug> ManagerLetterWithResponde
     pub async fn deliver(&mut self, manager: &mut ActorProxy<A>) {
         if let Some(message) = self.message.take() {
             if let Some(responder) = self.responder.take() {
-                manager.call(message, responder).await;
+                self.trace.in_scope(manager.call(message, responder)).await;
             }
         }
     }
@@ -205,4 +512,33 @@ impl<A: Actor + Respond<M>, M: 'static + Send + Debug> ManagerEnvelope
     fn get_actor_id(&self) -> A::Id {
         ManagerLetterWithResponder::<A, M>::get_actor_id(self)
     }
+
+    fn discard(&mut self, error: CallError) {
+        self.message.take();
+
+        if let Some(responder) = self.responder.take() {
+            let _ = responder.try_send(Err(error));
+        }
+    }
+
+    #[cfg(feature = "cluster")]
+    fn take_remote(&mut self) -> Option<RemoteLetter> {
+        match (self.message.take(), self.responder.take()) {
+            (Some(message), Some(responder)) => Some(RemoteLetter::call::<A, M>(
+                message,
+                responder,
+                self.deadline,
+            )),
+            _ => None,
+        }
+    }
+}
+
+// The manager may be gone before delivering the letter. In that case the actor never saw the message.
+impl<A: Actor + Respond<M>, M: Debug> Drop for ManagerLetterWithResponder<A, M> {
+    fn drop(&mut self) {
+        if let Some(responder) = self.responder.take() {
+            let _ = responder.try_send(Err(CallError::ActorStopped));
+        }
+    }
 }
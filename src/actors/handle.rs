@@ -9,7 +9,7 @@ use std::fmt::Debug;
 /// If you want to respond to messages, use the [Respond trait](./trait.Respond.html).
 ///
 /// This trait is compatible with [Respond trait](./trait.Respond.html) as you can implement, for the same message,
-/// both traits. This trait will be executed when using the "send_to_actor" or "send_to_actor_sync" method from System
+/// both traits. This trait will be executed when using the "send_to_actor" method from Acteur
 /// or the "send_to_actor" method from ActorAssistant.
 ///
 /// ```rust,no_run
@@ -37,7 +37,7 @@ use std::fmt::Debug;
 /// #         println!("Employee {:?} deactivated!", self.id);
 /// #     }
 /// # }
-/// use acteur::{ActorAssistant, Receive, Acteur};
+/// use acteur::{ActorAssistant, Receive, BlockingActeur};
 ///
 /// #[derive(Debug)]
 /// struct SalaryChanged(u32);
@@ -50,9 +50,9 @@ use std::fmt::Debug;
 /// }
 ///
 /// fn main() {
-///     let sys = Acteur::new();
+///     let sys = BlockingActeur::new();
 ///
-///     sys.send_to_actor_sync::<Employee, SalaryChanged>(42, SalaryChanged(55000));
+///     sys.send_to_actor::<Employee, SalaryChanged>(42, SalaryChanged(55000));
 ///
 ///     sys.wait_until_stopped();
 /// }
@@ -77,7 +77,7 @@ where
 /// This trait is like the [Receive trait](./trait.Receive.html) but additionally allows responding to messages
 ///
 /// This trait is compatible with [Receive trait](./trait.Receive.html) as you can implement, for the same message,
-/// both traits. This trait will be executed when using the "call_actor" or "call_actor_sync" method from System or
+/// both traits. This trait will be executed when using the "call_actor" method from Acteur or
 /// the "call_actor" method from ActorAssistant.
 ///
 /// ## Note about concurrency and performance
@@ -119,7 +119,7 @@ where
 /// #         println!("Employee {:?} deactivated!", self.id);
 /// #     }
 /// # }
-/// use acteur::{ActorAssistant, Respond, Acteur};
+/// use acteur::{ActorAssistant, Respond, BlockingActeur};
 ///
 /// #[derive(Debug)]
 /// struct SalaryChanged(u32);
@@ -134,9 +134,9 @@ where
 /// }
 ///
 /// fn main() {
-///     let sys = Acteur::new();
+///     let sys = BlockingActeur::new();
 ///
-///     let response = sys.call_actor_sync::<Employee, SalaryChanged>(42, SalaryChanged(55000));
+///     let response = sys.call_actor::<Employee, SalaryChanged>(42, SalaryChanged(55000));
 ///
 ///     println!("Response is: {:?}", response); // Response is: Thanks!
 ///
@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+use std::sync::{Mutex, MutexGuard};
+use std::time::SystemTime;
+
+/// Position of an actor in the `LruIndex`. The sequence number makes the key unique even
+/// if two actors receive a message at the same time.
+pub(crate) type LruKey = (SystemTime, u64);
+
+/// Keeps the actors of a type ordered by the last time they received a message, so the
+/// least recently used one can be found without checking all of them.
+///
+/// Only actors that can be evicted are in the index. Once an actor is evicted, it is removed
+/// from here even if it keeps running until it consumes its remaining messages.
+#[derive(Debug)]
+pub(crate) struct LruIndex<Id> {
+    entries: BTreeMap<LruKey, Id>,
+    sequence: u64,
+}
+
+impl<Id> LruIndex<Id> {
+    pub(crate) fn new() -> LruIndex<Id> {
+        LruIndex {
+            entries: BTreeMap::new(),
+            sequence: 0,
+        }
+    }
+
+    pub(crate) fn insert(&mut self, time: SystemTime, id: Id) -> LruKey {
+        self.sequence += 1;
+        let key = (time, self.sequence);
+        self.entries.insert(key, id);
+        key
+    }
+
+    /// Returns the Id if the key was in the index.
+    pub(crate) fn remove(&mut self, key: &LruKey) -> Option<Id> {
+        self.entries.remove(key)
+    }
+
+    pub(crate) fn oldest_time(&self) -> Option<SystemTime> {
+        self.entries.keys().next().map(|(time, _)| *time)
+    }
+
+    pub(crate) fn pop_oldest(&mut self) -> Option<Id> {
+        let key = *self.entries.keys().next()?;
+        self.entries.remove(&key)
+    }
+}
+
+/// What an actor counts for the limits: the actor itself and its estimated memory. It stops
+/// counting once it is evicted or removed. The memory is added to a total shared by all the
+/// actors, always while holding the lock, so the total never counts a change twice.
+#[derive(Debug)]
+pub(crate) struct ActorUsage {
+    state: Mutex<ActorUsageState>,
+}
+
+#[derive(Debug)]
+struct ActorUsageState {
+    memory: usize,
+    is_counted: bool,
+}
+
+impl ActorUsage {
+    pub(crate) fn new() -> ActorUsage {
+        ActorUsage {
+            state: Mutex::new(ActorUsageState {
+                memory: 0,
+                is_counted: false,
+            }),
+        }
+    }
+
+    /// Starts counting the actor with the given memory. Returns false if it was already counted.
+    pub(crate) fn count(&self, memory: usize, total: &AtomicUsize) -> bool {
+        let mut state = self.lock_state();
+
+        if state.is_counted {
+            return false;
+        }
+
+        state.memory = memory;
+        state.is_counted = true;
+        total.fetch_add(memory, Relaxed);
+        true
+    }
+
+    /// Stops counting the actor. Returns false if it wasn't counted.
+    pub(crate) fn uncount(&self, total: &AtomicUsize) -> bool {
+        let mut state = self.lock_state();
+
+        if !state.is_counted {
+            return false;
+        }
+
+        state.is_counted = false;
+        total.fetch_sub(state.memory, Relaxed);
+        true
+    }
+
+    /// Sets the memory of the actor, updating the total if the actor is counted.
+    pub(crate) fn set_memory(&self, memory: usize, total: &AtomicUsize) {
+        let mut state = self.lock_state();
+
+        if state.is_counted {
+            total.fetch_sub(state.memory, Relaxed);
+            total.fetch_add(memory, Relaxed);
+        }
+
+        state.memory = memory;
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, ActorUsageState> {
+        // The state is always updated in one step, so it is consistent even after a panic
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use crate::actors::actor::MailboxOverflow;
+use crate::actors::envelope::Envelope;
+use crate::actors::proxy::ActorProxyCommand;
+use crate::error::{CallError, TrySendError};
+use crate::Actor;
+use async_channel::{bounded, unbounded as channel, Receiver, RecvError, Sender};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// The inbox of an actor instance.
+///
+/// The commands channel is always unbounded, as the actor loop sends End commands to itself and it
+/// must never wait for room in its own mailbox. The capacity is enforced with a second channel that
+/// holds one slot per enqueued message: a slot is taken before enqueueing a message and released
+/// when the message leaves the mailbox.
+///
+/// Pushing never waits, as it is done by the manager loop of the actor type. With the `Await`
+/// policy, the messages that don't fit wait aside and they are enqueued, in order, as soon as the
+/// actor takes messages. The senders that must wait are notified through their letters.
+#[derive(Debug)]
+pub(crate) struct Mailbox<A: Actor> {
+    sender: Sender<ActorProxyCommand<A>>,
+    receiver: Receiver<ActorProxyCommand<A>>,
+    slots: Option<(Sender<()>, Receiver<()>)>,
+    overflow: MailboxOverflow,
+    waiting: Arc<Mutex<VecDeque<Box<dyn Envelope<Actor = A>>>>>,
+    is_closed: Arc<AtomicBool>,
+}
+
+impl<A: Actor> Mailbox<A> {
+    pub(crate) fn new(capacity: Option<usize>, overflow: MailboxOverflow) -> Mailbox<A> {
+        let (sender, receiver) = channel();
+
+        Mailbox {
+            sender,
+            receiver,
+            // A capacity of 0 would make impossible to send any message.
+            slots: capacity.map(|capacity| bounded(capacity.max(1))),
+            overflow,
+            waiting: Arc::new(Mutex::new(VecDeque::new())),
+            is_closed: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Enqueues the message applying the overflow policy. If `wait` is false, the `Await` policy
+    /// behaves as `Reject`. Discarded messages are notified to their callers, if any.
+    pub(crate) fn push(
+        &self,
+        mut envelope: Box<dyn Envelope<Actor = A>>,
+        wait: bool,
+    ) -> Result<(), TrySendError> {
+        let slots_sender = match &self.slots {
+            Some((slots_sender, _)) => slots_sender,
+            None => {
+                self.enqueue(envelope);
+                return Ok(());
+            }
+        };
+
+        let mut waiting = self.lock_waiting();
+
+        // Messages already waiting for room go first
+        if waiting.is_empty() {
+            loop {
+                if slots_sender.try_send(()).is_ok() {
+                    self.enqueue(envelope);
+                    return Ok(());
+                }
+
+                match self.overflow {
+                    // If the actor took all the messages meanwhile, we try again.
+                    MailboxOverflow::DropOldest if self.drop_oldest() => continue,
+                    _ => break,
+                }
+            }
+        }
+
+        match self.overflow {
+            MailboxOverflow::Await if wait => {
+                waiting.push_back(envelope);
+                Ok(())
+            }
+            _ => {
+                envelope.discard(CallError::MailboxFull);
+                Err(TrySendError::MailboxFull)
+            }
+        }
+    }
+
+    fn enqueue(&self, mut envelope: Box<dyn Envelope<Actor = A>>) {
+        envelope.enqueued();
+
+        // The channel is unbounded and this mailbox holds a receiver, so it cannot fail.
+        let _ = self.sender.try_send(ActorProxyCommand::Dispatch(envelope));
+    }
+
+    /// Enqueues the messages waiting for room, while there is room for them.
+    fn enqueue_waiting(&self) {
+        if let Some((slots_sender, _)) = &self.slots {
+            let mut waiting = self.lock_waiting();
+
+            while !waiting.is_empty() && slots_sender.try_send(()).is_ok() {
+                if let Some(envelope) = waiting.pop_front() {
+                    self.enqueue(envelope);
+                }
+            }
+        }
+    }
+
+    /// Discards the oldest enqueued message. Returns false if there were no messages.
+    fn drop_oldest(&self) -> bool {
+        // End commands found in the way are sent to the back of the queue. That only
+        // postpones the ending, same as when the actor loop finds messages after an End.
+        for _ in 0..self.receiver.len() {
+            match self.receiver.try_recv() {
+                Ok(ActorProxyCommand::Dispatch(mut envelope)) => {
+                    self.release_slot();
+                    envelope.discard(CallError::MailboxFull);
+                    return true;
+                }
+                Ok(ActorProxyCommand::End) => {
+                    let _ = self.sender.try_send(ActorProxyCommand::End);
+                }
+                Err(_) => return false,
+            }
+        }
+
+        false
+    }
+
+    /// Discards all the enqueued messages, letting know their callers. Returns how many
+    /// messages were discarded. End commands are kept, so the actor still ends.
+    pub(crate) fn discard_all(&self, error: CallError) -> usize {
+        // Taken first, so they don't get the room left by the discarded ones
+        let waiting = std::mem::take(&mut *self.lock_waiting());
+
+        let mut discarded = waiting.len();
+        let mut ends = 0;
+
+        for mut envelope in waiting {
+            envelope.discard(error);
+        }
+
+        for _ in 0..self.receiver.len() {
+            match self.receiver.try_recv() {
+                Ok(ActorProxyCommand::Dispatch(mut envelope)) => {
+                    self.release_slot();
+                    envelope.discard(error);
+                    discarded += 1;
+                }
+                Ok(ActorProxyCommand::End) => ends += 1,
+                Err(_) => break,
+            }
+        }
+
+        for _ in 0..ends {
+            let _ = self.sender.try_send(ActorProxyCommand::End);
+        }
+
+        discarded
+    }
+
+    pub(crate) async fn recv(&self) -> Result<ActorProxyCommand<A>, RecvError> {
+        let command = self.receiver.recv().await?;
+
+        if let ActorProxyCommand::Dispatch(_) = command {
+            self.release_slot();
+            self.enqueue_waiting();
+        }
+
+        Ok(command)
+    }
+
+    /// Stops accepting messages and discards the ones waiting for room. Used when the actor loop
+    /// stops without consuming the remaining messages.
+    pub(crate) fn close(&self) {
+        self.is_closed.store(true, Ordering::Relaxed);
+
+        if let Some((_, slots_receiver)) = &self.slots {
+            slots_receiver.close();
+        }
+
+        let waiting = std::mem::take(&mut *self.lock_waiting());
+
+        for mut envelope in waiting {
+            envelope.discard(CallError::ActorStopped);
+        }
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.is_closed.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn end(&self) {
+        // The channel is unbounded, so it only fails if the actor is already gone.
+        let _ = self.sender.try_send(ActorProxyCommand::End);
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.receiver.is_empty() && self.lock_waiting().is_empty()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.receiver.len() + self.lock_waiting().len()
+    }
+
+    fn release_slot(&self) {
+        if let Some((_, slots_receiver)) = &self.slots {
+            let _ = slots_receiver.try_recv();
+        }
+    }
+
+    fn lock_waiting(&self) -> MutexGuard<'_, VecDeque<Box<dyn Envelope<Actor = A>>>> {
+        // Messages are moved in or out of the queue in one step, so it is still consistent
+        // even if some thread panicked while holding the lock.
+        match self.waiting.lock() {
+            Ok(waiting) => waiting,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl<A: Actor> Clone for Mailbox<A> {
+    fn clone(&self) -> Self {
+        Mailbox {
+            sender: self.sender.clone(),
+            receiver: self.receiver.clone(),
+            slots: self.slots.clone(),
+            overflow: self.overflow,
+            waiting: self.waiting.clone(),
+            is_closed: self.is_closed.clone(),
+        }
+    }
+}
@@ -1,43 +1,111 @@
+use crate::actors::actor::{ActorConfiguration, MailboxOverflow, Passivation};
 use crate::actors::director::ActorsDirector;
 use crate::actors::envelope::ManagerEnvelope;
-use crate::actors::proxy::{ActorProxy, ActorReport};
+use crate::actors::lru::{ActorUsage, LruIndex};
+use crate::actors::proxy::ActorProxy;
+use crate::clock::Clock;
+#[cfg(feature = "cluster")]
+use crate::cluster::router::ClusterRouter;
+use crate::error::{CallError, SendError};
+use crate::metrics::Metrics;
+use crate::middleware::Middlewares;
+use crate::observer::Observer;
+use crate::report::ActorReport;
+use crate::runtime::Executor;
+use crate::shutdown::ShutdownState;
+use crate::supervision::Supervisor;
 use crate::system_director::SystemDirector;
 use crate::Actor;
-use async_channel::{unbounded as channel, Receiver, Sender};
-use async_std::{sync::Arc, task};
-use dashmap::mapref::entry::Entry;
+use async_channel::{unbounded, Receiver, SendError as ChannelSendError, Sender};
+use dashmap::mapref::entry::{Entry, OccupiedEntry};
 use dashmap::DashMap;
 use std::any::Any;
-use std::any::TypeId;
 use std::fmt::Debug;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::Duration;
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
 #[async_trait::async_trait]
 pub(crate) trait Manager: Send + Sync + Debug {
     fn end(&self);
-    fn get_type_id(&self) -> TypeId;
+    fn get_type_name(&self) -> &'static str;
     fn get_statistics(&self) -> ActorsManagerReport;
     fn get_sender_as_any(&self) -> Box<dyn Any>;
-    fn is_empty(&self) -> bool;
-    fn remove_actor(&self, actor_id: Box<dyn Any + Send>);
+    fn get_least_recently_used_time(&self) -> Option<SystemTime>;
+    fn evict_least_recently_used(&self) -> bool;
+    fn discard_pending_messages(&self) -> usize;
+    #[cfg(feature = "testkit")]
+    fn has_pending_messages(&self) -> bool;
 }
 
 #[derive(Debug)]
 pub(crate) enum ActorManagerProxyCommand<A: Actor> {
     Dispatch(Box<dyn ManagerEnvelope<Actor = A>>),
     DispatchToAll(Box<dyn ManagerEnvelope<Actor = A>>),
+    // Messages from other nodes of the cluster, always delivered in this one.
+    #[cfg(feature = "cluster")]
+    DispatchFromCluster(Box<dyn ManagerEnvelope<Actor = A>>),
     EndActor(A::Id),
 }
 
 pub(crate) type ActorsManagerReport = Vec<ActorReport>;
 
+/// The sending side of the manager of an actor type, as kept by `ActorRef`.
+#[derive(Debug)]
+pub(crate) struct ManagerSender<A: Actor> {
+    sender: Sender<ActorManagerProxyCommand<A>>,
+    // With bounded mailboxes and the `Await` policy, senders wait until their message is enqueued.
+    waits_for_room: bool,
+}
+
+impl<A: Actor> ManagerSender<A> {
+    /// A sender whose manager already ended, so every send fails.
+    pub(crate) fn closed() -> ManagerSender<A> {
+        let (sender, _) = unbounded();
+
+        ManagerSender {
+            sender,
+            waits_for_room: false,
+        }
+    }
+
+    pub(crate) async fn send(
+        &self,
+        command: ActorManagerProxyCommand<A>,
+    ) -> Result<(), ChannelSendError<ActorManagerProxyCommand<A>>> {
+        self.sender.send(command).await
+    }
+
+    pub(crate) fn waits_for_room(&self) -> bool {
+        self.waits_for_room
+    }
+}
+
+impl<A: Actor> Clone for ManagerSender<A> {
+    fn clone(&self) -> Self {
+        ManagerSender {
+            sender: self.sender.clone(),
+            waits_for_room: self.waits_for_room,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct ActorsManager<A: Actor> {
     actors: Arc<DashMap<A::Id, ActorProxy<A>>>,
     sender: Sender<ActorManagerProxyCommand<A>>,
+    // Only used for discarding the pending commands, the manager loop has its own.
+    receiver: Receiver<ActorManagerProxyCommand<A>>,
     is_ending: Arc<AtomicBool>,
     actors_director: ActorsDirector,
+    // Actors that can be evicted, ordered by their last message. Actors that are never
+    // passivated are not in the index, but they count for the limits.
+    lru: Arc<Mutex<LruIndex<A::Id>>>,
+    live_actors: Arc<AtomicUsize>,
+    max_actors: Option<usize>,
+    is_evictable: bool,
+    waits_for_room: bool,
 }
 
 impl<A: Actor> ActorsManager<A> {
@@ -45,9 +113,26 @@ impl<A: Actor> ActorsManager<A> {
         actors_director: ActorsDirector,
         system_director: SystemDirector,
         innactivity_duration_until_end: Duration,
+        max_actors: Option<usize>,
     ) -> ActorsManager<A> {
+        let mut configuration = A::configuration();
+
         // Channel in order to receive commands (like sending messages to actors, stopping, etc)
-        let (sender, receiver) = channel::<ActorManagerProxyCommand<A>>();
+        //
+        // It is unbounded even if the actors have bounded mailboxes, as the loop never waits for
+        // room in a mailbox. A full mailbox only affects the senders to that actor.
+        let (sender, receiver) = unbounded::<ActorManagerProxyCommand<A>>();
+
+        let waits_for_room = configuration.mailbox_capacity.is_some()
+            && configuration.mailbox_overflow == MailboxOverflow::Await;
+
+        let is_evictable = configuration.passivation != Passivation::Never;
+
+        // The actor type can override the system default
+        if let Passivation::SystemDefault = configuration.passivation {
+            configuration.passivation =
+                Passivation::AfterInactivity(innactivity_duration_until_end);
+        }
 
         let actors = Arc::new(DashMap::new());
         let is_ending = Arc::new(AtomicBool::new(false));
@@ -55,19 +140,25 @@ impl<A: Actor> ActorsManager<A> {
         let manager = ActorsManager {
             actors: actors.clone(),
             sender,
+            receiver: receiver.clone(),
             is_ending: is_ending.clone(),
             actors_director: actors_director.clone(),
+            lru: Arc::new(Mutex::new(LruIndex::new())),
+            live_actors: Arc::new(AtomicUsize::new(0)),
+            max_actors,
+            is_evictable,
+            waits_for_room,
         };
 
         // Loop for processing commands
-        task::spawn(actor_manager_loop(
+        manager.get_executor().spawn(actor_manager_loop(
             receiver,
             actors,
             actors_director,
             manager.clone(),
             is_ending,
             system_director,
-            innactivity_duration_until_end,
+            configuration,
         ));
 
         manager
@@ -79,6 +170,22 @@ impl<A: Actor> ActorsManager<A> {
         for actor in self.actors.iter() {
             actor.end();
         }
+
+        // If all the actors were already deallocated, no actor will signal its removal,
+        // so we check if the manager can be removed right now.
+        if self.actors.is_empty() {
+            let manager = self.clone();
+            self.get_executor().spawn(async move {
+                manager.signal_actor_removed().await;
+            });
+        }
+    }
+
+    /// Same as `end` but for a manager without actors. It will be removed once the actors created
+    /// for the messages already on their way are removed. We don't check it right now, as those
+    /// messages may not be enqueued yet.
+    pub(crate) fn mark_as_ending(&self) {
+        self.is_ending.store(true, Ordering::Relaxed);
     }
 
     pub(crate) async fn signal_actor_removed(&self) {
@@ -101,23 +208,197 @@ impl<A: Actor> ActorsManager<A> {
         if let Entry::Occupied(entry) = entry {
             // Remove the entry from the system HashMap
             entry.remove();
+            // The manager keeps a sender itself, so the loop only ends (and releases the system)
+            // once the channel is closed.
+            self.sender.close();
             // Signaling only when we really remove the manager.
             self.actors_director.signal_manager_removed().await;
         }
     }
 
+    /// Removes the actor from the manager. It must be called with the blocking entry
+    /// returned by `get_blocking_actor_entry`.
+    pub(crate) async fn remove_actor<S: BuildHasher>(
+        &self,
+        entry: OccupiedEntry<'_, A::Id, ActorProxy<A>, S>,
+    ) {
+        let actor = entry.remove();
+        self.forget_actor(&actor);
+        // Signaling only when we really remove the actor.
+        self.signal_actor_removed().await;
+    }
+
+    /// Registers a new actor for the limits and, unless it is never passivated, the eviction.
+    fn track_actor(&self, actor_id: A::Id, actor: &mut ActorProxy<A>) {
+        if self.is_evictable {
+            let key = self
+                .lock_lru()
+                .insert(actor.get_last_sent_message_time(), actor_id);
+            actor.set_lru_key(key);
+        }
+
+        if self
+            .actors_director
+            .signal_actor_added::<A>(actor.get_usage())
+        {
+            self.live_actors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Moves the actor to the most recently used position, if it is not being evicted.
+    fn touch_actor(&self, actor_id: A::Id, actor: &mut ActorProxy<A>) {
+        let mut lru = self.lock_lru();
+
+        // If the actor is not in the index, it was evicted and we must not add it again
+        if lru.remove(&actor.get_lru_key()).is_some() {
+            let key = lru.insert(actor.get_last_sent_message_time(), actor_id);
+            actor.set_lru_key(key);
+        }
+    }
+
+    fn forget_actor(&self, actor: &ActorProxy<A>) {
+        self.lock_lru().remove(&actor.get_lru_key());
+
+        // If the actor was evicted, it was already discounted
+        if self
+            .actors_director
+            .signal_actor_forgotten(actor.get_usage())
+        {
+            self.live_actors.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Updates the memory the actor counts for the memory budget, if there is a budget.
+    pub(crate) fn update_actor_memory(&self, usage: &ActorUsage, actor: &A) {
+        self.actors_director.signal_actor_memory(usage, actor);
+    }
+
+    /// Asks the least recently used actor to end. The actor will process all its enqueued messages
+    /// and then it will be deactivated. Returns false if there is no actor that can be evicted.
+    pub(crate) fn evict_least_recently_used(&self) -> bool {
+        let actor_id = match self.lock_lru().pop_oldest() {
+            Some(actor_id) => actor_id,
+            None => return false,
+        };
+
+        if let Some(actor) = self.actors.get(&actor_id) {
+            if self
+                .actors_director
+                .signal_actor_forgotten(actor.get_usage())
+            {
+                self.live_actors.fetch_sub(1, Ordering::Relaxed);
+            }
+
+            actor.end();
+        }
+
+        true
+    }
+
+    /// Discards the messages not delivered yet and the ones waiting in the actors mailboxes. The
+    /// actors are not interrupted. Returns how many messages were discarded.
+    pub(crate) fn discard_pending_messages(&self) -> usize {
+        let mut discarded = 0;
+
+        for _ in 0..self.receiver.len() {
+            match self.receiver.try_recv() {
+                Ok(ActorManagerProxyCommand::Dispatch(mut envelope))
+                | Ok(ActorManagerProxyCommand::DispatchToAll(mut envelope)) => {
+                    envelope.discard(CallError::SystemStopping);
+                    discarded += 1;
+                }
+                #[cfg(feature = "cluster")]
+                Ok(ActorManagerProxyCommand::DispatchFromCluster(mut envelope)) => {
+                    envelope.discard(CallError::SystemStopping);
+                    discarded += 1;
+                }
+                Ok(ActorManagerProxyCommand::EndActor(_)) => (),
+                Err(_) => break,
+            }
+        }
+
+        for actor in self.actors.iter() {
+            discarded += actor.discard_pending_messages();
+        }
+
+        discarded
+    }
+
+    pub(crate) fn get_least_recently_used_time(&self) -> Option<SystemTime> {
+        self.lock_lru().oldest_time()
+    }
+
+    /// Evicts actors until there is room for a new one, first checking the limit of this actor
+    /// type and then the system wide limits.
+    async fn make_room_for_new_actor(&self) {
+        if let Some(max_actors) = self.max_actors {
+            while self.live_actors.load(Ordering::Relaxed) >= max_actors {
+                if !self.evict_least_recently_used() {
+                    break;
+                }
+            }
+        }
+
+        self.actors_director.make_room_for_new_actor::<A>().await;
+    }
+
+    pub(crate) fn get_supervisor(&self) -> Option<Arc<dyn Supervisor>> {
+        self.actors_director.get_supervisor()
+    }
+
+    pub(crate) fn get_metrics(&self) -> &Metrics {
+        self.actors_director.get_metrics()
+    }
+
+    pub(crate) fn get_middlewares(&self) -> &Middlewares {
+        self.actors_director.get_middlewares()
+    }
+
+    pub(crate) fn get_clock(&self) -> &Clock {
+        self.actors_director.get_clock()
+    }
+
+    pub(crate) fn get_executor(&self) -> &Executor {
+        self.actors_director.get_executor()
+    }
+
+    pub(crate) fn get_observer(&self) -> &Observer {
+        self.actors_director.get_observer()
+    }
+
+    pub(crate) fn get_shutdown_state(&self) -> &ShutdownState {
+        self.actors_director.get_shutdown_state()
+    }
+
+    #[cfg(feature = "cluster")]
+    pub(crate) fn get_cluster(&self) -> Option<&ClusterRouter> {
+        self.actors_director.get_cluster()
+    }
+
+    fn lock_lru(&self) -> std::sync::MutexGuard<'_, LruIndex<A::Id>> {
+        // If some thread panicked while holding the lock the index is still consistent, as
+        // all the operations in it are done in one step.
+        match self.lru.lock() {
+            Ok(lru) => lru,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
     /// A manager is ready to be removed only if there are no more messages pending to be delivered,
     /// has no active actors and it is flagged to be ended.
     fn is_ready_to_be_removed(&self) -> bool {
         self.is_ending.load(Ordering::Acquire) && self.actors.is_empty() && self.sender.is_empty()
     }
 
-    pub(crate) fn get_sender(&self) -> Sender<ActorManagerProxyCommand<A>> {
-        self.sender.clone()
+    pub(crate) fn get_sender(&self) -> ManagerSender<A> {
+        ManagerSender {
+            sender: self.sender.clone(),
+            waits_for_room: self.waits_for_room,
+        }
     }
 
-    pub(crate) fn get_type_id(&self) -> TypeId {
-        TypeId::of::<A>()
+    pub(crate) fn get_type_name(&self) -> &'static str {
+        std::any::type_name::<A>()
     }
 
     pub(crate) fn get_statistics(&self) -> ActorsManagerReport {
@@ -130,33 +411,33 @@ impl<A: Actor> ActorsManager<A> {
         report
     }
 
-    pub(crate) fn remove_actor(&self, actor_id: A::Id) {
-        self.actors.remove(&actor_id);
-    }
-
     /// Returns the Entry of the actorProxy in the general HashMap, making not possible to send any messages
     /// until the Entry is droped.
-    pub(crate) fn get_blocking_actor_entry(&self, id: A::Id) -> Entry<A::Id, ActorProxy<A>> {
+    pub(crate) fn get_blocking_actor_entry(&self, id: A::Id) -> Entry<'_, A::Id, ActorProxy<A>> {
         self.actors.entry(id)
     }
-
-    fn is_empty(&self) -> bool {
-        self.actors.is_empty() && self.sender.is_empty()
-    }
 }
 
-async fn actor_manager_loop<'a, A: Actor>(
+async fn actor_manager_loop<A: Actor>(
     receiver: Receiver<ActorManagerProxyCommand<A>>,
     actors: Arc<DashMap<A::Id, ActorProxy<A>>>,
     actors_director: ActorsDirector,
     manager: ActorsManager<A>,
     is_ending: Arc<AtomicBool>,
     system_director: SystemDirector,
-    innactivity_duration_until_end: Duration,
+    configuration: ActorConfiguration,
 ) {
     while let Ok(command) = receiver.recv().await {
+        let _busy = manager.get_observer().busy();
+
         match command {
             ActorManagerProxyCommand::Dispatch(command) => {
+                #[cfg(feature = "cluster")]
+                let command = match forward_to_owner(command, &manager) {
+                    Some(command) => command,
+                    None => continue,
+                };
+
                 process_dispatch_command(
                     command,
                     &actors,
@@ -164,12 +445,25 @@ async fn actor_manager_loop<'a, A: Actor>(
                     &manager,
                     &is_ending,
                     &system_director,
-                    &innactivity_duration_until_end,
+                    &configuration,
+                )
+                .await;
+            }
+            #[cfg(feature = "cluster")]
+            ActorManagerProxyCommand::DispatchFromCluster(command) => {
+                process_dispatch_command(
+                    command,
+                    &actors,
+                    &actors_director,
+                    &manager,
+                    &is_ending,
+                    &system_director,
+                    &configuration,
                 )
                 .await;
             }
             ActorManagerProxyCommand::DispatchToAll(command) => {
-                process_dispatch_all_command(command, &actors).await;
+                process_dispatch_all_command(command, &actors, &manager).await;
             }
             ActorManagerProxyCommand::EndActor(actor_id) => {
                 process_end_actor_command(actor_id, &actors).await;
@@ -178,9 +472,35 @@ async fn actor_manager_loop<'a, A: Actor>(
     }
 }
 
-async fn process_end_actor_command<'a, A: Actor>(
+/// Sends the message to the node of the cluster owning the actor, if it is not this one.
+/// Returns the message back if it must be delivered here.
+#[cfg(feature = "cluster")]
+fn forward_to_owner<A: Actor>(
+    mut command: Box<dyn ManagerEnvelope<Actor = A>>,
+    manager: &ActorsManager<A>,
+) -> Option<Box<dyn ManagerEnvelope<Actor = A>>> {
+    let router = match manager.get_cluster() {
+        Some(router) => router,
+        None => return Some(command),
+    };
+
+    let route = match router.route::<A>(&command.get_actor_id()) {
+        Some(route) => route,
+        None => return Some(command),
+    };
+
+    match command.take_remote() {
+        Some(letter) => {
+            router.forward::<A>(route, letter, manager.get_clock());
+            None
+        }
+        None => Some(command),
+    }
+}
+
+async fn process_end_actor_command<A: Actor>(
     actor_id: A::Id,
-    actors: &'a Arc<DashMap<A::Id, ActorProxy<A>>>,
+    actors: &Arc<DashMap<A::Id, ActorProxy<A>>>,
 ) {
     if let Some(actor) = actors.get_mut(&actor_id) {
         actor.end();
@@ -194,22 +514,26 @@ async fn process_dispatch_command<'a, A: Actor>(
     manager: &'a ActorsManager<A>,
     is_ending: &'a Arc<AtomicBool>,
     system_director: &'a SystemDirector,
-    innactivity_duration_until_end: &'a Duration,
+    configuration: &'a ActorConfiguration,
 ) {
     let actor_id = command.get_actor_id();
 
     if let Some(mut actor) = actors.get_mut(&actor_id) {
         command.deliver(&mut actor).await;
+        manager.touch_actor(actor_id, &mut actor);
         return;
     }
 
-    let mut actor = ActorProxy::<A>::new(
-        system_director.clone(),
-        actors_director.clone(),
-        manager.clone(),
-        actor_id.clone(),
-        *innactivity_duration_until_end,
-    );
+    // The actor would be activated only to be ended again. If the sender asked to know it, the
+    // message is refused and, as no actor is added, we check if the manager can be removed.
+    if is_ending.load(Ordering::Relaxed) && command.refuse(SendError::ActorEnding) {
+        manager.signal_actor_removed().await;
+        return;
+    }
+
+    manager.make_room_for_new_actor().await;
+
+    let mut actor = ActorProxy::<A>::new(configuration);
 
     command.deliver(&mut actor).await;
 
@@ -217,15 +541,32 @@ async fn process_dispatch_command<'a, A: Actor>(
         actor.end();
     }
 
-    actors.insert(actor_id, actor);
+    manager.track_actor(actor_id.clone(), &mut actor);
+
+    actors.insert(actor_id.clone(), actor);
+
+    // The actor starts only once its proxy is in the map, so if the activation fails, the
+    // actor loop finds the proxy for removing it. Only this loop inserts or starts proxies.
+    if let Some(actor) = actors.get(&actor_id) {
+        actor.start(
+            system_director.clone(),
+            actors_director.clone(),
+            manager.clone(),
+            actor_id.clone(),
+            configuration.clone(),
+        );
+    }
 }
 
-async fn process_dispatch_all_command<'a, A: Actor>(
+async fn process_dispatch_all_command<A: Actor>(
     mut command: Box<dyn ManagerEnvelope<Actor = A>>,
-    actors: &'a Arc<DashMap<A::Id, ActorProxy<A>>>,
+    actors: &Arc<DashMap<A::Id, ActorProxy<A>>>,
+    manager: &ActorsManager<A>,
 ) {
     for mut actor in actors.iter_mut() {
         command.deliver(&mut actor).await;
+        let actor_id = actor.key().clone();
+        manager.touch_actor(actor_id, actor.value_mut());
     }
 }
 
@@ -234,8 +575,14 @@ impl<A: Actor> Clone for ActorsManager<A> {
         ActorsManager {
             actors: self.actors.clone(),
             sender: self.sender.clone(),
+            receiver: self.receiver.clone(),
             is_ending: self.is_ending.clone(),
             actors_director: self.actors_director.clone(),
+            lru: self.lru.clone(),
+            live_actors: self.live_actors.clone(),
+            max_actors: self.max_actors,
+            is_evictable: self.is_evictable,
+            waits_for_room: self.waits_for_room,
         }
     }
 }
@@ -246,25 +593,32 @@ impl<A: Actor> Manager for ActorsManager<A> {
         ActorsManager::<A>::end(self)
     }
 
-    fn get_type_id(&self) -> TypeId {
-        ActorsManager::<A>::get_type_id(self)
+    fn get_type_name(&self) -> &'static str {
+        ActorsManager::<A>::get_type_name(self)
     }
 
     fn get_statistics(&self) -> ActorsManagerReport {
         ActorsManager::<A>::get_statistics(self)
     }
 
-    fn is_empty(&self) -> bool {
-        ActorsManager::<A>::is_empty(self)
-    }
-
     fn get_sender_as_any(&self) -> Box<dyn Any> {
         Box::new(ActorsManager::<A>::get_sender(self))
     }
 
-    fn remove_actor(&self, actor_id: Box<dyn Any + Send>) {
-        if let Ok(actor_id) = actor_id.downcast::<A::Id>() {
-            ActorsManager::<A>::remove_actor(self, *actor_id)
-        }
+    fn get_least_recently_used_time(&self) -> Option<SystemTime> {
+        ActorsManager::<A>::get_least_recently_used_time(self)
+    }
+
+    fn evict_least_recently_used(&self) -> bool {
+        ActorsManager::<A>::evict_least_recently_used(self)
+    }
+
+    fn discard_pending_messages(&self) -> usize {
+        ActorsManager::<A>::discard_pending_messages(self)
+    }
+
+    #[cfg(feature = "testkit")]
+    fn has_pending_messages(&self) -> bool {
+        !self.sender.is_empty() || self.actors.iter().any(|actor| actor.get_inbox_length() > 0)
     }
 }
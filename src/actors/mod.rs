@@ -1,7 +1,10 @@
 pub mod actor;
+pub mod actor_ref;
 pub mod assistant;
 pub mod director;
 pub mod envelope;
 pub mod handle;
+pub mod lru;
+pub mod mailbox;
 pub mod manager;
 pub mod proxy;
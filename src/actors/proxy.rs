@@ -1,14 +1,22 @@
+use crate::actors::actor::{ActorConfiguration, Passivation};
 use crate::actors::director::ActorsDirector;
-use crate::actors::envelope::{Envelope, Letter, LetterWithResponder};
+use crate::actors::envelope::{Envelope, Letter, LetterWithResponder, Responder, SendReport};
+use crate::actors::lru::{ActorUsage, LruKey};
+use crate::actors::mailbox::Mailbox;
 use crate::actors::manager::ActorsManager;
+use crate::error::{CallError, SendError, TrySendError};
+use crate::middleware::{MessageContext, MiddlewareDecision};
+use crate::report::ActorReport;
+use crate::supervision::{panic_message, Failure, Supervision, SupervisorDecision};
 use crate::system_director::SystemDirector;
 use crate::{Actor, ActorAssistant, Receive, Respond};
-use async_channel::{unbounded as channel, Receiver, Sender};
-use async_std::{future, task};
 use dashmap::mapref::entry::Entry::Occupied;
+use futures::FutureExt;
+use std::any::type_name;
 use std::fmt::Debug;
-use std::time::Duration;
-use std::time::SystemTime;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
 
 #[derive(Debug)]
 pub(crate) enum ActorProxyCommand<A: Actor> {
@@ -16,86 +24,131 @@ pub(crate) enum ActorProxyCommand<A: Actor> {
     End,
 }
 
-pub struct ActorReport {
-    pub last_message_on: SystemTime,
-    pub enqueued_messages: usize,
-}
-
 #[derive(Debug)]
 pub(crate) struct ActorProxy<A: Actor> {
-    sender: Sender<ActorProxyCommand<A>>,
+    mailbox: Mailbox<A>,
     last_sent_message_time: SystemTime,
+    lru_key: LruKey,
+    usage: Arc<ActorUsage>,
 }
 
 impl<A: Actor> ActorProxy<A> {
-    pub fn new(
+    /// Creates the proxy with an empty mailbox. The actor is not activated until `start`.
+    pub fn new(configuration: &ActorConfiguration) -> ActorProxy<A> {
+        let mailbox = Mailbox::new(
+            configuration.mailbox_capacity,
+            configuration.mailbox_overflow,
+        );
+
+        ActorProxy {
+            mailbox,
+            last_sent_message_time: SystemTime::now(),
+            // The manager sets the real one when the actor is tracked
+            lru_key: (SystemTime::UNIX_EPOCH, 0),
+            usage: Arc::new(ActorUsage::new()),
+        }
+    }
+
+    /// Activates the actor and starts consuming its mailbox. The proxy must be already in the
+    /// manager, as the loop removes it from there if the actor fails.
+    pub fn start(
+        &self,
         system_director: SystemDirector,
         actors_director: ActorsDirector,
         manager: ActorsManager<A>,
         id: A::Id,
-        innactivity_duration_until_end: Duration,
-    ) -> ActorProxy<A> {
-        let (sender, receiver): (Sender<ActorProxyCommand<A>>, Receiver<ActorProxyCommand<A>>) =
-            channel();
-
+        configuration: ActorConfiguration,
+    ) {
         let assistant = ActorAssistant::new(system_director, actors_director, id.clone());
 
         actor_loop(
             id,
-            sender.clone(),
-            receiver,
+            self.mailbox.clone(),
+            self.usage.clone(),
             assistant,
             manager,
-            innactivity_duration_until_end,
+            configuration,
         );
+    }
 
-        ActorProxy {
-            sender,
-            last_sent_message_time: SystemTime::now(),
-        }
+    pub async fn send<M>(&mut self, message: M)
+    where
+        A: Receive<M>,
+        M: Send + Debug + 'static,
+    {
+        self.last_sent_message_time = SystemTime::now();
+
+        let message = Letter::<A, M>::new(message);
+
+        // If the message doesn't fit in the mailbox, the overflow policy already took care of it
+        let _ = self.mailbox.push(Box::new(message), true);
     }
 
-    pub async fn send<M: 'static>(&mut self, message: M)
+    /// Same as `send` but it never waits for room in the mailbox.
+    pub async fn try_send<M>(&mut self, message: M) -> Result<(), TrySendError>
     where
         A: Receive<M>,
-        M: Send + Debug,
+        M: Send + Debug + 'static,
     {
         self.last_sent_message_time = SystemTime::now();
 
         let message = Letter::<A, M>::new(message);
 
-        // TODO: Handle the channel disconnection properly
-        let _ = self
-            .sender
-            .send(ActorProxyCommand::Dispatch(Box::new(message)))
-            .await;
+        self.mailbox.push(Box::new(message), false)
+    }
+
+    /// Same as `send` but it reports if the message was enqueued. The report comes later if the
+    /// message waits for room in the mailbox.
+    pub async fn send_checked<M>(&mut self, message: M, report: SendReport)
+    where
+        A: Receive<M>,
+        M: Send + Debug + 'static,
+    {
+        // The actor failed and its loop won't take more messages.
+        if self.mailbox.is_closed() {
+            let _ = report.try_send(Err(SendError::MailboxClosed));
+            return;
+        }
+
+        self.last_sent_message_time = SystemTime::now();
+
+        let message = Letter::<A, M>::with_report(message, report);
+
+        // If the overflow policy discards the message, the letter reports why
+        let _ = self.mailbox.push(Box::new(message), true);
     }
 
-    pub async fn call<M: 'static>(
-        &mut self,
-        message: M,
-        responder: Sender<<A as Respond<M>>::Response>,
-    ) where
+    pub async fn call<M>(&mut self, message: M, responder: Responder<A, M>)
+    where
         A: Respond<M>,
-        M: Send + Debug,
+        M: Send + Debug + 'static,
     {
         self.last_sent_message_time = SystemTime::now();
 
         let message = LetterWithResponder::<A, M>::new(message, responder);
 
-        // TODO: Handle the channel disconnection properly
-        let _ = self
-            .sender
-            .send(ActorProxyCommand::Dispatch(Box::new(message)))
-            .await;
+        // If the message is discarded, the caller gets the error through the responder
+        let _ = self.mailbox.push(Box::new(message), true);
     }
 
     pub fn get_last_sent_message_time(&self) -> SystemTime {
         self.last_sent_message_time
     }
 
+    pub fn get_lru_key(&self) -> LruKey {
+        self.lru_key
+    }
+
+    pub fn set_lru_key(&mut self, key: LruKey) {
+        self.lru_key = key;
+    }
+
+    pub fn get_usage(&self) -> &ActorUsage {
+        &self.usage
+    }
+
     pub fn get_inbox_length(&self) -> usize {
-        self.sender.len()
+        self.mailbox.len()
     }
 
     pub fn get_report(&self) -> ActorReport {
@@ -105,30 +158,85 @@ impl<A: Actor> ActorProxy<A> {
         }
     }
 
+    /// Discards the messages waiting in the mailbox. Returns how many were discarded.
+    pub fn discard_pending_messages(&self) -> usize {
+        self.mailbox.discard_all(CallError::SystemStopping)
+    }
+
     pub fn end(&self) {
-        let sender = self.sender.clone();
-        task::spawn(async move {
-            let _ = sender.send(ActorProxyCommand::End).await;
-        });
+        self.mailbox.end();
     }
 }
 
 fn actor_loop<A: Actor>(
     id: A::Id,
-    sender: Sender<ActorProxyCommand<A>>,
-    receiver: Receiver<ActorProxyCommand<A>>,
+    mailbox: Mailbox<A>,
+    usage: Arc<ActorUsage>,
     assistant: ActorAssistant<A>,
     manager: ActorsManager<A>,
-    innactivity_duration_until_end: Duration,
+    configuration: ActorConfiguration,
 ) {
-    task::spawn(async move {
-        let mut actor = A::activate(id.clone(), &assistant).await;
+    manager.get_executor().clone().spawn(async move {
+        let activation = {
+            let _busy = manager.get_observer().busy();
+            supervised_activate(&id, &assistant, &manager).await
+        };
+
+        let mut actor = match activation {
+            Some(actor) => actor,
+            None => {
+                // If the actor cannot be activated, we remove it and all its messages are discarded.
+                remove_after_failure(&id, &mailbox, &assistant, &manager).await;
+                return;
+            }
+        };
+
+        manager.update_actor_memory(&usage, &actor);
+
+        manager.get_executor().clone().spawn(async move {
+            let passivation = configuration.passivation;
+            let supervision =
+                Supervision::new(configuration.supervision, configuration.max_restarts);
+            let mut handled_messages: usize = 0;
 
-        task::spawn(async move {
             loop {
-                match future::timeout(innactivity_duration_until_end, receiver.recv()).await {
-                    Ok(Ok(ActorProxyCommand::Dispatch(mut envelope))) => {
-                        envelope.dispatch(&mut actor, &assistant).await
+                let command = match passivation {
+                    Passivation::AfterInactivity(duration) => {
+                        manager.get_clock().timeout(duration, mailbox.recv()).await
+                    }
+                    _ => Some(mailbox.recv().await),
+                };
+
+                let _busy = manager.get_observer().busy();
+
+                match command {
+                    Some(Ok(ActorProxyCommand::Dispatch(envelope))) => {
+                        if !supervised_dispatch(
+                            envelope,
+                            &mut actor,
+                            &id,
+                            &assistant,
+                            &manager,
+                            &supervision,
+                        )
+                        .await
+                        {
+                            remove_after_failure(&id, &mailbox, &assistant, &manager).await;
+                            break;
+                        }
+
+                        manager.update_actor_memory(&usage, &actor);
+
+                        if let Passivation::AfterMessages(max_messages) = passivation {
+                            handled_messages += 1;
+
+                            // We only request the end once. If more messages arrive after the
+                            // End command, the ending process takes care of them. Zero messages
+                            // is the same as one, as the actor is activated by a message.
+                            if handled_messages == max_messages.max(1) {
+                                mailbox.end();
+                            }
+                        }
                     }
                     // The end process is a bit complicated. We don't want that if a End message
                     // is issued at the same time that someone else is sending a message we end
@@ -146,13 +254,23 @@ fn actor_loop<A: Actor>(
                     // This allows the method to stop the sending to this actor, to check any remaining
                     // remaining mesages in the queue, consume them (if any), requeue them (if any) and
                     // finally removing the actor or not from the managers HashMap
-                    Ok(Ok(ActorProxyCommand::End)) => {
+                    Some(Ok(ActorProxyCommand::End)) => {
                         // We may find cases where we can have several End command in a row.
                         // In that case, we want to consume all the end command together until
                         // we find nothing or a not-end command
-                        match recv_until_command_or_end!(receiver, ActorProxyCommand::End).await {
+                        match recv_until_command_or_end!(mailbox, ActorProxyCommand::End).await {
                             // We start the actor ending process.
                             None | Some(ActorProxyCommand::End) => {
+                                // We deactivate the actor before blocking the entry as we don't want to
+                                // block the message sending while the actor is releasing its resources.
+                                // As the ActorProxy is still in the manager HashMap, no other instance
+                                // of this actor can be created meanwhile.
+                                #[cfg(feature = "persistence")]
+                                assistant.snapshot_before_deactivate(&actor).await;
+                                supervised_deactivate(&mut actor).await;
+                                assistant.clear_subscriptions();
+                                manager.get_metrics().actor_deactivated::<A>();
+
                                 // We take the entry for this A::Id until we finish cleaning everything up.
                                 // This blocks any entry trying to get the ActorProxy in order to send messages.
                                 // Also, if any new message is sent to this actor, it will block the whole
@@ -163,23 +281,52 @@ fn actor_loop<A: Actor>(
 
                                 // We check again if there is any remainign message and, if any,
                                 // we requeue it and abort the ending.
-                                match recv_until_command_or_end!(receiver, ActorProxyCommand::End)
+                                match recv_until_command_or_end!(mailbox, ActorProxyCommand::End)
                                     .await
                                 {
-                                    Some(ActorProxyCommand::Dispatch(mut envelope)) => {
+                                    Some(ActorProxyCommand::Dispatch(envelope)) => {
                                         // We stop blocking the entry as we will continue receiving messages
                                         drop(entry);
+                                        // A message arrived while deactivating, so we bring the actor back
+                                        actor = match supervised_activate(&id, &assistant, &manager)
+                                            .await
+                                        {
+                                            Some(actor) => actor,
+                                            None => {
+                                                remove_after_failure(
+                                                    &id, &mailbox, &assistant, &manager,
+                                                )
+                                                .await;
+                                                break;
+                                            }
+                                        };
+                                        handled_messages = 0;
                                         // We postpone the ending of the actor
-                                        let _ = sender.send(ActorProxyCommand::End).await;
+                                        mailbox.end();
                                         // and process the found message
-                                        envelope.dispatch(&mut actor, &assistant).await
+                                        if !supervised_dispatch(
+                                            envelope,
+                                            &mut actor,
+                                            &id,
+                                            &assistant,
+                                            &manager,
+                                            &supervision,
+                                        )
+                                        .await
+                                        {
+                                            remove_after_failure(
+                                                &id, &mailbox, &assistant, &manager,
+                                            )
+                                            .await;
+                                            break;
+                                        }
+
+                                        manager.update_actor_memory(&usage, &actor);
                                     }
                                     None | Some(ActorProxyCommand::End) => {
                                         // If not messages are found, we just remove the actor from the HashMap
                                         if let Occupied(entry) = entry {
-                                            entry.remove();
-                                            // Signaling only when we really remove the actor.
-                                            manager.signal_actor_removed().await;
+                                            manager.remove_actor(entry).await;
                                         }
 
                                         // and stop the main loop
@@ -187,30 +334,189 @@ fn actor_loop<A: Actor>(
                                     }
                                 }
                             }
-                            Some(ActorProxyCommand::Dispatch(mut envelope)) => {
+                            Some(ActorProxyCommand::Dispatch(envelope)) => {
                                 // If there are any message left, we postpone the shutdown.
-                                let _ = sender.send(ActorProxyCommand::End).await;
+                                mailbox.end();
                                 // and process the found message
-                                envelope.dispatch(&mut actor, &assistant).await
+                                if !supervised_dispatch(
+                                    envelope,
+                                    &mut actor,
+                                    &id,
+                                    &assistant,
+                                    &manager,
+                                    &supervision,
+                                )
+                                .await
+                                {
+                                    remove_after_failure(&id, &mailbox, &assistant, &manager).await;
+                                    break;
+                                }
+
+                                manager.update_actor_memory(&usage, &actor);
                             }
                         }
                     }
-                    Ok(Err(_)) => {
+                    Some(Err(_)) => {
                         // TODO: The next comment is not fully right as seems that since async_std
                         // changed their channels in order to return an Result instead of an Option
                         // other type of errors can happen.
                         //
                         // `None` indicates that the channel is disconnected. In this case
                         // we end the actor proxy.
-                        let _ = sender.send(ActorProxyCommand::End).await;
+                        mailbox.end();
                     }
-                    Err(_) => {
+                    None => {
                         // This indicated timeout waiting for messages. In such case, we end
                         // the actor proxy
-                        let _ = sender.send(ActorProxyCommand::End).await;
+                        mailbox.end();
                     }
                 }
             }
         });
     });
 }
+
+/// Dispatches the envelope catching any panic from the handler. If the handler panics, the supervision
+/// strategy decides what to do. Returns false if the actor must be stopped.
+async fn supervised_dispatch<A: Actor>(
+    mut envelope: Box<dyn Envelope<Actor = A>>,
+    actor: &mut A,
+    id: &A::Id,
+    assistant: &ActorAssistant<A>,
+    manager: &ActorsManager<A>,
+    supervision: &Supervision,
+) -> bool {
+    // Nobody waits for the response, so the message is skipped as if it never arrived: no
+    // middleware, observer or metric sees it.
+    if envelope.is_abandoned() {
+        return true;
+    }
+
+    let message_type_name = envelope.message_type_name();
+    let middlewares = manager.get_middlewares();
+    let started = Instant::now();
+
+    // Returns the time spent in the handler, or None if a middleware rejected the message.
+    let result = AssertUnwindSafe(async {
+        let mut context = MessageContext::new(
+            type_name::<A>(),
+            Some(id),
+            message_type_name,
+            envelope.message_mut(),
+        );
+
+        if let MiddlewareDecision::Reject = middlewares.before::<A>(&mut context).await {
+            envelope.discard(CallError::Rejected);
+            return None;
+        }
+
+        manager.get_observer().record_actor_message::<A>(
+            id,
+            message_type_name,
+            envelope.message_debug(),
+        );
+
+        let started = Instant::now();
+        envelope.dispatch(actor, assistant).await;
+        let duration = started.elapsed();
+
+        let mut context = MessageContext::new(type_name::<A>(), Some(id), message_type_name, None);
+        middlewares.after::<A>(&mut context, duration).await;
+
+        Some(duration)
+    })
+    .catch_unwind()
+    .await;
+
+    // Dropping the envelope lets know any caller waiting for a response that the handler panicked.
+    drop(envelope);
+
+    let payload = match result {
+        Ok(Some(duration)) => {
+            manager
+                .get_metrics()
+                .actor_message_handled::<A>(message_type_name, duration);
+            manager
+                .get_shutdown_state()
+                .message_handled(type_name::<A>());
+            #[cfg(feature = "persistence")]
+            assistant.snapshot_after_message(actor).await;
+            return true;
+        }
+        Ok(None) => return true,
+        Err(payload) => payload,
+    };
+
+    manager
+        .get_metrics()
+        .actor_message_handled::<A>(message_type_name, started.elapsed());
+    manager
+        .get_shutdown_state()
+        .message_handled(type_name::<A>());
+
+    let failure = Failure {
+        type_name: type_name::<A>(),
+        actor_id: Some(format!("{:?}", id)),
+        panic_message: panic_message(payload),
+    };
+
+    let supervisor = manager.get_supervisor();
+
+    match supervision.decide(supervisor.as_deref(), &failure) {
+        SupervisorDecision::Restart => {
+            // The new instance makes its own subscriptions
+            assistant.clear_subscriptions();
+
+            match supervised_activate(id, assistant, manager).await {
+                Some(new_actor) => {
+                    *actor = new_actor;
+                    true
+                }
+                None => false,
+            }
+        }
+        SupervisorDecision::Stop => false,
+    }
+}
+
+/// Activates the actor. Returns None if the `activate` method panicked.
+async fn supervised_activate<A: Actor>(
+    id: &A::Id,
+    assistant: &ActorAssistant<A>,
+    manager: &ActorsManager<A>,
+) -> Option<A> {
+    let actor = AssertUnwindSafe(A::activate(id.clone(), assistant))
+        .catch_unwind()
+        .await
+        .ok();
+
+    if actor.is_some() {
+        manager.get_metrics().actor_activated::<A>();
+    }
+
+    actor
+}
+
+/// Deactivates the actor. If the `deactivate` method panics, the actor is removed anyway, as its
+/// state is discarded in both cases.
+async fn supervised_deactivate<A: Actor>(actor: &mut A) {
+    let _ = AssertUnwindSafe(actor.deactivate()).catch_unwind().await;
+}
+
+/// Removes a failed actor from the manager and its subscriptions. Its enqueued messages are
+/// discarded when the loop ends.
+async fn remove_after_failure<A: Actor>(
+    id: &A::Id,
+    mailbox: &Mailbox<A>,
+    assistant: &ActorAssistant<A>,
+    manager: &ActorsManager<A>,
+) {
+    assistant.clear_subscriptions();
+
+    // Nobody will take the messages waiting for room in the mailbox, so their senders are released.
+    mailbox.close();
+
+    if let Occupied(entry) = manager.get_blocking_actor_entry(id.clone()) {
+        manager.remove_actor(entry).await;
+    }
+}
@@ -0,0 +1,497 @@
+use crate::actors::actor_ref::ActorRef;
+use crate::error::{CallError, SendError, TrySendError};
+use crate::facade::Acteur;
+use crate::report::SystemReport;
+use crate::runtime;
+use crate::scheduler::ScheduleHandle;
+use crate::services::handle::{Listen, Serve};
+use crate::services::service::Service;
+use crate::shutdown::{ShutdownOptions, ShutdownSummary};
+use crate::{Actor, Receive, Respond};
+use std::fmt::Debug;
+use std::time::Duration;
+
+/// Same as [Acteur](./struct.Acteur.html), but with sync methods that block the current thread
+/// until they finish. Useful for sync code, like the `main` function or web frameworks without
+/// async support.
+///
+/// It is a handle to the same system as the `Acteur` it comes from, so both can be used at the
+/// same time. `BlockingActeur::new()` returns a handle to the global system, same as
+/// `Acteur::new()`, and `Acteur::blocking` returns one for any other system.
+///
+/// Blocking a thread of the async runtime may deadlock the whole system, so the methods panic if
+/// they are called from async code (for example, from a handler). There, use `Acteur` and await
+/// its methods instead. With the `tokio` feature, blocking is allowed inside multi-threaded
+/// runtimes.
+///
+/// ```rust,no_run
+/// use acteur::{Actor, ActorAssistant, BlockingActeur, Receive};
+/// use async_trait::async_trait;
+///
+/// #[derive(Debug)]
+/// struct Employee {
+///     salary: u32,
+/// }
+///
+/// #[async_trait]
+/// impl Actor for Employee {
+///     type Id = u32;
+///
+///     async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+///         Employee { salary: 0 }
+///     }
+/// }
+///
+/// #[derive(Debug)]
+/// struct SalaryChanged(u32);
+///
+/// #[async_trait]
+/// impl Receive<SalaryChanged> for Employee {
+///     async fn handle(&mut self, message: SalaryChanged, _: &ActorAssistant<Employee>) {
+///         self.salary = message.0;
+///     }
+/// }
+///
+/// let sys = BlockingActeur::new();
+///
+/// sys.send_to_actor::<Employee, SalaryChanged>(42, SalaryChanged(55000));
+///
+/// sys.stop();
+/// sys.wait_until_stopped();
+/// ```
+pub struct BlockingActeur {
+    acteur: Acteur,
+}
+
+impl Default for BlockingActeur {
+    fn default() -> Self {
+        BlockingActeur::new()
+    }
+}
+
+impl BlockingActeur {
+    /// Returns a handle to the global system, the same one returned by `Acteur::new()`.
+    pub fn new() -> BlockingActeur {
+        BlockingActeur::from_acteur(Acteur::new())
+    }
+
+    pub(crate) fn from_acteur(acteur: Acteur) -> BlockingActeur {
+        BlockingActeur { acteur }
+    }
+
+    /// Returns the async handle to this same system.
+    pub fn to_async(&self) -> Acteur {
+        self.acteur.clone()
+    }
+
+    /// Same as `Acteur::send_to_actor`.
+    pub fn send_to_actor<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        message: M,
+    ) {
+        runtime::block_on(self.acteur.send_to_actor::<A, M>(actor_id, message))
+    }
+
+    /// Same as `Acteur::send_to_actor_checked`.
+    pub fn send_to_actor_checked<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        message: M,
+    ) -> Result<(), SendError> {
+        runtime::block_on(self.acteur.send_to_actor_checked::<A, M>(actor_id, message))
+    }
+
+    /// Same as `Acteur::try_send_to_actor`.
+    pub fn try_send_to_actor<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        message: M,
+    ) -> Result<(), TrySendError> {
+        runtime::block_on(self.acteur.try_send_to_actor::<A, M>(actor_id, message))
+    }
+
+    /// Same as `Acteur::schedule_send_to_actor`.
+    pub fn schedule_send_to_actor<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        duration: Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        runtime::block_on(
+            self.acteur
+                .schedule_send_to_actor::<A, M>(actor_id, duration, message),
+        )
+    }
+
+    /// Same as `Acteur::schedule_interval_to_actor`.
+    pub fn schedule_interval_to_actor<A: Actor + Receive<M>, M: Debug + Send + Clone + 'static>(
+        &self,
+        actor_id: A::Id,
+        interval: Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        runtime::block_on(
+            self.acteur
+                .schedule_interval_to_actor::<A, M>(actor_id, interval, message),
+        )
+    }
+
+    /// Same as `Acteur::send_to_all_actors`.
+    pub fn send_to_all_actors<A: Actor + Receive<M>, M: Debug + Send + 'static>(&self, message: M) {
+        runtime::block_on(self.acteur.send_to_all_actors::<A, M>(message))
+    }
+
+    /// Same as `Acteur::schedule_send_to_all_actors`.
+    pub fn schedule_send_to_all_actors<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        duration: Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        runtime::block_on(
+            self.acteur
+                .schedule_send_to_all_actors::<A, M>(duration, message),
+        )
+    }
+
+    /// Same as `Acteur::schedule_interval_to_all_actors`.
+    pub fn schedule_interval_to_all_actors<
+        A: Actor + Receive<M>,
+        M: Debug + Send + Clone + 'static,
+    >(
+        &self,
+        interval: Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        runtime::block_on(
+            self.acteur
+                .schedule_interval_to_all_actors::<A, M>(interval, message),
+        )
+    }
+
+    /// Same as `Acteur::call_actor`.
+    pub fn call_actor<A: Actor + Respond<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        message: M,
+    ) -> Result<<A as Respond<M>>::Response, CallError> {
+        runtime::block_on(self.acteur.call_actor::<A, M>(actor_id, message))
+    }
+
+    /// Same as `Acteur::call_actor_with_timeout`.
+    pub fn call_actor_with_timeout<A: Actor + Respond<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        message: M,
+        timeout: Duration,
+    ) -> Result<<A as Respond<M>>::Response, CallError> {
+        runtime::block_on(
+            self.acteur
+                .call_actor_with_timeout::<A, M>(actor_id, message, timeout),
+        )
+    }
+
+    /// Same as `Acteur::actor_ref`, but the methods of the returned reference block the current
+    /// thread. See [BlockingActorRef](./struct.BlockingActorRef.html).
+    pub fn actor_ref<A: Actor>(&self, actor_id: A::Id) -> BlockingActorRef<A> {
+        BlockingActorRef {
+            actor_ref: runtime::block_on(self.acteur.actor_ref::<A>(actor_id)),
+        }
+    }
+
+    /// Same as `Acteur::send_to_service`.
+    pub fn send_to_service<S: Service + Listen<M>, M: Debug + Send + 'static>(&self, message: M) {
+        runtime::block_on(self.acteur.send_to_service::<S, M>(message))
+    }
+
+    /// Same as `Acteur::send_to_service_checked`.
+    pub fn send_to_service_checked<S: Service + Listen<M>, M: Debug + Send + 'static>(
+        &self,
+        message: M,
+    ) -> Result<(), SendError> {
+        runtime::block_on(self.acteur.send_to_service_checked::<S, M>(message))
+    }
+
+    /// Same as `Acteur::schedule_send_to_service`.
+    pub fn schedule_send_to_service<S: Service + Listen<M>, M: Debug + Send + 'static>(
+        &self,
+        duration: Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        runtime::block_on(
+            self.acteur
+                .schedule_send_to_service::<S, M>(duration, message),
+        )
+    }
+
+    /// Same as `Acteur::schedule_interval_to_service`.
+    pub fn schedule_interval_to_service<
+        S: Service + Listen<M>,
+        M: Debug + Send + Clone + 'static,
+    >(
+        &self,
+        interval: Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        runtime::block_on(
+            self.acteur
+                .schedule_interval_to_service::<S, M>(interval, message),
+        )
+    }
+
+    /// Same as `Acteur::call_service`.
+    pub fn call_service<S: Service + Serve<M>, M: Debug + Send + 'static>(
+        &self,
+        message: M,
+    ) -> Result<<S as Serve<M>>::Response, CallError> {
+        runtime::block_on(self.acteur.call_service::<S, M>(message))
+    }
+
+    /// Same as `Acteur::call_service_with_timeout`.
+    pub fn call_service_with_timeout<S: Service + Serve<M>, M: Debug + Send + 'static>(
+        &self,
+        message: M,
+        timeout: Duration,
+    ) -> Result<<S as Serve<M>>::Response, CallError> {
+        runtime::block_on(
+            self.acteur
+                .call_service_with_timeout::<S, M>(message, timeout),
+        )
+    }
+
+    /// Same as `Acteur::stop`. It doesn't wait for the system to stop, use `wait_until_stopped`
+    /// for that.
+    pub fn stop(&self) {
+        self.acteur.stop()
+    }
+
+    /// Same as `Acteur::preload_service`.
+    pub fn preload_service<S: Service>(&self) {
+        runtime::block_on(self.acteur.preload_service::<S>())
+    }
+
+    /// Same as `Acteur::wait_until_stopped`.
+    pub fn wait_until_stopped(&self) {
+        runtime::block_on(self.acteur.wait_until_stopped())
+    }
+
+    /// Same as `Acteur::shutdown`.
+    pub fn shutdown(&self, options: ShutdownOptions) -> ShutdownSummary {
+        runtime::block_on(self.acteur.shutdown(options))
+    }
+
+    /// Same as `Acteur::get_statistics`.
+    pub fn get_statistics(&self) -> SystemReport {
+        self.acteur.get_statistics()
+    }
+
+    /// Same as `Acteur::render_metrics`.
+    #[cfg(feature = "metrics")]
+    pub fn render_metrics(&self) -> String {
+        self.acteur.render_metrics()
+    }
+
+    /// Same as `Acteur::publish`.
+    pub fn publish<M: Send + Clone + 'static>(&mut self, message: M) {
+        runtime::block_on(self.acteur.publish(message))
+    }
+
+    /// Same as `Acteur::publish_to_topic`.
+    pub fn publish_to_topic<M: Send + Clone + 'static>(&mut self, topic: &str, message: M) {
+        runtime::block_on(self.acteur.publish_to_topic(topic, message))
+    }
+
+    /// Same as `Acteur::subscribe_all_actors`.
+    pub fn subscribe_all_actors<A: Actor + Receive<M>, M: Sync + Send + Clone + Debug + 'static>(
+        &self,
+    ) {
+        self.acteur.subscribe_all_actors::<A, M>()
+    }
+
+    /// Same as `Acteur::subscribe_all_actors_to_topic`.
+    pub fn subscribe_all_actors_to_topic<
+        A: Actor + Receive<M>,
+        M: Sync + Send + Clone + Debug + 'static,
+    >(
+        &self,
+        topic_pattern: &str,
+    ) {
+        self.acteur
+            .subscribe_all_actors_to_topic::<A, M>(topic_pattern)
+    }
+
+    /// Same as `Acteur::unsubscribe_all_actors`.
+    pub fn unsubscribe_all_actors<
+        A: Actor + Receive<M>,
+        M: Sync + Send + Clone + Debug + 'static,
+    >(
+        &self,
+    ) {
+        self.acteur.unsubscribe_all_actors::<A, M>()
+    }
+
+    /// Same as `Acteur::unsubscribe_all_actors_from_topic`.
+    pub fn unsubscribe_all_actors_from_topic<
+        A: Actor + Receive<M>,
+        M: Sync + Send + Clone + Debug + 'static,
+    >(
+        &self,
+        topic_pattern: &str,
+    ) {
+        self.acteur
+            .unsubscribe_all_actors_from_topic::<A, M>(topic_pattern)
+    }
+}
+
+impl Debug for BlockingActeur {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "BlockingActeur ()")
+    }
+}
+
+impl Clone for BlockingActeur {
+    fn clone(&self) -> Self {
+        BlockingActeur {
+            acteur: self.acteur.clone(),
+        }
+    }
+}
+
+/// Same as [ActorRef](./struct.ActorRef.html), but with sync methods that block the current
+/// thread until they finish. Returned by
+/// [BlockingActeur::actor_ref](./struct.BlockingActeur.html#method.actor_ref).
+///
+/// As with `BlockingActeur`, the methods panic if they are called from async code. There, use
+/// the `ActorRef` returned by `as_async` instead.
+///
+/// ```rust,no_run
+/// use acteur::{Actor, ActorAssistant, BlockingActeur, Receive, Respond};
+/// use async_trait::async_trait;
+///
+/// #[derive(Debug)]
+/// struct Counter(u32);
+///
+/// #[async_trait]
+/// impl Actor for Counter {
+///     type Id = u32;
+///
+///     async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+///         Counter(0)
+///     }
+/// }
+///
+/// #[derive(Debug)]
+/// struct Increment;
+///
+/// #[async_trait]
+/// impl Receive<Increment> for Counter {
+///     async fn handle(&mut self, _: Increment, _: &ActorAssistant<Counter>) {
+///         self.0 += 1;
+///     }
+/// }
+///
+/// #[derive(Debug)]
+/// struct Get;
+///
+/// #[async_trait]
+/// impl Respond<Get> for Counter {
+///     type Response = u32;
+///
+///     async fn handle(&mut self, _: Get, _: &ActorAssistant<Counter>) -> u32 {
+///         self.0
+///     }
+/// }
+///
+/// let sys = BlockingActeur::new();
+///
+/// let counter = sys.actor_ref::<Counter>(42);
+///
+/// counter.send(Increment);
+/// counter.send(Increment);
+///
+/// assert_eq!(counter.call(Get), Ok(2));
+///
+/// sys.stop();
+/// sys.wait_until_stopped();
+/// ```
+pub struct BlockingActorRef<A: Actor> {
+    actor_ref: ActorRef<A>,
+}
+
+impl<A: Actor> BlockingActorRef<A> {
+    /// Returns the Id of the referenced actor.
+    pub fn id(&self) -> &A::Id {
+        self.actor_ref.id()
+    }
+
+    /// Returns the async version of this reference, for sending messages from async code.
+    pub fn as_async(&self) -> ActorRef<A> {
+        self.actor_ref.clone()
+    }
+
+    /// Same as `ActorRef::send`.
+    pub fn send<M: Debug + Send + 'static>(&self, message: M)
+    where
+        A: Receive<M>,
+    {
+        runtime::block_on(self.actor_ref.send::<M>(message))
+    }
+
+    /// Same as `ActorRef::send_checked`.
+    pub fn send_checked<M: Debug + Send + 'static>(&self, message: M) -> Result<(), SendError>
+    where
+        A: Receive<M>,
+    {
+        runtime::block_on(self.actor_ref.send_checked::<M>(message))
+    }
+
+    /// Same as `ActorRef::try_send`.
+    pub fn try_send<M: Debug + Send + 'static>(&self, message: M) -> Result<(), TrySendError>
+    where
+        A: Receive<M>,
+    {
+        runtime::block_on(self.actor_ref.try_send::<M>(message))
+    }
+
+    /// Same as `ActorRef::call`.
+    pub fn call<M: Debug + Send + 'static>(
+        &self,
+        message: M,
+    ) -> Result<<A as Respond<M>>::Response, CallError>
+    where
+        A: Respond<M>,
+    {
+        runtime::block_on(self.actor_ref.call::<M>(message))
+    }
+
+    /// Same as `ActorRef::call_with_timeout`.
+    pub fn call_with_timeout<M: Debug + Send + 'static>(
+        &self,
+        message: M,
+        timeout: Duration,
+    ) -> Result<<A as Respond<M>>::Response, CallError>
+    where
+        A: Respond<M>,
+    {
+        runtime::block_on(self.actor_ref.call_with_timeout::<M>(message, timeout))
+    }
+}
+
+impl<A: Actor> Clone for BlockingActorRef<A> {
+    fn clone(&self) -> Self {
+        BlockingActorRef {
+            actor_ref: self.actor_ref.clone(),
+        }
+    }
+}
+
+impl<A: Actor> Debug for BlockingActorRef<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "BlockingActorRef for {} ({:?})",
+            std::any::type_name::<A>(),
+            self.actor_ref.id()
+        )
+    }
+}
@@ -0,0 +1,182 @@
+#[cfg(feature = "cluster")]
+use crate::cluster::Cluster;
+use crate::facade::Acteur;
+use crate::middleware::Middleware;
+#[cfg(feature = "persistence")]
+use crate::persistence::journal::Journal;
+#[cfg(feature = "persistence")]
+use crate::persistence::snapshot::SnapshotStore;
+use crate::runtime::Executor;
+use crate::supervision::Supervisor;
+use crate::system_director::{SystemConfiguration, SystemDirector};
+use crate::Actor;
+use std::any::TypeId;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Builds independent Acteur systems.
+///
+/// `Acteur::new()` always returns the same global system. If you need more than one system
+/// (for example, one per test) or you need to configure it, use `Acteur::builder()`.
+///
+/// Each built system has its own actors and services. Sending a message to an actor in one
+/// system will never reach the actor with the same Id in another system. Stopping one system
+/// doesn't affect the others.
+///
+/// ```rust,no_run
+/// use acteur::Acteur;
+/// use std::time::Duration;
+///
+/// let sys = Acteur::builder()
+///     .actors_inactivity_timeout(Duration::from_secs(60))
+///     .max_actors(100_000)
+///     .build();
+///
+/// // This system is independent from the one returned by `Acteur::new()`
+/// let other_sys = Acteur::builder().build();
+///
+/// sys.stop();
+/// other_sys.stop();
+///
+/// sys.blocking().wait_until_stopped();
+/// other_sys.blocking().wait_until_stopped();
+/// ```
+#[derive(Debug)]
+pub struct ActeurBuilder {
+    configuration: SystemConfiguration,
+}
+
+impl Default for ActeurBuilder {
+    fn default() -> Self {
+        ActeurBuilder::new()
+    }
+}
+
+impl ActeurBuilder {
+    /// Creates a builder with the default configuration.
+    pub fn new() -> ActeurBuilder {
+        ActeurBuilder {
+            configuration: SystemConfiguration::default(),
+        }
+    }
+
+    /// Sets how long an actor can stay without receiving messages before being deallocated.
+    /// By default, 5 minutes. Actors can override this with their own
+    /// [Passivation](./enum.Passivation.html) policy.
+    pub fn actors_inactivity_timeout(mut self, duration: Duration) -> ActeurBuilder {
+        self.configuration.innactivity_duration_until_actor_end = duration;
+        self
+    }
+
+    /// Sets the maximum number of actors, of any type, alive at the same time.
+    ///
+    /// When the limit is reached and a new actor needs to be activated, the least recently
+    /// used actor (the one that received a message longest ago) is asked to end. It will
+    /// consume all its enqueued messages and then it will be deactivated as usual, so no
+    /// messages are lost. Actors being evicted don't count for the limit. Actors with
+    /// `Passivation::Never` count for the limit, but they are never evicted.
+    pub fn max_actors(mut self, max_actors: usize) -> ActeurBuilder {
+        self.configuration.max_actors = Some(max_actors);
+        self
+    }
+
+    /// Same as `max_actors` but only for the actors of type A. Only actors of type A
+    /// will be evicted when this limit is reached.
+    pub fn max_actors_of<A: Actor>(mut self, max_actors: usize) -> ActeurBuilder {
+        self.configuration
+            .max_actors_per_type
+            .insert(TypeId::of::<A>(), max_actors);
+        self
+    }
+
+    /// Sets a memory budget, in bytes, for all the actors alive at the same time. Actors
+    /// are evicted the same way as with `max_actors`.
+    ///
+    /// The memory of each actor is the one returned by its
+    /// [estimated_memory](./trait.Actor.html#method.estimated_memory) method, which by default
+    /// only counts the size of its struct. Until an actor is activated, it counts the size of
+    /// its struct.
+    pub fn actors_memory_budget(mut self, bytes: usize) -> ActeurBuilder {
+        self.configuration.actors_memory_budget = Some(bytes);
+        self
+    }
+
+    /// Sets the supervisor that decides what to do when an actor or service using the
+    /// `SupervisionStrategy::Escalate` strategy panics.
+    pub fn supervisor<S: Supervisor>(mut self, supervisor: S) -> ActeurBuilder {
+        self.configuration.supervisor = Some(Arc::new(supervisor));
+        self
+    }
+
+    /// Sets a default timeout for `call_actor` and `call_service`. Calls that don't get a
+    /// response on time return `CallError::Timeout`. By default, calls wait forever.
+    ///
+    /// The methods `call_actor_with_timeout` and `call_service_with_timeout` ignore this default.
+    pub fn call_timeout(mut self, timeout: Duration) -> ActeurBuilder {
+        self.configuration.call_timeout = Some(timeout);
+        self
+    }
+
+    /// Adds a [Middleware](./trait.Middleware.html) that runs for every message handled by any
+    /// actor or service of the system.
+    pub fn middleware<M: Middleware>(mut self, middleware: M) -> ActeurBuilder {
+        self.configuration.middlewares.add(Arc::new(middleware));
+        self
+    }
+
+    /// Adds a [Middleware](./trait.Middleware.html) that only runs for the messages handled by
+    /// the actor or service of type T. They run after the system wide middlewares.
+    pub fn middleware_for<T: 'static, M: Middleware>(mut self, middleware: M) -> ActeurBuilder {
+        self.configuration
+            .middlewares
+            .add_for::<T>(Arc::new(middleware));
+        self
+    }
+
+    /// Sets the [Journal](./trait.Journal.html) where the
+    /// [persistent actors](./trait.PersistentActor.html) store their events. By default, an
+    /// [InMemoryJournal](./struct.InMemoryJournal.html). Only available with the `persistence` feature.
+    #[cfg(feature = "persistence")]
+    pub fn journal<J: Journal>(mut self, journal: J) -> ActeurBuilder {
+        self.configuration.journal = Arc::new(journal);
+        self
+    }
+
+    /// Sets the [SnapshotStore](./trait.SnapshotStore.html) where the
+    /// [snapshot actors](./trait.SnapshotActor.html) save their state. By default, an
+    /// [InMemorySnapshotStore](./struct.InMemorySnapshotStore.html). Only available with the
+    /// `persistence` feature.
+    #[cfg(feature = "persistence")]
+    pub fn snapshot_store<S: SnapshotStore>(mut self, snapshot_store: S) -> ActeurBuilder {
+        self.configuration.snapshot_store = Arc::new(snapshot_store);
+        self
+    }
+
+    /// Makes the system a node of a [Cluster](./struct.Cluster.html). Only available with the
+    /// `cluster` feature.
+    #[cfg(feature = "cluster")]
+    pub fn cluster(mut self, cluster: Cluster) -> ActeurBuilder {
+        self.configuration.cluster = Some(cluster);
+        self
+    }
+
+    #[cfg(feature = "testkit")]
+    pub(crate) fn into_configuration(self) -> SystemConfiguration {
+        SystemConfiguration {
+            executor: Executor::current(),
+            ..self.configuration
+        }
+    }
+
+    /// Creates a new system, independent of any other system.
+    ///
+    /// With the `tokio` feature, the tasks of the system run in the tokio runtime calling this
+    /// method, or in a runtime owned by Acteur if there is none. So, the system keeps working
+    /// even if it is used later from other runtimes.
+    pub fn build(self) -> Acteur {
+        Acteur::from_system_director(SystemDirector::new(SystemConfiguration {
+            executor: Executor::current(),
+            ..self.configuration
+        }))
+    }
+}
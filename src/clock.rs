@@ -0,0 +1,70 @@
+use crate::runtime;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "testkit")]
+use crate::testkit::clock::VirtualClock;
+#[cfg(feature = "testkit")]
+use std::sync::Arc;
+
+// The time as seen by a system. The scheduler and the passivation of actors wait through it,
+// so the testkit can replace the real time with a virtual one that only moves when the test
+// advances it.
+//
+// Without the `testkit` feature this structure is empty and it always uses the real time, so the
+// rest of the code doesn't need to care about the feature.
+
+#[cfg(feature = "testkit")]
+#[derive(Debug, Clone)]
+pub(crate) enum Clock {
+    Real,
+    Virtual(Arc<VirtualClock>),
+}
+
+#[cfg(not(feature = "testkit"))]
+#[derive(Debug, Clone)]
+pub(crate) struct Clock;
+
+#[cfg(feature = "testkit")]
+impl Clock {
+    pub(crate) fn real() -> Clock {
+        Clock::Real
+    }
+
+    pub(crate) fn now(&self) -> Instant {
+        match self {
+            Clock::Real => Instant::now(),
+            Clock::Virtual(clock) => clock.now(),
+        }
+    }
+
+    /// Waits for the future at most the duration. Returns None if the duration passes first.
+    pub(crate) async fn timeout<F>(&self, duration: Duration, future: F) -> Option<F::Output>
+    where
+        F: Future + Send,
+    {
+        match self {
+            Clock::Real => runtime::timeout(duration, future).await,
+            Clock::Virtual(clock) => clock.timeout(duration, future).await,
+        }
+    }
+}
+
+#[cfg(not(feature = "testkit"))]
+impl Clock {
+    pub(crate) fn real() -> Clock {
+        Clock
+    }
+
+    pub(crate) fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    /// Waits for the future at most the duration. Returns None if the duration passes first.
+    pub(crate) async fn timeout<F>(&self, duration: Duration, future: F) -> Option<F::Output>
+    where
+        F: Future + Send,
+    {
+        runtime::timeout(duration, future).await
+    }
+}
@@ -0,0 +1,227 @@
+pub(crate) mod registry;
+pub(crate) mod router;
+pub(crate) mod transport;
+
+use crate::cluster::registry::ClusterRegistry;
+use crate::cluster::router::ClusterRouter;
+use crate::{Actor, Receive, Respond};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::io;
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A node of a cluster of systems connected by TCP. Only available with the `cluster` feature.
+///
+/// Each actor of the types registered with `actor` lives in only one node of the cluster,
+/// chosen from the actor type name and its Id. The messages sent to it from any other node,
+/// with `send_to_actor`, `call_actor` or an [ActorRef](./struct.ActorRef.html), are forwarded
+/// to that node. So all the nodes must know each other by the same names and register the same
+/// actors and messages, with the same names.
+///
+/// Actors and messages are identified by the names given when registering them instead of by
+/// their Rust type, so they are serialized with [serde](https://serde.rs). Actor types not
+/// registered always live in the node where they are used, and the messages sent to a
+/// registered actor without being registered are discarded (their callers get
+/// `CallError::Serialization`, or `SendError::Serialization` with `send_to_actor_checked`).
+///
+/// Keep in mind:
+///
+///  - Messages sent from one node to an actor arrive in the order they were sent. Messages sent
+///    from different nodes can arrive in any order.
+///  - If a node cannot be reached, the messages for it are discarded and the calls waiting
+///    for a response get `CallError::NodeUnreachable`. `send_to_actor_checked` and
+///    `try_send_to_actor` wait until the message is written to the connection, so they get
+///    `NodeUnreachable` as well.
+///  - Calls with a timeout are skipped by the node owning the actor, the same as local ones, if
+///    the timeout expires before the actor handles them.
+///  - `send_to_all_actors`, publications and the statistics only involve the actors living in
+///    the node where they are used.
+///  - The nodes of the cluster cannot change while it runs.
+///
+/// ```rust,no_run
+/// use acteur::{Acteur, Actor, ActorAssistant, Cluster, Respond};
+/// use async_trait::async_trait;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug)]
+/// struct Counter(u32);
+///
+/// #[async_trait]
+/// impl Actor for Counter {
+///     type Id = u32;
+///
+///     async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+///         Counter(0)
+///     }
+/// }
+///
+/// #[derive(Debug, Serialize, Deserialize)]
+/// struct Increment;
+///
+/// #[async_trait]
+/// impl Respond<Increment> for Counter {
+///     type Response = u32;
+///
+///     async fn handle(&mut self, _: Increment, _: &ActorAssistant<Counter>) -> u32 {
+///         self.0 += 1;
+///         self.0
+///     }
+/// }
+///
+/// // Same registrations in every node
+/// fn register(cluster: Cluster) -> Cluster {
+///     cluster
+///         .actor::<Counter>("counter")
+///         .respond::<Counter, Increment>("increment")
+/// }
+///
+/// # fn main() -> std::io::Result<()> {
+/// // Two nodes in the same process, listening in any free port
+/// let node_a = Cluster::bind("a", "127.0.0.1:0")?;
+/// let node_b = Cluster::bind("b", "127.0.0.1:0")?;
+///
+/// let (address_a, address_b) = (node_a.local_addr(), node_b.local_addr());
+///
+/// let sys_a = Acteur::builder()
+///     .cluster(register(node_a.node("b", address_b)))
+///     .build()
+///     .blocking();
+///
+/// let sys_b = Acteur::builder()
+///     .cluster(register(node_b.node("a", address_a)))
+///     .build()
+///     .blocking();
+///
+/// // The counter lives in one of them, but both nodes see the same one.
+/// assert_eq!(sys_a.call_actor::<Counter, Increment>(42, Increment), Ok(1));
+/// assert_eq!(sys_b.call_actor::<Counter, Increment>(42, Increment), Ok(2));
+///
+/// sys_a.stop();
+/// sys_b.stop();
+/// sys_a.wait_until_stopped();
+/// sys_b.wait_until_stopped();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Cluster {
+    node: String,
+    listener: TcpListener,
+    nodes: HashMap<String, SocketAddr>,
+    connect_timeout: Duration,
+    registry: ClusterRegistry,
+}
+
+impl Cluster {
+    /// Creates the node with the given name, listening for the other nodes in the address.
+    /// Use the port 0 for any free port, then `local_addr` returns the one chosen.
+    pub fn bind<T: ToSocketAddrs>(node: &str, address: T) -> io::Result<Cluster> {
+        Ok(Cluster {
+            node: node.to_string(),
+            listener: TcpListener::bind(address)?,
+            nodes: HashMap::new(),
+            connect_timeout: Duration::from_secs(5),
+            registry: ClusterRegistry::default(),
+        })
+    }
+
+    /// Returns the address where this node listens.
+    pub fn local_addr(&self) -> SocketAddr {
+        match self.listener.local_addr() {
+            Ok(address) => address,
+            // Bound listeners always have an address
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Adds another node of the cluster, with the address where it listens.
+    pub fn node(mut self, node: &str, address: SocketAddr) -> Cluster {
+        if node != self.node {
+            self.nodes.insert(node.to_string(), address);
+        }
+
+        self
+    }
+
+    /// Sets how long to wait when connecting to another node. When it expires, the node is
+    /// considered unreachable. By default, 5 seconds.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Cluster {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Spreads the actors of type A over the nodes of the cluster, using the name for
+    /// identifying the type in every node.
+    ///
+    /// # Panics
+    ///
+    /// If the name is already used by another actor type.
+    pub fn actor<A: Actor>(mut self, name: &str) -> Cluster
+    where
+        A::Id: Serialize + DeserializeOwned,
+    {
+        self.registry.add_actor::<A>(name);
+        self
+    }
+
+    /// Allows sending messages of type M to actors of type A living in other nodes, using the
+    /// name for identifying the message type in every node.
+    ///
+    /// # Panics
+    ///
+    /// If the actor type is not registered with `actor` before.
+    pub fn receive<
+        A: Actor + Receive<M>,
+        M: Debug + Send + Serialize + DeserializeOwned + 'static,
+    >(
+        mut self,
+        name: &str,
+    ) -> Cluster
+    where
+        A::Id: Serialize + DeserializeOwned,
+    {
+        self.registry.add_message::<A, M>(name);
+        self
+    }
+
+    /// Same as `receive` but for calling actors of type A with messages of type M. The
+    /// response is serialized too.
+    ///
+    /// # Panics
+    ///
+    /// If the actor type is not registered with `actor` before.
+    pub fn respond<
+        A: Actor + Respond<M>,
+        M: Debug + Send + Serialize + DeserializeOwned + 'static,
+    >(
+        mut self,
+        name: &str,
+    ) -> Cluster
+    where
+        A::Id: Serialize + DeserializeOwned,
+        <A as Respond<M>>::Response: Serialize + DeserializeOwned,
+    {
+        self.registry.add_call::<A, M>(name);
+        self
+    }
+
+    /// Splits the node in the router, used by the actor managers, and the listener, that must
+    /// be started once the system exists.
+    pub(crate) fn into_router(self) -> (Arc<ClusterRouter>, TcpListener) {
+        let local_addr = self.local_addr();
+
+        let router = ClusterRouter::new(
+            self.node,
+            local_addr,
+            self.nodes,
+            self.connect_timeout,
+            self.registry,
+        );
+
+        (Arc::new(router), self.listener)
+    }
+}
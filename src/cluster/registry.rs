@@ -0,0 +1,366 @@
+use crate::actors::director::ActorsDirector;
+use crate::actors::envelope::Responder;
+use crate::cluster::transport::WriteReport;
+use crate::error::{CallError, SendError};
+use crate::{Actor, Receive, Respond};
+use futures::future::BoxFuture;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::any::{type_name, Any, TypeId};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::time::Instant;
+
+/// How a message is handled by the actor: without response (`Receive`) or with one (`Respond`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum MessageKind {
+    Send,
+    Call,
+}
+
+impl MessageKind {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            MessageKind::Send => "send",
+            MessageKind::Call => "call",
+        }
+    }
+
+    pub(crate) fn parse(kind: &str) -> Option<MessageKind> {
+        match kind {
+            "send" => Some(MessageKind::Send),
+            "call" => Some(MessageKind::Call),
+            _ => None,
+        }
+    }
+}
+
+/// The response of a call enqueued in a local actor, already serialized.
+pub(crate) type RemoteResponse = BoxFuture<'static, Result<Value, CallError>>;
+
+/// Enqueues a message that came from another node in the local actor. For calls, it returns the
+/// response as a future, so the connection can go on with the next messages meanwhile.
+pub(crate) type Deliver = fn(
+    ActorsDirector,
+    Value,
+    Value,
+) -> BoxFuture<'static, Result<Option<RemoteResponse>, CallError>>;
+
+/// Serializes a value known to be of the type registered.
+type Encode = fn(&dyn Any) -> serde_json::Result<Value>;
+
+/// Deserializes the response of a call into the type the caller waits for.
+type DecodeResponse = fn(Value) -> serde_json::Result<Box<dyn Any + Send>>;
+
+#[derive(Debug)]
+struct RemoteActor {
+    name: String,
+    encode_id: Encode,
+}
+
+/// How to send a message type to the actors of another node.
+#[derive(Debug)]
+pub(crate) struct OutgoingMessage {
+    pub(crate) name: String,
+    pub(crate) encode: Encode,
+    pub(crate) decode_response: Option<DecodeResponse>,
+}
+
+/// Stable names, and serialization, of the actors and messages that can go from one node to
+/// another. `TypeId`s can change between builds, so nodes only agree on names.
+#[derive(Debug, Default)]
+pub(crate) struct ClusterRegistry {
+    actors: HashMap<TypeId, RemoteActor>,
+    outgoing: HashMap<(TypeId, TypeId, MessageKind), OutgoingMessage>,
+    incoming: HashMap<(String, String, MessageKind), Deliver>,
+}
+
+impl ClusterRegistry {
+    pub(crate) fn add_actor<A: Actor>(&mut self, name: &str)
+    where
+        A::Id: Serialize + DeserializeOwned,
+    {
+        if self
+            .actors
+            .iter()
+            .any(|(type_id, actor)| actor.name == name && *type_id != TypeId::of::<A>())
+        {
+            panic!(
+                "The name {:?} is already used by another actor of the cluster",
+                name
+            );
+        }
+
+        self.actors.insert(
+            TypeId::of::<A>(),
+            RemoteActor {
+                name: name.to_string(),
+                encode_id: encode::<A::Id>,
+            },
+        );
+    }
+
+    pub(crate) fn add_message<
+        A: Actor + Receive<M>,
+        M: Debug + Send + Serialize + DeserializeOwned + 'static,
+    >(
+        &mut self,
+        name: &str,
+    ) where
+        A::Id: DeserializeOwned,
+    {
+        let actor_name = self.get_actor_name::<A>();
+
+        self.outgoing.insert(
+            (TypeId::of::<A>(), TypeId::of::<M>(), MessageKind::Send),
+            OutgoingMessage {
+                name: name.to_string(),
+                encode: encode::<M>,
+                decode_response: None,
+            },
+        );
+
+        self.incoming.insert(
+            (actor_name, name.to_string(), MessageKind::Send),
+            deliver_message::<A, M>,
+        );
+    }
+
+    pub(crate) fn add_call<
+        A: Actor + Respond<M>,
+        M: Debug + Send + Serialize + DeserializeOwned + 'static,
+    >(
+        &mut self,
+        name: &str,
+    ) where
+        A::Id: DeserializeOwned,
+        <A as Respond<M>>::Response: Serialize + DeserializeOwned,
+    {
+        let actor_name = self.get_actor_name::<A>();
+
+        self.outgoing.insert(
+            (TypeId::of::<A>(), TypeId::of::<M>(), MessageKind::Call),
+            OutgoingMessage {
+                name: name.to_string(),
+                encode: encode::<M>,
+                decode_response: Some(decode::<<A as Respond<M>>::Response>),
+            },
+        );
+
+        self.incoming.insert(
+            (actor_name, name.to_string(), MessageKind::Call),
+            deliver_call::<A, M>,
+        );
+    }
+
+    fn get_actor_name<A: Actor>(&self) -> String {
+        match self.actors.get(&TypeId::of::<A>()) {
+            Some(actor) => actor.name.clone(),
+            None => panic!(
+                "The actor {} must be registered with `Cluster::actor` before its messages",
+                type_name::<A>()
+            ),
+        }
+    }
+
+    /// Returns the name of the actor type and its Id serialized, or None if the actor type is
+    /// not registered (so it always lives in the node where it is used).
+    pub(crate) fn encode_actor_id<A: Actor>(&self, actor_id: &A::Id) -> Option<(&str, Value)> {
+        let actor = self.actors.get(&TypeId::of::<A>())?;
+
+        // If the Id cannot be serialized it cannot be sent anywhere, so the actor stays here.
+        match (actor.encode_id)(actor_id) {
+            Ok(actor_id) => Some((&actor.name, actor_id)),
+            Err(_) => None,
+        }
+    }
+
+    pub(crate) fn get_outgoing<A: Actor>(
+        &self,
+        message_type: TypeId,
+        kind: MessageKind,
+    ) -> Option<&OutgoingMessage> {
+        self.outgoing.get(&(TypeId::of::<A>(), message_type, kind))
+    }
+
+    pub(crate) fn get_incoming(
+        &self,
+        actor: &str,
+        message: &str,
+        kind: MessageKind,
+    ) -> Option<Deliver> {
+        self.incoming
+            .get(&(actor.to_string(), message.to_string(), kind))
+            .copied()
+    }
+}
+
+/// A message taken out of its envelope for sending it to the node owning the actor.
+pub(crate) struct RemoteLetter {
+    message_type: TypeId,
+    message: Box<dyn Any + Send>,
+    responder: Option<(RemoteResponder, IsAbandoned)>,
+    // When the caller of a call stops waiting for the response
+    deadline: Option<Instant>,
+    on_forwarded: Option<WriteReport>,
+}
+
+/// Sends the response, deserialized by the registry, back to the caller.
+pub(crate) type RemoteResponder = Box<dyn FnOnce(Result<Box<dyn Any + Send>, CallError>) + Send>;
+
+/// Returns true once the caller doesn't wait for the response anymore, for example because its
+/// timeout expired.
+pub(crate) type IsAbandoned = Box<dyn Fn() -> bool + Send>;
+
+impl RemoteLetter {
+    pub(crate) fn send<M: Send + 'static>(message: M) -> RemoteLetter {
+        RemoteLetter {
+            message_type: TypeId::of::<M>(),
+            message: Box::new(message),
+            responder: None,
+            deadline: None,
+            on_forwarded: None,
+        }
+    }
+
+    pub(crate) fn call<A: Actor + Respond<M>, M: Debug + Send + 'static>(
+        message: M,
+        responder: Responder<A, M>,
+        deadline: Option<Instant>,
+    ) -> RemoteLetter {
+        let caller = responder.clone();
+        let is_abandoned: IsAbandoned = Box::new(move || caller.is_closed());
+
+        let responder: RemoteResponder = Box::new(move |response| {
+            let response = response.map(|response| {
+                match response.downcast::<<A as Respond<M>>::Response>() {
+                    Ok(response) => *response,
+                    // The registry decodes the response with the type registered for the message
+                    Err(_) => unreachable!(),
+                }
+            });
+
+            // The channel has capacity for 1 message and nothing was sent yet, so it cannot be full.
+            let _ = responder.try_send(response);
+        });
+
+        RemoteLetter {
+            message_type: TypeId::of::<M>(),
+            message: Box::new(message),
+            responder: Some((responder, is_abandoned)),
+            deadline,
+            on_forwarded: None,
+        }
+    }
+
+    /// Runs the callback once the message is written to the connection with the other node, or
+    /// with the reason it couldn't be sent.
+    pub(crate) fn on_forwarded<F: FnOnce(Result<(), SendError>) + Send + 'static>(
+        mut self,
+        callback: F,
+    ) -> RemoteLetter {
+        self.on_forwarded = Some(Box::new(callback));
+        self
+    }
+
+    pub(crate) fn get_kind(&self) -> MessageKind {
+        match self.responder {
+            Some(_) => MessageKind::Call,
+            None => MessageKind::Send,
+        }
+    }
+
+    pub(crate) fn get_message_type(&self) -> TypeId {
+        self.message_type
+    }
+
+    pub(crate) fn get_deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    pub(crate) fn get_message(&self) -> &dyn Any {
+        &*self.message
+    }
+
+    pub(crate) fn take_responder(&mut self) -> Option<(RemoteResponder, IsAbandoned)> {
+        self.responder.take()
+    }
+
+    pub(crate) fn take_on_forwarded(&mut self) -> Option<WriteReport> {
+        self.on_forwarded.take()
+    }
+}
+
+fn encode<T: Serialize + 'static>(value: &dyn Any) -> serde_json::Result<Value> {
+    match value.downcast_ref::<T>() {
+        Some(value) => serde_json::to_value(value),
+        // The registry only calls it with values of the type it was registered for
+        None => unreachable!(),
+    }
+}
+
+fn decode<T: DeserializeOwned + Send + 'static>(
+    value: Value,
+) -> serde_json::Result<Box<dyn Any + Send>> {
+    Ok(Box::new(serde_json::from_value::<T>(value)?))
+}
+
+fn decode_letter<A: Actor, M: DeserializeOwned>(
+    actor_id: Value,
+    message: Value,
+) -> Result<(A::Id, M), CallError>
+where
+    A::Id: DeserializeOwned,
+{
+    match (
+        serde_json::from_value(actor_id),
+        serde_json::from_value(message),
+    ) {
+        (Ok(actor_id), Ok(message)) => Ok((actor_id, message)),
+        _ => Err(CallError::Serialization),
+    }
+}
+
+fn deliver_message<A: Actor + Receive<M>, M: Debug + Send + DeserializeOwned + 'static>(
+    actors_director: ActorsDirector,
+    actor_id: Value,
+    message: Value,
+) -> BoxFuture<'static, Result<Option<RemoteResponse>, CallError>>
+where
+    A::Id: DeserializeOwned,
+{
+    Box::pin(async move {
+        let (actor_id, message) = decode_letter::<A, M>(actor_id, message)?;
+
+        actors_director
+            .send_from_cluster::<A, M>(actor_id, message)
+            .await;
+
+        Ok(None)
+    })
+}
+
+fn deliver_call<A: Actor + Respond<M>, M: Debug + Send + DeserializeOwned + 'static>(
+    actors_director: ActorsDirector,
+    actor_id: Value,
+    message: Value,
+) -> BoxFuture<'static, Result<Option<RemoteResponse>, CallError>>
+where
+    A::Id: DeserializeOwned,
+    <A as Respond<M>>::Response: Serialize,
+{
+    Box::pin(async move {
+        let (actor_id, message) = decode_letter::<A, M>(actor_id, message)?;
+
+        let response = actors_director
+            .call_from_cluster::<A, M>(actor_id, message)
+            .await;
+
+        let response: RemoteResponse = Box::pin(async move {
+            serde_json::to_value(response.await?).map_err(|_| CallError::Serialization)
+        });
+
+        Ok(Some(response))
+    })
+}
@@ -0,0 +1,225 @@
+use crate::actors::director::ActorsDirector;
+use crate::clock::Clock;
+use crate::cluster::registry::{ClusterRegistry, MessageKind, RemoteLetter, RemoteResponse};
+use crate::cluster::transport::{Inbound, Peer};
+use crate::error::{CallError, SendError};
+use crate::Actor;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Decides which node owns each actor and sends there the messages of the actors owned by
+/// other nodes.
+#[derive(Debug)]
+pub(crate) struct ClusterRouter {
+    node: String,
+    // All the nodes, including this one. As every node knows all of them, every node agrees
+    // on the owner of each actor.
+    nodes: Vec<String>,
+    peers: HashMap<String, Peer>,
+    registry: ClusterRegistry,
+    inbound: Inbound,
+}
+
+/// Where to send the messages of an actor owned by another node.
+pub(crate) struct Route<'a> {
+    peer: &'a Peer,
+    actor: &'a str,
+    actor_id: Value,
+}
+
+impl ClusterRouter {
+    pub(crate) fn new(
+        node: String,
+        local_addr: SocketAddr,
+        peers: HashMap<String, SocketAddr>,
+        connect_timeout: Duration,
+        registry: ClusterRegistry,
+    ) -> ClusterRouter {
+        let mut nodes: Vec<String> = peers.keys().cloned().collect();
+        nodes.push(node.clone());
+
+        let peers = peers
+            .into_iter()
+            .map(|(name, address)| (name, Peer::new(address, connect_timeout)))
+            .collect();
+
+        ClusterRouter {
+            node,
+            nodes,
+            peers,
+            registry,
+            inbound: Inbound::new(local_addr),
+        }
+    }
+
+    /// Returns where to send the messages of the actor, or None if this node owns it.
+    pub(crate) fn route<A: Actor>(&self, actor_id: &A::Id) -> Option<Route<'_>> {
+        let (actor, actor_id) = self.registry.encode_actor_id::<A>(actor_id)?;
+
+        let owner = owner_of(&self.nodes, actor, &actor_id);
+
+        if owner == self.node {
+            return None;
+        }
+
+        Some(Route {
+            peer: self.peers.get(owner)?,
+            actor,
+            actor_id,
+        })
+    }
+
+    /// Sends the message to the node owning the actor. Messages not registered for this actor
+    /// type are discarded, and their callers get `CallError::Serialization` (or
+    /// `SendError::Serialization`, for checked sends).
+    pub(crate) fn forward<A: Actor>(
+        &self,
+        route: Route<'_>,
+        mut letter: RemoteLetter,
+        clock: &Clock,
+    ) {
+        let kind = letter.get_kind();
+
+        let encoded = self
+            .registry
+            .get_outgoing::<A>(letter.get_message_type(), kind)
+            .and_then(|outgoing| {
+                let payload = (outgoing.encode)(letter.get_message()).ok()?;
+                Some((outgoing, payload))
+            });
+
+        let (outgoing, payload) = match encoded {
+            Some(encoded) => encoded,
+            None => {
+                if let Some((responder, _)) = letter.take_responder() {
+                    responder(Err(CallError::Serialization));
+                }
+
+                if let Some(report) = letter.take_on_forwarded() {
+                    report(Err(SendError::Serialization));
+                }
+
+                return;
+            }
+        };
+
+        let mut frame = json!({
+            "kind": kind.as_str(),
+            "actor": route.actor,
+            "message": outgoing.name,
+            "id": route.actor_id,
+            "payload": payload,
+        });
+
+        // Clocks of different nodes don't agree, so the other node gets the time left instead
+        // of the deadline. Once it passes, the call is skipped there as well.
+        if let Some(deadline) = letter.get_deadline() {
+            let timeout = deadline.saturating_duration_since(clock.now());
+            frame["timeout"] = json!(timeout.as_millis() as u64);
+        }
+
+        match (letter.take_responder(), outgoing.decode_response) {
+            // The caller stopped waiting while the message was enqueued in this node
+            (Some((_, is_abandoned)), Some(_)) if is_abandoned() => (),
+            (Some((responder, is_abandoned)), Some(decode_response)) => {
+                route.peer.call(
+                    frame,
+                    Box::new(move |response| {
+                        responder(response.and_then(|response| {
+                            decode_response(response).map_err(|_| CallError::Serialization)
+                        }))
+                    }),
+                    is_abandoned,
+                );
+            }
+            (Some((responder, _)), None) => responder(Err(CallError::Serialization)),
+            (None, _) => route.peer.send(frame, letter.take_on_forwarded()),
+        }
+    }
+
+    /// Enqueues a message that came from another node in the local actor.
+    pub(crate) async fn deliver(
+        &self,
+        actors_director: &ActorsDirector,
+        frame: &Value,
+    ) -> Result<Option<RemoteResponse>, CallError> {
+        let kind = frame["kind"].as_str().and_then(MessageKind::parse);
+        let actor = frame["actor"].as_str();
+        let message = frame["message"].as_str();
+
+        let deliver = match (kind, actor, message) {
+            (Some(kind), Some(actor), Some(message)) => {
+                self.registry.get_incoming(actor, message, kind)
+            }
+            _ => None,
+        };
+
+        match deliver {
+            Some(deliver) => {
+                deliver(
+                    actors_director.clone(),
+                    frame["id"].clone(),
+                    frame["payload"].clone(),
+                )
+                .await
+            }
+            None => Err(CallError::Serialization),
+        }
+    }
+
+    pub(crate) fn get_inbound(&self) -> &Inbound {
+        &self.inbound
+    }
+
+    /// Forgets the calls sent to other nodes whose callers don't wait for the response anymore.
+    pub(crate) fn discard_abandoned_calls(&self) {
+        for peer in self.peers.values() {
+            peer.discard_abandoned_calls();
+        }
+    }
+
+    /// Closes the connections with the other nodes, after sending the messages already
+    /// forwarded.
+    pub(crate) fn close(&self) {
+        for peer in self.peers.values() {
+            peer.close();
+        }
+
+        self.inbound.close();
+    }
+}
+
+// Rendezvous hashing: each node gets a score for the actor and the highest one owns it. Every
+// node gets the same owner only from the names, and adding or removing a node only moves the
+// actors owned by that node.
+fn owner_of<'a>(nodes: &'a [String], actor: &str, actor_id: &Value) -> &'a str {
+    let actor_id = actor_id.to_string();
+
+    nodes
+        .iter()
+        .max_by_key(|node| {
+            let score = hash(&[node.as_bytes(), actor.as_bytes(), actor_id.as_bytes()]);
+            (score, node.as_str())
+        })
+        .map(String::as_str)
+        .unwrap_or_default()
+}
+
+// FNV-1a with a final mix, as the hashers of std may change between builds and nodes must agree.
+fn hash(parts: &[&[u8]]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+
+    for part in parts {
+        for byte in part.iter().chain(&[0xff]) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^= hash >> 33;
+    hash
+}
@@ -0,0 +1,547 @@
+use crate::actors::director::ActorsDirector;
+use crate::cluster::registry::IsAbandoned;
+use crate::cluster::router::ClusterRouter;
+use crate::error::{CallError, SendError};
+use crate::runtime;
+use async_channel::{unbounded, Receiver, Sender};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt::Debug;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+// Nodes talk with frames of JSON preceded by their length (u32, big endian). Each node opens
+// one connection to each other node for sending its messages, and the responses of its calls
+// come back through the same connection. As connections are read in order, the messages sent
+// to an actor from one node arrive in the same order they were sent.
+//
+// Connections use std sockets in their own threads, so they work the same with any `Runtime`.
+
+// Bigger frames are considered garbage, not messages.
+const MAX_FRAME_LENGTH: usize = 64 * 1024 * 1024;
+
+fn write_frame(stream: &mut TcpStream, frame: &[u8]) -> io::Result<()> {
+    let length: u32 = match frame.len().try_into() {
+        Ok(length) if frame.len() <= MAX_FRAME_LENGTH => length,
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "frame too big")),
+    };
+
+    stream.write_all(&length.to_be_bytes())?;
+    stream.write_all(frame)
+}
+
+fn read_frame(stream: &mut TcpStream) -> io::Result<Value> {
+    let mut length = [0; 4];
+    stream.read_exact(&mut length)?;
+
+    let length = u32::from_be_bytes(length) as usize;
+
+    if length > MAX_FRAME_LENGTH {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too big"));
+    }
+
+    let mut frame = vec![0; length];
+    stream.read_exact(&mut frame)?;
+
+    serde_json::from_slice(&frame).map_err(io::Error::from)
+}
+
+fn encode_response(call: u64, response: Result<Value, CallError>) -> Vec<u8> {
+    let frame = match response {
+        Ok(response) => json!({ "call": call, "response": response }),
+        Err(error) => json!({ "call": call, "error": error_code(error) }),
+    };
+
+    // A Value always serializes
+    serde_json::to_vec(&frame).unwrap_or_default()
+}
+
+fn decode_response(frame: &Value) -> Result<Value, CallError> {
+    match (
+        frame.get("response"),
+        frame.get("error").and_then(Value::as_str),
+    ) {
+        (Some(response), _) => Ok(response.clone()),
+        (None, Some(error)) => Err(error_from_code(error)),
+        (None, None) => Err(CallError::Serialization),
+    }
+}
+
+fn error_code(error: CallError) -> &'static str {
+    match error {
+        CallError::ActorStopped => "actor_stopped",
+        CallError::ServiceStopped => "service_stopped",
+        CallError::SystemStopping => "system_stopping",
+        CallError::ResponderDropped => "responder_dropped",
+        CallError::HandlerPanicked => "handler_panicked",
+        CallError::Timeout => "timeout",
+        CallError::MailboxFull => "mailbox_full",
+        CallError::Rejected => "rejected",
+        CallError::NodeUnreachable => "node_unreachable",
+        CallError::Serialization => "serialization",
+    }
+}
+
+fn error_from_code(code: &str) -> CallError {
+    match code {
+        "actor_stopped" => CallError::ActorStopped,
+        "service_stopped" => CallError::ServiceStopped,
+        "system_stopping" => CallError::SystemStopping,
+        "responder_dropped" => CallError::ResponderDropped,
+        "handler_panicked" => CallError::HandlerPanicked,
+        "timeout" => CallError::Timeout,
+        "mailbox_full" => CallError::MailboxFull,
+        "rejected" => CallError::Rejected,
+        "node_unreachable" => CallError::NodeUnreachable,
+        _ => CallError::Serialization,
+    }
+}
+
+/// Lets the sender know if the frame was written to the connection with the node, or why not.
+pub(crate) type WriteReport = Box<dyn FnOnce(Result<(), SendError>) + Send>;
+
+// A frame waiting to be written, and who wants to know if it was.
+struct Outgoing {
+    frame: Vec<u8>,
+    report: Option<WriteReport>,
+}
+
+impl Outgoing {
+    fn report(self, result: Result<(), SendError>) {
+        if let Some(report) = self.report {
+            report(result);
+        }
+    }
+}
+
+/// Gets the serialized response of a call, or the reason it couldn't be obtained.
+pub(crate) type PendingCall = Box<dyn FnOnce(Result<Value, CallError>) + Send>;
+
+#[derive(Default)]
+struct PendingCalls(Mutex<HashMap<u64, (PendingCall, IsAbandoned)>>);
+
+impl PendingCalls {
+    fn lock(&self) -> MutexGuard<'_, HashMap<u64, (PendingCall, IsAbandoned)>> {
+        // Calls are inserted and removed in one step, so the map is consistent even if some
+        // thread panicked while holding the lock.
+        match self.0.lock() {
+            Ok(calls) => calls,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    fn insert(&self, call: u64, pending: PendingCall, is_abandoned: IsAbandoned) {
+        self.lock().insert(call, (pending, is_abandoned));
+    }
+
+    fn respond(&self, call: u64, response: Result<Value, CallError>) {
+        // The lock must be released before responding
+        let pending = self.lock().remove(&call);
+
+        if let Some((pending, _)) = pending {
+            pending(response);
+        }
+    }
+
+    fn fail_all(&self, error: CallError) {
+        let calls = std::mem::take(&mut *self.lock());
+
+        for (_, (pending, _)) in calls {
+            pending(Err(error));
+        }
+    }
+
+    fn discard_abandoned(&self) {
+        self.lock().retain(|_, (_, is_abandoned)| !is_abandoned());
+    }
+}
+
+/// The connection to another node, for sending the messages of the actors it owns.
+pub(crate) struct Peer {
+    sender: Sender<Outgoing>,
+    pending: Arc<PendingCalls>,
+    next_call: AtomicU64,
+}
+
+impl Peer {
+    /// Starts the thread writing to the node. It connects with the first message and, if the
+    /// connection fails, again with the next message after the failure.
+    pub(crate) fn new(address: SocketAddr, connect_timeout: Duration) -> Peer {
+        let (sender, receiver) = unbounded::<Outgoing>();
+        let pending = Arc::new(PendingCalls::default());
+
+        let writer_pending = pending.clone();
+
+        thread::Builder::new()
+            .name("acteur-cluster-writer".to_string())
+            .spawn(move || write_messages(address, connect_timeout, receiver, writer_pending))
+            .expect("Cannot create the thread for a cluster connection");
+
+        Peer {
+            sender,
+            pending,
+            next_call: AtomicU64::new(0),
+        }
+    }
+
+    /// Enqueues the frame for writing it to the node. The report, if any, gets the result of
+    /// the write.
+    pub(crate) fn send(&self, frame: Value, report: Option<WriteReport>) {
+        let outgoing = Outgoing {
+            // A Value always serializes
+            frame: serde_json::to_vec(&frame).unwrap_or_default(),
+            report,
+        };
+
+        // The connection is only closed by `close`, when the system stops
+        if let Err(error) = self.sender.try_send(outgoing) {
+            error.into_inner().report(Err(SendError::SystemStopped));
+        }
+    }
+
+    pub(crate) fn call(&self, mut frame: Value, pending: PendingCall, is_abandoned: IsAbandoned) {
+        let call = self.next_call.fetch_add(1, Ordering::Relaxed);
+
+        frame["call"] = json!(call);
+
+        // The call must be waiting before the response can arrive
+        self.pending.insert(call, pending, is_abandoned);
+
+        let calls = self.pending.clone();
+
+        self.send(
+            frame,
+            Some(Box::new(move |result| {
+                let error = match result {
+                    Ok(()) => return,
+                    Err(SendError::SystemStopped) => CallError::SystemStopping,
+                    Err(_) => CallError::NodeUnreachable,
+                };
+
+                calls.respond(call, Err(error));
+            })),
+        );
+    }
+
+    /// Forgets the calls whose callers don't wait for the response anymore. If the response
+    /// arrives later, it is ignored.
+    pub(crate) fn discard_abandoned_calls(&self) {
+        self.pending.discard_abandoned();
+    }
+
+    /// Sends the messages still enqueued and closes the connection.
+    pub(crate) fn close(&self) {
+        self.sender.close();
+    }
+}
+
+impl Debug for Peer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Peer ()")
+    }
+}
+
+struct Connection {
+    stream: TcpStream,
+    reader: JoinHandle<()>,
+}
+
+impl Connection {
+    fn open(
+        address: SocketAddr,
+        timeout: Duration,
+        pending: Arc<PendingCalls>,
+    ) -> io::Result<Connection> {
+        let stream = TcpStream::connect_timeout(&address, timeout)?;
+        stream.set_nodelay(true)?;
+
+        let mut reader_stream = stream.try_clone()?;
+
+        let reader = thread::Builder::new()
+            .name("acteur-cluster-reader".to_string())
+            .spawn(move || read_responses(&mut reader_stream, &pending))?;
+
+        Ok(Connection { stream, reader })
+    }
+
+    /// Closes the connection and waits for the reader, so it cannot touch the calls sent through
+    /// the next connection.
+    fn close(self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+        let _ = self.reader.join();
+    }
+}
+
+fn write_messages(
+    address: SocketAddr,
+    connect_timeout: Duration,
+    receiver: Receiver<Outgoing>,
+    pending: Arc<PendingCalls>,
+) {
+    let mut connection: Option<Connection> = None;
+
+    while let Ok(outgoing) = runtime::block_on(receiver.recv()) {
+        let opened = match &mut connection {
+            Some(opened) => opened,
+            None => match Connection::open(address, connect_timeout, pending.clone()) {
+                Ok(opened) => connection.insert(opened),
+                Err(_) => {
+                    // The enqueued messages would wait for the same node, they are discarded.
+                    outgoing.report(Err(SendError::NodeUnreachable));
+
+                    while let Ok(discarded) = receiver.try_recv() {
+                        discarded.report(Err(SendError::NodeUnreachable));
+                    }
+
+                    pending.fail_all(CallError::NodeUnreachable);
+                    continue;
+                }
+            },
+        };
+
+        if write_frame(&mut opened.stream, &outgoing.frame).is_err() {
+            if let Some(failed) = connection.take() {
+                failed.close();
+            }
+
+            outgoing.report(Err(SendError::NodeUnreachable));
+            pending.fail_all(CallError::NodeUnreachable);
+            continue;
+        }
+
+        outgoing.report(Ok(()));
+    }
+
+    if let Some(opened) = connection {
+        opened.close();
+    }
+
+    pending.fail_all(CallError::SystemStopping);
+}
+
+fn read_responses(stream: &mut TcpStream, pending: &PendingCalls) {
+    while let Ok(frame) = read_frame(stream) {
+        if let Some(call) = frame.get("call").and_then(Value::as_u64) {
+            pending.respond(call, decode_response(&frame));
+        }
+    }
+
+    // The writer notices it with the next message. Until then, nobody can respond the calls.
+    let _ = stream.shutdown(Shutdown::Both);
+    pending.fail_all(CallError::NodeUnreachable);
+}
+
+/// Connections from other nodes, closed when the system stops.
+#[derive(Debug)]
+pub(crate) struct Inbound {
+    local_addr: SocketAddr,
+    is_closed: AtomicBool,
+    // The open connections, by the order they were accepted. Each one is removed when it ends.
+    streams: Mutex<HashMap<u64, TcpStream>>,
+    next_connection: AtomicU64,
+}
+
+impl Inbound {
+    pub(crate) fn new(local_addr: SocketAddr) -> Inbound {
+        Inbound {
+            local_addr,
+            is_closed: AtomicBool::new(false),
+            streams: Mutex::new(HashMap::new()),
+            next_connection: AtomicU64::new(0),
+        }
+    }
+
+    fn lock_streams(&self) -> MutexGuard<'_, HashMap<u64, TcpStream>> {
+        // Streams are only inserted, removed or drained, so the map is consistent even if some
+        // thread panicked while holding the lock.
+        match self.streams.lock() {
+            Ok(streams) => streams,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Returns the id of the connection for `untrack`, or None if the connection cannot be kept
+    /// because the node is closed.
+    fn track(&self, stream: &TcpStream) -> Option<u64> {
+        let mut streams = self.lock_streams();
+
+        if self.is_closed.load(Ordering::Acquire) {
+            return None;
+        }
+
+        let connection = self.next_connection.fetch_add(1, Ordering::Relaxed);
+
+        if let Ok(stream) = stream.try_clone() {
+            streams.insert(connection, stream);
+        }
+
+        Some(connection)
+    }
+
+    fn untrack(&self, connection: u64) {
+        self.lock_streams().remove(&connection);
+    }
+
+    pub(crate) fn close(&self) {
+        if self.is_closed.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        for (_, stream) in self.lock_streams().drain() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+
+        // Wakes up the listener, waiting for connections, so it sees that it must finish.
+        let _ = TcpStream::connect(self.local_addr);
+    }
+}
+
+/// Starts the thread accepting connections from other nodes.
+pub(crate) fn listen(
+    listener: TcpListener,
+    router: Arc<ClusterRouter>,
+    actors_director: ActorsDirector,
+) {
+    thread::Builder::new()
+        .name("acteur-cluster-listener".to_string())
+        .spawn(move || accept_connections(listener, router, actors_director))
+        .expect("Cannot create the thread for the cluster listener");
+}
+
+fn accept_connections(
+    listener: TcpListener,
+    router: Arc<ClusterRouter>,
+    actors_director: ActorsDirector,
+) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(_) => continue,
+        };
+
+        let connection = match router.get_inbound().track(&stream) {
+            Some(connection) => connection,
+            None => return,
+        };
+
+        let connection_router = router.clone();
+        let actors_director = actors_director.clone();
+
+        let spawned = thread::Builder::new()
+            .name("acteur-cluster-inbound".to_string())
+            .spawn(move || {
+                serve_connection(stream, &connection_router, &actors_director);
+                connection_router.get_inbound().untrack(connection);
+            });
+
+        if spawned.is_err() {
+            router.get_inbound().untrack(connection);
+        }
+    }
+}
+
+fn serve_connection(
+    mut stream: TcpStream,
+    router: &ClusterRouter,
+    actors_director: &ActorsDirector,
+) {
+    let mut writer_stream = match stream.try_clone() {
+        Ok(writer_stream) => writer_stream,
+        Err(_) => return,
+    };
+
+    let _ = stream.set_nodelay(true);
+
+    let (responses, receiver) = unbounded::<Vec<u8>>();
+
+    // Responses are written in their own thread as they come in any order. It finishes when
+    // this connection and all the calls made through it are done.
+    let _ = thread::Builder::new()
+        .name("acteur-cluster-responses".to_string())
+        .spawn(move || {
+            while let Ok(response) = runtime::block_on(receiver.recv()) {
+                if write_frame(&mut writer_stream, &response).is_err() {
+                    break;
+                }
+            }
+        });
+
+    while let Ok(frame) = read_frame(&mut stream) {
+        let call = frame.get("call").and_then(Value::as_u64);
+        let timeout = frame
+            .get("timeout")
+            .and_then(Value::as_u64)
+            .map(Duration::from_millis);
+
+        // Waiting until the message is enqueued keeps the order of the messages.
+        match runtime::block_on(router.deliver(actors_director, &frame)) {
+            Ok(None) => (),
+            Ok(Some(response)) => {
+                if let Some(call) = call {
+                    let responses = responses.clone();
+                    let clock = actors_director.get_clock().clone();
+
+                    actors_director.get_executor().spawn(async move {
+                        // Same as with local calls, once the caller stops waiting the response
+                        // receiver is dropped, so the message is skipped if it wasn't handled.
+                        let response = match timeout {
+                            Some(timeout) => clock
+                                .timeout(timeout, response)
+                                .await
+                                .unwrap_or(Err(CallError::Timeout)),
+                            None => response.await,
+                        };
+
+                        let _ = responses.try_send(encode_response(call, response));
+                    });
+                }
+            }
+            Err(error) => {
+                if let Some(call) = call {
+                    let _ = responses.try_send(encode_response(call, Err(error)));
+                }
+            }
+        }
+    }
+
+    let _ = stream.shutdown(Shutdown::Both);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Inbound;
+    use std::net::{TcpListener, TcpStream};
+
+    #[test]
+    fn inbound_forgets_the_connections_that_ended() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let inbound = Inbound::new(listener.local_addr().unwrap());
+
+        let mut connections = vec![];
+
+        for _ in 0..3 {
+            let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+            let (stream, _) = listener.accept().unwrap();
+            connections.push(inbound.track(&stream).unwrap());
+        }
+
+        assert_eq!(inbound.lock_streams().len(), 3);
+
+        for connection in connections {
+            inbound.untrack(connection);
+        }
+
+        assert!(inbound.lock_streams().is_empty());
+
+        inbound.close();
+
+        let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        assert_eq!(inbound.track(&stream), None);
+    }
+}
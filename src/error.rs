@@ -0,0 +1,265 @@
+use std::fmt;
+
+/// Error returned by `call_actor` and `call_service` (and their sync versions) when
+/// a response couldn't be obtained.
+///
+/// Not all the errors mean the same. For example, if the system is stopping it doesn't make
+/// sense to retry, but if the actor stopped in the middle of the message, a new call will
+/// start the actor again.
+///
+/// ```rust,no_run
+/// # use acteur::{Actor, Respond, ActorAssistant, BlockingActeur, CallError};
+/// # use async_trait::async_trait;
+/// #
+/// # #[derive(Debug)]
+/// # struct Employee;
+/// #
+/// # #[async_trait]
+/// # impl Actor for Employee {
+/// #     type Id = u32;
+/// #
+/// #     async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+/// #         Employee
+/// #     }
+/// # }
+/// #
+/// # #[derive(Debug)]
+/// # struct GetSalary;
+/// #
+/// # #[async_trait]
+/// # impl Respond<GetSalary> for Employee {
+/// #     type Response = u32;
+/// #     async fn handle(&mut self, _: GetSalary, _: &ActorAssistant<Employee>) -> u32 {
+/// #         55000
+/// #     }
+/// # }
+/// let sys = BlockingActeur::new();
+///
+/// match sys.call_actor::<Employee, GetSalary>(42, GetSalary) {
+///     Ok(salary) => println!("Salary is: {:?}", salary),
+///     Err(CallError::SystemStopping) => println!("Too late, the system is stopping"),
+///     Err(error) => println!("Something went wrong: {}", error),
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallError {
+    /// The actor stopped before processing the message. The message was not handled.
+    ActorStopped,
+    /// The service stopped before processing the message. The message was not handled.
+    ServiceStopped,
+    /// The system is stopping and the message couldn't be processed.
+    SystemStopping,
+    /// The handler finished without sending a response.
+    ResponderDropped,
+    /// The handler panicked while processing the message.
+    HandlerPanicked,
+    /// The response didn't arrive on time.
+    Timeout,
+    /// The actor mailbox was full and the message was discarded. See
+    /// [MailboxOverflow](./enum.MailboxOverflow.html).
+    MailboxFull,
+    /// A [Middleware](./trait.Middleware.html) rejected the message. The message was not handled.
+    Rejected,
+    /// The node of the [Cluster](./struct.Cluster.html) owning the actor couldn't be reached, or
+    /// the connection was lost before getting the response. The message may have been handled.
+    #[cfg(feature = "cluster")]
+    NodeUnreachable,
+    /// The message or the response couldn't be sent between nodes of the
+    /// [Cluster](./struct.Cluster.html). Usually because it is not registered, with the same
+    /// name, in both nodes. The message was not handled.
+    #[cfg(feature = "cluster")]
+    Serialization,
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            CallError::ActorStopped => "the actor stopped before handling the message",
+            CallError::ServiceStopped => "the service stopped before handling the message",
+            CallError::SystemStopping => "the system is stopping",
+            CallError::ResponderDropped => "the handler finished without responding",
+            CallError::HandlerPanicked => "the handler panicked while handling the message",
+            CallError::Timeout => "the response didn't arrive on time",
+            CallError::MailboxFull => "the actor mailbox was full",
+            CallError::Rejected => "a middleware rejected the message",
+            #[cfg(feature = "cluster")]
+            CallError::NodeUnreachable => "the node owning the actor couldn't be reached",
+            #[cfg(feature = "cluster")]
+            CallError::Serialization => "the message couldn't be sent to the node owning the actor",
+        };
+
+        write!(f, "{}", description)
+    }
+}
+
+impl std::error::Error for CallError {}
+
+/// Error returned by `try_send_to_actor` when the message couldn't be enqueued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrySendError {
+    /// The actor mailbox is full. The message was discarded.
+    MailboxFull,
+    /// The system is stopping and the message couldn't be enqueued.
+    SystemStopping,
+    /// The node of the [Cluster](./struct.Cluster.html) owning the actor couldn't be reached.
+    #[cfg(feature = "cluster")]
+    NodeUnreachable,
+    /// The message couldn't be sent to the node of the [Cluster](./struct.Cluster.html) owning
+    /// the actor. Usually because it is not registered, with the same name, in both nodes.
+    #[cfg(feature = "cluster")]
+    Serialization,
+}
+
+impl fmt::Display for TrySendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            TrySendError::MailboxFull => "the actor mailbox is full",
+            TrySendError::SystemStopping => "the system is stopping",
+            #[cfg(feature = "cluster")]
+            TrySendError::NodeUnreachable => "the node owning the actor couldn't be reached",
+            #[cfg(feature = "cluster")]
+            TrySendError::Serialization => {
+                "the message couldn't be sent to the node owning the actor"
+            }
+        };
+
+        write!(f, "{}", description)
+    }
+}
+
+impl std::error::Error for TrySendError {}
+
+/// Error returned by `send_to_actor_checked` and `send_to_service_checked` (and their sync
+/// versions) when the message couldn't be enqueued. In all the cases the message was discarded.
+///
+/// Unlike `send_to_actor`, the checked versions never activate actors or services while the
+/// system is stopping.
+///
+/// ```rust,no_run
+/// # use acteur::{Actor, Receive, ActorAssistant, BlockingActeur, SendError};
+/// # use async_trait::async_trait;
+/// #
+/// # #[derive(Debug)]
+/// # struct Employee;
+/// #
+/// # #[async_trait]
+/// # impl Actor for Employee {
+/// #     type Id = u32;
+/// #
+/// #     async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+/// #         Employee
+/// #     }
+/// # }
+/// #
+/// # #[derive(Debug)]
+/// # struct SalaryChanged(u32);
+/// #
+/// # #[async_trait]
+/// # impl Receive<SalaryChanged> for Employee {
+/// #     async fn handle(&mut self, _: SalaryChanged, _: &ActorAssistant<Employee>) {}
+/// # }
+/// let sys = BlockingActeur::new();
+///
+/// match sys.send_to_actor_checked::<Employee, _>(42, SalaryChanged(55000)) {
+///     Ok(()) => println!("The salary will be changed"),
+///     Err(SendError::SystemStopped) => println!("Too late, the system is stopping"),
+///     Err(error) => println!("The salary won't be changed: {}", error),
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendError {
+    /// The system is stopping and the actor or service type is not running.
+    SystemStopped,
+    /// The actor type is stopping and the actor is not loaded. Sending the message would activate
+    /// the actor again.
+    ActorEnding,
+    /// The actor or service doesn't accept more messages, usually because it failed and it is
+    /// being removed. A new message will start it again.
+    MailboxClosed,
+    /// The actor mailbox was full and its [MailboxOverflow](./enum.MailboxOverflow.html) policy
+    /// discarded the message.
+    MailboxFull,
+    /// The node of the [Cluster](./struct.Cluster.html) owning the actor couldn't be reached.
+    #[cfg(feature = "cluster")]
+    NodeUnreachable,
+    /// The message couldn't be sent to the node of the [Cluster](./struct.Cluster.html) owning
+    /// the actor. Usually because it is not registered, with the same name, in both nodes.
+    #[cfg(feature = "cluster")]
+    Serialization,
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            SendError::SystemStopped => "the system is stopping",
+            SendError::ActorEnding => "the actor is ending",
+            SendError::MailboxClosed => "the mailbox is closed",
+            SendError::MailboxFull => "the actor mailbox was full",
+            #[cfg(feature = "cluster")]
+            SendError::NodeUnreachable => "the node owning the actor couldn't be reached",
+            #[cfg(feature = "cluster")]
+            SendError::Serialization => "the message couldn't be sent to the node owning the actor",
+        };
+
+        write!(f, "{}", description)
+    }
+}
+
+impl std::error::Error for SendError {}
+
+/// Error returned by a [Journal](./trait.Journal.html), a [SnapshotStore](./trait.SnapshotStore.html)
+/// and by the methods of the [ActorAssistant](./struct.ActorAssistant.html) that persist or
+/// recover events.
+///
+/// When persisting fails, the events are not applied to the actor, so the actor state is
+/// still the same as the one stored in the journal.
+#[cfg(feature = "persistence")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JournalError {
+    /// The storage of the journal failed (for example, a file couldn't be written).
+    Storage(String),
+    /// An event couldn't be serialized or deserialized.
+    Serialization(String),
+    /// The last event stored for the persistence id is not the one the actor expected, so the
+    /// events were not appended. Usually another instance of the actor (for example, in another
+    /// process) persisted events in the meantime.
+    SequenceMismatch { expected: u64, actual: u64 },
+}
+
+#[cfg(feature = "persistence")]
+impl fmt::Display for JournalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JournalError::Storage(error) => write!(f, "the journal storage failed: {}", error),
+            JournalError::Serialization(error) => {
+                write!(
+                    f,
+                    "the event couldn't be serialized or deserialized: {}",
+                    error
+                )
+            }
+            JournalError::SequenceMismatch { expected, actual } => write!(
+                f,
+                "the last event in the journal is {} but {} was expected",
+                actual, expected
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "persistence")]
+impl std::error::Error for JournalError {}
+
+#[cfg(feature = "persistence")]
+impl From<std::io::Error> for JournalError {
+    fn from(error: std::io::Error) -> JournalError {
+        JournalError::Storage(error.to_string())
+    }
+}
+
+#[cfg(feature = "persistence")]
+impl From<serde_json::Error> for JournalError {
+    fn from(error: serde_json::Error) -> JournalError {
+        JournalError::Serialization(error.to_string())
+    }
+}
@@ -1,23 +1,35 @@
-use crate::actors::proxy::ActorReport;
+use crate::actors::actor_ref::ActorRef;
+use crate::blocking::BlockingActeur;
+use crate::builder::ActeurBuilder;
+use crate::error::{CallError, SendError, TrySendError};
+use crate::report::SystemReport;
+use crate::scheduler::ScheduleHandle;
 use crate::services::handle::{Listen, Serve};
 use crate::services::service::Service;
-use crate::system_director::SystemDirector;
+use crate::shutdown::{ShutdownOptions, ShutdownSummary};
+use crate::system_director::{SystemConfiguration, SystemDirector};
 use crate::{Actor, Receive, Respond};
-use async_std::task;
 use lazy_static::lazy_static;
-use std::any::TypeId;
 use std::fmt::Debug;
 
 // We do this in order to keep all the actors in the same system. If not, two calls
 // to "new" can create duplicated actors.
 lazy_static! {
-    static ref SYSTEM_DIRECTOR: SystemDirector = SystemDirector::new();
+    static ref SYSTEM_DIRECTOR: SystemDirector =
+        SystemDirector::new(SystemConfiguration::default());
 }
 
 /// Acteur is the main inteface to the actor runtime.
 /// It allows sending messages, stopping the runtime, set configurations, etc.
 /// Once contructed with the method "new" you can start sending messages.
 /// The system will automatically start any required actor and unload them when not used.
+///
+/// The method "new" always returns the same global system. If you need independent
+/// systems or you want to configure them, use the method "builder".
+///
+/// No method of Acteur blocks the current thread, so it can be used from async code running in
+/// any task. For sync code, use the [BlockingActeur](./struct.BlockingActeur.html) returned by the
+/// method "blocking".
 pub struct Acteur {
     system_director: SystemDirector,
 }
@@ -30,12 +42,30 @@ impl Default for Acteur {
 
 impl Acteur {
     /// Initializes the system. After this, you can send messages using the send method.
+    ///
+    /// All the calls to this method return the same global system.
     pub fn new() -> Acteur {
         Acteur {
             system_director: SYSTEM_DIRECTOR.clone(),
         }
     }
 
+    /// Returns a builder for creating a new system, independent of the global one and
+    /// of any other system created with it.
+    pub fn builder() -> ActeurBuilder {
+        ActeurBuilder::new()
+    }
+
+    /// Returns a handle to this same system with sync methods that block the current thread
+    /// until they finish. See [BlockingActeur](./struct.BlockingActeur.html).
+    pub fn blocking(&self) -> BlockingActeur {
+        BlockingActeur::from_acteur(self.clone())
+    }
+
+    pub(crate) fn from_system_director(system_director: SystemDirector) -> Acteur {
+        Acteur { system_director }
+    }
+
     /// Sends a message to an actor with an ID.
     ///
     /// This method will execute the [Receive::handle](./trait.Receive.html) implemented for
@@ -48,43 +78,86 @@ impl Acteur {
         actor_id: A::Id,
         message: M,
     ) {
+        if self.system_director.reject_external_message() {
+            return;
+        }
+
         self.system_director
             .send_to_actor::<A, M>(actor_id, message)
             .await;
     }
 
-    /// Same as `send_to_actor` method, but sync version.
-    pub fn send_to_actor_sync<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+    /// Same as `send_to_actor` but it returns a [SendError](./enum.SendError.html) if the message
+    /// couldn't be enqueued, for example, because the system is stopping. Unlike `send_to_actor`,
+    /// it doesn't activate actors while the system is stopping.
+    pub async fn send_to_actor_checked<A: Actor + Receive<M>, M: Debug + Send + 'static>(
         &self,
         actor_id: A::Id,
         message: M,
-    ) {
-        task::block_on(async move { self.send_to_actor::<A, M>(actor_id, message).await })
+    ) -> Result<(), SendError> {
+        if self.system_director.reject_external_message() {
+            return Err(SendError::SystemStopped);
+        }
+
+        self.system_director
+            .send_to_actor_checked::<A, M>(actor_id, message)
+            .await
     }
 
-    /// Same as `send_to_actor` but it delays the message sending
+    /// Same as `send_to_actor` but it never waits for room in the actor mailbox. If the
+    /// mailbox is full, the message is discarded and `TrySendError::MailboxFull` is returned.
+    ///
+    /// Mailboxes are unlimited unless the actor sets a `mailbox_capacity` in its
+    /// [ActorConfiguration](./struct.ActorConfiguration.html). Like `send_to_actor_checked`, it
+    /// doesn't activate actors while the system is stopping.
+    pub async fn try_send_to_actor<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        message: M,
+    ) -> Result<(), TrySendError> {
+        if self.system_director.reject_external_message() {
+            return Err(TrySendError::SystemStopping);
+        }
+
+        self.system_director
+            .try_send_to_actor::<A, M>(actor_id, message)
+            .await
+    }
+
+    /// Same as `send_to_actor` but it delays the message sending. It returns immediately with a
+    /// [ScheduleHandle](./struct.ScheduleHandle.html) that allows to cancel the message.
     pub async fn schedule_send_to_actor<A: Actor + Receive<M>, M: Debug + Send + 'static>(
         &self,
         actor_id: A::Id,
         duration: std::time::Duration,
         message: M,
-    ) {
+    ) -> ScheduleHandle {
+        if self.system_director.reject_external_message() {
+            return ScheduleHandle::new();
+        }
+
         self.system_director
             .schedule_send_to_actor::<A, M>(actor_id, duration, message)
-            .await;
     }
 
-    /// Same as `schedule_send_to_actor` method, but sync version.
-    pub fn schedule_send_to_actor_sync<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+    /// Sends a copy of the message to an actor every time the interval passes, until
+    /// the returned [ScheduleHandle](./struct.ScheduleHandle.html) is cancelled or the
+    /// system stops. The first message is sent after the first interval.
+    pub async fn schedule_interval_to_actor<
+        A: Actor + Receive<M>,
+        M: Debug + Send + Clone + 'static,
+    >(
         &self,
         actor_id: A::Id,
-        duration: std::time::Duration,
+        interval: std::time::Duration,
         message: M,
-    ) {
-        task::block_on(async move {
-            self.schedule_send_to_actor::<A, M>(actor_id, duration, message)
-                .await
-        })
+    ) -> ScheduleHandle {
+        if self.system_director.reject_external_message() {
+            return ScheduleHandle::new();
+        }
+
+        self.system_director
+            .schedule_interval_to_actor::<A, M>(actor_id, interval, message)
     }
 
     /// Sends a message to all actors of a type independently of their ID
@@ -97,43 +170,47 @@ impl Acteur {
         &self,
         message: M,
     ) {
+        if self.system_director.reject_external_message() {
+            return;
+        }
+
         self.system_director
             .send_to_all_actors::<A, M>(message)
             .await;
     }
 
-    /// Same as `send_to_all_actors` method, but sync version.
-    pub async fn send_to_all_actors_sync<A: Actor + Receive<M>, M: Debug + Send + 'static>(
-        &self,
-        message: M,
-    ) {
-        task::block_on(async move { self.send_to_all_actors::<A, M>(message).await })
-    }
-
-    /// Same as `send_to_all_actors` but it delays the message sending
+    /// Same as `send_to_all_actors` but it delays the message sending. It returns immediately
+    /// with a [ScheduleHandle](./struct.ScheduleHandle.html) that allows to cancel the message.
     pub async fn schedule_send_to_all_actors<A: Actor + Receive<M>, M: Debug + Send + 'static>(
         &self,
         duration: std::time::Duration,
         message: M,
-    ) {
+    ) -> ScheduleHandle {
+        if self.system_director.reject_external_message() {
+            return ScheduleHandle::new();
+        }
+
         self.system_director
             .schedule_send_to_all_actors::<A, M>(duration, message)
-            .await;
     }
 
-    /// Same as `schedule_send_to_all_actors` method, but sync version.
-    pub async fn schedule_send_to_all_actors_sync<
+    /// Sends a copy of the message to all actors of a type every time the interval passes,
+    /// until the returned [ScheduleHandle](./struct.ScheduleHandle.html) is cancelled or the
+    /// system stops. Only actors loaded in Ram at each moment receive the message.
+    pub async fn schedule_interval_to_all_actors<
         A: Actor + Receive<M>,
-        M: Debug + Send + 'static,
+        M: Debug + Send + Clone + 'static,
     >(
         &self,
-        duration: std::time::Duration,
+        interval: std::time::Duration,
         message: M,
-    ) {
-        task::block_on(async move {
-            self.schedule_send_to_all_actors::<A, M>(duration, message)
-                .await
-        })
+    ) -> ScheduleHandle {
+        if self.system_director.reject_external_message() {
+            return ScheduleHandle::new();
+        }
+
+        self.system_director
+            .schedule_interval_to_all_actors::<A, M>(interval, message)
     }
 
     /// As send_to_actor method, it sends a message to an actor with an ID but this one
@@ -144,23 +221,47 @@ impl Acteur {
     ///
     /// If the actor is not loaded in Ram, this method will load them first
     /// by calling their "activate" method.
+    ///
+    /// If the response cannot be obtained, the returned [CallError](./enum.CallError.html)
+    /// tells why.
     pub async fn call_actor<A: Actor + Respond<M>, M: Debug + Send + 'static>(
         &self,
         actor_id: A::Id,
         message: M,
-    ) -> Result<<A as Respond<M>>::Response, &str> {
+    ) -> Result<<A as Respond<M>>::Response, CallError> {
+        if self.system_director.reject_external_message() {
+            return Err(CallError::SystemStopping);
+        }
+
         self.system_director
             .call_actor::<A, M>(actor_id, message)
             .await
     }
 
-    /// Same as `call_actor` method, but sync version.
-    pub fn call_actor_sync<A: Actor + Respond<M>, M: Debug + Send + 'static>(
+    /// Same as `call_actor` but it returns `CallError::Timeout` if the response doesn't arrive
+    /// on time. If the actor didn't start handling the message yet, the message is skipped.
+    ///
+    /// A default timeout for all the calls can be set with `ActeurBuilder::call_timeout`.
+    pub async fn call_actor_with_timeout<A: Actor + Respond<M>, M: Debug + Send + 'static>(
         &self,
         actor_id: A::Id,
         message: M,
-    ) -> Result<<A as Respond<M>>::Response, &str> {
-        task::block_on(async move { self.call_actor::<A, M>(actor_id, message).await })
+        timeout: std::time::Duration,
+    ) -> Result<<A as Respond<M>>::Response, CallError> {
+        if self.system_director.reject_external_message() {
+            return Err(CallError::SystemStopping);
+        }
+
+        self.system_director
+            .call_actor_with_timeout::<A, M>(actor_id, message, timeout)
+            .await
+    }
+
+    /// Returns a reference to the actor with the Id, for sending it messages without looking for
+    /// the actor type in the system each time. The actor is not activated until the first message.
+    /// See [ActorRef](./struct.ActorRef.html).
+    pub async fn actor_ref<A: Actor>(&self, actor_id: A::Id) -> ActorRef<A> {
+        self.system_director.actor_ref::<A>(actor_id).await
     }
 
     /// Sends a message to a Service.
@@ -174,15 +275,60 @@ impl Acteur {
         &self,
         message: M,
     ) {
+        if self.system_director.reject_external_message() {
+            return;
+        }
+
         self.system_director.send_to_service::<S, M>(message).await;
     }
 
-    /// Same as `send_to_service` method, but sync version.
-    pub fn send_to_service_sync<S: Service + Listen<M>, M: Debug + Send + 'static>(
+    /// Same as `send_to_service` but it returns a [SendError](./enum.SendError.html) if the
+    /// message couldn't be enqueued. Unlike `send_to_service`, it doesn't initialize services
+    /// while the system is stopping.
+    pub async fn send_to_service_checked<S: Service + Listen<M>, M: Debug + Send + 'static>(
         &self,
         message: M,
-    ) {
-        task::block_on(async move { self.send_to_service::<S, M>(message).await })
+    ) -> Result<(), SendError> {
+        if self.system_director.reject_external_message() {
+            return Err(SendError::SystemStopped);
+        }
+
+        self.system_director
+            .send_to_service_checked::<S, M>(message)
+            .await
+    }
+
+    /// Same as `send_to_service` but it delays the message sending. It returns immediately with a
+    /// [ScheduleHandle](./struct.ScheduleHandle.html) that allows to cancel the message.
+    pub async fn schedule_send_to_service<S: Service + Listen<M>, M: Debug + Send + 'static>(
+        &self,
+        duration: std::time::Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        if self.system_director.reject_external_message() {
+            return ScheduleHandle::new();
+        }
+
+        self.system_director
+            .schedule_send_to_service::<S, M>(duration, message)
+    }
+
+    /// Sends a copy of the message to a Service every time the interval passes, until the
+    /// returned [ScheduleHandle](./struct.ScheduleHandle.html) is cancelled or the system stops.
+    pub async fn schedule_interval_to_service<
+        S: Service + Listen<M>,
+        M: Debug + Send + Clone + 'static,
+    >(
+        &self,
+        interval: std::time::Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        if self.system_director.reject_external_message() {
+            return ScheduleHandle::new();
+        }
+
+        self.system_director
+            .schedule_interval_to_service::<S, M>(interval, message)
     }
 
     /// As send_to_service method, it sends a message to a Service but this one
@@ -193,26 +339,43 @@ impl Acteur {
     ///
     /// If the Service is not loaded in Ram, this method will load them first
     /// by calling their "initialize" method.
+    ///
+    /// If the response cannot be obtained, the returned [CallError](./enum.CallError.html)
+    /// tells why.
     pub async fn call_service<S: Service + Serve<M>, M: Debug + Send + 'static>(
         &self,
         message: M,
-    ) -> Result<<S as Serve<M>>::Response, &str> {
+    ) -> Result<<S as Serve<M>>::Response, CallError> {
+        if self.system_director.reject_external_message() {
+            return Err(CallError::SystemStopping);
+        }
+
         self.system_director.call_service::<S, M>(message).await
     }
 
-    /// Same as `call_service` method, but sync version.
-    pub fn call_service_sync<S: Service + Serve<M>, M: Debug + Send + 'static>(
+    /// Same as `call_service` but it returns `CallError::Timeout` if the response doesn't arrive
+    /// on time. If the service didn't start handling the message yet, the message is skipped.
+    ///
+    /// A default timeout for all the calls can be set with `ActeurBuilder::call_timeout`.
+    pub async fn call_service_with_timeout<S: Service + Serve<M>, M: Debug + Send + 'static>(
         &self,
         message: M,
-    ) -> Result<<S as Serve<M>>::Response, &str> {
-        task::block_on(async move { self.call_service::<S, M>(message).await })
+        timeout: std::time::Duration,
+    ) -> Result<<S as Serve<M>>::Response, CallError> {
+        if self.system_director.reject_external_message() {
+            return Err(CallError::SystemStopping);
+        }
+
+        self.system_director
+            .call_service_with_timeout::<S, M>(message, timeout)
+            .await
     }
 
     /// Send an stop message to all actors in the system.
     /// Actors will process all the enqued messages before stop
     pub fn stop(&self) {
         let system = self.system_director.clone();
-        task::spawn(async move {
+        self.system_director.get_executor().spawn(async move {
             system.stop().await;
         });
     }
@@ -223,34 +386,193 @@ impl Acteur {
         self.system_director.preload_service::<S>().await;
     }
 
-    /// Same as `preload_service` but sync version
-    pub fn preload_service_sync<S: Service>(&self) {
-        let system = self.system_director.clone();
-        task::block_on(async move {
-            system.preload_service::<S>().await;
-        });
-    }
-
     /// Waits until all actors are stopped.
     /// If you call "system.stop()" this method will wait untill all actor
     /// have consumed all messages before returning.
-    pub fn wait_until_stopped(&self) {
-        task::block_on(async { self.system_director.wait_until_stopped().await });
+    pub async fn wait_until_stopped(&self) {
+        self.system_director.wait_until_stopped().await
     }
 
-    pub fn get_statistics(&self) -> Vec<(TypeId, Vec<ActorReport>)> {
+    /// Stops the system gracefully, waiting at most the deadline of the options. Unlike `stop`,
+    /// it stops accepting messages from outside the system first, and services are stopped only
+    /// after all the actors are stopped, so actors can still rely on them while finishing their
+    /// work. See [ShutdownOptions](./struct.ShutdownOptions.html) for the phases.
+    ///
+    /// It returns a [ShutdownSummary](./struct.ShutdownSummary.html) with the messages handled
+    /// during the shutdown and the ones discarded because the deadline expired.
+    pub async fn shutdown(&self, options: ShutdownOptions) -> ShutdownSummary {
+        self.system_director.shutdown(options).await
+    }
+
+    /// Returns the statistics of the actors and services loaded in Ram.
+    pub fn get_statistics(&self) -> SystemReport {
         self.system_director.get_statistics()
     }
 
-    /// Allows to publish messages for Services to receive. In order for the mesage
-    /// to be received by a service, the service must register itself for that message type.
+    /// Returns the metrics of the system in OpenMetrics text format (compatible with Prometheus),
+    /// ready to be served in a `/metrics` endpoint. Only available with the `metrics` feature.
+    ///
+    /// It includes, per actor type and message type, the messages sent and handled and the time
+    /// spent in the handlers; the activations and deactivations and live actors and enqueued
+    /// messages per actor type, and the same message metrics and enqueued messages per service.
+    ///
+    /// ```rust,no_run
+    /// use acteur::Acteur;
+    ///
+    /// let sys = Acteur::new();
+    ///
+    /// // ... send some messages
+    ///
+    /// println!("{}", sys.render_metrics());
+    /// ```
+    #[cfg(feature = "metrics")]
+    pub fn render_metrics(&self) -> String {
+        self.system_director.render_metrics()
+    }
+
+    /// Allows to publish messages for Services and Actors to receive. In order for the mesage
+    /// to be received by a service or an actor, it must subscribe itself for that message type.
+    /// See [ServiceAssistant::subscribe](./struct.ServiceAssistant.html#method.subscribe) and
+    /// [ActorAssistant::subscribe](./struct.ActorAssistant.html#method.subscribe).
+    ///
+    /// Only the subscriptions without topic pattern receive messages published with this method.
     pub async fn publish<M: Send + Clone + 'static>(&mut self, message: M) {
-        self.system_director.publish(message).await
+        if self.system_director.reject_external_message() {
+            return;
+        }
+
+        self.system_director.publish(None, message).await
     }
 
-    /// Same as publish method, but sync version
-    pub fn publish_sync<M: Send + Clone + 'static>(&mut self, message: M) {
-        task::block_on(async { self.system_director.publish(message).await });
+    /// Same as `publish` but the message is published in a topic. It is received by the
+    /// subscriptions without topic pattern and by the subscriptions with a pattern matching the
+    /// topic, so one message type can be routed to different subscribers.
+    ///
+    /// Topics are words separated by dots, like `orders.eu.created`. In the patterns, `*` matches
+    /// exactly one word and `#` matches zero or more words, so `orders.*.created` and `orders.#`
+    /// match that topic.
+    ///
+    /// ```rust,no_run
+    /// use acteur::{Acteur, Service, Listen, ServiceConfiguration, ServiceAssistant};
+    ///
+    /// #[derive(Debug)]
+    /// struct EuropeanWarehouse;
+    ///
+    /// #[async_trait::async_trait]
+    /// impl Service for EuropeanWarehouse {
+    ///     async fn initialize(assistant: &ServiceAssistant<Self>) -> (Self, ServiceConfiguration) {
+    ///         assistant.subscribe_to_topic::<OrderCreated>("orders.eu.*").await;
+    ///
+    ///         (EuropeanWarehouse, ServiceConfiguration::default())
+    ///     }
+    /// }
+    ///
+    /// #[derive(Debug, Clone)]
+    /// struct OrderCreated(u32);
+    ///
+    /// #[async_trait::async_trait]
+    /// impl Listen<OrderCreated> for EuropeanWarehouse {
+    ///     async fn handle(&self, order: OrderCreated, _: &ServiceAssistant<Self>) {
+    ///         println!("Preparing order {}", order.0);
+    ///     }
+    /// }
+    ///
+    /// # async_std::task::block_on(async {
+    /// let mut sys = Acteur::new();
+    ///
+    /// sys.preload_service::<EuropeanWarehouse>().await;
+    ///
+    /// // Received by the EuropeanWarehouse
+    /// sys.publish_to_topic("orders.eu.spain", OrderCreated(1)).await;
+    /// // Not received by the EuropeanWarehouse
+    /// sys.publish_to_topic("orders.us.texas", OrderCreated(2)).await;
+    /// # });
+    /// ```
+    pub async fn publish_to_topic<M: Send + Clone + 'static>(&mut self, topic: &str, message: M) {
+        if self.system_director.reject_external_message() {
+            return;
+        }
+
+        self.system_director.publish(Some(topic), message).await
+    }
+
+    /// Subscribes all the actors of a type to a message type. Every message of that type published
+    /// with `publish` will be sent to all the actors of that type already loaded in Ram, each of
+    /// them receiving a copy. Subscribing twice has no effect.
+    ///
+    /// ```rust,no_run
+    /// use acteur::{Acteur, Actor, ActorAssistant, Receive};
+    /// use async_trait::async_trait;
+    ///
+    /// #[derive(Debug)]
+    /// struct Player;
+    ///
+    /// #[async_trait]
+    /// impl Actor for Player {
+    ///     type Id = u32;
+    ///
+    ///     async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+    ///         Player
+    ///     }
+    /// }
+    ///
+    /// #[derive(Debug, Clone)]
+    /// struct ServerRestarting;
+    ///
+    /// #[async_trait]
+    /// impl Receive<ServerRestarting> for Player {
+    ///     async fn handle(&mut self, _: ServerRestarting, _: &ActorAssistant<Player>) {
+    ///         println!("Saving the game");
+    ///     }
+    /// }
+    ///
+    /// # async_std::task::block_on(async {
+    /// let mut sys = Acteur::new();
+    ///
+    /// sys.subscribe_all_actors::<Player, ServerRestarting>();
+    ///
+    /// sys.publish(ServerRestarting).await;
+    /// # });
+    /// ```
+    pub fn subscribe_all_actors<A: Actor + Receive<M>, M: Sync + Send + Clone + Debug + 'static>(
+        &self,
+    ) {
+        self.system_director.subscribe_all_actors::<A, M>(None);
+    }
+
+    /// Same as `subscribe_all_actors` but only for the messages published in a topic matching the
+    /// pattern. See `publish_to_topic`.
+    pub fn subscribe_all_actors_to_topic<
+        A: Actor + Receive<M>,
+        M: Sync + Send + Clone + Debug + 'static,
+    >(
+        &self,
+        topic_pattern: &str,
+    ) {
+        self.system_director
+            .subscribe_all_actors::<A, M>(Some(topic_pattern));
+    }
+
+    /// Removes the subscription made with `subscribe_all_actors`.
+    pub fn unsubscribe_all_actors<
+        A: Actor + Receive<M>,
+        M: Sync + Send + Clone + Debug + 'static,
+    >(
+        &self,
+    ) {
+        self.system_director.unsubscribe_all_actors::<A, M>(None);
+    }
+
+    /// Removes the subscription made with `subscribe_all_actors_to_topic` with the same pattern.
+    pub fn unsubscribe_all_actors_from_topic<
+        A: Actor + Receive<M>,
+        M: Sync + Send + Clone + Debug + 'static,
+    >(
+        &self,
+        topic_pattern: &str,
+    ) {
+        self.system_director
+            .unsubscribe_all_actors::<A, M>(Some(topic_pattern));
     }
 }
 
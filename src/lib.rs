@@ -40,16 +40,16 @@
 //!  - You want to have a full ACID compliant system
 //!  - You want to fully follow the Actor model
 //!  - You need to scale to A LOT of traffic. In which case you will need more than one server. (I'm planning to
-//!  implement some multi-server clustering, but for now, only one server).
+//!    implement some multi-server clustering, but for now, only one server).
 //!
 //! But it may help you if you want:
 //!
 //!  - To have a database but not incur in the cost of READ, APPLY, SAVE, and instead you want to keep object
-//!  instances in RAM.
+//!    instances in RAM.
 //!  - You don't want to deal with optimistic concurrency and you want the messages to process one by one for each
-//!  ID, but concurrently between IDs.
+//!    ID, but concurrently between IDs.
 //!  - You want to make an backend for an online videogame with many entities interacting at the same time but don't
-//!  want to go all the way with ECS.
+//!    want to go all the way with ECS.
 //!
 //! ## Main features of Acteur
 //!
@@ -61,15 +61,15 @@
 //!
 //! ### Regarding the implementation:
 //!
-//!  - Acteur is **asynchronous** and uses `async_std` under the hood.
+//!  - Acteur is **asynchronous** and uses `async_std` under the hood by default, or `tokio` with the `tokio` feature.
 //!  - Actors have an *ID* which its type is defined by the developer.
 //!  - Messages are routed to an *Actor* and an *ID* .
 //!  - Actor life-cycle is *automatically* managed by the framework.
 //!  - Messages for the same Actor & ID are *sequential*. Everything else is executed **concurrently**.
 //!  - Services are provided for other concurrency forms.
 //!  - Services **don't** have ID and are concurrent.
-//!  - Services can **subscribe** to messages and everyone can **publish** messages.
-//!  - Acteur is **global**, only one instance can exist.
+//!  - Services and Actors can **subscribe** to messages and everyone can **publish** messages.
+//!  - Acteur has a **global** system by default, but independent systems can be built with `Acteur::builder()`.
 //!
 //! ### State of the implementation
 //!
@@ -80,10 +80,10 @@
 //! - ☑️ System can send messages to any actor / service
 //! - ☑️ Actors / Services can optionally, respond to messages
 //! - ☑️ Services: statefull or stateless, without ID (like real actors) and concurrent.
-//! - ☑️ Automatic deallocation of unused actors (after 5 minutes without messages)
-//! - ☑️ Services can subscribe to messages
-//! - □ Actor deallocation configuration (based in RAM, Actor count or timeout)
-//! - □ Clustering: Implement Raft in order to assign each actor to a different server
+//! - ☑️ Automatic deallocation of unused actors (after 5 minutes without messages, configurable per actor type)
+//! - ☑️ Services and Actors can subscribe to messages
+//! - ☑️ Actor deallocation configuration (based in RAM, Actor count or timeout)
+//! - ☑️ Clustering (`cluster` feature): each actor is assigned to one server of a fixed list of servers
 //!
 //! ## Acteur structure
 //!
@@ -100,16 +100,16 @@
 //! For Actors you have two traits in order to handle messages:
 //!
 //! - [Receive](./trait.Receive.html): Receives a message without responding to it. The most
-//! efficient way to handle messages.
+//!   efficient way to handle messages.
 //! - [Respond](./trait.Respond.html): Receives a message and allows to respond to it. Forces
-//! to sender to await until the actor respond.
+//!   to sender to await until the actor respond.
 //!
 //! For Services you have other two traits.
 //!
 //! - [Listen](./trait.Listen.html): Receives a message without responding to it. The most efficient way
-//! to handle messages.
+//!   to handle messages.
 //! - [Serve](./trait.Serve.html): Receives a message and allows to respond to it. Forces to sender to
-//! await until the actor respond.
+//!   await until the actor respond.
 //!
 //! ### Why are you using 4 different trait instead of 1 or 2?
 //!
@@ -157,15 +157,24 @@
 //! ## Subscription or Pub/Sub
 //!
 //! Sometime we don't want to know who should receive the message but to subscribe to a type and wait.
-//! Acteur models the Pub/Sub patter with Services. Actors in Acteur can't perform subscriptions as
-//! that would require the framework to know all possible IDs of all possible Actor instances in
-//! order to direct the message to the correct one (or all) and it doesn't play well with the deallocation
-//! of unused actors.
+//! Services can subscribe to a message type with `ServiceAssistant::subscribe`.
 //!
-//! If you want to send messages to some Actors from a Subscription, you can create a Service that
+//! Actors can subscribe too, but only while they are loaded in Ram. An actor instance subscribes with
+//! `ActorAssistant::subscribe` (usually in its `activate` method) and the subscription is removed when
+//! the actor deactivates, so publishing never wakes up actors. A whole actor type can subscribe with
+//! `Acteur::subscribe_all_actors` or `ActorAssistant::subscribe_all_actors`, and then the published
+//! messages are sent to all the actors of that type already loaded, each one receiving a copy.
+//!
+//! If you want to send messages to Actors that may not be loaded, you can create a Service that
 //! subscribes to a message and then figures out to what Actor IDs to send the message. For example,
 //! doing a query in the DB/Service in order to get the set of IDs that need to receive some message.
 //!
+//! Messages can also be published in a topic with `Acteur::publish_to_topic`, so one message type can
+//! be routed to different subscribers. Topics are words separated by dots (`orders.eu.created`) and
+//! subscribers choose the topics with patterns, where `*` matches one word and `#` matches zero or more
+//! words (`orders.*.created`, `orders.#`). Subscriptions are idempotent and can be removed with the
+//! `unsubscribe` methods.
+//!
 //! Unlike sending/calling to services/actors, publishing doesn't know who needs to receive the
 //! message in compilation time. That is the reason behind requiring the Services to subscribe in
 //! runtime to any message they want to receive. In order to ensure that services perform the
@@ -175,7 +184,7 @@
 //! ## Simple Example
 //!
 //! ```rust,no_run
-//! use acteur::{Actor, Receive, ActorAssistant, Acteur};
+//! use acteur::{Actor, Receive, ActorAssistant, BlockingActeur};
 //! use async_trait::async_trait;
 //!
 //! #[derive(Debug)]
@@ -204,13 +213,11 @@
 //!     }
 //! }
 //!
-//! fn main() {
-//!     let sys = Acteur::new();
+//! let sys = BlockingActeur::new();
 //!
-//!     sys.send_to_actor_sync::<Employee, SalaryChanged>(42, SalaryChanged(55000));
+//! sys.send_to_actor::<Employee, SalaryChanged>(42, SalaryChanged(55000));
 //!
-//!     sys.wait_until_stopped();
-//! }
+//! sys.wait_until_stopped();
 //!
 //! ```
 //!
@@ -219,12 +226,12 @@
 //! Somethings bothered me.
 //!
 //! 1. Actor systems are a concurrency level but I see example of them being used for business logic. Using
-//! a normal HTTP framework + SQL feels more natural than using Actix.
+//!    a normal HTTP framework + SQL feels more natural than using Actix.
 //! 2. In order to use Actix you need to learn how it works. You need to manage the concurrency,
-//! the addresses, etc
+//!    the addresses, etc
 //! 3. Unsafe. I don't want unsafe. I wouldn't trust myself to do something like this in C++,
-//! therefore, I don't want to have unsafe code. Rust opens the door to do these kind of projects
-//! to people with less than 10 years of experience in C/C++ in a safer way.
+//!    therefore, I don't want to have unsafe code. Rust opens the door to do these kind of projects
+//!    to people with less than 10 years of experience in C/C++ in a safer way.
 //!
 //! After async_std 1.0 announcement and speaking with some friends I started to envision how I would
 //! like an actor framework be. Not that Actix and others are wrong, but they are too low level in my
@@ -241,9 +248,14 @@
 //!
 //! Given that all actors are managed by the framework, it is really easy to have, for
 //! example, Rocket or Tide getting new HTTP calls and just calling `acteur.call_service` or
-//! `acteur.call_actor` and wait for the response. You can use the sync version of the call
-//! if you are working with synchronous code. Keep in mind that you can clone Acteur and send
-//! it to as many threads/struct you need.
+//! `acteur.call_actor` and wait for the response. If you are working with synchronous code,
+//! `acteur.blocking()` returns a `BlockingActeur` with the same methods, but blocking the
+//! thread until they finish. Never use it inside async code, as it would block the executor.
+//! Keep in mind that you can clone Acteur and send it to as many threads/struct you need.
+//!
+//! If the handlers always talk to the same actors, `acteur.actor_ref` returns an `ActorRef` that can be
+//! kept in the state of the server. It is cheap to clone and it skips looking for the actor type in each call.
+//! `BlockingActeur::actor_ref` returns a `BlockingActorRef`, with the same methods for sync code.
 //!
 //! ```rust,no_run
 //!
@@ -276,6 +288,70 @@
 //! a service construction and use the method `preload_service` for trying to start the service on the
 //! app startup and let the app crash is something goes wrong.
 //!
+//! ## Graceful shutdown
+//!
+//! The method `stop` asks actors and services to stop at the same time, and `wait_until_stopped` waits for them
+//! as long as needed. If your application needs to finish in a limited time (for example, when a container is being
+//! replaced), use `shutdown` instead. It stops accepting messages from outside the system, lets actors handle their
+//! enqueued messages and then lets services handle theirs. When the deadline of the `ShutdownOptions` expires, the
+//! messages still enqueued are discarded. The returned `ShutdownSummary` tells how many messages were handled and
+//! discarded per actor and service type.
+//!
+//! ## Metrics
+//!
+//! With the `metrics` feature enabled, the method `render_metrics` returns the messages sent and handled,
+//! handler durations, live actors and enqueued messages of the system in OpenMetrics text format, so you
+//! can serve them in your HTTP server for Prometheus to scrape them. Without the feature nothing is counted.
+//!
+//! ## Tracing
+//!
+//! With the `tracing` feature enabled, every message remembers the `tracing` span that was current when it was
+//! sent, and handlers run inside an `actor_handler` or `service_handler` span (with the actor type, actor id and
+//! message type as fields) child of that one. So, if an actor calls a service that publishes a message, all the
+//! handlers involved show up under the span of the code that sent the first message.
+//!
+//! ## Persistence
+//!
+//! With the `persistence` feature enabled, actors implementing `PersistentActor` can be event sourced. Their
+//! handlers persist events with `ActorAssistant::persist`, which stores them in a `Journal` before applying
+//! them to the actor, and `ActorAssistant::recover` rebuilds the state in `activate` replaying the stored
+//! events. The events of each actor instance are stored under the key returned by its `persistence_id`. Acteur includes an `InMemoryJournal` (the default) and a `FileJournal`, and any other storage can
+//! be used implementing the `Journal` trait.
+//!
+//! Actors implementing `SnapshotActor` too can be recovered with `ActorAssistant::recover_from_snapshot`, which
+//! starts from the last snapshot of the actor in the `SnapshotStore` and only replays the events stored after it.
+//! Then the framework saves snapshots every N messages and/or before deactivating the actor, as its
+//! `SnapshotPolicy` says. There are an `InMemorySnapshotStore` (the default) and a `FileSnapshotStore`. See the
+//! `persistent_actor` example.
+//!
+//! ## Testing
+//!
+//! With the `testkit` feature enabled (usually only in the dev-dependencies), `acteur::testkit::TestSystem`
+//! creates an isolated system where time is virtual. Scheduled messages and the deallocation of inactive actors
+//! only happen when the test calls `advance`, which goes through them in order without sleeping. The method
+//! `wait_until_idle` waits until every message sent, including the ones sent by the handlers, was handled, and
+//! `record` keeps the messages delivered to an actor type so tests can make assertions on them. For sync tests,
+//! `TestSystem::blocking` returns a `BlockingTestSystem` with the same methods, blocking the current thread.
+//!
+//! For testing the handlers of one actor or service alone, `acteur::testkit::MockSystem` creates assistants
+//! that capture the messages sent, scheduled, called and published instead of sending them, and answers the
+//! calls with stubs set per actor/service and message type. Then the handlers can be called directly.
+//!
+//! ## Macros
+//!
+//! With the `derive` feature enabled, `#[derive(Actor)]` implements `Actor` for structs implementing `Default`,
+//! using the field marked with `#[actor(id)]` as the Id, and `#[handlers]` on an `impl` block turns its methods
+//! marked with `#[receive]` or `#[respond]` into `Receive` and `Respond` implementations, so there is no need to
+//! write the `#[async_trait]` blocks. See the `derive` example.
+//!
+//! ## Cluster
+//!
+//! With the `cluster` feature enabled, several systems, usually in different servers, can be connected by TCP
+//! with `ActeurBuilder::cluster`. Each actor of the types registered in the `Cluster` lives in only one of the
+//! nodes, and `send_to_actor` and `call_actor` forward the messages to that node from any other one. Actors Ids,
+//! messages and responses are serialized with serde and identified by the names given when registering them,
+//! so every node must register the same ones. See the `Cluster` struct and the `cluster` example.
+//!
 //! ## Safe Rust
 //!
 //! No unsafe code was directly used in this crate. You can check in lib.rs the `#![deny(unsafe_code)]` line.
@@ -307,16 +383,67 @@
 #[macro_use]
 mod utils;
 mod actors;
+mod blocking;
+mod builder;
+mod clock;
+#[cfg(feature = "cluster")]
+mod cluster;
+mod error;
 mod facade;
+mod metrics;
+mod middleware;
+mod observer;
+#[cfg(feature = "persistence")]
+mod persistence;
+mod report;
+mod runtime;
+mod scheduler;
 mod services;
+mod shutdown;
+mod supervision;
 mod system_director;
+#[cfg(feature = "testkit")]
+pub mod testkit;
+mod trace;
 
+pub use blocking::{BlockingActeur, BlockingActorRef};
+pub use builder::ActeurBuilder;
+#[cfg(feature = "cluster")]
+pub use cluster::Cluster;
+#[cfg(feature = "persistence")]
+pub use error::JournalError;
+pub use error::{CallError, SendError, TrySendError};
 pub use facade::Acteur;
+pub use middleware::{MessageContext, Middleware, MiddlewareDecision};
+#[cfg(feature = "persistence")]
+pub use persistence::journal::{FileJournal, InMemoryJournal, Journal, JournalEntry};
+#[cfg(feature = "persistence")]
+pub use persistence::persistent_actor::{PersistentActor, SnapshotActor};
+#[cfg(feature = "persistence")]
+pub use persistence::snapshot::{
+    FileSnapshotStore, InMemorySnapshotStore, Snapshot, SnapshotPolicy, SnapshotStore,
+};
+pub use report::{ActorReport, ServiceReport, SystemReport};
+pub use scheduler::ScheduleHandle;
+pub use shutdown::{ShutdownOptions, ShutdownSummary};
 
-pub use actors::actor::Actor;
+pub use actors::actor::{Actor, ActorConfiguration, MailboxOverflow, Passivation};
+pub use actors::actor_ref::ActorRef;
 pub use actors::assistant::ActorAssistant;
 pub use actors::handle::{Receive, Respond};
 
 pub use services::handle::{Listen, Serve};
 pub use services::service::{Service, ServiceConcurrency, ServiceConfiguration};
 pub use services::system_facade::ServiceAssistant;
+
+pub use supervision::{Failure, SupervisionStrategy, Supervisor, SupervisorDecision};
+
+#[cfg(feature = "derive")]
+pub use acteur_derive::{handlers, Actor};
+
+// Used by the code generated by the `derive` macros.
+#[cfg(feature = "derive")]
+#[doc(hidden)]
+pub mod __private {
+    pub use async_trait::async_trait;
+}
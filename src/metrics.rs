@@ -0,0 +1,410 @@
+use std::time::Duration;
+
+#[cfg(feature = "metrics")]
+use crate::report::SystemReport;
+#[cfg(feature = "metrics")]
+use dashmap::DashMap;
+#[cfg(feature = "metrics")]
+use std::any::type_name;
+#[cfg(feature = "metrics")]
+use std::fmt::Write;
+#[cfg(feature = "metrics")]
+use std::hash::Hash;
+#[cfg(feature = "metrics")]
+use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+
+// Counters and histograms of a system. They are updated by the directors, managers and actor
+// loops, and rendered in OpenMetrics text format by `Acteur::render_metrics`.
+//
+// Without the `metrics` feature this structure is empty and all the methods do nothing, so the
+// rest of the code doesn't need to care about the feature.
+
+#[cfg(feature = "metrics")]
+#[derive(Debug, Default)]
+pub(crate) struct Metrics {
+    // (actor type, message type)
+    actor_messages_sent: Counters<(&'static str, &'static str)>,
+    actor_messages_handled: Counters<(&'static str, &'static str)>,
+    actor_handler_duration: DashMap<(&'static str, &'static str), Histogram>,
+    // actor type
+    actor_activations: Counters<&'static str>,
+    actor_deactivations: Counters<&'static str>,
+    // (service type, message type)
+    service_messages_sent: Counters<(&'static str, &'static str)>,
+    service_messages_handled: Counters<(&'static str, &'static str)>,
+    service_handler_duration: DashMap<(&'static str, &'static str), Histogram>,
+}
+
+#[cfg(not(feature = "metrics"))]
+#[derive(Debug, Default)]
+pub(crate) struct Metrics;
+
+#[cfg(feature = "metrics")]
+impl Metrics {
+    pub(crate) fn new() -> Metrics {
+        Metrics::default()
+    }
+
+    pub(crate) fn actor_message_sent<A: 'static, M: 'static>(&self) {
+        self.actor_messages_sent
+            .increment((type_name::<A>(), type_name::<M>()));
+    }
+
+    pub(crate) fn actor_message_handled<A: 'static>(
+        &self,
+        message: &'static str,
+        duration: Duration,
+    ) {
+        let key = (type_name::<A>(), message);
+        self.actor_messages_handled.increment(key);
+        observe(&self.actor_handler_duration, key, duration);
+    }
+
+    pub(crate) fn actor_activated<A: 'static>(&self) {
+        self.actor_activations.increment(type_name::<A>());
+    }
+
+    pub(crate) fn actor_deactivated<A: 'static>(&self) {
+        self.actor_deactivations.increment(type_name::<A>());
+    }
+
+    pub(crate) fn service_message_sent<S: 'static, M: 'static>(&self) {
+        self.service_messages_sent
+            .increment((type_name::<S>(), type_name::<M>()));
+    }
+
+    pub(crate) fn service_message_handled<S: 'static>(
+        &self,
+        message: &'static str,
+        duration: Duration,
+    ) {
+        let key = (type_name::<S>(), message);
+        self.service_messages_handled.increment(key);
+        observe(&self.service_handler_duration, key, duration);
+    }
+
+    /// Renders all the metrics in OpenMetrics text format. The gauges (live actors, mailbox
+    /// depth, etc) are taken from the system report.
+    pub(crate) fn render(&self, report: &SystemReport) -> String {
+        let mut output = String::new();
+
+        render_counter(
+            &mut output,
+            "acteur_actor_messages_sent",
+            "Messages sent to actors.",
+            &self.actor_messages_sent,
+            |(actor, message)| {
+                format!(
+                    "actor=\"{}\",message=\"{}\"",
+                    escape(actor),
+                    escape(message)
+                )
+            },
+        );
+        render_counter(
+            &mut output,
+            "acteur_actor_messages_handled",
+            "Messages handled by actors.",
+            &self.actor_messages_handled,
+            |(actor, message)| {
+                format!(
+                    "actor=\"{}\",message=\"{}\"",
+                    escape(actor),
+                    escape(message)
+                )
+            },
+        );
+        render_histogram(
+            &mut output,
+            "acteur_actor_handler_duration_seconds",
+            "Time spent in actor handlers.",
+            &self.actor_handler_duration,
+            |(actor, message)| {
+                format!(
+                    "actor=\"{}\",message=\"{}\"",
+                    escape(actor),
+                    escape(message)
+                )
+            },
+        );
+        render_counter(
+            &mut output,
+            "acteur_actor_activations",
+            "Actor activations.",
+            &self.actor_activations,
+            |actor| format!("actor=\"{}\"", escape(actor)),
+        );
+        render_counter(
+            &mut output,
+            "acteur_actor_deactivations",
+            "Actor deactivations.",
+            &self.actor_deactivations,
+            |actor| format!("actor=\"{}\"", escape(actor)),
+        );
+
+        let mut actors: Vec<_> = report.actors.iter().collect();
+        actors.sort_by_key(|(actor, _)| *actor);
+
+        let _ = writeln!(output, "# HELP acteur_actors_live Actors loaded in Ram.");
+        let _ = writeln!(output, "# TYPE acteur_actors_live gauge");
+        for (actor, reports) in actors.iter() {
+            let _ = writeln!(
+                output,
+                "acteur_actors_live{{actor=\"{}\"}} {}",
+                escape(actor),
+                reports.len()
+            );
+        }
+
+        let _ = writeln!(
+            output,
+            "# HELP acteur_actor_mailbox_depth Messages enqueued in the mailboxes of all the actors of a type."
+        );
+        let _ = writeln!(output, "# TYPE acteur_actor_mailbox_depth gauge");
+        for (actor, reports) in actors.iter() {
+            let depth: usize = reports.iter().map(|report| report.enqueued_messages).sum();
+            let _ = writeln!(
+                output,
+                "acteur_actor_mailbox_depth{{actor=\"{}\"}} {}",
+                escape(actor),
+                depth
+            );
+        }
+
+        render_counter(
+            &mut output,
+            "acteur_service_messages_sent",
+            "Messages sent to services.",
+            &self.service_messages_sent,
+            |(service, message)| {
+                format!(
+                    "service=\"{}\",message=\"{}\"",
+                    escape(service),
+                    escape(message)
+                )
+            },
+        );
+        render_counter(
+            &mut output,
+            "acteur_service_messages_handled",
+            "Messages handled by services.",
+            &self.service_messages_handled,
+            |(service, message)| {
+                format!(
+                    "service=\"{}\",message=\"{}\"",
+                    escape(service),
+                    escape(message)
+                )
+            },
+        );
+        render_histogram(
+            &mut output,
+            "acteur_service_handler_duration_seconds",
+            "Time spent in service handlers.",
+            &self.service_handler_duration,
+            |(service, message)| {
+                format!(
+                    "service=\"{}\",message=\"{}\"",
+                    escape(service),
+                    escape(message)
+                )
+            },
+        );
+
+        let mut services: Vec<_> = report.services.iter().collect();
+        services.sort_by_key(|(service, _)| *service);
+
+        let _ = writeln!(
+            output,
+            "# HELP acteur_service_mailbox_depth Messages enqueued in all the concurrency lanes of a service."
+        );
+        let _ = writeln!(output, "# TYPE acteur_service_mailbox_depth gauge");
+        for (service, report) in services.iter() {
+            let depth: usize = report.enqueued_messages.iter().sum();
+            let _ = writeln!(
+                output,
+                "acteur_service_mailbox_depth{{service=\"{}\"}} {}",
+                escape(service),
+                depth
+            );
+        }
+
+        let _ = writeln!(output, "# EOF");
+
+        output
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+impl Metrics {
+    pub(crate) fn new() -> Metrics {
+        Metrics
+    }
+
+    pub(crate) fn actor_message_sent<A: 'static, M: 'static>(&self) {}
+
+    pub(crate) fn actor_message_handled<A: 'static>(&self, _: &'static str, _: Duration) {}
+
+    pub(crate) fn actor_activated<A: 'static>(&self) {}
+
+    pub(crate) fn actor_deactivated<A: 'static>(&self) {}
+
+    pub(crate) fn service_message_sent<S: 'static, M: 'static>(&self) {}
+
+    pub(crate) fn service_message_handled<S: 'static>(&self, _: &'static str, _: Duration) {}
+}
+
+#[cfg(feature = "metrics")]
+#[derive(Debug)]
+struct Counters<K: Eq + Hash> {
+    values: DashMap<K, AtomicU64>,
+}
+
+#[cfg(feature = "metrics")]
+impl<K: Eq + Hash + Copy + Ord> Counters<K> {
+    fn increment(&self, key: K) {
+        // The read lock is enough for existing counters, which is the common case.
+        if let Some(counter) = self.values.get(&key) {
+            counter.fetch_add(1, Relaxed);
+            return;
+        }
+
+        self.values.entry(key).or_default().fetch_add(1, Relaxed);
+    }
+
+    fn sorted_values(&self) -> Vec<(K, u64)> {
+        let mut values: Vec<_> = self
+            .values
+            .iter()
+            .map(|counter| (*counter.key(), counter.load(Relaxed)))
+            .collect();
+        values.sort_by_key(|(key, _)| *key);
+        values
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl<K: Eq + Hash> Default for Counters<K> {
+    fn default() -> Self {
+        Counters {
+            values: DashMap::new(),
+        }
+    }
+}
+
+// Upper bounds of the histogram buckets, in seconds.
+#[cfg(feature = "metrics")]
+const BUCKETS: [f64; 11] = [
+    0.0001, 0.0005, 0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.5, 1.0, 5.0,
+];
+
+#[cfg(feature = "metrics")]
+#[derive(Debug, Default)]
+struct Histogram {
+    // Not cumulative, they are accumulated when rendering.
+    buckets: [AtomicU64; 11],
+    count: AtomicU64,
+    sum_nanos: AtomicU64,
+}
+
+#[cfg(feature = "metrics")]
+impl Histogram {
+    fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+
+        if let Some(index) = BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[index].fetch_add(1, Relaxed);
+        }
+
+        self.count.fetch_add(1, Relaxed);
+        self.sum_nanos
+            .fetch_add(duration.as_nanos() as u64, Relaxed);
+    }
+}
+
+#[cfg(feature = "metrics")]
+fn observe<K: Eq + Hash>(histograms: &DashMap<K, Histogram>, key: K, duration: Duration) {
+    if let Some(histogram) = histograms.get(&key) {
+        histogram.observe(duration);
+        return;
+    }
+
+    histograms.entry(key).or_default().observe(duration);
+}
+
+#[cfg(feature = "metrics")]
+fn render_counter<K: Eq + Hash + Copy + Ord>(
+    output: &mut String,
+    name: &str,
+    help: &str,
+    counters: &Counters<K>,
+    labels: impl Fn(K) -> String,
+) {
+    let _ = writeln!(output, "# HELP {} {}", name, help);
+    let _ = writeln!(output, "# TYPE {} counter", name);
+
+    for (key, value) in counters.sorted_values() {
+        let _ = writeln!(output, "{}_total{{{}}} {}", name, labels(key), value);
+    }
+}
+
+#[cfg(feature = "metrics")]
+fn render_histogram<K: Eq + Hash + Copy + Ord>(
+    output: &mut String,
+    name: &str,
+    help: &str,
+    histograms: &DashMap<K, Histogram>,
+    labels: impl Fn(K) -> String,
+) {
+    let _ = writeln!(output, "# HELP {} {}", name, help);
+    let _ = writeln!(output, "# TYPE {} histogram", name);
+
+    let mut keys: Vec<K> = histograms
+        .iter()
+        .map(|histogram| *histogram.key())
+        .collect();
+    keys.sort();
+
+    for key in keys {
+        let histogram = match histograms.get(&key) {
+            Some(histogram) => histogram,
+            None => continue,
+        };
+
+        let labels = labels(key);
+        let count = histogram.count.load(Relaxed);
+        let mut cumulative = 0;
+
+        for (bound, bucket) in BUCKETS.iter().zip(histogram.buckets.iter()) {
+            cumulative += bucket.load(Relaxed);
+            // Debug formatting always prints the decimal point, as OpenMetrics requires
+            let _ = writeln!(
+                output,
+                "{}_bucket{{{},le=\"{:?}\"}} {}",
+                name, labels, bound, cumulative
+            );
+        }
+
+        let _ = writeln!(
+            output,
+            "{}_bucket{{{},le=\"+Inf\"}} {}",
+            name, labels, count
+        );
+        let _ = writeln!(
+            output,
+            "{}_sum{{{}}} {}",
+            name,
+            labels,
+            histogram.sum_nanos.load(Relaxed) as f64 / 1_000_000_000.0
+        );
+        let _ = writeln!(output, "{}_count{{{}}} {}", name, labels, count);
+    }
+}
+
+#[cfg(feature = "metrics")]
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,231 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Code that runs before and after every message handled by actors and services, without
+/// touching the handlers. Typical uses are authorization checks, audit logs or metrics.
+///
+/// Middlewares are registered in the [ActeurBuilder](./struct.ActeurBuilder.html), for all the
+/// actors and services with `middleware` or for one actor or service type with `middleware_for`.
+/// The `before` methods run in the order the middlewares were registered (system wide ones first)
+/// and the `after` methods run in the opposite order.
+///
+/// If `before` returns `MiddlewareDecision::Reject` the message is discarded: the handler and the
+/// remaining middlewares are not called and any caller waiting for a response receives
+/// `CallError::Rejected`. The `after` methods only run if the handler ran.
+/// Calls whose caller stopped waiting for the response (for example, because it timed out) are
+/// skipped before any middleware runs.
+///
+/// A panic in a middleware is handled as a panic in the handler. See
+/// [SupervisionStrategy](./enum.SupervisionStrategy.html).
+///
+/// ```rust,no_run
+/// use acteur::{Acteur, Middleware, MiddlewareDecision, MessageContext};
+/// use async_trait::async_trait;
+/// use std::time::Duration;
+///
+/// struct AuditLog;
+///
+/// #[async_trait]
+/// impl Middleware for AuditLog {
+///     async fn after(&self, context: &mut MessageContext<'_>, duration: Duration) {
+///         println!(
+///             "{} {:?} handled {} in {:?}",
+///             context.type_name(),
+///             context.actor_id(),
+///             context.message_type_name(),
+///             duration
+///         );
+///     }
+/// }
+///
+/// #[derive(Debug)]
+/// struct Withdraw {
+///     amount: u32,
+/// }
+///
+/// struct WithdrawLimit;
+///
+/// #[async_trait]
+/// impl Middleware for WithdrawLimit {
+///     async fn before(&self, context: &mut MessageContext<'_>) -> MiddlewareDecision {
+///         match context.message::<Withdraw>() {
+///             Some(withdraw) if withdraw.amount > 1000 => MiddlewareDecision::Reject,
+///             _ => MiddlewareDecision::Continue,
+///         }
+///     }
+/// }
+///
+/// let sys = Acteur::builder()
+///     .middleware(AuditLog)
+///     .middleware(WithdrawLimit)
+///     .build();
+/// ```
+#[async_trait::async_trait]
+pub trait Middleware: Send + Sync + 'static {
+    /// Runs before the handler. The message can be inspected and modified through the context.
+    async fn before(&self, _context: &mut MessageContext<'_>) -> MiddlewareDecision {
+        MiddlewareDecision::Continue
+    }
+
+    /// Runs after the handler, with the time the handler took. The message is not available
+    /// anymore as the handler consumed it, and the response (if any) was already sent to the
+    /// caller, so the context only has the type names and the actor Id.
+    async fn after(&self, _context: &mut MessageContext<'_>, _duration: Duration) {}
+}
+
+impl Debug for dyn Middleware {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "Middleware ()")
+    }
+}
+
+/// Returned by `Middleware::before` in order to decide if the message goes on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MiddlewareDecision {
+    /// The next middleware (or the handler, if there are no more) receives the message.
+    Continue,
+    /// The message is discarded.
+    Reject,
+}
+
+/// What a middleware can see of the message being handled.
+pub struct MessageContext<'a> {
+    type_name: &'static str,
+    actor_id: Option<&'a dyn AnyId>,
+    message_type_name: &'static str,
+    message: Option<&'a mut (dyn Any + Send)>,
+}
+
+impl<'a> MessageContext<'a> {
+    pub(crate) fn new(
+        type_name: &'static str,
+        actor_id: Option<&'a dyn AnyId>,
+        message_type_name: &'static str,
+        message: Option<&'a mut (dyn Any + Send)>,
+    ) -> MessageContext<'a> {
+        MessageContext {
+            type_name,
+            actor_id,
+            message_type_name,
+            message,
+        }
+    }
+
+    /// Type name of the actor or service handling the message.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// The Id of the actor handling the message. Services have no Id.
+    pub fn actor_id(&self) -> Option<&dyn Debug> {
+        self.actor_id.map(|id| id.as_debug())
+    }
+
+    /// The Id of the actor handling the message, if it is of type Id.
+    pub fn actor_id_as<Id: 'static>(&self) -> Option<&Id> {
+        self.actor_id
+            .and_then(|id| id.as_any().downcast_ref::<Id>())
+    }
+
+    /// Type name of the message.
+    pub fn message_type_name(&self) -> &'static str {
+        self.message_type_name
+    }
+
+    /// The message, if it is of type M and it wasn't handled yet. It is always None in
+    /// `Middleware::after`.
+    pub fn message<M: 'static>(&self) -> Option<&M> {
+        self.message
+            .as_ref()
+            .and_then(|message| message.downcast_ref::<M>())
+    }
+
+    /// Same as `message` but allows to modify the message before the handler receives it.
+    pub fn message_mut<M: 'static>(&mut self) -> Option<&mut M> {
+        self.message
+            .as_mut()
+            .and_then(|message| message.downcast_mut::<M>())
+    }
+}
+
+impl<'a> Debug for MessageContext<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        f.debug_struct("MessageContext")
+            .field("type_name", &self.type_name)
+            .field("actor_id", &self.actor_id())
+            .field("message_type_name", &self.message_type_name)
+            .finish()
+    }
+}
+
+/// Actor Ids seen as Debug and as Any at the same time.
+pub(crate) trait AnyId: Send + Sync {
+    fn as_debug(&self) -> &dyn Debug;
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: Debug + Send + Sync + 'static> AnyId for T {
+    fn as_debug(&self) -> &dyn Debug {
+        self
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// All the middlewares of a system. It is filled by the `ActeurBuilder`.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Middlewares {
+    system_wide: Vec<Arc<dyn Middleware>>,
+    per_type: HashMap<TypeId, Vec<Arc<dyn Middleware>>>,
+}
+
+impl Middlewares {
+    pub(crate) fn add(&mut self, middleware: Arc<dyn Middleware>) {
+        self.system_wide.push(middleware);
+    }
+
+    pub(crate) fn add_for<T: 'static>(&mut self, middleware: Arc<dyn Middleware>) {
+        self.per_type
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .push(middleware);
+    }
+
+    fn of<T: 'static>(&self) -> impl DoubleEndedIterator<Item = &Arc<dyn Middleware>> {
+        let own: &[Arc<dyn Middleware>] = match self.per_type.get(&TypeId::of::<T>()) {
+            Some(middlewares) => middlewares,
+            None => &[],
+        };
+
+        self.system_wide.iter().chain(own.iter())
+    }
+
+    /// Runs the `before` methods of the middlewares of T until one of them rejects the message.
+    pub(crate) async fn before<T: 'static>(
+        &self,
+        context: &mut MessageContext<'_>,
+    ) -> MiddlewareDecision {
+        for middleware in self.of::<T>() {
+            if let MiddlewareDecision::Reject = middleware.before(context).await {
+                return MiddlewareDecision::Reject;
+            }
+        }
+
+        MiddlewareDecision::Continue
+    }
+
+    pub(crate) async fn after<T: 'static>(
+        &self,
+        context: &mut MessageContext<'_>,
+        duration: Duration,
+    ) {
+        for middleware in self.of::<T>().rev() {
+            middleware.after(context, duration).await;
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::fmt::Debug;
+
+#[cfg(feature = "testkit")]
+use crate::testkit::{MessageRecorder, RecordedMessage};
+#[cfg(feature = "testkit")]
+use dashmap::DashMap;
+#[cfg(feature = "testkit")]
+use std::any::{type_name, TypeId};
+#[cfg(feature = "testkit")]
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+#[cfg(feature = "testkit")]
+use std::sync::Arc;
+
+// Tells the testkit what a system is doing. Every loop (actor managers, actors, services and the
+// scheduler) holds a `BusyGuard` while it works, so the testkit can know when all the messages
+// were handled. It also records the messages delivered to the actor types being recorded.
+//
+// Without the `testkit` feature this structure is empty and all the methods do nothing, so the
+// rest of the code doesn't need to care about the feature.
+
+#[cfg(feature = "testkit")]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Observer {
+    state: Arc<ObserverState>,
+}
+
+#[cfg(feature = "testkit")]
+#[derive(Debug, Default)]
+struct ObserverState {
+    busy: AtomicUsize,
+    // Incremented each time some work starts or finishes. If it didn't change between two
+    // checks, nothing happened in the middle.
+    generation: AtomicU64,
+    recorders: DashMap<TypeId, Vec<MessageRecorder>>,
+}
+
+#[cfg(not(feature = "testkit"))]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Observer;
+
+/// Marks the system as busy until it is dropped.
+#[cfg(feature = "testkit")]
+#[derive(Debug)]
+pub(crate) struct BusyGuard {
+    state: Arc<ObserverState>,
+}
+
+#[cfg(not(feature = "testkit"))]
+#[derive(Debug)]
+pub(crate) struct BusyGuard;
+
+#[cfg(feature = "testkit")]
+impl Observer {
+    pub(crate) fn new() -> Observer {
+        Observer::default()
+    }
+
+    pub(crate) fn busy(&self) -> BusyGuard {
+        self.state.busy.fetch_add(1, Ordering::SeqCst);
+        self.state.generation.fetch_add(1, Ordering::SeqCst);
+
+        BusyGuard {
+            state: self.state.clone(),
+        }
+    }
+
+    pub(crate) fn is_busy(&self) -> bool {
+        self.state.busy.load(Ordering::SeqCst) > 0
+    }
+
+    pub(crate) fn generation(&self) -> u64 {
+        self.state.generation.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn add_recorder<A: 'static>(&self, recorder: MessageRecorder) {
+        self.state
+            .recorders
+            .entry(TypeId::of::<A>())
+            .or_default()
+            .push(recorder);
+    }
+
+    pub(crate) fn record_actor_message<A: 'static>(
+        &self,
+        actor_id: &dyn Debug,
+        message_type: &'static str,
+        message: Option<&dyn Debug>,
+    ) {
+        let recorders = match self.state.recorders.get(&TypeId::of::<A>()) {
+            Some(recorders) => recorders,
+            None => return,
+        };
+
+        let recorded = RecordedMessage {
+            actor_type: type_name::<A>(),
+            actor_id: format!("{:?}", actor_id),
+            message_type,
+            message: message.map(|message| format!("{:?}", message)),
+        };
+
+        for recorder in recorders.iter() {
+            recorder.push(recorded.clone());
+        }
+    }
+}
+
+#[cfg(not(feature = "testkit"))]
+impl Observer {
+    pub(crate) fn new() -> Observer {
+        Observer
+    }
+
+    pub(crate) fn busy(&self) -> BusyGuard {
+        BusyGuard
+    }
+
+    pub(crate) fn record_actor_message<A: 'static>(
+        &self,
+        _actor_id: &dyn Debug,
+        _message_type: &'static str,
+        _message: Option<&dyn Debug>,
+    ) {
+    }
+}
+
+#[cfg(feature = "testkit")]
+impl Drop for BusyGuard {
+    fn drop(&mut self) {
+        self.state.generation.fetch_add(1, Ordering::SeqCst);
+        self.state.busy.fetch_sub(1, Ordering::SeqCst);
+    }
+}
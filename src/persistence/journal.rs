@@ -0,0 +1,313 @@
+use crate::error::JournalError;
+use crate::runtime;
+use dashmap::{mapref::entry::Entry, DashMap};
+use std::convert::TryInto;
+use std::fmt::Debug;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// An event stored in a [Journal](./trait.Journal.html).
+///
+/// The sequence number starts at 1 for the first event of each persistence id and it is
+/// incremented by one for each event. The payload is the serialized event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalEntry {
+    pub sequence_nr: u64,
+    pub payload: Vec<u8>,
+}
+
+/// Storage for the events of the [persistent actors](./trait.PersistentActor.html).
+///
+/// Each actor instance has its own list of events identified by its persistence id. Acteur
+/// ships an [InMemoryJournal](./struct.InMemoryJournal.html), used by default, and a
+/// [FileJournal](./struct.FileJournal.html). Implement this trait for storing the events
+/// anywhere else, like a database.
+///
+/// The journal is configured for the whole system with
+/// [ActeurBuilder::journal](./struct.ActeurBuilder.html#method.journal).
+#[async_trait::async_trait]
+pub trait Journal: Send + Sync + 'static {
+    /// Appends the entries at the end of the events of the persistence id. Either all the
+    /// entries are stored or none of them is.
+    ///
+    /// `expected_sequence_nr` is the sequence number of the last event the caller knows about (0
+    /// if none). If the last stored event has another one, nothing is stored and
+    /// `JournalError::SequenceMismatch` is returned, so two writers cannot interleave events.
+    async fn append(
+        &self,
+        persistence_id: &str,
+        expected_sequence_nr: u64,
+        entries: Vec<JournalEntry>,
+    ) -> Result<(), JournalError>;
+
+    /// Returns, in order, the entries of the persistence id with a sequence number equal or
+    /// greater than `from_sequence_nr`.
+    async fn read(
+        &self,
+        persistence_id: &str,
+        from_sequence_nr: u64,
+    ) -> Result<Vec<JournalEntry>, JournalError>;
+}
+
+impl Debug for dyn Journal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "Journal ()")
+    }
+}
+
+/// A [Journal](./trait.Journal.html) that keeps the events in memory. They are lost when the
+/// process ends, so it is meant for tests and prototypes. It is the default journal.
+///
+/// Clones share the same events, so a clone can be kept for inspecting them.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryJournal {
+    entries: Arc<DashMap<String, Vec<JournalEntry>>>,
+}
+
+impl InMemoryJournal {
+    pub fn new() -> InMemoryJournal {
+        InMemoryJournal::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl Journal for InMemoryJournal {
+    async fn append(
+        &self,
+        persistence_id: &str,
+        expected_sequence_nr: u64,
+        entries: Vec<JournalEntry>,
+    ) -> Result<(), JournalError> {
+        // The entry keeps the events locked, so nobody can append between the check and the write
+        let mut stored = self.entries.entry(persistence_id.to_string()).or_default();
+
+        let actual = stored.last().map_or(0, |entry| entry.sequence_nr);
+
+        if actual != expected_sequence_nr {
+            return Err(JournalError::SequenceMismatch {
+                expected: expected_sequence_nr,
+                actual,
+            });
+        }
+
+        stored.extend(entries);
+
+        Ok(())
+    }
+
+    async fn read(
+        &self,
+        persistence_id: &str,
+        from_sequence_nr: u64,
+    ) -> Result<Vec<JournalEntry>, JournalError> {
+        Ok(match self.entries.get(persistence_id) {
+            Some(entries) => entries
+                .iter()
+                .filter(|entry| entry.sequence_nr >= from_sequence_nr)
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        })
+    }
+}
+
+/// A [Journal](./trait.Journal.html) that appends the events to files, one file per persistence
+/// id, in a directory. Every append is flushed to the disk before returning.
+///
+/// Each entry is written as its sequence number (8 bytes), the length of its payload (4 bytes),
+/// both little endian, and the payload. If the process crashes in the middle of an append, the
+/// incomplete entry at the end of the file is skipped by the reads and removed the next time the
+/// file is appended to.
+///
+/// ```rust,no_run
+/// use acteur::{Acteur, FileJournal};
+///
+/// let sys = Acteur::builder()
+///     .journal(FileJournal::new("./journal").expect("Cannot create the journal directory"))
+///     .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct FileJournal {
+    directory: Arc<PathBuf>,
+    // The sequence number of the last event in each file already appended to, so the file
+    // doesn't need to be read on every append.
+    last_sequence_nrs: Arc<DashMap<PathBuf, u64>>,
+}
+
+impl FileJournal {
+    /// Creates a journal that stores the files in the directory, creating it if needed.
+    pub fn new<P: Into<PathBuf>>(directory: P) -> std::io::Result<FileJournal> {
+        let directory = directory.into();
+
+        fs::create_dir_all(&directory)?;
+
+        Ok(FileJournal {
+            directory: Arc::new(directory),
+            last_sequence_nrs: Arc::new(DashMap::new()),
+        })
+    }
+
+    fn path_for(&self, persistence_id: &str) -> PathBuf {
+        self.directory
+            .join(format!("{}.journal", file_name_for(persistence_id)))
+    }
+}
+
+#[async_trait::async_trait]
+impl Journal for FileJournal {
+    async fn append(
+        &self,
+        persistence_id: &str,
+        expected_sequence_nr: u64,
+        entries: Vec<JournalEntry>,
+    ) -> Result<(), JournalError> {
+        let path = self.path_for(persistence_id);
+        let last_sequence_nrs = self.last_sequence_nrs.clone();
+
+        runtime::spawn_blocking(move || {
+            // The entry keeps the file locked for the other appends of this journal until the
+            // events are written.
+            let mut last_sequence_nr = match last_sequence_nrs.entry(path.clone()) {
+                Entry::Occupied(entry) => entry.into_ref(),
+                Entry::Vacant(entry) => entry.insert(last_sequence_nr_in_file(&path)?),
+            };
+
+            if *last_sequence_nr != expected_sequence_nr {
+                return Err(JournalError::SequenceMismatch {
+                    expected: expected_sequence_nr,
+                    actual: *last_sequence_nr,
+                });
+            }
+
+            append_to_file(&path, &entries)?;
+
+            if let Some(entry) = entries.last() {
+                *last_sequence_nr = entry.sequence_nr;
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn read(
+        &self,
+        persistence_id: &str,
+        from_sequence_nr: u64,
+    ) -> Result<Vec<JournalEntry>, JournalError> {
+        let path = self.path_for(persistence_id);
+
+        runtime::spawn_blocking(move || read_from_file(&path, from_sequence_nr)).await
+    }
+}
+
+const ENTRY_HEADER_LENGTH: usize = 12;
+
+fn append_to_file(path: &Path, entries: &[JournalEntry]) -> Result<(), JournalError> {
+    let mut buffer = Vec::new();
+
+    for entry in entries {
+        let length: u32 = entry.payload.len().try_into().map_err(|_| {
+            JournalError::Serialization("the event is bigger than 4 GiB".to_string())
+        })?;
+
+        buffer.extend_from_slice(&entry.sequence_nr.to_le_bytes());
+        buffer.extend_from_slice(&length.to_le_bytes());
+        buffer.extend_from_slice(&entry.payload);
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let length_before = file.metadata()?.len();
+
+    let result = file.write_all(&buffer).and_then(|_| file.sync_data());
+
+    if result.is_err() {
+        // We remove whatever was written, so the next append doesn't start after a broken entry.
+        let _ = file.set_len(length_before);
+    }
+
+    Ok(result?)
+}
+
+/// Also removes the incomplete entry at the end of the file, if any, so the next entries are
+/// appended right after the last complete one. It must only be called while holding the entry of
+/// the file in `last_sequence_nrs`, so no append is being written at the same time.
+fn last_sequence_nr_in_file(path: &Path) -> Result<u64, JournalError> {
+    let content = read_file(path)?;
+    let (entries, valid_length) = parse_entries(&content, 0);
+
+    // We remove the incomplete entry, so the next append doesn't start after it.
+    if valid_length < content.len() {
+        OpenOptions::new()
+            .write(true)
+            .open(path)?
+            .set_len(valid_length as u64)?;
+    }
+
+    Ok(entries.last().map_or(0, |entry| entry.sequence_nr))
+}
+
+/// Doesn't modify the file, as an append can be being written while reading it.
+fn read_from_file(path: &Path, from_sequence_nr: u64) -> Result<Vec<JournalEntry>, JournalError> {
+    let content = read_file(path)?;
+
+    Ok(parse_entries(&content, from_sequence_nr).0)
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, JournalError> {
+    let mut content = Vec::new();
+
+    match File::open(path) {
+        Ok(mut file) => file.read_to_end(&mut content)?,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error.into()),
+    };
+
+    Ok(content)
+}
+
+/// Returns the complete entries with a sequence number equal or greater than `from_sequence_nr`
+/// and the length of the content until the end of the last complete entry.
+fn parse_entries(content: &[u8], from_sequence_nr: u64) -> (Vec<JournalEntry>, usize) {
+    let mut entries = Vec::new();
+    let mut rest = content;
+
+    while rest.len() >= ENTRY_HEADER_LENGTH {
+        let sequence_nr = u64::from_le_bytes(rest[0..8].try_into().unwrap());
+        let length = u32::from_le_bytes(rest[8..12].try_into().unwrap()) as usize;
+
+        // An incomplete entry can only be the last one, written by a crashed process or by an
+        // append that is still being written.
+        if rest.len() < ENTRY_HEADER_LENGTH + length {
+            break;
+        }
+
+        if sequence_nr >= from_sequence_nr {
+            entries.push(JournalEntry {
+                sequence_nr,
+                payload: rest[ENTRY_HEADER_LENGTH..ENTRY_HEADER_LENGTH + length].to_vec(),
+            });
+        }
+
+        rest = &rest[ENTRY_HEADER_LENGTH + length..];
+    }
+
+    (entries, content.len() - rest.len())
+}
+
+/// Persistence ids can contain any character, so everything but ASCII letters, digits, `-` and
+/// `_` is escaped as `%XX` in order to get a valid and unique file name.
+pub(crate) fn file_name_for(persistence_id: &str) -> String {
+    let mut file_name = String::with_capacity(persistence_id.len());
+
+    for byte in persistence_id.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' => file_name.push(byte as char),
+            _ => file_name.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    file_name
+}
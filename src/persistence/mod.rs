@@ -0,0 +1,4 @@
+pub mod journal;
+pub mod persistent_actor;
+pub mod snapshot;
+pub(crate) mod state;
@@ -0,0 +1,158 @@
+use crate::persistence::snapshot::SnapshotPolicy;
+use crate::Actor;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+
+/// An actor which state is rebuilt from the events it persisted, instead of being loaded and
+/// saved by hand (event sourcing). Only available with the `persistence` feature.
+///
+/// Handlers don't modify the state directly. They persist events with
+/// [ActorAssistant::persist](./struct.ActorAssistant.html#method.persist), which appends them to
+/// the [Journal](./trait.Journal.html) and, only if that succeeded, applies them to the actor
+/// with the `apply` method. So, when a handler responds, its events are already stored.
+///
+/// In the `activate` method, [ActorAssistant::recover](./struct.ActorAssistant.html#method.recover)
+/// replays all the stored events over an initial state.
+///
+/// Events are serialized as JSON with [serde](https://serde.rs).
+///
+/// ```rust,no_run
+/// use acteur::{Acteur, Actor, ActorAssistant, PersistentActor, Respond};
+/// use async_trait::async_trait;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug)]
+/// struct BankAccount {
+///     balance: u64,
+/// }
+///
+/// #[derive(Debug, Serialize, Deserialize)]
+/// enum BankAccountEvent {
+///     Deposited(u64),
+///     Withdrawn(u64),
+/// }
+///
+/// #[async_trait]
+/// impl Actor for BankAccount {
+///     type Id = u32;
+///
+///     async fn activate(_: Self::Id, assistant: &ActorAssistant<Self>) -> Self {
+///         assistant
+///             .recover(BankAccount { balance: 0 })
+///             .await
+///             .expect("Cannot recover the bank account")
+///     }
+/// }
+///
+/// impl PersistentActor for BankAccount {
+///     type Event = BankAccountEvent;
+///
+///     fn apply(&mut self, event: &BankAccountEvent) {
+///         match event {
+///             BankAccountEvent::Deposited(amount) => self.balance += amount,
+///             BankAccountEvent::Withdrawn(amount) => self.balance -= amount,
+///         }
+///     }
+///
+///     fn persistence_id(id: &u32) -> String {
+///         format!("bank-account-{}", id)
+///     }
+/// }
+///
+/// #[derive(Debug)]
+/// struct Withdraw(u64);
+///
+/// #[async_trait]
+/// impl Respond<Withdraw> for BankAccount {
+///     type Response = Result<u64, String>;
+///
+///     async fn handle(&mut self, message: Withdraw, assistant: &ActorAssistant<Self>) -> Self::Response {
+///         if message.0 > self.balance {
+///             return Err("Not enough money".to_string());
+///         }
+///
+///         assistant
+///             .persist(self, BankAccountEvent::Withdrawn(message.0))
+///             .await
+///             .map_err(|error| error.to_string())?;
+///
+///         Ok(self.balance)
+///     }
+/// }
+/// ```
+pub trait PersistentActor: Actor {
+    /// The events persisted by the actor.
+    type Event: Serialize + DeserializeOwned + Debug + Send + Sync + 'static;
+
+    /// Applies an event to the state. It is called for each persisted event and for each event
+    /// replayed while recovering, so it should only modify the state, without side effects.
+    fn apply(&mut self, event: &Self::Event);
+
+    /// The key of the events and snapshots of the actor instance, like `bank-account-42`. It must
+    /// be unique for each instance and stay the same between versions of the application, as the
+    /// events stored with a key are only recovered with that same key.
+    fn persistence_id(id: &Self::Id) -> String;
+}
+
+/// A [PersistentActor](./trait.PersistentActor.html) which state can be saved in snapshots, so
+/// it doesn't need to replay all its events each time it is activated. Only available with the
+/// `persistence` feature.
+///
+/// Recover it with
+/// [ActorAssistant::recover_from_snapshot](./struct.ActorAssistant.html#method.recover_from_snapshot)
+/// instead of `recover`. It restores the last snapshot from the
+/// [SnapshotStore](./trait.SnapshotStore.html) and applies only the events persisted after it.
+/// Then the framework saves new snapshots as the policy says. The state is serialized as JSON
+/// with [serde](https://serde.rs), so fields that shouldn't be in the snapshot can be skipped
+/// with `#[serde(skip)]`.
+///
+/// ```rust,no_run
+/// use acteur::{Actor, ActorAssistant, PersistentActor, SnapshotActor, SnapshotPolicy};
+/// use async_trait::async_trait;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, Default, Serialize, Deserialize)]
+/// struct Inventory {
+///     stock: std::collections::HashMap<String, u64>,
+/// }
+///
+/// #[derive(Debug, Serialize, Deserialize)]
+/// struct StockChanged(String, u64);
+///
+/// #[async_trait]
+/// impl Actor for Inventory {
+///     type Id = u32;
+///
+///     async fn activate(_: Self::Id, assistant: &ActorAssistant<Self>) -> Self {
+///         assistant
+///             .recover_from_snapshot(Inventory::default())
+///             .await
+///             .expect("Cannot recover the inventory")
+///     }
+/// }
+///
+/// impl PersistentActor for Inventory {
+///     type Event = StockChanged;
+///
+///     fn apply(&mut self, event: &StockChanged) {
+///         self.stock.insert(event.0.clone(), event.1);
+///     }
+///
+///     fn persistence_id(id: &u32) -> String {
+///         format!("inventory-{}", id)
+///     }
+/// }
+///
+/// impl SnapshotActor for Inventory {
+///     fn snapshot_policy() -> SnapshotPolicy {
+///         SnapshotPolicy::EveryMessagesAndOnDeactivate(1000)
+///     }
+/// }
+/// ```
+pub trait SnapshotActor: PersistentActor + Serialize + DeserializeOwned {
+    /// When the snapshots are saved. By default, before the actor is deactivated.
+    fn snapshot_policy() -> SnapshotPolicy {
+        SnapshotPolicy::OnDeactivate
+    }
+}
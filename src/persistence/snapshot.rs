@@ -0,0 +1,194 @@
+use crate::error::JournalError;
+use crate::persistence::journal::file_name_for;
+use crate::runtime;
+use dashmap::DashMap;
+use std::convert::TryInto;
+use std::fmt::Debug;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The serialized state of a [snapshot actor](./trait.SnapshotActor.html) after applying all the
+/// events up to `sequence_nr`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub sequence_nr: u64,
+    pub payload: Vec<u8>,
+}
+
+/// Defines when the state of a [snapshot actor](./trait.SnapshotActor.html) is saved. A snapshot
+/// is only saved if the actor persisted events since the previous one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotPolicy {
+    /// Saves a snapshot after handling the given number of messages.
+    EveryMessages(usize),
+    /// Saves a snapshot before the actor is deactivated.
+    OnDeactivate,
+    /// Saves a snapshot after handling the given number of messages and before the actor
+    /// is deactivated.
+    EveryMessagesAndOnDeactivate(usize),
+}
+
+impl SnapshotPolicy {
+    pub(crate) fn every_messages(&self) -> Option<usize> {
+        match self {
+            SnapshotPolicy::EveryMessages(messages)
+            | SnapshotPolicy::EveryMessagesAndOnDeactivate(messages) => Some(*messages),
+            SnapshotPolicy::OnDeactivate => None,
+        }
+    }
+
+    pub(crate) fn on_deactivate(&self) -> bool {
+        match self {
+            SnapshotPolicy::EveryMessages(_) => false,
+            SnapshotPolicy::OnDeactivate | SnapshotPolicy::EveryMessagesAndOnDeactivate(_) => true,
+        }
+    }
+}
+
+/// Storage for the last snapshot of each [snapshot actor](./trait.SnapshotActor.html) instance,
+/// identified by its persistence id. Acteur ships an
+/// [InMemorySnapshotStore](./struct.InMemorySnapshotStore.html), used by default, and a
+/// [FileSnapshotStore](./struct.FileSnapshotStore.html).
+///
+/// The store is configured for the whole system with
+/// [ActeurBuilder::snapshot_store](./struct.ActeurBuilder.html#method.snapshot_store).
+#[async_trait::async_trait]
+pub trait SnapshotStore: Send + Sync + 'static {
+    /// Saves the snapshot, replacing the previous one of the persistence id.
+    async fn save(&self, persistence_id: &str, snapshot: Snapshot) -> Result<(), JournalError>;
+
+    /// Returns the last snapshot saved for the persistence id, if any.
+    async fn load(&self, persistence_id: &str) -> Result<Option<Snapshot>, JournalError>;
+}
+
+impl Debug for dyn SnapshotStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "SnapshotStore ()")
+    }
+}
+
+/// A [SnapshotStore](./trait.SnapshotStore.html) that keeps the snapshots in memory. It is the
+/// default snapshot store. Clones share the same snapshots.
+#[derive(Debug, Clone, Default)]
+pub struct InMemorySnapshotStore {
+    snapshots: Arc<DashMap<String, Snapshot>>,
+}
+
+impl InMemorySnapshotStore {
+    pub fn new() -> InMemorySnapshotStore {
+        InMemorySnapshotStore::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl SnapshotStore for InMemorySnapshotStore {
+    async fn save(&self, persistence_id: &str, snapshot: Snapshot) -> Result<(), JournalError> {
+        self.snapshots.insert(persistence_id.to_string(), snapshot);
+        Ok(())
+    }
+
+    async fn load(&self, persistence_id: &str) -> Result<Option<Snapshot>, JournalError> {
+        Ok(self
+            .snapshots
+            .get(persistence_id)
+            .map(|snapshot| snapshot.value().clone()))
+    }
+}
+
+/// A [SnapshotStore](./trait.SnapshotStore.html) that keeps the last snapshot of each persistence
+/// id in a file in a directory.
+///
+/// Snapshots are written to a temporary file that then replaces the previous one, so a crash
+/// while saving never leaves a broken snapshot behind.
+///
+/// ```rust,no_run
+/// use acteur::{Acteur, FileJournal, FileSnapshotStore};
+///
+/// let sys = Acteur::builder()
+///     .journal(FileJournal::new("./journal").expect("Cannot create the journal directory"))
+///     .snapshot_store(FileSnapshotStore::new("./snapshots").expect("Cannot create the snapshots directory"))
+///     .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct FileSnapshotStore {
+    directory: Arc<PathBuf>,
+}
+
+impl FileSnapshotStore {
+    /// Creates a store that keeps the files in the directory, creating it if needed.
+    pub fn new<P: Into<PathBuf>>(directory: P) -> std::io::Result<FileSnapshotStore> {
+        let directory = directory.into();
+
+        fs::create_dir_all(&directory)?;
+
+        Ok(FileSnapshotStore {
+            directory: Arc::new(directory),
+        })
+    }
+
+    fn path_for(&self, persistence_id: &str, extension: &str) -> PathBuf {
+        self.directory
+            .join(format!("{}.{}", file_name_for(persistence_id), extension))
+    }
+}
+
+#[async_trait::async_trait]
+impl SnapshotStore for FileSnapshotStore {
+    async fn save(&self, persistence_id: &str, snapshot: Snapshot) -> Result<(), JournalError> {
+        let path = self.path_for(persistence_id, "snapshot");
+        let temporary_path = self.path_for(persistence_id, "snapshot.tmp");
+
+        runtime::spawn_blocking(move || write_snapshot(&path, &temporary_path, &snapshot)).await
+    }
+
+    async fn load(&self, persistence_id: &str) -> Result<Option<Snapshot>, JournalError> {
+        let path = self.path_for(persistence_id, "snapshot");
+
+        runtime::spawn_blocking(move || read_snapshot(&path)).await
+    }
+}
+
+// Each file contains the sequence number (8 bytes, little endian) followed by the payload.
+const SNAPSHOT_HEADER_LENGTH: usize = 8;
+
+fn write_snapshot(
+    path: &Path,
+    temporary_path: &Path,
+    snapshot: &Snapshot,
+) -> Result<(), JournalError> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(temporary_path)?;
+
+    file.write_all(&snapshot.sequence_nr.to_le_bytes())?;
+    file.write_all(&snapshot.payload)?;
+    file.sync_data()?;
+
+    fs::rename(temporary_path, path)?;
+
+    Ok(())
+}
+
+fn read_snapshot(path: &Path) -> Result<Option<Snapshot>, JournalError> {
+    let content = match fs::read(path) {
+        Ok(content) => content,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error.into()),
+    };
+
+    if content.len() < SNAPSHOT_HEADER_LENGTH {
+        return Err(JournalError::Storage(format!(
+            "the snapshot file {:?} is broken",
+            path
+        )));
+    }
+
+    Ok(Some(Snapshot {
+        sequence_nr: u64::from_le_bytes(content[..SNAPSHOT_HEADER_LENGTH].try_into().unwrap()),
+        payload: content[SNAPSHOT_HEADER_LENGTH..].to_vec(),
+    }))
+}
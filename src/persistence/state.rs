@@ -0,0 +1,120 @@
+use crate::persistence::snapshot::{Snapshot, SnapshotPolicy};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// What the framework remembers about the persistence of an actor instance between messages.
+/// It lives in the `ActorAssistant`, so it survives deactivations and restarts of the instance.
+pub(crate) struct PersistenceState<A> {
+    // Sequence number of the last event persisted or recovered
+    last_sequence_nr: AtomicU64,
+    // Only set when the actor was recovered with snapshots
+    snapshots: Mutex<Option<SnapshotSettings<A>>>,
+}
+
+struct SnapshotSettings<A> {
+    policy: SnapshotPolicy,
+    persistence_id: String,
+    serialize: fn(&A) -> serde_json::Result<Vec<u8>>,
+    messages_since_snapshot: usize,
+    last_snapshot_sequence_nr: u64,
+}
+
+impl<A> PersistenceState<A> {
+    pub(crate) fn new() -> PersistenceState<A> {
+        PersistenceState {
+            last_sequence_nr: AtomicU64::new(0),
+            snapshots: Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn last_sequence_nr(&self) -> u64 {
+        self.last_sequence_nr.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_last_sequence_nr(&self, sequence_nr: u64) {
+        self.last_sequence_nr.store(sequence_nr, Ordering::Relaxed);
+    }
+
+    pub(crate) fn disable_snapshots(&self) {
+        *self.lock_snapshots() = None;
+    }
+
+    pub(crate) fn enable_snapshots(
+        &self,
+        policy: SnapshotPolicy,
+        persistence_id: String,
+        serialize: fn(&A) -> serde_json::Result<Vec<u8>>,
+        last_snapshot_sequence_nr: u64,
+    ) {
+        *self.lock_snapshots() = Some(SnapshotSettings {
+            policy,
+            persistence_id,
+            serialize,
+            messages_since_snapshot: 0,
+            last_snapshot_sequence_nr,
+        });
+    }
+
+    /// Counts a handled message and returns the snapshot to save, and the persistence id where to
+    /// save it, if the policy asks for one.
+    pub(crate) fn snapshot_after_message(&self, actor: &A) -> Option<(String, Snapshot)> {
+        let mut snapshots = self.lock_snapshots();
+        let settings = snapshots.as_mut()?;
+        let every_messages = settings.policy.every_messages()?;
+
+        settings.messages_since_snapshot += 1;
+
+        if settings.messages_since_snapshot < every_messages {
+            return None;
+        }
+
+        self.take_snapshot(settings, actor)
+    }
+
+    /// Returns the snapshot to save before the deactivation, if the policy asks for one.
+    pub(crate) fn snapshot_before_deactivate(&self, actor: &A) -> Option<(String, Snapshot)> {
+        let mut snapshots = self.lock_snapshots();
+        let settings = snapshots.as_mut()?;
+
+        if !settings.policy.on_deactivate() {
+            return None;
+        }
+
+        self.take_snapshot(settings, actor)
+    }
+
+    fn take_snapshot(
+        &self,
+        settings: &mut SnapshotSettings<A>,
+        actor: &A,
+    ) -> Option<(String, Snapshot)> {
+        let sequence_nr = self.last_sequence_nr();
+
+        // Nothing changed since the previous snapshot
+        if sequence_nr == settings.last_snapshot_sequence_nr {
+            return None;
+        }
+
+        // If the state cannot be serialized, the events are still in the journal.
+        let payload = (settings.serialize)(actor).ok()?;
+
+        settings.messages_since_snapshot = 0;
+        settings.last_snapshot_sequence_nr = sequence_nr;
+
+        Some((
+            settings.persistence_id.clone(),
+            Snapshot {
+                sequence_nr,
+                payload,
+            },
+        ))
+    }
+
+    fn lock_snapshots(&self) -> std::sync::MutexGuard<'_, Option<SnapshotSettings<A>>> {
+        // A panic while holding the lock cannot leave the settings half updated
+        match self.snapshots.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
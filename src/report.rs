@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+/// Statistics of the whole system, returned by `Acteur::get_statistics`.
+///
+/// Actors and services are keyed by their type name (as returned by `std::any::type_name`).
+/// Only actors and services loaded in Ram appear in the report.
+///
+/// ```rust,no_run
+/// use acteur::Acteur;
+///
+/// let sys = Acteur::new();
+///
+/// let report = sys.get_statistics();
+///
+/// for (actor_type, actors) in report.actors.iter() {
+///     let enqueued: usize = actors.iter().map(|actor| actor.enqueued_messages).sum();
+///     println!("{}: {} actors, {} enqueued messages", actor_type, actors.len(), enqueued);
+/// }
+///
+/// for (service_type, service) in report.services.iter() {
+///     println!("{}: {} handled messages", service_type, service.handled_messages);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SystemReport {
+    pub actors: HashMap<&'static str, Vec<ActorReport>>,
+    pub services: HashMap<&'static str, ServiceReport>,
+}
+
+/// Statistics of an actor instance.
+#[derive(Debug, Clone)]
+pub struct ActorReport {
+    /// Last time a message was sent to this actor.
+    pub last_message_on: SystemTime,
+    pub enqueued_messages: usize,
+}
+
+/// Statistics of a service.
+#[derive(Debug, Clone)]
+pub struct ServiceReport {
+    /// Last time the service started handling a message. None if it didn't handle any yet.
+    pub last_message_on: Option<SystemTime>,
+    /// Enqueued messages in each concurrency lane. See [ServiceConcurrency](./enum.ServiceConcurrency.html).
+    pub enqueued_messages: Vec<usize>,
+    /// Messages handled since the service was initialized.
+    pub handled_messages: usize,
+    /// Messages being handled right now.
+    pub in_flight_messages: usize,
+}
@@ -0,0 +1,253 @@
+use futures::future::BoxFuture;
+use std::future::Future;
+use std::time::Duration;
+
+#[cfg(not(any(feature = "async-std", feature = "tokio")))]
+compile_error!("Acteur needs a runtime. Enable the feature `async-std` (default) or `tokio`.");
+
+// The operations that Acteur needs from an async runtime. All the timeouts and blocking calls
+// of Acteur go through this trait, and the tasks of a system through its `Executor`.
+//
+// The runtime is chosen at compile time with the cargo features:
+//
+// - `async-std` (default): uses `AsyncStdRuntime`.
+// - `tokio`: uses `TokioRuntime`. Disable the default features in order to use it:
+//   `acteur = { version = "*", default-features = false, features = ["tokio"] }`.
+//
+// If both features are enabled, async-std is used.
+pub(crate) trait Runtime: 'static {
+    /// Waits for the duration without blocking the thread.
+    fn sleep(duration: Duration) -> BoxFuture<'static, ()>;
+
+    /// Waits for the future at most the duration. Returns None if the duration passes first,
+    /// in which case the future is dropped.
+    fn timeout<'a, F>(duration: Duration, future: F) -> BoxFuture<'a, Option<F::Output>>
+    where
+        F: Future + Send + 'a;
+
+    /// Blocks the current thread until the future finishes. Used by the `BlockingActeur`.
+    fn block_on<F: Future>(future: F) -> F::Output;
+
+    /// Returns false if the current thread must not be blocked with `block_on`, usually because
+    /// it is running tasks of the runtime. Blocking it could deadlock the system.
+    fn can_block() -> bool;
+
+    /// Runs blocking code (like file IO) in a thread where blocking is allowed and waits for
+    /// its result without blocking the current thread.
+    fn spawn_blocking<F, T>(function: F) -> BoxFuture<'static, T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static;
+}
+
+/// Runs Acteur on [async-std](https://docs.rs/async-std). Enabled with the `async-std` feature.
+#[cfg(feature = "async-std")]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AsyncStdRuntime;
+
+#[cfg(feature = "async-std")]
+impl Runtime for AsyncStdRuntime {
+    fn sleep(duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(async_std::task::sleep(duration))
+    }
+
+    fn timeout<'a, F>(duration: Duration, future: F) -> BoxFuture<'a, Option<F::Output>>
+    where
+        F: Future + Send + 'a,
+    {
+        Box::pin(async move { async_std::future::timeout(duration, future).await.ok() })
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        async_std::task::block_on(future)
+    }
+
+    fn can_block() -> bool {
+        // Only tasks (including the ones run by `block_on`) have a current task.
+        async_std::task::try_current().is_none()
+    }
+
+    fn spawn_blocking<F, T>(function: F) -> BoxFuture<'static, T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        Box::pin(async_std::task::spawn_blocking(function))
+    }
+}
+
+/// Runs Acteur on [tokio](https://docs.rs/tokio). Enabled with the `tokio` feature.
+///
+/// The tasks of a system run in the tokio runtime where the system was built (see `Executor`).
+/// If there is none (for example, for the global system or when building it from a plain thread)
+/// Acteur uses its own multi-threaded runtime.
+///
+/// The blocking methods can be called from inside a tokio runtime only if it is multi-threaded,
+/// as they block the current worker thread.
+#[cfg(all(feature = "tokio", not(feature = "async-std")))]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TokioRuntime;
+
+#[cfg(all(feature = "tokio", not(feature = "async-std")))]
+lazy_static::lazy_static! {
+    static ref FALLBACK_TOKIO_RUNTIME: tokio::runtime::Runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_time()
+        .thread_name("acteur")
+        .build()
+        .expect("Cannot create the tokio runtime for Acteur");
+}
+
+// The tokio timers need a runtime when they are created. When the futures of Acteur are awaited
+// outside of tokio, the timers are created in the fallback runtime, which drives them.
+#[cfg(all(feature = "tokio", not(feature = "async-std")))]
+fn fallback_context() -> Option<tokio::runtime::EnterGuard<'static>> {
+    match tokio::runtime::Handle::try_current() {
+        Ok(_) => None,
+        Err(_) => Some(FALLBACK_TOKIO_RUNTIME.enter()),
+    }
+}
+
+#[cfg(all(feature = "tokio", not(feature = "async-std")))]
+impl Runtime for TokioRuntime {
+    fn sleep(duration: Duration) -> BoxFuture<'static, ()> {
+        let _context = fallback_context();
+        Box::pin(tokio::time::sleep(duration))
+    }
+
+    fn timeout<'a, F>(duration: Duration, future: F) -> BoxFuture<'a, Option<F::Output>>
+    where
+        F: Future + Send + 'a,
+    {
+        let _context = fallback_context();
+        let timeout = tokio::time::timeout(duration, future);
+        Box::pin(async move { timeout.await.ok() })
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => tokio::task::block_in_place(|| handle.block_on(future)),
+            Err(_) => FALLBACK_TOKIO_RUNTIME.block_on(future),
+        }
+    }
+
+    fn can_block() -> bool {
+        // In multi-threaded runtimes `block_on` moves the tasks of the worker to other threads.
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread,
+            Err(_) => true,
+        }
+    }
+
+    fn spawn_blocking<F, T>(function: F) -> BoxFuture<'static, T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let handle = match tokio::runtime::Handle::try_current() {
+            Ok(handle) => handle.spawn_blocking(function),
+            Err(_) => FALLBACK_TOKIO_RUNTIME.spawn_blocking(function),
+        };
+
+        Box::pin(async move {
+            match handle.await {
+                Ok(result) => result,
+                // Same as in async-std, a panic in the function is a panic in the caller
+                Err(error) => std::panic::resume_unwind(error.into_panic()),
+            }
+        })
+    }
+}
+
+/// The runtime selected by the cargo features.
+#[cfg(feature = "async-std")]
+pub(crate) type DefaultRuntime = AsyncStdRuntime;
+
+#[cfg(all(feature = "tokio", not(feature = "async-std")))]
+pub(crate) type DefaultRuntime = TokioRuntime;
+
+/// Runs the tasks owned by a system: the loops of managers, actors and services, the scheduler,
+/// etc. It is captured when the system is built, so those tasks don't depend on the runtime of
+/// the code sending messages later, which may stop before the system does.
+#[derive(Debug, Clone)]
+pub(crate) struct Executor {
+    #[cfg(all(feature = "tokio", not(feature = "async-std")))]
+    handle: tokio::runtime::Handle,
+}
+
+// async-std has only one global executor, so there is nothing to capture.
+#[cfg(feature = "async-std")]
+impl Executor {
+    pub(crate) fn current() -> Executor {
+        Executor {}
+    }
+
+    pub(crate) fn owned() -> Executor {
+        Executor {}
+    }
+
+    pub(crate) fn spawn<F>(&self, future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        async_std::task::spawn(future);
+    }
+}
+
+#[cfg(all(feature = "tokio", not(feature = "async-std")))]
+impl Executor {
+    /// The runtime of the calling code or, if there is none, the one owned by Acteur.
+    pub(crate) fn current() -> Executor {
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => Executor { handle },
+            Err(_) => Executor::owned(),
+        }
+    }
+
+    /// The runtime owned by Acteur, which lives as long as the process.
+    pub(crate) fn owned() -> Executor {
+        Executor {
+            handle: FALLBACK_TOKIO_RUNTIME.handle().clone(),
+        }
+    }
+
+    pub(crate) fn spawn<F>(&self, future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.handle.spawn(future);
+    }
+}
+
+// Shortcuts for the rest of the crate, so no other module depends on a concrete runtime.
+
+#[cfg_attr(not(feature = "testkit"), allow(dead_code))]
+pub(crate) fn sleep(duration: Duration) -> BoxFuture<'static, ()> {
+    DefaultRuntime::sleep(duration)
+}
+
+pub(crate) fn timeout<'a, F>(duration: Duration, future: F) -> BoxFuture<'a, Option<F::Output>>
+where
+    F: Future + Send + 'a,
+{
+    DefaultRuntime::timeout(duration, future)
+}
+
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    // A clear panic is better than a deadlock that is hard to debug.
+    assert!(
+        DefaultRuntime::can_block(),
+        "Acteur cannot block a thread of the async runtime. Inside async code, use `Acteur` and \
+         await its methods instead of using `BlockingActeur` or other blocking methods."
+    );
+
+    DefaultRuntime::block_on(future)
+}
+
+#[cfg_attr(not(feature = "persistence"), allow(dead_code))]
+pub(crate) fn spawn_blocking<F, T>(function: F) -> BoxFuture<'static, T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    DefaultRuntime::spawn_blocking(function)
+}
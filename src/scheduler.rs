@@ -0,0 +1,384 @@
+use crate::clock::Clock;
+use crate::observer::Observer;
+use crate::runtime::Executor;
+use async_channel::{unbounded as channel, Receiver, Sender};
+use futures::future::BoxFuture;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Resolution of the timers. Messages are never sent before their time, but they may be sent
+// up to one tick later.
+const TICK: Duration = Duration::from_millis(10);
+const SLOTS: usize = 256;
+
+/// Returned by the methods that schedule messages. It allows to cancel the scheduled message or,
+/// for intervals, all the future ones.
+///
+/// Dropping the handle doesn't cancel anything. All the pending schedules are discarded, and their
+/// handles cancelled, when the system stops.
+///
+/// ```rust,no_run
+/// # use acteur::{Actor, Receive, ActorAssistant, BlockingActeur};
+/// # use async_trait::async_trait;
+/// # use std::time::Duration;
+/// #
+/// # #[derive(Debug)]
+/// # struct Game;
+/// #
+/// # #[async_trait]
+/// # impl Actor for Game {
+/// #     type Id = u32;
+/// #
+/// #     async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+/// #         Game
+/// #     }
+/// # }
+/// #
+/// #[derive(Debug, Clone)]
+/// struct Tick;
+///
+/// # #[async_trait]
+/// # impl Receive<Tick> for Game {
+/// #     async fn handle(&mut self, _: Tick, _: &ActorAssistant<Game>) {}
+/// # }
+/// #
+/// let sys = BlockingActeur::new();
+///
+/// let ticks = sys.schedule_interval_to_actor::<Game, Tick>(1, Duration::from_millis(100), Tick);
+///
+/// // ... later
+/// ticks.cancel();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ScheduleHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl ScheduleHandle {
+    pub(crate) fn new() -> ScheduleHandle {
+        ScheduleHandle {
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Cancels the schedule. Messages already sent are not affected.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if the schedule was cancelled or if the system stopped before scheduling it.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+type Action = Box<dyn FnMut() -> BoxFuture<'static, ()> + Send>;
+
+enum SchedulerCommand {
+    Schedule(Timer),
+    // Answered with the number of timers discarded
+    Stop(Sender<usize>),
+}
+
+struct Timer {
+    deadline: Instant,
+    interval: Option<Duration>,
+    handle: ScheduleHandle,
+    action: Action,
+}
+
+/// Runs all the scheduled messages of a system from a single task, using a timer wheel.
+#[derive(Debug, Clone)]
+pub(crate) struct Scheduler {
+    sender: Sender<SchedulerCommand>,
+    clock: Clock,
+}
+
+impl Scheduler {
+    pub(crate) fn new(clock: Clock, observer: Observer, executor: Executor) -> Scheduler {
+        let (sender, receiver) = channel();
+
+        executor
+            .clone()
+            .spawn(scheduler_loop(receiver, clock.clone(), observer, executor));
+
+        Scheduler { sender, clock }
+    }
+
+    /// Runs the action once, after the duration.
+    pub(crate) fn schedule_once<F>(&self, duration: Duration, action: F) -> ScheduleHandle
+    where
+        F: FnOnce() -> BoxFuture<'static, ()> + Send + 'static,
+    {
+        let mut action = Some(action);
+
+        self.schedule(duration, None, move || match action.take() {
+            Some(action) => action(),
+            // Once timers are never fired twice
+            None => Box::pin(async {}),
+        })
+    }
+
+    /// Runs the action every time the interval passes, until the handle is cancelled.
+    pub(crate) fn schedule_interval<F>(&self, interval: Duration, action: F) -> ScheduleHandle
+    where
+        F: FnMut() -> BoxFuture<'static, ()> + Send + 'static,
+    {
+        self.schedule(interval, Some(interval), action)
+    }
+
+    fn schedule<F>(
+        &self,
+        duration: Duration,
+        interval: Option<Duration>,
+        action: F,
+    ) -> ScheduleHandle
+    where
+        F: FnMut() -> BoxFuture<'static, ()> + Send + 'static,
+    {
+        let handle = ScheduleHandle::new();
+
+        let timer = Timer {
+            deadline: self.clock.now() + duration,
+            interval,
+            handle: handle.clone(),
+            action: Box::new(action),
+        };
+
+        // If the scheduler is stopped, nothing will be sent.
+        if self
+            .sender
+            .try_send(SchedulerCommand::Schedule(timer))
+            .is_err()
+        {
+            handle.cancel();
+        }
+
+        handle
+    }
+
+    /// Discards all the pending timers and stops the scheduler. Returns how many timers were
+    /// discarded, without counting the ones already cancelled.
+    pub(crate) async fn stop(&self) -> usize {
+        let (sender, receiver) = channel();
+
+        if self
+            .sender
+            .try_send(SchedulerCommand::Stop(sender))
+            .is_err()
+        {
+            // Already stopped
+            return 0;
+        }
+
+        receiver.recv().await.unwrap_or(0)
+    }
+}
+
+async fn scheduler_loop(
+    receiver: Receiver<SchedulerCommand>,
+    clock: Clock,
+    observer: Observer,
+    executor: Executor,
+) {
+    let mut wheel = TimerWheel::new(clock.now());
+
+    loop {
+        let command = if wheel.is_empty() {
+            receiver.recv().await
+        } else {
+            let wait = wheel
+                .next_expiration_instant()
+                .saturating_duration_since(clock.now());
+
+            match clock.timeout(wait, receiver.recv()).await {
+                Some(command) => command,
+                None => {
+                    let _busy = observer.busy();
+                    let now = clock.now();
+
+                    for timer in wheel.advance(now) {
+                        fire(timer, &mut wheel, now, &observer, &executor);
+                    }
+                    continue;
+                }
+            }
+        };
+
+        match command {
+            Ok(SchedulerCommand::Schedule(timer)) => wheel.insert(timer, clock.now()),
+            Ok(SchedulerCommand::Stop(reply)) => {
+                let _ = reply.try_send(discard(wheel, &receiver));
+                break;
+            }
+            Err(_) => break,
+        }
+    }
+}
+
+/// Cancels the timers of the wheel and the ones still in the channel, which is closed so no
+/// more are sent. Returns how many were not cancelled yet.
+fn discard(wheel: TimerWheel, receiver: &Receiver<SchedulerCommand>) -> usize {
+    receiver.close();
+
+    let mut timers = wheel.into_timers();
+
+    while let Ok(command) = receiver.try_recv() {
+        match command {
+            SchedulerCommand::Schedule(timer) => timers.push(timer),
+            SchedulerCommand::Stop(reply) => {
+                let _ = reply.try_send(0);
+            }
+        }
+    }
+
+    let mut discarded = 0;
+
+    for timer in timers {
+        if !timer.handle.is_cancelled() {
+            timer.handle.cancel();
+            discarded += 1;
+        }
+    }
+
+    discarded
+}
+
+fn fire(
+    mut timer: Timer,
+    wheel: &mut TimerWheel,
+    now: Instant,
+    observer: &Observer,
+    executor: &Executor,
+) {
+    if timer.handle.is_cancelled() {
+        return;
+    }
+
+    // The action only creates the future. Sending the message happens in its own task so
+    // a slow manager doesn't delay other timers.
+    let action = (timer.action)();
+    let busy = observer.busy();
+
+    executor.spawn(async move {
+        let _busy = busy;
+        action.await;
+    });
+
+    if let Some(interval) = timer.interval {
+        timer.deadline += interval;
+        wheel.insert(timer, now);
+    }
+}
+
+/// Hashed timer wheel. Each slot contains the timers that expire in that tick, or in that tick
+/// plus some full turns of the wheel.
+struct TimerWheel {
+    start: Instant,
+    // Last tick processed
+    current_tick: u64,
+    slots: Vec<Vec<Timer>>,
+    len: usize,
+}
+
+impl TimerWheel {
+    fn new(now: Instant) -> TimerWheel {
+        TimerWheel {
+            start: now,
+            current_tick: 0,
+            slots: (0..SLOTS).map(|_| Vec::new()).collect(),
+            len: 0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn into_timers(self) -> Vec<Timer> {
+        self.slots.into_iter().flatten().collect()
+    }
+
+    fn insert(&mut self, timer: Timer, now: Instant) {
+        // If the wheel was idle, we don't want to go over all the ticks that passed meanwhile.
+        if self.is_empty() {
+            self.current_tick = self.elapsed_ticks(now);
+        }
+
+        let tick = self.expiration_tick(timer.deadline);
+
+        self.slots[(tick % SLOTS as u64) as usize].push(timer);
+        self.len += 1;
+    }
+
+    /// Removes and returns all the timers expired until now.
+    fn advance(&mut self, now: Instant) -> Vec<Timer> {
+        let now_tick = self.elapsed_ticks(now);
+        let mut expired = vec![];
+
+        if now_tick <= self.current_tick {
+            return expired;
+        }
+
+        // If more than a whole turn passed, every slot is visited once.
+        let ticks_to_visit = (now_tick - self.current_tick).min(SLOTS as u64);
+
+        for tick in (self.current_tick + 1)..=(self.current_tick + ticks_to_visit) {
+            let slot = &mut self.slots[(tick % SLOTS as u64) as usize];
+            let mut index = 0;
+
+            while index < slot.len() {
+                if slot[index].deadline <= now {
+                    expired.push(slot.swap_remove(index));
+                } else {
+                    index += 1;
+                }
+            }
+        }
+
+        self.len -= expired.len();
+        self.current_tick = now_tick;
+
+        // Order matters for timers sending messages to the same actor.
+        expired.sort_by_key(|timer| timer.deadline);
+
+        expired
+    }
+
+    /// The end of the tick in which the first timer expires. The scheduler sleeps until then
+    /// instead of waking up every tick.
+    fn next_expiration_instant(&self) -> Instant {
+        let mut next_tick = u64::MAX;
+
+        // Timers expiring in the next turn of the wheel are usually found in the first slots
+        for tick in (self.current_tick + 1)..=(self.current_tick + SLOTS as u64) {
+            for timer in &self.slots[(tick % SLOTS as u64) as usize] {
+                let timer_tick = self.expiration_tick(timer.deadline);
+
+                if timer_tick == tick {
+                    return self.tick_instant(tick);
+                }
+
+                next_tick = next_tick.min(timer_tick);
+            }
+        }
+
+        self.tick_instant(next_tick)
+    }
+
+    /// Timers are never fired before their deadline, so we round up. A timer for the current
+    /// tick (or before) goes to the next one as the current one is already processed.
+    fn expiration_tick(&self, deadline: Instant) -> u64 {
+        self.elapsed_ticks(deadline + TICK - Duration::from_nanos(1))
+            .max(self.current_tick + 1)
+    }
+
+    fn tick_instant(&self, tick: u64) -> Instant {
+        self.start + Duration::from_nanos(TICK.as_nanos() as u64 * tick)
+    }
+
+    fn elapsed_ticks(&self, instant: Instant) -> u64 {
+        (instant.saturating_duration_since(self.start).as_nanos() / TICK.as_nanos()) as u64
+    }
+}
@@ -1,96 +1,403 @@
+use crate::actors::director::ActorsDirector;
 use crate::services::director::ServicesDirector;
 use crate::services::handle::Listen;
 use crate::services::service::Service;
-use async_std::sync::Arc;
-use dashmap::{mapref::entry::Entry, DashMap};
+use crate::trace::TraceContext;
+use crate::{Actor, Receive};
+use dashmap::DashMap;
 use std::any::Any;
 use std::any::TypeId;
+use std::collections::HashSet;
 use std::fmt::Debug;
 use std::marker::PhantomData;
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct MessageBroker {
-    managers: Arc<DashMap<TypeId, Vec<Box<dyn ServiceManagerPublish>>>>,
+    subscriptions: Arc<DashMap<TypeId, Vec<Subscription>>>,
     system_director: Box<ServicesDirector>,
 }
 
+/// A subscriber and the topic pattern it is interested in. Subscriptions without pattern
+/// receive all the messages of their type, with or without topic.
+#[derive(Debug, Clone)]
+struct Subscription {
+    topic_pattern: Option<String>,
+    subscriber: Arc<dyn Subscriber>,
+}
+
+impl Subscription {
+    fn receives(&self, topic: Option<&str>) -> bool {
+        match (&self.topic_pattern, topic) {
+            (None, _) => true,
+            (Some(pattern), Some(topic)) => topic_matches(pattern, topic),
+            (Some(_), None) => false,
+        }
+    }
+}
+
 impl MessageBroker {
     pub(crate) fn new(system_director: ServicesDirector) -> MessageBroker {
         MessageBroker {
-            managers: Arc::new(DashMap::new()),
+            subscriptions: Arc::new(DashMap::new()),
             system_director: Box::new(system_director),
         }
     }
 
-    pub(crate) fn register<S: Service + Listen<M>, M: Sync + Send + Debug + 'static>(&self) {
-        let type_id = TypeId::of::<M>();
+    /// Adds the subscription unless the subscriber is already subscribed to the same pattern.
+    fn subscribe<M: 'static>(&self, subscriber: Arc<dyn Subscriber>, topic_pattern: Option<&str>) {
+        let mut subscriptions = self.subscriptions.entry(TypeId::of::<M>()).or_default();
 
-        let managers_entry = self.managers.entry(type_id);
+        let is_subscribed = subscriptions.iter().any(|subscription| {
+            subscription.topic_pattern.as_deref() == topic_pattern
+                && subscription.subscriber.is_same(&*subscriber)
+        });
 
-        match managers_entry {
-            Entry::Occupied(mut entry) => {
-                entry
-                    .get_mut()
-                    .push(Box::new(ServiceManagerWrapper::<S, M>::new()));
-            }
-            Entry::Vacant(entry) => {
-                entry.insert(vec![Box::new(ServiceManagerWrapper::<S, M>::new())]);
+        if !is_subscribed {
+            subscriptions.push(Subscription {
+                topic_pattern: topic_pattern.map(String::from),
+                subscriber,
+            });
+        }
+    }
+
+    fn unsubscribe<M: 'static>(&self, subscriber: &dyn Subscriber, topic_pattern: Option<&str>) {
+        if let Some(mut subscriptions) = self.subscriptions.get_mut(&TypeId::of::<M>()) {
+            subscriptions.retain(|subscription| {
+                subscription.topic_pattern.as_deref() != topic_pattern
+                    || !subscription.subscriber.is_same(subscriber)
+            });
+        }
+    }
+
+    pub(crate) fn register<S: Service + Listen<M>, M: Sync + Send + Debug + 'static>(
+        &self,
+        topic_pattern: Option<&str>,
+    ) {
+        self.subscribe::<M>(Arc::new(ServiceSubscriber::<S, M>::new()), topic_pattern);
+    }
+
+    pub(crate) fn unregister<S: Service + Listen<M>, M: Sync + Send + Debug + 'static>(
+        &self,
+        topic_pattern: Option<&str>,
+    ) {
+        self.unsubscribe::<M>(&ServiceSubscriber::<S, M>::new(), topic_pattern);
+    }
+
+    /// Subscribes one actor instance. The subscription lasts until the actor deactivates.
+    pub(crate) fn register_actor<A: Actor + Receive<M>, M: Sync + Send + Debug + 'static>(
+        &self,
+        actors_director: ActorsDirector,
+        actor_id: A::Id,
+        topic_pattern: Option<&str>,
+    ) {
+        self.subscribe::<M>(
+            Arc::new(ActorSubscriber::<A, M>::new(actors_director, actor_id)),
+            topic_pattern,
+        );
+    }
+
+    pub(crate) fn unregister_actor<A: Actor + Receive<M>, M: Sync + Send + Debug + 'static>(
+        &self,
+        actors_director: ActorsDirector,
+        actor_id: A::Id,
+        topic_pattern: Option<&str>,
+    ) {
+        self.unsubscribe::<M>(
+            &ActorSubscriber::<A, M>::new(actors_director, actor_id),
+            topic_pattern,
+        );
+    }
+
+    /// Subscribes all the actors of a type. Each loaded actor receives a copy of the messages.
+    pub(crate) fn register_all_actors<
+        A: Actor + Receive<M>,
+        M: Sync + Send + Clone + Debug + 'static,
+    >(
+        &self,
+        actors_director: ActorsDirector,
+        topic_pattern: Option<&str>,
+    ) {
+        self.subscribe::<M>(
+            Arc::new(AllActorsSubscriber::<A, M>::new(actors_director)),
+            topic_pattern,
+        );
+    }
+
+    pub(crate) fn unregister_all_actors<
+        A: Actor + Receive<M>,
+        M: Sync + Send + Clone + Debug + 'static,
+    >(
+        &self,
+        actors_director: ActorsDirector,
+        topic_pattern: Option<&str>,
+    ) {
+        self.unsubscribe::<M>(
+            &AllActorsSubscriber::<A, M>::new(actors_director),
+            topic_pattern,
+        );
+    }
+
+    /// Removes the subscriptions of an actor instance to the given message types, for any topic.
+    pub(crate) fn unregister_actor_instance<A: Actor>(
+        &self,
+        actor_id: &A::Id,
+        message_types: &HashSet<TypeId>,
+    ) {
+        for message_type in message_types {
+            if let Some(mut subscriptions) = self.subscriptions.get_mut(message_type) {
+                subscriptions.retain(|subscription| {
+                    !subscription
+                        .subscriber
+                        .is_actor_instance(TypeId::of::<A>(), actor_id as &dyn Any)
+                });
             }
-        };
+        }
     }
 
-    pub(crate) async fn publish<M: Send + Clone + 'static>(&self, message: M) {
+    /// Sends the message to the subscribers of its type. With a topic, only the subscriptions
+    /// without pattern and the ones with a pattern matching the topic receive it. Without a
+    /// topic, only the subscriptions without pattern receive it.
+    pub(crate) async fn publish<M: Send + Clone + 'static>(&self, topic: Option<&str>, message: M) {
         let type_id = TypeId::of::<M>();
 
-        if let Some(mut managers) = self.managers.get_mut(&type_id) {
-            for manager in managers.value_mut() {
-                let message = message.clone();
-                manager.send(Box::new(message), &self.system_director).await;
-            }
+        // The letters for each subscriber capture the span of the publisher
+        let trace = TraceContext::current();
+
+        // We don't keep the map locked while sending, as actors can subscribe or be
+        // deactivated meanwhile.
+        let subscribers: Vec<Arc<dyn Subscriber>> = match self.subscriptions.get(&type_id) {
+            Some(subscriptions) => subscriptions
+                .iter()
+                .filter(|subscription| subscription.receives(topic))
+                .map(|subscription| subscription.subscriber.clone())
+                .collect(),
+            None => return,
+        };
+
+        for subscriber in subscribers {
+            let message = message.clone();
+            trace
+                .in_scope(subscriber.send(Box::new(message), &self.system_director))
+                .await;
+        }
+    }
+}
+
+/// Topics are words separated by dots, like `orders.eu.created`. In the patterns, `*` matches
+/// exactly one word and `#` matches zero or more words. Empty segments (as in `orders..created`)
+/// are not words, so only `#` or an empty segment in the pattern match them.
+fn topic_matches(pattern: &str, topic: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('.').collect();
+    let topic: Vec<&str> = topic.split('.').collect();
+
+    words_match(&pattern, &topic)
+}
+
+fn words_match(pattern: &[&str], topic: &[&str]) -> bool {
+    match (pattern.split_first(), topic.split_first()) {
+        (None, None) => true,
+        (Some((&"#", rest)), _) => {
+            // Either `#` matches nothing, or it consumes one more word
+            words_match(rest, topic) || (!topic.is_empty() && words_match(pattern, &topic[1..]))
+        }
+        (Some((&word, pattern_rest)), Some((&topic_word, topic_rest))) => {
+            let is_match = word == topic_word || (word == "*" && !topic_word.is_empty());
+            is_match && words_match(pattern_rest, topic_rest)
         }
+        _ => false,
     }
 }
 
 #[async_trait::async_trait]
-trait ServiceManagerPublish: Send + Sync {
-    async fn send(&mut self, message: Box<(dyn Any + Send)>, system_director: &ServicesDirector);
+trait Subscriber: Send + Sync + 'static {
+    async fn send(&self, message: Box<dyn Any + Send>, system_director: &ServicesDirector);
+
+    fn as_any(&self) -> &dyn Any;
+
+    /// Returns true if both subscribers send the messages to the same service or actors.
+    fn is_same(&self, other: &dyn Subscriber) -> bool;
+
+    /// Returns true if this is the subscription of the actor of type `actor_type` and Id `actor_id`.
+    fn is_actor_instance(&self, _actor_type: TypeId, _actor_id: &dyn Any) -> bool {
+        false
+    }
 }
 
 #[derive(Debug)]
-struct ServiceManagerWrapper<S: Service + Listen<M>, M: Debug> {
+struct ServiceSubscriber<S: Service + Listen<M>, M: Debug> {
     phantom_service: PhantomData<S>,
     phantom_message: PhantomData<M>,
 }
 
-impl<S: Service + Listen<M>, M: Debug + Send + 'static> ServiceManagerWrapper<S, M> {
-    fn new() -> ServiceManagerWrapper<S, M> {
-        ServiceManagerWrapper {
+impl<S: Service + Listen<M>, M: Debug + Send + 'static> ServiceSubscriber<S, M> {
+    fn new() -> ServiceSubscriber<S, M> {
+        ServiceSubscriber {
             phantom_service: PhantomData,
             phantom_message: PhantomData,
         }
     }
+}
+
+#[async_trait::async_trait]
+impl<S: Service + Listen<M>, M: Debug + Send + Sync + 'static> Subscriber
+    for ServiceSubscriber<S, M>
+{
+    async fn send(&self, message: Box<dyn Any + Send>, system_director: &ServicesDirector) {
+        match message.downcast::<M>() {
+            Ok(message) => system_director.send::<S, M>(*message).await,
+            Err(_) => unreachable!(),
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn is_same(&self, other: &dyn Subscriber) -> bool {
+        other.as_any().is::<Self>()
+    }
+}
+
+#[derive(Debug)]
+struct ActorSubscriber<A: Actor + Receive<M>, M: Debug> {
+    actors_director: ActorsDirector,
+    actor_id: A::Id,
+    phantom_message: PhantomData<M>,
+}
+
+impl<A: Actor + Receive<M>, M: Debug + Send + 'static> ActorSubscriber<A, M> {
+    fn new(actors_director: ActorsDirector, actor_id: A::Id) -> ActorSubscriber<A, M> {
+        ActorSubscriber {
+            actors_director,
+            actor_id,
+            phantom_message: PhantomData,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<A: Actor + Receive<M>, M: Debug + Send + Sync + 'static> Subscriber for ActorSubscriber<A, M> {
+    async fn send(&self, message: Box<dyn Any + Send>, _: &ServicesDirector) {
+        match message.downcast::<M>() {
+            Ok(message) => {
+                self.actors_director
+                    .send::<A, M>(self.actor_id.clone(), *message)
+                    .await
+            }
+            Err(_) => unreachable!(),
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn is_same(&self, other: &dyn Subscriber) -> bool {
+        match other.as_any().downcast_ref::<Self>() {
+            Some(other) => other.actor_id == self.actor_id,
+            None => false,
+        }
+    }
+
+    fn is_actor_instance(&self, actor_type: TypeId, actor_id: &dyn Any) -> bool {
+        actor_type == TypeId::of::<A>() && actor_id.downcast_ref() == Some(&self.actor_id)
+    }
+}
 
-    async fn send(&mut self, message: M, system_director: &ServicesDirector) {
-        system_director.send::<S, M>(message).await;
+#[derive(Debug)]
+struct AllActorsSubscriber<A: Actor + Receive<M>, M: Debug> {
+    actors_director: ActorsDirector,
+    phantom_actor: PhantomData<A>,
+    phantom_message: PhantomData<M>,
+}
+
+impl<A: Actor + Receive<M>, M: Debug + Send + Clone + 'static> AllActorsSubscriber<A, M> {
+    fn new(actors_director: ActorsDirector) -> AllActorsSubscriber<A, M> {
+        AllActorsSubscriber {
+            actors_director,
+            phantom_actor: PhantomData,
+            phantom_message: PhantomData,
+        }
     }
 }
 
 #[async_trait::async_trait]
-impl<S: Service + Listen<M>, M: Debug + Send + Sync + 'static> ServiceManagerPublish
-    for ServiceManagerWrapper<S, M>
+impl<A: Actor + Receive<M>, M: Debug + Send + Sync + Clone + 'static> Subscriber
+    for AllActorsSubscriber<A, M>
 {
-    async fn send(&mut self, message: Box<(dyn Any + Send)>, system_director: &ServicesDirector) {
+    async fn send(&self, message: Box<dyn Any + Send>, _: &ServicesDirector) {
         match message.downcast::<M>() {
             Ok(message) => {
-                ServiceManagerWrapper::send(self, *message, system_director).await;
+                self.actors_director
+                    .send_copy_to_all::<A, M>(*message)
+                    .await
             }
             Err(_) => unreachable!(),
         }
     }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn is_same(&self, other: &dyn Subscriber) -> bool {
+        other.as_any().is::<Self>()
+    }
 }
 
-impl Debug for dyn ServiceManagerPublish {
+impl Debug for dyn Subscriber {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-        write!(f, "ServiceManagerPublish ()")
+        write!(f, "Subscriber ()")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::topic_matches;
+
+    #[test]
+    fn exact_patterns_match_only_the_same_topic() {
+        assert!(topic_matches("orders.eu.created", "orders.eu.created"));
+        assert!(!topic_matches("orders.eu.created", "orders.eu"));
+        assert!(!topic_matches("orders.eu", "orders.eu.created"));
+        assert!(!topic_matches("orders.eu.created", "orders.us.created"));
+    }
+
+    #[test]
+    fn hash_matches_zero_words() {
+        assert!(topic_matches("orders.#", "orders"));
+        assert!(topic_matches("orders.#.created", "orders.created"));
+        assert!(topic_matches("#", ""));
+    }
+
+    #[test]
+    fn hash_matches_many_words_at_the_start_and_at_the_end() {
+        assert!(topic_matches("#.created", "orders.eu.created"));
+        assert!(topic_matches("#.created", "created"));
+        assert!(!topic_matches("#.created", "orders.eu.deleted"));
+
+        assert!(topic_matches("orders.#", "orders.eu.created"));
+        assert!(!topic_matches("orders.#", "invoices.eu.created"));
+
+        assert!(topic_matches("#", "orders.eu.created"));
+    }
+
+    #[test]
+    fn star_matches_exactly_one_word() {
+        assert!(topic_matches("orders.*.created", "orders.eu.created"));
+        assert!(!topic_matches("orders.*.created", "orders.created"));
+        assert!(!topic_matches("orders.*.created", "orders.eu.west.created"));
+        assert!(!topic_matches("orders.*", "orders"));
+    }
+
+    #[test]
+    fn star_doesnt_match_empty_segments() {
+        assert!(!topic_matches("orders.*.created", "orders..created"));
+        assert!(!topic_matches("orders.*", "orders."));
+        assert!(!topic_matches("*", ""));
+
+        assert!(topic_matches("orders..created", "orders..created"));
+        assert!(topic_matches("orders.#.created", "orders..created"));
     }
 }
@@ -1,17 +1,27 @@
 use crate::actors::envelope::Letter;
+use crate::clock::Clock;
+use crate::error::{CallError, SendError};
+use crate::metrics::Metrics;
+use crate::middleware::Middlewares;
+use crate::observer::Observer;
+use crate::report::ServiceReport;
+use crate::runtime::Executor;
 use crate::services::broker::MessageBroker;
 use crate::services::envelope::ServiceLetterWithResponders;
 use crate::services::handle::Listen;
 use crate::services::handle::Serve;
 use crate::services::manager::{Manager, ServiceManager, ServiceManagerCommand};
-use crate::system_director::SystemDirector;
+use crate::shutdown::ShutdownState;
+use crate::supervision::Supervisor;
+use crate::system_director::WeakSystemDirector;
 use crate::Service;
 use async_channel::{bounded as channel, Sender};
-use async_std::sync::{Arc, Mutex};
 use dashmap::mapref::one::RefMut;
 use dashmap::{mapref::entry::Entry, DashMap};
 use futures::task::AtomicWaker;
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, Ordering::Relaxed};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::{
     any::TypeId,
     fmt::Debug,
@@ -20,6 +30,18 @@ use std::{
     task::{Context, Poll},
 };
 
+#[derive(Debug)]
+pub(crate) struct ServicesDirectorConfiguration {
+    pub(crate) supervisor: Option<Arc<dyn Supervisor>>,
+    pub(crate) call_timeout: Option<std::time::Duration>,
+    pub(crate) metrics: Arc<Metrics>,
+    pub(crate) middlewares: Arc<Middlewares>,
+    pub(crate) clock: Clock,
+    pub(crate) executor: Executor,
+    pub(crate) observer: Observer,
+    pub(crate) shutdown: Arc<ShutdownState>,
+}
+
 // TODO: This structure is getting big and with several responsiblities, maybe it should be splitted.
 #[derive(Debug)]
 pub(crate) struct ServicesDirector {
@@ -27,18 +49,20 @@ pub(crate) struct ServicesDirector {
     // TODO: Should be a WakerSet as there may be more than one thread that wants to wait
     waker: Arc<AtomicWaker>,
     is_stopping: Arc<AtomicBool>,
-    system: Arc<Mutex<Option<SystemDirector>>>,
+    system: Arc<Mutex<Option<WeakSystemDirector>>>,
     broker: Option<MessageBroker>,
+    configuration: Arc<ServicesDirectorConfiguration>,
 }
 
 impl ServicesDirector {
-    pub(crate) fn new() -> ServicesDirector {
+    pub(crate) fn new(configuration: ServicesDirectorConfiguration) -> ServicesDirector {
         let mut director = ServicesDirector {
             managers: Arc::new(DashMap::new()),
             waker: Arc::new(AtomicWaker::new()),
             is_stopping: Arc::new(AtomicBool::new(false)),
             system: Arc::new(Mutex::new(None)),
             broker: None,
+            configuration: Arc::new(configuration),
         };
 
         let broker = MessageBroker::new(director.clone());
@@ -48,8 +72,8 @@ impl ServicesDirector {
         director
     }
 
-    pub(crate) async fn set_system(&mut self, system_director: SystemDirector) {
-        let mut system = self.system.lock().await;
+    pub(crate) fn set_system(&mut self, system_director: WeakSystemDirector) {
+        let mut system = self.lock_system();
 
         if system.is_none() {
             system.replace(system_director);
@@ -58,23 +82,37 @@ impl ServicesDirector {
         }
     }
 
-    async fn get_mamager<S: Service>(&self) -> RefMut<'_, TypeId, Box<dyn Manager>> {
+    fn lock_system(&self) -> MutexGuard<'_, Option<WeakSystemDirector>> {
+        // The system is set only once, so it is consistent even if some thread panicked.
+        match self.system.lock() {
+            Ok(system) => system,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    async fn get_mamager<S: Service>(&self) -> Option<RefMut<'_, TypeId, Box<dyn Manager>>> {
         let type_id = TypeId::of::<S>();
 
         let managers_entry = self.managers.entry(type_id);
 
         match managers_entry {
-            Entry::Occupied(entry) => entry.into_ref(),
+            Entry::Occupied(entry) => Some(entry.into_ref()),
             Entry::Vacant(entry) => {
-                let manager = self.create_manager::<S>().await;
-                entry.insert(Box::new(manager))
+                let manager = self.create_manager::<S>().await?;
+                Some(entry.insert(Box::new(manager)))
             }
         }
     }
 
     // Ensures that there is a manager for that type and returns a sender to it
     async fn get_or_create_manager_sender<S: Service>(&self) -> Sender<ServiceManagerCommand<S>> {
-        let any_sender = self.get_mamager::<S>().await.get_sender_as_any().await;
+        let mut manager = match self.get_mamager::<S>().await {
+            Some(manager) => manager,
+            // Nothing can handle the message, as if the manager had already ended
+            None => return channel(1).0,
+        };
+
+        let any_sender = manager.get_sender_as_any().await;
 
         match any_sender.downcast::<Sender<ServiceManagerCommand<S>>>() {
             Ok(sender) => *sender,
@@ -89,6 +127,8 @@ impl ServicesDirector {
     }
 
     pub(crate) async fn send<S: Service + Listen<M>, M: Debug + Send + 'static>(&self, message: M) {
+        self.configuration.metrics.service_message_sent::<S, M>();
+
         let _ = self
             .get_or_create_manager_sender::<S>()
             .await
@@ -98,12 +138,71 @@ impl ServicesDirector {
             .await;
     }
 
-    // TODO: Create a proper return type without &str
+    /// Returns true if the system is stopping and the service is not running anymore, so
+    /// checked sends and calls don't initialize it again.
+    fn is_stopped<S: Service>(&self) -> bool {
+        self.is_stopping.load(Relaxed) && !self.managers.contains_key(&TypeId::of::<S>())
+    }
+
+    /// Same as `send` but it reports if the message was enqueued. While the system stops, it
+    /// doesn't initialize services.
+    pub(crate) async fn send_checked<S: Service + Listen<M>, M: Debug + Send + 'static>(
+        &self,
+        message: M,
+    ) -> Result<(), SendError> {
+        if self.is_stopped::<S>() {
+            return Err(SendError::SystemStopped);
+        }
+
+        self.configuration.metrics.service_message_sent::<S, M>();
+
+        self.get_or_create_manager_sender::<S>()
+            .await
+            .send(ServiceManagerCommand::Dispatch(Box::new(
+                Letter::new_for_service(message),
+            )))
+            .await
+            .map_err(|_| SendError::MailboxClosed)
+    }
+
     pub(crate) async fn call<A: Service + Serve<M>, M: Debug + Send + 'static>(
         &self,
         message: M,
-    ) -> Result<<A as Serve<M>>::Response, &str> {
-        let (sender, receiver) = channel::<<A as Serve<M>>::Response>(1);
+    ) -> Result<<A as Serve<M>>::Response, CallError> {
+        match self.configuration.call_timeout {
+            Some(timeout) => self.call_with_timeout::<A, M>(message, timeout).await,
+            None => self.call_without_timeout::<A, M>(message).await,
+        }
+    }
+
+    pub(crate) async fn call_with_timeout<A: Service + Serve<M>, M: Debug + Send + 'static>(
+        &self,
+        message: M,
+        timeout: std::time::Duration,
+    ) -> Result<<A as Serve<M>>::Response, CallError> {
+        // Same as with actors, a message not dispatched yet is skipped after the timeout.
+        match self
+            .configuration
+            .clock
+            .timeout(timeout, self.call_without_timeout::<A, M>(message))
+            .await
+        {
+            Some(result) => result,
+            None => Err(CallError::Timeout),
+        }
+    }
+
+    async fn call_without_timeout<A: Service + Serve<M>, M: Debug + Send + 'static>(
+        &self,
+        message: M,
+    ) -> Result<<A as Serve<M>>::Response, CallError> {
+        if self.is_stopped::<A>() {
+            return Err(CallError::SystemStopping);
+        }
+
+        self.configuration.metrics.service_message_sent::<A, M>();
+
+        let (sender, receiver) = channel::<Result<<A as Serve<M>>::Response, CallError>>(1);
 
         let _ = self
             .get_or_create_manager_sender::<A>()
@@ -113,22 +212,34 @@ impl ServicesDirector {
             )))
             .await;
 
-        receiver.recv().await.or(Err("Ups"))
+        match receiver.recv().await {
+            Ok(Ok(response)) => Ok(response),
+            // Same as with actors, if the system is stopping, that is the real reason.
+            Ok(Err(CallError::ServiceStopped)) | Err(_) if self.is_stopping.load(Relaxed) => {
+                Err(CallError::SystemStopping)
+            }
+            Ok(Err(error)) => Err(error),
+            Err(_) => Err(CallError::ResponderDropped),
+        }
     }
 
     pub(crate) async fn wait_until_stopped(&self) {
         ServicesDirectorStopAwaiter::new(self.clone()).await;
     }
 
-    pub(crate) async fn create_manager<S: Service>(&self) -> ServiceManager<S> {
-        // We use unwrap here as we must guarantee that there is a system director in every other director
-        let system = if let Some(system) = &*self.system.lock().await {
-            system.clone()
-        } else {
-            unreachable!();
+    // Returns `None` if the system is already gone, which happens only once it is stopped.
+    pub(crate) async fn create_manager<S: Service>(&self) -> Option<ServiceManager<S>> {
+        // The system is set as soon as it is created, before any manager is needed
+        let system = match &*self.lock_system() {
+            Some(system) => system.upgrade()?,
+            None => unreachable!(),
         };
 
-        ServiceManager::<S>::new(self.clone(), system, self.broker.as_ref().unwrap().clone()).await
+        let manager =
+            ServiceManager::<S>::new(self.clone(), system, self.broker.as_ref().unwrap().clone())
+                .await;
+
+        Some(manager)
     }
 
     pub(crate) async fn signal_manager_removed(&self) {
@@ -148,12 +259,85 @@ impl ServicesDirector {
         }
     }
 
-    pub(crate) fn get_blocking_manager_entry(&self, id: TypeId) -> Entry<TypeId, Box<dyn Manager>> {
+    /// Ends the service of type S, if it is running. Used when a service handler panics and
+    /// the supervision decision is to stop it.
+    pub(crate) fn end_service<S: Service>(&self) {
+        if let Some(manager) = self.managers.get(&TypeId::of::<S>()) {
+            manager.end();
+        }
+    }
+
+    pub(crate) fn get_supervisor(&self) -> Option<Arc<dyn Supervisor>> {
+        self.configuration.supervisor.clone()
+    }
+
+    pub(crate) fn get_metrics(&self) -> &Metrics {
+        &self.configuration.metrics
+    }
+
+    pub(crate) fn get_middlewares(&self) -> &Middlewares {
+        &self.configuration.middlewares
+    }
+
+    pub(crate) fn get_executor(&self) -> &Executor {
+        &self.configuration.executor
+    }
+
+    pub(crate) fn get_observer(&self) -> &Observer {
+        &self.configuration.observer
+    }
+
+    pub(crate) fn get_shutdown_state(&self) -> &ShutdownState {
+        &self.configuration.shutdown
+    }
+
+    /// Returns true if any service has messages waiting to be handled.
+    #[cfg(feature = "testkit")]
+    pub(crate) fn has_pending_messages(&self) -> bool {
+        self.managers
+            .iter()
+            .any(|manager| manager.has_pending_messages())
+    }
+
+    pub(crate) fn get_statistics(&self) -> HashMap<&'static str, ServiceReport> {
+        let mut statistics = HashMap::new();
+
+        for manager in self.managers.iter() {
+            statistics.insert(manager.get_type_name(), manager.get_statistics());
+        }
+
+        statistics
+    }
+
+    /// Discards the messages still enqueued for any service. Returns how many were discarded
+    /// for each service type.
+    pub(crate) fn discard_pending_messages(&self) -> HashMap<&'static str, usize> {
+        let mut discarded = HashMap::new();
+
+        for manager in self.managers.iter() {
+            let count = manager.discard_pending_messages();
+
+            if count > 0 {
+                discarded.insert(manager.get_type_name(), count);
+            }
+        }
+
+        discarded
+    }
+
+    pub(crate) fn get_blocking_manager_entry(
+        &self,
+        id: TypeId,
+    ) -> Entry<'_, TypeId, Box<dyn Manager>> {
         self.managers.entry(id)
     }
 
-    pub(crate) async fn publish<M: Send + Clone + 'static>(&self, message: M) {
-        self.broker.as_ref().unwrap().publish(message).await
+    pub(crate) async fn publish<M: Send + Clone + 'static>(&self, topic: Option<&str>, message: M) {
+        self.get_broker().publish(topic, message).await
+    }
+
+    pub(crate) fn get_broker(&self) -> &MessageBroker {
+        self.broker.as_ref().unwrap()
     }
 }
 
@@ -165,6 +349,7 @@ impl Clone for ServicesDirector {
             is_stopping: self.is_stopping.clone(),
             system: self.system.clone(),
             broker: self.broker.clone(),
+            configuration: self.configuration.clone(),
         }
     }
 }
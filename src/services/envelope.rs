@@ -1,9 +1,12 @@
 use crate::actors::envelope::Letter;
+use crate::error::CallError;
 use crate::services::handle::Listen;
 use crate::services::handle::Serve;
 use crate::services::service::Service;
 use crate::services::system_facade::ServiceAssistant;
+use crate::trace::TraceContext;
 use async_channel::Sender;
+use std::any::{type_name, Any};
 use std::fmt::Debug;
 use std::marker::PhantomData;
 
@@ -13,6 +16,20 @@ pub(crate) trait ServiceEnvelope: Send + Debug {
     type Service: Service;
 
     async fn dispatch(&mut self, service: &Self::Service, system: &ServiceAssistant<Self::Service>);
+
+    fn message_type_name(&self) -> &'static str;
+
+    /// The message, if it wasn't handled yet.
+    fn message_mut(&mut self) -> Option<&mut (dyn Any + Send)>;
+
+    /// Discards the message without handling it, letting the caller know why (if any).
+    fn discard(&mut self, _error: CallError) {}
+
+    /// True if the caller is not waiting for the response anymore (for example, it timed out).
+    /// Then the message is skipped.
+    fn is_abandoned(&self) -> bool {
+        false
+    }
 }
 
 /// For send without response we can use the normal Letter struct
@@ -25,19 +42,25 @@ impl<S: Service + Listen<M>, M: Debug> Letter<S, M> {
         Letter {
             message: Some(message),
             phantom: PhantomData,
+            report: None,
+            trace: TraceContext::current(),
         }
     }
 
     pub async fn dispatch_service(&mut self, service: &S, system: &ServiceAssistant<S>) {
         if let Some(message) = self.message.take() {
-            <S as Listen<M>>::handle(service, message, system).await;
+            let handler = <S as Listen<M>>::handle(service, message, system);
+
+            self.trace
+                .in_service_handler::<S, _>(type_name::<M>(), handler)
+                .await;
         }
     }
 }
 
 /// For send without response we can use the normal Letter struct
 #[async_trait::async_trait]
-impl<S: Service + Listen<M>, M: Debug + Send> ServiceEnvelope for Letter<S, M> {
+impl<S: Service + Listen<M>, M: Debug + Send + 'static> ServiceEnvelope for Letter<S, M> {
     type Service = S;
 
     async fn dispatch(
@@ -47,19 +70,33 @@ impl<S: Service + Listen<M>, M: Debug + Send> ServiceEnvelope for Letter<S, M> {
     ) {
         Letter::<S, M>::dispatch_service(self, service, system).await;
     }
+
+    fn message_type_name(&self) -> &'static str {
+        type_name::<M>()
+    }
+
+    fn message_mut(&mut self) -> Option<&mut (dyn Any + Send)> {
+        self.message
+            .as_mut()
+            .map(|message| message as &mut (dyn Any + Send))
+    }
 }
 
+/// The channel used for sending back the response of a `Serve` handler.
+pub(crate) type ServiceResponder<S, M> = Sender<Result<<S as Serve<M>>::Response, CallError>>;
+
 /// For messages with a response we need to use a different structure than LetterWithResponder
 #[derive(Debug)]
 pub(crate) struct ServiceLetterWithResponders<S: Service + Serve<M>, M: Debug> {
     message: Option<M>,
-    responder: Option<Sender<<S as Serve<M>>::Response>>,
+    responder: Option<ServiceResponder<S, M>>,
     phantom: PhantomData<S>,
+    trace: TraceContext,
 }
 
 /// For messages with a response we need to use a different structure than LetterWithResponder
 impl<S: Service + Serve<M>, M: Debug> ServiceLetterWithResponders<S, M> {
-    pub fn new(message: M, responder: Sender<<S as Serve<M>>::Response>) -> Self
+    pub fn new(message: M, responder: ServiceResponder<S, M>) -> Self
     where
         S: Serve<M>,
     {
@@ -67,25 +104,74 @@ impl<S: Service + Serve<M>, M: Debug> ServiceLetterWithResponders<S, M> {
             message: Some(message),
             phantom: PhantomData,
             responder: Some(responder),
+            trace: TraceContext::current(),
         }
     }
 
     async fn dispatch(&mut self, service: &S, system: &ServiceAssistant<S>) {
         if let Some(message) = self.message.take() {
+            let handler = <S as Serve<M>>::handle(service, message, system);
+
+            let result = self
+                .trace
+                .in_service_handler::<S, _>(type_name::<M>(), handler)
+                .await;
+
             if let Some(responder) = self.responder.take() {
-                let result = <S as Serve<M>>::handle(service, message, system).await;
-                let _ = responder.send(result).await;
+                let _ = responder.send(Ok(result)).await;
             }
         }
     }
 }
 
+/// Same as in actors, if the letter is dropped without responding we tell the caller why.
+impl<S: Service + Serve<M>, M: Debug> Drop for ServiceLetterWithResponders<S, M> {
+    fn drop(&mut self) {
+        if let Some(responder) = self.responder.take() {
+            let error = if self.message.is_some() {
+                CallError::ServiceStopped
+            } else {
+                CallError::HandlerPanicked
+            };
+
+            let _ = responder.try_send(Err(error));
+        }
+    }
+}
+
 /// For messages with a response we need to use a different structure than LetterWithResponder
 #[async_trait::async_trait]
-impl<S: Service + Serve<M>, M: Debug + Send> ServiceEnvelope for ServiceLetterWithResponders<S, M> {
+impl<S: Service + Serve<M>, M: Debug + Send + 'static> ServiceEnvelope
+    for ServiceLetterWithResponders<S, M>
+{
     type Service = S;
 
     async fn dispatch(&mut self, service: &Self::Service, system: &ServiceAssistant<S>) {
         ServiceLetterWithResponders::<S, M>::dispatch(self, service, system).await;
     }
+
+    fn message_type_name(&self) -> &'static str {
+        type_name::<M>()
+    }
+
+    fn message_mut(&mut self) -> Option<&mut (dyn Any + Send)> {
+        self.message
+            .as_mut()
+            .map(|message| message as &mut (dyn Any + Send))
+    }
+
+    fn discard(&mut self, error: CallError) {
+        self.message.take();
+
+        if let Some(responder) = self.responder.take() {
+            let _ = responder.try_send(Err(error));
+        }
+    }
+
+    fn is_abandoned(&self) -> bool {
+        match &self.responder {
+            Some(responder) => responder.is_closed(),
+            None => false,
+        }
+    }
 }
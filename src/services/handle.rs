@@ -9,11 +9,11 @@ use std::fmt::Debug;
 /// If you want to respond to messages, use the [Serve trait](./trait.Serve.html).
 ///
 /// This trait is compatible with [Serve trait](./trait.Serve.html) as you can implement, for the same message,
-/// both traits. This trait will be executed when using the "call" or "call_sync" method from Acteur or the "call"
+/// both traits. This trait will be executed when using the "send_to_service" method from Acteur or the "call"
 /// method from ActorAssistant.
 ///
 /// ```rust,no_run
-/// use acteur::{Acteur, Service, Listen, ServiceAssistant, ServiceConfiguration};
+/// use acteur::{BlockingActeur, Service, Listen, ServiceAssistant, ServiceConfiguration};
 /// use async_std::sync::Mutex;
 ///
 /// #[derive(Debug)]
@@ -44,8 +44,8 @@ use std::fmt::Debug;
 ///     }
 /// }
 /// fn main() {
-///     let sys = Acteur::new();
-///     sys.send_to_service_sync::<EmployeeExpensesCalculator, _>(EmployeeHired(55000.0));
+///     let sys = BlockingActeur::new();
+///     sys.send_to_service::<EmployeeExpensesCalculator, _>(EmployeeHired(55000.0));
 ///     sys.stop();
 ///     sys.wait_until_stopped();
 /// }
@@ -64,7 +64,7 @@ where
 /// If you don't need to respond messages, use the [Listen trait](./trait.Listen.html).
 ///
 /// This trait is compatible with [Listen trait](./trait.Listen.html) as you can implement, for the same message,
-/// both traits. This trait will be executed when using the "notify" or "notify_sync" method from Acteur or the "notify"
+/// both traits. This trait will be executed when using the "call_service" method from Acteur or the "notify"
 /// method from ActorAssistant
 ///
 /// Keep in mind that if someone waits for this service to respond and this service has a long queue of messages
@@ -72,7 +72,7 @@ where
 /// the [Serve trait](./trait.Serve.html) if you can.
 ///
 /// ```rust,no_run
-/// use acteur::{Acteur, Service, Serve, ServiceConfiguration, ServiceAssistant};
+/// use acteur::{BlockingActeur, Service, Serve, ServiceConfiguration, ServiceAssistant};
 ///
 /// #[derive(Debug)]
 /// struct EmployeeTaxesCalculator {
@@ -102,9 +102,9 @@ where
 /// }
 ///
 /// fn main() {
-///     let sys = Acteur::new();
+///     let sys = BlockingActeur::new();
 ///     
-///     let taxes = sys.call_service_sync::<EmployeeTaxesCalculator, _>(EmployeeSalaryChange(55000.0)).unwrap();
+///     let taxes = sys.call_service::<EmployeeTaxesCalculator, _>(EmployeeSalaryChange(55000.0)).unwrap();
 ///
 ///     println!("Employee taxes are: {:?}", taxes);
 ///
@@ -1,32 +1,72 @@
+use crate::error::CallError;
+use crate::middleware::{MessageContext, MiddlewareDecision};
+use crate::report::ServiceReport;
 use crate::services::broker::MessageBroker;
 use crate::services::director::ServicesDirector;
 use crate::services::envelope::ServiceEnvelope;
 use crate::services::service::{Service, ServiceConcurrency};
 use crate::services::system_facade::ServiceAssistant;
+use crate::supervision::{panic_message, Failure, Supervision, SupervisorDecision};
 use crate::system_director::SystemDirector;
 use async_channel::{unbounded as channel, Receiver, Sender};
-use async_std::sync::Mutex;
-use async_std::{sync::Arc, task};
 use dashmap::mapref::entry::Entry::Occupied;
-use std::any::Any;
-use std::any::TypeId;
+use futures::lock::Mutex;
+use futures::FutureExt;
+use std::any::{type_name, Any};
 use std::fmt::Debug;
+use std::panic::AssertUnwindSafe;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::SystemTime;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
 
 #[async_trait::async_trait]
 pub(crate) trait Manager: Send + Sync + Debug {
     fn end(&self);
-    fn get_type_id(&self) -> TypeId;
-    async fn get_sender_as_any(&mut self) -> Box<(dyn Any + Send)>;
+    async fn get_sender_as_any(&mut self) -> Box<dyn Any + Send>;
+    fn get_type_name(&self) -> &'static str;
     fn get_statistics(&self) -> ServiceReport;
-    fn clone(&self) -> Box<dyn Manager>;
+    fn discard_pending_messages(&self) -> usize;
+    #[cfg(feature = "testkit")]
+    fn has_pending_messages(&self) -> bool;
 }
 
-pub struct ServiceReport {
-    pub last_message_on: SystemTime,
-    pub enqueued_messages: usize,
+/// Counters shared by all the concurrency lanes of a service.
+#[derive(Debug)]
+struct ServiceStatistics {
+    last_message_on: std::sync::Mutex<Option<SystemTime>>,
+    handled_messages: AtomicUsize,
+    in_flight_messages: AtomicUsize,
+}
+
+impl ServiceStatistics {
+    fn new() -> ServiceStatistics {
+        ServiceStatistics {
+            last_message_on: std::sync::Mutex::new(None),
+            handled_messages: AtomicUsize::new(0),
+            in_flight_messages: AtomicUsize::new(0),
+        }
+    }
+
+    fn message_started(&self) {
+        self.in_flight_messages.fetch_add(1, Ordering::Relaxed);
+
+        if let Ok(mut last_message_on) = self.last_message_on.lock() {
+            last_message_on.replace(SystemTime::now());
+        }
+    }
+
+    fn message_finished(&self) {
+        self.in_flight_messages.fetch_sub(1, Ordering::Relaxed);
+        self.handled_messages.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn get_last_message_on(&self) -> Option<SystemTime> {
+        match self.last_message_on.lock() {
+            Ok(last_message_on) => *last_message_on,
+            Err(poisoned) => *poisoned.into_inner(),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -38,9 +78,12 @@ pub(crate) enum ServiceManagerCommand<S: Service> {
 #[derive(Debug)]
 pub(crate) struct ServiceManager<S: Service> {
     senders: Arc<Vec<Sender<ServiceManagerCommand<S>>>>,
+    // Only used for discarding the pending commands, each lane has its own.
+    receivers: Arc<Vec<Receiver<ServiceManagerCommand<S>>>>,
     current: Arc<Mutex<usize>>,
     is_ending: Arc<AtomicBool>,
     active_services: Arc<AtomicUsize>,
+    statistics: Arc<ServiceStatistics>,
 }
 
 impl<S: Service> ServiceManager<S> {
@@ -93,12 +136,20 @@ impl<S: Service> ServiceManager<S> {
         let senders = Arc::new(senders);
         let current = Arc::new(Mutex::new(0));
         let active_services = Arc::new(AtomicUsize::new(concurrency));
+        let statistics = Arc::new(ServiceStatistics::new());
+        // Shared by the loops, as they share the service instance
+        let supervision = Arc::new(Supervision::new(
+            service_conf.supervision,
+            service_conf.max_restarts,
+        ));
 
         let manager = ServiceManager {
             senders: senders.clone(),
+            receivers: Arc::new(receivers.clone()),
             current,
             is_ending: Arc::new(AtomicBool::new(false)),
             active_services: active_services.clone(),
+            statistics: statistics.clone(),
         };
 
         for (receiver, sender) in receivers.iter().zip(senders.as_ref()) {
@@ -111,6 +162,8 @@ impl<S: Service> ServiceManager<S> {
                 system_director.clone(),
                 broker.clone(),
                 wait_for_service,
+                supervision.clone(),
+                statistics.clone(),
             );
         }
 
@@ -142,18 +195,40 @@ impl<S: Service> ServiceManager<S> {
         *current
     }
 
+    /// Discards the messages waiting in all the lanes. The handlers running are not interrupted.
+    /// Returns how many messages were discarded.
+    fn discard_pending_messages(&self) -> usize {
+        let mut discarded = 0;
+
+        for receiver in self.receivers.iter() {
+            for _ in 0..receiver.len() {
+                match receiver.try_recv() {
+                    Ok(ServiceManagerCommand::Dispatch(mut envelope)) => {
+                        envelope.discard(CallError::SystemStopping);
+                        discarded += 1;
+                    }
+                    Ok(ServiceManagerCommand::End) => (),
+                    Err(_) => break,
+                }
+            }
+        }
+
+        // The End commands were discarded too, so the lanes are asked to end again.
+        self.end();
+
+        discarded
+    }
+
     fn end(&self) {
         for sender in self.senders.iter() {
-            let sender = sender.clone();
-            task::spawn(async move {
-                // The channel is unbounded and it is ok if it is closed.
-                // That is the reason for ignoring the error
-                let _ = sender.send(ServiceManagerCommand::End).await;
-            });
+            // The channel is unbounded and it is ok if it is closed.
+            // That is the reason for ignoring the error
+            let _ = sender.try_send(ServiceManagerCommand::End);
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn service_loop<S: Service>(
     receiver: Receiver<ServiceManagerCommand<S>>,
     sender: Sender<ServiceManagerCommand<S>>,
@@ -163,15 +238,28 @@ fn service_loop<S: Service>(
     system_director: SystemDirector,
     broker: MessageBroker,
     wait_for_service: bool,
+    supervision: Arc<Supervision>,
+    statistics: Arc<ServiceStatistics>,
 ) {
-    task::spawn(async move {
+    director.get_executor().clone().spawn(async move {
         let system_facade = Arc::new(ServiceAssistant::<S>::new(system_director, broker));
 
         loop {
             if let Ok(command) = receiver.recv().await {
+                let _busy = director.get_observer().busy();
+
                 match command {
                     ServiceManagerCommand::Dispatch(envelope) => {
-                        dispatch::<S>(&service, &system_facade, envelope, wait_for_service).await;
+                        dispatch::<S>(
+                            &service,
+                            &system_facade,
+                            envelope,
+                            wait_for_service,
+                            &director,
+                            &supervision,
+                            &statistics,
+                        )
+                        .await;
                     }
                     // This algorithm is basically the same as the one in the Actor's Proxy. Check that file
                     // for an explanation in detail.
@@ -204,6 +292,9 @@ fn service_loop<S: Service>(
                                             &system_facade,
                                             envelope,
                                             wait_for_service,
+                                            &director,
+                                            &supervision,
+                                            &statistics,
                                         )
                                         .await;
                                     }
@@ -229,8 +320,16 @@ fn service_loop<S: Service>(
                             }
                             Some(ServiceManagerCommand::Dispatch(envelope)) => {
                                 let _ = sender.send(ServiceManagerCommand::End).await;
-                                dispatch::<S>(&service, &system_facade, envelope, wait_for_service)
-                                    .await;
+                                dispatch::<S>(
+                                    &service,
+                                    &system_facade,
+                                    envelope,
+                                    wait_for_service,
+                                    &director,
+                                    &supervision,
+                                    &statistics,
+                                )
+                                .await;
                             }
                         }
                     }
@@ -243,40 +342,143 @@ fn service_loop<S: Service>(
 async fn dispatch<'a, S: Service>(
     service: &'a Arc<S>,
     system_facade: &'a Arc<ServiceAssistant<S>>,
-    mut envelope: Box<dyn ServiceEnvelope<Service = S>>,
+    envelope: Box<dyn ServiceEnvelope<Service = S>>,
     wait_for_service: bool,
+    director: &'a ServicesDirector,
+    supervision: &'a Arc<Supervision>,
+    statistics: &'a Arc<ServiceStatistics>,
 ) {
     if wait_for_service {
-        envelope.dispatch(&service, &system_facade).await;
+        statistics.message_started();
+        supervised_dispatch(service, system_facade, envelope, director, supervision).await;
+        statistics.message_finished();
     } else {
         let service = service.clone();
         let system_facade = system_facade.clone();
-        task::spawn(async move { envelope.dispatch(&service, &system_facade).await });
+        let director = director.clone();
+        let statistics = statistics.clone();
+        let supervision = supervision.clone();
+        // The system is busy until the handler finishes, not only until the loop spawns it
+        let busy = director.get_observer().busy();
+        director.get_executor().clone().spawn(async move {
+            let _busy = busy;
+            statistics.message_started();
+            supervised_dispatch(&service, &system_facade, envelope, &director, &supervision).await;
+            statistics.message_finished();
+        });
+    }
+}
+
+/// Dispatches the message catching any panic in the handler. As the service instance is shared
+/// by all the loops, restarting the service means continuing with the next message.
+async fn supervised_dispatch<S: Service>(
+    service: &Arc<S>,
+    system_facade: &Arc<ServiceAssistant<S>>,
+    mut envelope: Box<dyn ServiceEnvelope<Service = S>>,
+    director: &ServicesDirector,
+    supervision: &Supervision,
+) {
+    // Same as with actors, the messages nobody waits for are skipped without running any hook.
+    if envelope.is_abandoned() {
+        return;
+    }
+
+    let message_type_name = envelope.message_type_name();
+    let middlewares = director.get_middlewares();
+    let started = Instant::now();
+
+    // Same as with actors, returns the time spent in the handler or None if it was rejected.
+    let result = AssertUnwindSafe(async {
+        let mut context = MessageContext::new(
+            type_name::<S>(),
+            None,
+            message_type_name,
+            envelope.message_mut(),
+        );
+
+        if let MiddlewareDecision::Reject = middlewares.before::<S>(&mut context).await {
+            envelope.discard(CallError::Rejected);
+            return None;
+        }
+
+        let started = Instant::now();
+        envelope.dispatch(service, system_facade).await;
+        let duration = started.elapsed();
+
+        let mut context = MessageContext::new(type_name::<S>(), None, message_type_name, None);
+        middlewares.after::<S>(&mut context, duration).await;
+
+        Some(duration)
+    })
+    .catch_unwind()
+    .await;
+
+    // Dropping the envelope lets know any caller waiting for a response that the handler panicked.
+    drop(envelope);
+
+    let payload = match result {
+        Ok(Some(duration)) => {
+            director
+                .get_metrics()
+                .service_message_handled::<S>(message_type_name, duration);
+            director
+                .get_shutdown_state()
+                .message_handled(type_name::<S>());
+            return;
+        }
+        Ok(None) => return,
+        Err(payload) => payload,
+    };
+
+    director
+        .get_metrics()
+        .service_message_handled::<S>(message_type_name, started.elapsed());
+    director
+        .get_shutdown_state()
+        .message_handled(type_name::<S>());
+
+    let failure = Failure {
+        type_name: type_name::<S>(),
+        actor_id: None,
+        panic_message: panic_message(payload),
+    };
+
+    let supervisor = director.get_supervisor();
+
+    if let SupervisorDecision::Stop = supervision.decide(supervisor.as_deref(), &failure) {
+        director.end_service::<S>();
     }
 }
 
 #[async_trait::async_trait]
 impl<S: Service> Manager for ServiceManager<S> {
-    async fn get_sender_as_any(&mut self) -> Box<(dyn Any + Send + 'static)> {
+    async fn get_sender_as_any(&mut self) -> Box<dyn Any + Send + 'static> {
         Box::new(self.get_sender().await)
     }
-    fn get_type_id(&self) -> TypeId {
-        std::any::TypeId::of::<S>()
-    }
     fn end(&self) {
         self.end();
     }
 
-    // TODO: Implement
+    fn get_type_name(&self) -> &'static str {
+        std::any::type_name::<S>()
+    }
+
+    fn discard_pending_messages(&self) -> usize {
+        ServiceManager::<S>::discard_pending_messages(self)
+    }
+
     fn get_statistics(&self) -> ServiceReport {
         ServiceReport {
-            last_message_on: SystemTime::now(),
-            enqueued_messages: 10000,
+            last_message_on: self.statistics.get_last_message_on(),
+            enqueued_messages: self.senders.iter().map(|sender| sender.len()).collect(),
+            handled_messages: self.statistics.handled_messages.load(Ordering::Relaxed),
+            in_flight_messages: self.statistics.in_flight_messages.load(Ordering::Relaxed),
         }
     }
 
-    fn clone(&self) -> Box<dyn Manager> {
-        Box::new(Clone::clone(self))
+    #[cfg(feature = "testkit")]
+    fn has_pending_messages(&self) -> bool {
+        self.senders.iter().any(|sender| !sender.is_empty())
     }
 }
 
@@ -284,9 +486,11 @@ impl<S: Service> Clone for ServiceManager<S> {
     fn clone(&self) -> ServiceManager<S> {
         ServiceManager {
             senders: self.senders.clone(),
+            receivers: self.receivers.clone(),
             current: self.current.clone(),
             is_ending: self.is_ending.clone(),
             active_services: self.active_services.clone(),
+            statistics: self.statistics.clone(),
         }
     }
 }
@@ -1,4 +1,5 @@
 use crate::services::system_facade::ServiceAssistant;
+use crate::supervision::SupervisionStrategy;
 use std::fmt::Debug;
 
 ///
@@ -7,7 +8,7 @@ use std::fmt::Debug;
 /// ServiceConfiguration struct.
 ///
 /// ```rust,no_run
-/// use acteur::{Acteur, Service, Serve, ServiceAssistant, ServiceConfiguration};
+/// use acteur::{BlockingActeur, Service, Serve, ServiceAssistant, ServiceConfiguration};
 ///
 /// #[derive(Debug)]
 /// struct EmployeeTaxesCalculator {
@@ -40,9 +41,9 @@ use std::fmt::Debug;
 /// }
 ///
 /// fn main() {
-///     let sys = Acteur::new();
+///     let sys = BlockingActeur::new();
 ///
-///     let taxes = sys.call_service_sync::<EmployeeTaxesCalculator, _>(EmployeeSalaryChange(55000.0)).unwrap();
+///     let taxes = sys.call_service::<EmployeeTaxesCalculator, _>(EmployeeSalaryChange(55000.0)).unwrap();
 ///
 ///     println!("Employee taxes are: {:?}", taxes);
 ///
@@ -82,18 +83,25 @@ pub enum ServiceConcurrency {
     Unlimited,
 }
 
-/// Defines the service configuration. For now it only contains the concurrency parameters.
+/// Defines the service configuration: the concurrency parameters and what to do when a handler panics.
 ///
 /// This struct implements the Default trait. You can call it with `ServiceConfiguration::default()` and
 /// it should work well for the most of the cases..
 pub struct ServiceConfiguration {
     pub concurrency: ServiceConcurrency,
+    /// By default, `SupervisionStrategy::Restart`.
+    pub supervision: SupervisionStrategy,
+    /// Maximum number of times the service is restarted because of its failures until it is
+    /// initialized again. By default, None (unlimited).
+    pub max_restarts: Option<usize>,
 }
 
 impl Default for ServiceConfiguration {
     fn default() -> ServiceConfiguration {
         ServiceConfiguration {
             concurrency: ServiceConcurrency::Automatic,
+            supervision: SupervisionStrategy::Restart,
+            max_restarts: None,
         }
     }
 }
@@ -1,9 +1,12 @@
+use crate::error::{CallError, SendError, TrySendError};
+use crate::scheduler::ScheduleHandle;
 use crate::services::broker::MessageBroker;
 use crate::services::handle::{Listen, Serve};
 use crate::services::service::Service;
 use crate::system_director::SystemDirector;
+#[cfg(feature = "testkit")]
+use crate::testkit::outbox::Outbox;
 use crate::{Actor, Receive, Respond};
-use async_std::task;
 use std::fmt::Debug;
 use std::marker::PhantomData;
 
@@ -45,6 +48,8 @@ use std::marker::PhantomData;
 ///
 pub struct ServiceAssistant<S: Service> {
     system_director: SystemDirector,
+    #[cfg(feature = "testkit")]
+    outbox: Outbox,
     broker: MessageBroker,
     phantom_system: PhantomData<S>,
 }
@@ -55,12 +60,32 @@ impl<S: Service> ServiceAssistant<S> {
         broker: MessageBroker,
     ) -> ServiceAssistant<S> {
         ServiceAssistant {
+            #[cfg(feature = "testkit")]
+            outbox: Outbox::System(system_director.clone()),
             system_director,
             broker,
             phantom_system: PhantomData,
         }
     }
 
+    // The `MockSystem` captures the outgoing messages instead of sending them
+    #[cfg(feature = "testkit")]
+    pub(crate) fn with_outbox(mut self, outbox: Outbox) -> ServiceAssistant<S> {
+        self.outbox = outbox;
+        self
+    }
+
+    // Without the `testkit` feature, the messages go straight to the system
+    #[cfg(not(feature = "testkit"))]
+    fn outbox(&self) -> &SystemDirector {
+        &self.system_director
+    }
+
+    #[cfg(feature = "testkit")]
+    fn outbox(&self) -> &Outbox {
+        &self.outbox
+    }
+
     /// Sends a message to the Actor with the specified Id.
     /// If the Actor is not loaded, it will load the actor before, calling its method `activate`
     pub async fn send_to_actor<A: Actor + Receive<M>, M: Debug + Send + 'static>(
@@ -68,8 +93,30 @@ impl<S: Service> ServiceAssistant<S> {
         actor_id: A::Id,
         message: M,
     ) {
-        self.system_director
-            .send_to_actor::<A, M>(actor_id, message)
+        self.outbox().send_to_actor::<A, M>(actor_id, message).await
+    }
+
+    /// Same as `send_to_actor` but it returns a [SendError](./enum.SendError.html) if the message
+    /// couldn't be enqueued. It doesn't activate actors while the system is stopping.
+    pub async fn send_to_actor_checked<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        message: M,
+    ) -> Result<(), SendError> {
+        self.outbox()
+            .send_to_actor_checked::<A, M>(actor_id, message)
+            .await
+    }
+
+    /// Same as `send_to_actor` but it never waits for room in the actor mailbox. If the
+    /// mailbox is full, it returns `TrySendError::MailboxFull`.
+    pub async fn try_send_to_actor<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        message: M,
+    ) -> Result<(), TrySendError> {
+        self.outbox()
+            .try_send_to_actor::<A, M>(actor_id, message)
             .await
     }
 
@@ -79,9 +126,7 @@ impl<S: Service> ServiceAssistant<S> {
         &self,
         message: M,
     ) {
-        self.system_director
-            .send_to_all_actors::<A, M>(message)
-            .await
+        self.outbox().send_to_all_actors::<A, M>(message).await
     }
 
     /// Schedules to sends a message to the Actor with the specified Id.
@@ -91,10 +136,24 @@ impl<S: Service> ServiceAssistant<S> {
         actor_id: A::Id,
         duration: std::time::Duration,
         message: M,
-    ) {
-        self.system_director
+    ) -> ScheduleHandle {
+        self.outbox()
             .schedule_send_to_actor::<A, M>(actor_id, duration, message)
-            .await
+    }
+
+    /// Sends a copy of the message to the Actor with the specified Id every time the interval
+    /// passes, until the returned handle is cancelled.
+    pub async fn schedule_interval_to_actor<
+        A: Actor + Receive<M>,
+        M: Debug + Send + Clone + 'static,
+    >(
+        &self,
+        actor_id: A::Id,
+        interval: std::time::Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        self.outbox()
+            .schedule_interval_to_actor::<A, M>(actor_id, interval, message)
     }
 
     /// Schedules to sends a message to all actor of a type, independently of the ID.
@@ -103,10 +162,23 @@ impl<S: Service> ServiceAssistant<S> {
         &self,
         duration: std::time::Duration,
         message: M,
-    ) {
-        self.system_director
+    ) -> ScheduleHandle {
+        self.outbox()
             .schedule_send_to_all_actors::<A, M>(duration, message)
-            .await
+    }
+
+    /// Sends a copy of the message to all actors of a type every time the interval passes,
+    /// until the returned handle is cancelled.
+    pub async fn schedule_interval_to_all_actors<
+        A: Actor + Receive<M>,
+        M: Debug + Send + Clone + 'static,
+    >(
+        &self,
+        interval: std::time::Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        self.outbox()
+            .schedule_interval_to_all_actors::<A, M>(interval, message)
     }
 
     /// Sends a message to the Actor with the specified Id and waits the actor's response .
@@ -115,9 +187,20 @@ impl<S: Service> ServiceAssistant<S> {
         &self,
         actor_id: A::Id,
         message: M,
-    ) -> Result<<A as Respond<M>>::Response, &str> {
-        self.system_director
-            .call_actor::<A, M>(actor_id, message)
+    ) -> Result<<A as Respond<M>>::Response, CallError> {
+        self.outbox().call_actor::<A, M>(actor_id, message).await
+    }
+
+    /// Same as `call_actor` but it returns `CallError::Timeout` if the response doesn't arrive on time.
+    /// If the actor didn't start handling the message yet, the message is skipped.
+    pub async fn call_actor_with_timeout<A: Actor + Respond<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        message: M,
+        timeout: std::time::Duration,
+    ) -> Result<<A as Respond<M>>::Response, CallError> {
+        self.outbox()
+            .call_actor_with_timeout::<A, M>(actor_id, message, timeout)
             .await
     }
 
@@ -127,7 +210,43 @@ impl<S: Service> ServiceAssistant<S> {
         &self,
         message: M,
     ) {
-        self.system_director.send_to_service::<S1, M>(message).await
+        self.outbox().send_to_service::<S1, M>(message).await
+    }
+
+    /// Same as `send_to_service` but it returns a [SendError](./enum.SendError.html) if the
+    /// message couldn't be enqueued. It doesn't initialize services while the system is stopping.
+    pub async fn send_to_service_checked<S1: Service + Listen<M>, M: Debug + Send + 'static>(
+        &self,
+        message: M,
+    ) -> Result<(), SendError> {
+        self.outbox()
+            .send_to_service_checked::<S1, M>(message)
+            .await
+    }
+
+    /// Schedules to send a message to a Service.
+    /// If the Service is not loaded, it will load the service before, calling its method `initialize`
+    pub async fn schedule_send_to_service<S1: Service + Listen<M>, M: Debug + Send + 'static>(
+        &self,
+        duration: std::time::Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        self.outbox()
+            .schedule_send_to_service::<S1, M>(duration, message)
+    }
+
+    /// Sends a copy of the message to a Service every time the interval passes, until the
+    /// returned handle is cancelled.
+    pub async fn schedule_interval_to_service<
+        S1: Service + Listen<M>,
+        M: Debug + Send + Clone + 'static,
+    >(
+        &self,
+        interval: std::time::Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        self.outbox()
+            .schedule_interval_to_service::<S1, M>(interval, message)
     }
 
     /// Sends a message to a Service and waits for its response.
@@ -135,8 +254,20 @@ impl<S: Service> ServiceAssistant<S> {
     pub async fn call_service<S1: Service + Serve<M>, M: Debug + Send + 'static>(
         &self,
         message: M,
-    ) -> Result<<S1 as Serve<M>>::Response, &str> {
-        self.system_director.call_service::<S1, M>(message).await
+    ) -> Result<<S1 as Serve<M>>::Response, CallError> {
+        self.outbox().call_service::<S1, M>(message).await
+    }
+
+    /// Same as `call_service` but it returns `CallError::Timeout` if the response doesn't arrive on time.
+    /// If the service didn't start handling the message yet, the message is skipped.
+    pub async fn call_service_with_timeout<S1: Service + Serve<M>, M: Debug + Send + 'static>(
+        &self,
+        message: M,
+        timeout: std::time::Duration,
+    ) -> Result<<S1 as Serve<M>>::Response, CallError> {
+        self.outbox()
+            .call_service_with_timeout::<S1, M>(message, timeout)
+            .await
     }
 
     /// Send an stop message to all actors in the system.
@@ -144,16 +275,59 @@ impl<S: Service> ServiceAssistant<S> {
     pub fn stop_system(&self) {
         let system = self.system_director.clone();
 
-        task::spawn(async move {
+        self.system_director.get_executor().spawn(async move {
             system.stop().await;
         });
     }
 
+    /// Publishes a message for the Services and Actors subscribed to its type. See
+    /// [Acteur::publish](./struct.Acteur.html#method.publish).
+    pub async fn publish<M: Send + Clone + 'static>(&self, message: M) {
+        self.outbox().publish(None, message).await
+    }
+
+    /// Same as `publish` but the message is published in a topic. See
+    /// [Acteur::publish_to_topic](./struct.Acteur.html#method.publish_to_topic).
+    pub async fn publish_to_topic<M: Send + Clone + 'static>(&self, topic: &str, message: M) {
+        self.outbox().publish(Some(topic), message).await
+    }
+
+    /// Subscribes the service to a message type. Every message of that type published with
+    /// `publish` or `publish_to_topic` will be sent to this service. Subscribing twice has no effect.
     pub async fn subscribe<M: Sync + Send + Debug + 'static>(&self)
     where
         S: Service + Listen<M>,
     {
-        self.broker.register::<S, M>();
+        self.broker.register::<S, M>(None);
+    }
+
+    /// Same as `subscribe` but the service only receives the messages published with
+    /// `publish_to_topic` in a topic matching the pattern. Topics are words separated by dots,
+    /// like `orders.eu.created`. In the patterns, `*` matches exactly one word and `#` matches
+    /// zero or more words, so `orders.*.created` and `orders.#` match that topic.
+    pub async fn subscribe_to_topic<M: Sync + Send + Debug + 'static>(&self, topic_pattern: &str)
+    where
+        S: Service + Listen<M>,
+    {
+        self.broker.register::<S, M>(Some(topic_pattern));
+    }
+
+    /// Removes the subscription made with `subscribe`.
+    pub async fn unsubscribe<M: Sync + Send + Debug + 'static>(&self)
+    where
+        S: Service + Listen<M>,
+    {
+        self.broker.unregister::<S, M>(None);
+    }
+
+    /// Removes the subscription made with `subscribe_to_topic` with the same pattern.
+    pub async fn unsubscribe_from_topic<M: Sync + Send + Debug + 'static>(
+        &self,
+        topic_pattern: &str,
+    ) where
+        S: Service + Listen<M>,
+    {
+        self.broker.unregister::<S, M>(Some(topic_pattern));
     }
 }
 
@@ -161,6 +335,8 @@ impl<S: Service> Clone for ServiceAssistant<S> {
     fn clone(&self) -> Self {
         ServiceAssistant {
             system_director: self.system_director.clone(),
+            #[cfg(feature = "testkit")]
+            outbox: self.outbox.clone(),
             broker: self.broker.clone(),
             phantom_system: PhantomData,
         }
@@ -0,0 +1,119 @@
+use dashmap::DashMap;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Options for [Acteur::shutdown](./struct.Acteur.html#method.shutdown).
+///
+/// The shutdown goes through these phases, in order:
+///
+/// 1. The system stops accepting messages sent through the `Acteur` methods (sends, calls,
+///    publications and scheduled messages). Rejected sends are dropped and counted, calls return
+///    `CallError::SystemStopping`. Pending scheduled messages are discarded and their handles
+///    cancelled. Actors and services can still message each other, and so can
+///    any [ActorRef](./struct.ActorRef.html), as their messages may be needed to drain the rest.
+/// 2. Actors handle all their enqueued messages and are deactivated.
+/// 3. Services handle all their enqueued messages and are stopped. Actors activated by
+///    services in this phase are stopped too. Services with unlimited concurrency (see
+///    [ServiceConcurrency](./enum.ServiceConcurrency.html)) don't wait for their handlers, so
+///    these may still be running.
+///
+/// If the deadline expires in the middle, the messages still enqueued are discarded (callers
+/// waiting for a response get `CallError::SystemStopping`) and the shutdown returns. Handlers
+/// already running are not interrupted, so they may still be running after `shutdown` returns.
+///
+/// ```rust,no_run
+/// use acteur::{BlockingActeur, ShutdownOptions};
+/// use std::time::Duration;
+///
+/// let sys = BlockingActeur::new();
+///
+/// // ... send some messages
+///
+/// let summary = sys.shutdown(ShutdownOptions {
+///     deadline: Duration::from_secs(5),
+/// });
+///
+/// for (receiver_type, count) in summary.discarded.iter() {
+///     println!("{}: {} messages were not handled", receiver_type, count);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ShutdownOptions {
+    /// Maximum time for the whole shutdown. By default, 30 seconds.
+    pub deadline: Duration,
+}
+
+impl Default for ShutdownOptions {
+    fn default() -> Self {
+        ShutdownOptions {
+            deadline: Duration::from_secs(30),
+        }
+    }
+}
+
+/// What happened with the messages during a shutdown. Returned by `Acteur::shutdown`.
+///
+/// Actors and services are keyed by their type name (as returned by `std::any::type_name`).
+#[derive(Debug, Clone)]
+pub struct ShutdownSummary {
+    /// Messages handled by each actor or service type since the shutdown started.
+    pub drained: HashMap<&'static str, usize>,
+    /// Messages still enqueued in each actor or service type when the deadline expired. They
+    /// were not handled.
+    pub discarded: HashMap<&'static str, usize>,
+    /// Messages sent through `Acteur` after the shutdown started. They were not handled.
+    pub rejected: usize,
+    /// Scheduled messages and intervals that were pending when the shutdown started. They were
+    /// discarded and their handles cancelled.
+    pub cancelled_schedules: usize,
+    /// True if the deadline expired before everything was stopped. Then the handlers that were
+    /// running are not waited for, and they may still be running when `shutdown` returns.
+    pub timed_out: bool,
+    /// Time spent in the shutdown.
+    pub elapsed: Duration,
+}
+
+// Shared by the whole system so the loops can count what they drain while shutting down.
+#[derive(Debug, Default)]
+pub(crate) struct ShutdownState {
+    is_shutting_down: AtomicBool,
+    rejected: AtomicUsize,
+    drained: DashMap<&'static str, usize>,
+}
+
+impl ShutdownState {
+    pub(crate) fn new() -> ShutdownState {
+        ShutdownState::default()
+    }
+
+    pub(crate) fn start(&self) {
+        self.is_shutting_down.store(true, Ordering::SeqCst);
+    }
+
+    pub(crate) fn is_shutting_down(&self) -> bool {
+        self.is_shutting_down.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn message_rejected(&self) {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a message handled by an actor or service type, only if the system is shutting down.
+    pub(crate) fn message_handled(&self, type_name: &'static str) {
+        if self.is_shutting_down() {
+            *self.drained.entry(type_name).or_insert(0) += 1;
+        }
+    }
+
+    pub(crate) fn get_rejected(&self) -> usize {
+        self.rejected.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn get_drained(&self) -> HashMap<&'static str, usize> {
+        self.drained
+            .iter()
+            .map(|entry| (*entry.key(), *entry.value()))
+            .collect()
+    }
+}
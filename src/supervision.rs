@@ -0,0 +1,133 @@
+use std::any::Any;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Defines what happens when a handler panics while processing a message.
+///
+/// It is configured per actor type with the [ActorConfiguration](./struct.ActorConfiguration.html)
+/// and per service with the [ServiceConfiguration](./struct.ServiceConfiguration.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SupervisionStrategy {
+    /// For actors, the actor state is discarded and the actor is activated again by calling the
+    /// `activate` method. The enqueued messages are kept and processed by the new instance.
+    ///
+    /// For services, the service keeps running and continues with the next message. The service is
+    /// not initialized again as the same instance is shared by all its concurrent loops.
+    ///
+    /// Once the `max_restarts` of the configuration are reached, the next failure stops the actor
+    /// or the service instead.
+    Restart,
+    /// For actors, the actor is removed and all its enqueued messages are discarded. The next message
+    /// for that actor will activate it again.
+    ///
+    /// For services, the service is ended after processing the enqueued messages. The next message
+    /// will initialize it again.
+    Stop,
+    /// Asks the [Supervisor](./trait.Supervisor.html) configured in the system what to do. If there
+    /// is no supervisor, it behaves as `Stop`.
+    Escalate,
+}
+
+/// The decision returned by a [Supervisor](./trait.Supervisor.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SupervisorDecision {
+    /// Same as `SupervisionStrategy::Restart`
+    Restart,
+    /// Same as `SupervisionStrategy::Stop`
+    Stop,
+}
+
+/// Information about a handler that panicked.
+#[derive(Debug, Clone)]
+pub struct Failure {
+    /// Type name of the actor or service.
+    pub type_name: &'static str,
+    /// Debug representation of the actor Id. Services have no Id.
+    pub actor_id: Option<String>,
+    /// The panic message, if it was a string.
+    pub panic_message: String,
+}
+
+/// Receives the failures of actors and services using the `SupervisionStrategy::Escalate` strategy
+/// and decides what to do with them. It is configured with `ActeurBuilder::supervisor`.
+///
+/// ```rust,no_run
+/// use acteur::{Acteur, Failure, Supervisor, SupervisorDecision};
+///
+/// struct LogAndRestart;
+///
+/// impl Supervisor for LogAndRestart {
+///     fn on_failure(&self, failure: &Failure) -> SupervisorDecision {
+///         println!("{} {:?} failed: {}", failure.type_name, failure.actor_id, failure.panic_message);
+///         SupervisorDecision::Restart
+///     }
+/// }
+///
+/// let sys = Acteur::builder().supervisor(LogAndRestart).build();
+/// ```
+pub trait Supervisor: Send + Sync + 'static {
+    fn on_failure(&self, failure: &Failure) -> SupervisorDecision;
+}
+
+impl Debug for dyn Supervisor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "Supervisor ()")
+    }
+}
+
+/// The supervision of an actor instance or a service: its strategy and how many times it was
+/// restarted.
+#[derive(Debug)]
+pub(crate) struct Supervision {
+    strategy: SupervisionStrategy,
+    max_restarts: Option<usize>,
+    restarts: AtomicUsize,
+}
+
+impl Supervision {
+    pub(crate) fn new(strategy: SupervisionStrategy, max_restarts: Option<usize>) -> Supervision {
+        Supervision {
+            strategy,
+            max_restarts,
+            restarts: AtomicUsize::new(0),
+        }
+    }
+
+    /// Resolves the strategy to a decision, asking the supervisor if needed. Once there were
+    /// `max_restarts` restarts, the decision is always to stop.
+    pub(crate) fn decide(
+        &self,
+        supervisor: Option<&dyn Supervisor>,
+        failure: &Failure,
+    ) -> SupervisorDecision {
+        let decision = match self.strategy {
+            SupervisionStrategy::Restart => SupervisorDecision::Restart,
+            SupervisionStrategy::Stop => SupervisorDecision::Stop,
+            SupervisionStrategy::Escalate => match supervisor {
+                Some(supervisor) => supervisor.on_failure(failure),
+                None => SupervisorDecision::Stop,
+            },
+        };
+
+        if decision == SupervisorDecision::Stop {
+            return decision;
+        }
+
+        let restarts = self.restarts.fetch_add(1, Ordering::Relaxed);
+
+        match self.max_restarts {
+            Some(max_restarts) if restarts >= max_restarts => SupervisorDecision::Stop,
+            _ => SupervisorDecision::Restart,
+        }
+    }
+}
+
+pub(crate) fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&'static str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => String::from("Box<dyn Any>"),
+        },
+    }
+}
@@ -1,48 +1,179 @@
 use crate::actors::actor::Actor;
+use crate::actors::actor_ref::ActorRef;
 use crate::actors::director::{ActorsDirector, ActorsDirectorConfiguration};
 use crate::actors::handle::Receive;
 use crate::actors::handle::Respond;
-use crate::actors::proxy::ActorReport;
-use crate::services::director::ServicesDirector;
+use crate::clock::Clock;
+#[cfg(feature = "cluster")]
+use crate::cluster::{transport, Cluster};
+use crate::error::{CallError, SendError, TrySendError};
+use crate::metrics::Metrics;
+use crate::middleware::Middlewares;
+use crate::observer::Observer;
+#[cfg(feature = "persistence")]
+use crate::persistence::journal::{InMemoryJournal, Journal};
+#[cfg(feature = "persistence")]
+use crate::persistence::snapshot::{InMemorySnapshotStore, SnapshotStore};
+use crate::report::SystemReport;
+use crate::runtime::{self, Executor};
+use crate::scheduler::{ScheduleHandle, Scheduler};
+#[cfg(feature = "testkit")]
+use crate::services::broker::MessageBroker;
+use crate::services::director::{ServicesDirector, ServicesDirectorConfiguration};
 use crate::services::handle::Listen;
 use crate::services::handle::Serve;
 use crate::services::service::Service;
-use async_std::{sync::Arc, task::block_on};
+use crate::shutdown::{ShutdownOptions, ShutdownState, ShutdownSummary};
+use crate::supervision::Supervisor;
 use futures::join;
 use std::any::TypeId;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+/// Configuration of a whole system. It is filled by the `ActeurBuilder`.
+#[derive(Debug)]
+pub(crate) struct SystemConfiguration {
+    pub(crate) innactivity_duration_until_actor_end: Duration,
+    pub(crate) max_actors: Option<usize>,
+    pub(crate) max_actors_per_type: HashMap<TypeId, usize>,
+    pub(crate) actors_memory_budget: Option<usize>,
+    pub(crate) supervisor: Option<Arc<dyn Supervisor>>,
+    pub(crate) call_timeout: Option<Duration>,
+    pub(crate) middlewares: Middlewares,
+    pub(crate) clock: Clock,
+    pub(crate) executor: Executor,
+    pub(crate) observer: Observer,
+    #[cfg(feature = "persistence")]
+    pub(crate) journal: Arc<dyn Journal>,
+    #[cfg(feature = "persistence")]
+    pub(crate) snapshot_store: Arc<dyn SnapshotStore>,
+    #[cfg(feature = "cluster")]
+    pub(crate) cluster: Option<Cluster>,
+}
+
+impl Default for SystemConfiguration {
+    fn default() -> SystemConfiguration {
+        SystemConfiguration {
+            innactivity_duration_until_actor_end: Duration::from_secs(300),
+            max_actors: None,
+            max_actors_per_type: HashMap::new(),
+            actors_memory_budget: None,
+            supervisor: None,
+            call_timeout: None,
+            middlewares: Middlewares::default(),
+            clock: Clock::real(),
+            executor: Executor::owned(),
+            observer: Observer::new(),
+            #[cfg(feature = "persistence")]
+            journal: Arc::new(InMemoryJournal::new()),
+            #[cfg(feature = "persistence")]
+            snapshot_store: Arc::new(InMemorySnapshotStore::new()),
+            #[cfg(feature = "cluster")]
+            cluster: None,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub(crate) struct SystemDirector {
     actors_director: Arc<ActorsDirector>,
     services_director: Arc<ServicesDirector>,
+    scheduler: Scheduler,
+    shutdown: Arc<ShutdownState>,
 }
 
 impl SystemDirector {
-    pub(crate) fn new() -> SystemDirector {
+    pub(crate) fn new(configuration: SystemConfiguration) -> SystemDirector {
+        let metrics = Arc::new(Metrics::new());
+        let middlewares = Arc::new(configuration.middlewares);
+        let shutdown = Arc::new(ShutdownState::new());
+        #[cfg(feature = "cluster")]
+        let (cluster, listener) = match configuration.cluster {
+            Some(cluster) => {
+                let (router, listener) = cluster.into_router();
+                (Some(router), Some(listener))
+            }
+            None => (None, None),
+        };
+
         let mut actors_director = Arc::new(ActorsDirector::new(ActorsDirectorConfiguration {
-            innactivity_seconds_until_actor_end: std::time::Duration::from_secs(300),
+            innactivity_seconds_until_actor_end: configuration.innactivity_duration_until_actor_end,
+            max_actors: configuration.max_actors,
+            max_actors_per_type: configuration.max_actors_per_type,
+            actors_memory_budget: configuration.actors_memory_budget,
+            supervisor: configuration.supervisor.clone(),
+            call_timeout: configuration.call_timeout,
+            metrics: metrics.clone(),
+            middlewares: middlewares.clone(),
+            clock: configuration.clock.clone(),
+            executor: configuration.executor.clone(),
+            observer: configuration.observer.clone(),
+            shutdown: shutdown.clone(),
+            #[cfg(feature = "persistence")]
+            journal: configuration.journal,
+            #[cfg(feature = "persistence")]
+            snapshot_store: configuration.snapshot_store,
+            #[cfg(feature = "cluster")]
+            cluster: cluster.clone(),
         }));
 
-        let mut services_director = Arc::new(ServicesDirector::new());
+        let mut services_director =
+            Arc::new(ServicesDirector::new(ServicesDirectorConfiguration {
+                supervisor: configuration.supervisor,
+                call_timeout: configuration.call_timeout,
+                metrics,
+                middlewares,
+                clock: configuration.clock.clone(),
+                executor: configuration.executor.clone(),
+                observer: configuration.observer.clone(),
+                shutdown: shutdown.clone(),
+            }));
 
         let system = SystemDirector {
             actors_director: actors_director.clone(),
             services_director: services_director.clone(),
+            scheduler: Scheduler::new(
+                configuration.clock,
+                configuration.observer,
+                configuration.executor,
+            ),
+            shutdown,
         };
 
-        let system_to_return = system.clone();
+        Arc::make_mut(&mut actors_director).set_system(system.downgrade());
+        Arc::make_mut(&mut services_director).set_system(system.downgrade());
+
+        // Other nodes can send messages only once the system is ready for them
+        #[cfg(feature = "cluster")]
+        if let (Some(router), Some(listener)) = (cluster, listener) {
+            transport::listen(listener, router, (*actors_director).clone());
+        }
+
+        system
+    }
+
+    pub(crate) fn downgrade(&self) -> WeakSystemDirector {
+        WeakSystemDirector {
+            actors_director: Arc::downgrade(&self.actors_director),
+            services_director: Arc::downgrade(&self.services_director),
+            scheduler: self.scheduler.clone(),
+            shutdown: self.shutdown.clone(),
+        }
+    }
+
+    pub(crate) fn get_executor(&self) -> &Executor {
+        self.actors_director.get_executor()
+    }
 
-        block_on(async move {
-            Arc::make_mut(&mut actors_director)
-                .set_system(system.clone())
-                .await;
-            Arc::make_mut(&mut services_director)
-                .set_system(system.clone())
-                .await;
-        });
+    pub(crate) async fn actor_ref<A: Actor>(&self, actor_id: A::Id) -> ActorRef<A> {
+        let manager = self
+            .actors_director
+            .get_or_create_manager_sender::<A>()
+            .await;
 
-        system_to_return
+        ActorRef::new(actor_id, manager, (*self.actors_director).clone())
     }
 
     pub async fn send_to_actor<A: Actor + Receive<M>, M: Debug + Send + 'static>(
@@ -53,14 +184,53 @@ impl SystemDirector {
         self.actors_director.send::<A, M>(actor_id, message).await
     }
 
-    pub async fn schedule_send_to_actor<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+    pub async fn send_to_actor_checked<A: Actor + Receive<M>, M: Debug + Send + 'static>(
         &self,
         actor_id: A::Id,
-        duration: std::time::Duration,
         message: M,
-    ) {
-        async_std::task::sleep(duration).await;
-        self.actors_director.send::<A, M>(actor_id, message).await
+    ) -> Result<(), SendError> {
+        self.actors_director
+            .send_checked::<A, M>(actor_id, message)
+            .await
+    }
+
+    pub async fn try_send_to_actor<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        message: M,
+    ) -> Result<(), TrySendError> {
+        self.actors_director
+            .try_send::<A, M>(actor_id, message)
+            .await
+    }
+
+    pub fn schedule_send_to_actor<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        duration: Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        let system = self.clone();
+
+        self.scheduler.schedule_once(duration, move || {
+            Box::pin(async move { system.send_to_actor::<A, M>(actor_id, message).await })
+        })
+    }
+
+    pub fn schedule_interval_to_actor<A: Actor + Receive<M>, M: Debug + Send + Clone + 'static>(
+        &self,
+        actor_id: A::Id,
+        interval: Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        let system = self.clone();
+
+        self.scheduler.schedule_interval(interval, move || {
+            let system = system.clone();
+            let actor_id = actor_id.clone();
+            let message = message.clone();
+            Box::pin(async move { system.send_to_actor::<A, M>(actor_id, message).await })
+        })
     }
 
     pub async fn send_to_all_actors<A: Actor + Receive<M>, M: Debug + Send + 'static>(
@@ -70,23 +240,54 @@ impl SystemDirector {
         self.actors_director.send_to_all::<A, M>(message).await
     }
 
-    pub async fn schedule_send_to_all_actors<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+    pub fn schedule_send_to_all_actors<A: Actor + Receive<M>, M: Debug + Send + 'static>(
         &self,
-        duration: std::time::Duration,
+        duration: Duration,
         message: M,
-    ) {
-        async_std::task::sleep(duration).await;
-        self.actors_director.send_to_all::<A, M>(message).await
+    ) -> ScheduleHandle {
+        let system = self.clone();
+
+        self.scheduler.schedule_once(duration, move || {
+            Box::pin(async move { system.send_to_all_actors::<A, M>(message).await })
+        })
+    }
+
+    pub fn schedule_interval_to_all_actors<
+        A: Actor + Receive<M>,
+        M: Debug + Send + Clone + 'static,
+    >(
+        &self,
+        interval: Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        let system = self.clone();
+
+        self.scheduler.schedule_interval(interval, move || {
+            let system = system.clone();
+            let message = message.clone();
+            Box::pin(async move { system.send_to_all_actors::<A, M>(message).await })
+        })
     }
 
     pub async fn call_actor<A: Actor + Respond<M>, M: Debug + Send + 'static>(
         &self,
         actor_id: A::Id,
         message: M,
-    ) -> Result<<A as Respond<M>>::Response, &str> {
+    ) -> Result<<A as Respond<M>>::Response, CallError> {
         self.actors_director.call::<A, M>(actor_id, message).await
     }
 
+    pub async fn call_actor_with_timeout<A: Actor + Respond<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        message: M,
+        timeout: Duration,
+    ) -> Result<<A as Respond<M>>::Response, CallError> {
+        self.actors_director
+            .call_with_timeout::<A, M>(actor_id, message, timeout)
+            .await
+    }
+
     pub async fn send_to_service<S: Service + Listen<M>, M: Debug + Send + 'static>(
         &self,
         message: M,
@@ -94,13 +295,69 @@ impl SystemDirector {
         self.services_director.send::<S, M>(message).await
     }
 
+    pub async fn send_to_service_checked<S: Service + Listen<M>, M: Debug + Send + 'static>(
+        &self,
+        message: M,
+    ) -> Result<(), SendError> {
+        self.services_director.send_checked::<S, M>(message).await
+    }
+
+    pub fn schedule_send_to_service<S: Service + Listen<M>, M: Debug + Send + 'static>(
+        &self,
+        duration: Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        let system = self.clone();
+
+        self.scheduler.schedule_once(duration, move || {
+            Box::pin(async move { system.send_to_service::<S, M>(message).await })
+        })
+    }
+
+    pub fn schedule_interval_to_service<
+        S: Service + Listen<M>,
+        M: Debug + Send + Clone + 'static,
+    >(
+        &self,
+        interval: Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        let system = self.clone();
+
+        self.scheduler.schedule_interval(interval, move || {
+            let system = system.clone();
+            let message = message.clone();
+            Box::pin(async move { system.send_to_service::<S, M>(message).await })
+        })
+    }
+
     pub async fn call_service<S: Service + Serve<M>, M: Debug + Send + 'static>(
         &self,
         message: M,
-    ) -> Result<<S as Serve<M>>::Response, &str> {
+    ) -> Result<<S as Serve<M>>::Response, CallError> {
         self.services_director.call::<S, M>(message).await
     }
 
+    pub async fn call_service_with_timeout<S: Service + Serve<M>, M: Debug + Send + 'static>(
+        &self,
+        message: M,
+        timeout: Duration,
+    ) -> Result<<S as Serve<M>>::Response, CallError> {
+        self.services_director
+            .call_with_timeout::<S, M>(message, timeout)
+            .await
+    }
+
+    #[cfg(feature = "testkit")]
+    pub(crate) fn get_actors_director(&self) -> ActorsDirector {
+        (*self.actors_director).clone()
+    }
+
+    #[cfg(feature = "testkit")]
+    pub(crate) fn get_broker(&self) -> MessageBroker {
+        self.services_director.get_broker().clone()
+    }
+
     pub(crate) async fn preload_service<S: Service>(&self) {
         self.services_director.preload::<S>().await;
     }
@@ -110,18 +367,169 @@ impl SystemDirector {
         let services_future = self.services_director.wait_until_stopped();
 
         join!(actors_future, services_future);
+
+        #[cfg(feature = "cluster")]
+        self.close_cluster();
+    }
+
+    /// Closes the connections with the other nodes of the cluster, if any. Messages for the
+    /// actors of this node are not accepted anymore.
+    #[cfg(feature = "cluster")]
+    fn close_cluster(&self) {
+        if let Some(cluster) = self.actors_director.get_cluster() {
+            cluster.close();
+        }
     }
 
     pub(crate) async fn stop(&self) {
+        self.scheduler.stop().await;
         join!(self.actors_director.stop(), self.services_director.stop());
     }
 
-    pub(crate) fn get_statistics(&self) -> Vec<(TypeId, Vec<ActorReport>)> {
-        self.actors_director.get_statistics()
+    /// Stops the system in phases: first it stops accepting external messages, then actors drain
+    /// their mailboxes and then services drain theirs. Whatever is still enqueued when the
+    /// deadline expires is discarded.
+    pub(crate) async fn shutdown(&self, options: ShutdownOptions) -> ShutdownSummary {
+        let started = Instant::now();
+        let remaining = || options.deadline.saturating_sub(started.elapsed());
+
+        self.shutdown.start();
+        let cancelled_schedules = self.scheduler.stop().await;
+
+        self.actors_director.stop().await;
+
+        let mut timed_out =
+            runtime::timeout(remaining(), self.actors_director.wait_until_stopped())
+                .await
+                .is_none();
+
+        if !timed_out {
+            // Actors activated by services from now on stop after handling their messages, so
+            // once services stop, we wait for them again.
+            self.services_director.stop().await;
+
+            timed_out = runtime::timeout(remaining(), async {
+                self.services_director.wait_until_stopped().await;
+                self.actors_director.wait_until_stopped().await;
+            })
+            .await
+            .is_none();
+        }
+
+        let mut discarded = HashMap::new();
+
+        if timed_out {
+            // If actors didn't finish on time, services were not asked to stop yet.
+            self.services_director.stop().await;
+
+            discarded.extend(self.actors_director.discard_pending_messages());
+            discarded.extend(self.services_director.discard_pending_messages());
+        }
+
+        #[cfg(feature = "cluster")]
+        self.close_cluster();
+
+        ShutdownSummary {
+            drained: self.shutdown.get_drained(),
+            discarded,
+            rejected: self.shutdown.get_rejected(),
+            cancelled_schedules,
+            timed_out,
+            elapsed: started.elapsed(),
+        }
+    }
+
+    /// Returns true, counting the message as rejected, if the system is shutting down. Then
+    /// messages from outside the system are not accepted anymore.
+    pub(crate) fn reject_external_message(&self) -> bool {
+        if self.shutdown.is_shutting_down() {
+            self.shutdown.message_rejected();
+            return true;
+        }
+
+        false
+    }
+
+    pub(crate) fn get_statistics(&self) -> SystemReport {
+        SystemReport {
+            actors: self.actors_director.get_statistics(),
+            services: self.services_director.get_statistics(),
+        }
     }
 
-    pub(crate) async fn publish<M: Send + Clone + 'static>(&self, message: M) {
-        self.services_director.publish(message).await
+    /// Returns true if there are no messages enqueued and nothing is being handled.
+    #[cfg(feature = "testkit")]
+    pub(crate) fn is_idle(&self) -> bool {
+        !self.actors_director.get_observer().is_busy()
+            && !self.actors_director.has_pending_messages()
+            && !self.services_director.has_pending_messages()
+    }
+
+    #[cfg(feature = "metrics")]
+    pub(crate) fn render_metrics(&self) -> String {
+        self.actors_director
+            .get_metrics()
+            .render(&self.get_statistics())
+    }
+
+    pub(crate) async fn publish<M: Send + Clone + 'static>(&self, topic: Option<&str>, message: M) {
+        self.services_director.publish(topic, message).await
+    }
+
+    pub(crate) fn subscribe_actor<A: Actor + Receive<M>, M: Sync + Send + Debug + 'static>(
+        &self,
+        actor_id: A::Id,
+        topic_pattern: Option<&str>,
+    ) {
+        self.services_director.get_broker().register_actor::<A, M>(
+            (*self.actors_director).clone(),
+            actor_id,
+            topic_pattern,
+        );
+    }
+
+    pub(crate) fn unsubscribe_actor<A: Actor + Receive<M>, M: Sync + Send + Debug + 'static>(
+        &self,
+        actor_id: A::Id,
+        topic_pattern: Option<&str>,
+    ) {
+        self.services_director
+            .get_broker()
+            .unregister_actor::<A, M>((*self.actors_director).clone(), actor_id, topic_pattern);
+    }
+
+    pub(crate) fn subscribe_all_actors<
+        A: Actor + Receive<M>,
+        M: Sync + Send + Clone + Debug + 'static,
+    >(
+        &self,
+        topic_pattern: Option<&str>,
+    ) {
+        self.services_director
+            .get_broker()
+            .register_all_actors::<A, M>((*self.actors_director).clone(), topic_pattern);
+    }
+
+    pub(crate) fn unsubscribe_all_actors<
+        A: Actor + Receive<M>,
+        M: Sync + Send + Clone + Debug + 'static,
+    >(
+        &self,
+        topic_pattern: Option<&str>,
+    ) {
+        self.services_director
+            .get_broker()
+            .unregister_all_actors::<A, M>((*self.actors_director).clone(), topic_pattern);
+    }
+
+    pub(crate) fn clear_actor_subscriptions<A: Actor>(
+        &self,
+        actor_id: &A::Id,
+        message_types: &HashSet<TypeId>,
+    ) {
+        self.services_director
+            .get_broker()
+            .unregister_actor_instance::<A>(actor_id, message_types);
     }
 }
 
@@ -130,6 +538,31 @@ impl Clone for SystemDirector {
         SystemDirector {
             actors_director: self.actors_director.clone(),
             services_director: self.services_director.clone(),
+            scheduler: self.scheduler.clone(),
+            shutdown: self.shutdown.clone(),
         }
     }
 }
+
+/// The system as the directors see it. It doesn't keep the system alive, as the system keeps the
+/// directors and that cycle would never be freed.
+#[derive(Debug, Clone)]
+pub(crate) struct WeakSystemDirector {
+    actors_director: Weak<ActorsDirector>,
+    services_director: Weak<ServicesDirector>,
+    scheduler: Scheduler,
+    shutdown: Arc<ShutdownState>,
+}
+
+impl WeakSystemDirector {
+    /// Returns `None` once nothing uses the system anymore, that is, once it is stopped and there
+    /// are no handles to it.
+    pub(crate) fn upgrade(&self) -> Option<SystemDirector> {
+        Some(SystemDirector {
+            actors_director: self.actors_director.upgrade()?,
+            services_director: self.services_director.upgrade()?,
+            scheduler: self.scheduler.clone(),
+            shutdown: self.shutdown.clone(),
+        })
+    }
+}
@@ -0,0 +1,125 @@
+use crate::observer::{BusyGuard, Observer};
+use async_channel::{bounded, Sender};
+use futures::future::{select, Either};
+use futures::pin_mut;
+use std::future::Future;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// A clock that only moves when the test advances it. Everything waiting on it (scheduled
+/// messages, passivation of actors) is woken up in order of deadline.
+#[derive(Debug)]
+pub(crate) struct VirtualClock {
+    start: Instant,
+    observer: Observer,
+    state: Mutex<VirtualClockState>,
+}
+
+#[derive(Debug, Default)]
+struct VirtualClockState {
+    elapsed: Duration,
+    timers: Vec<VirtualTimer>,
+}
+
+#[derive(Debug)]
+struct VirtualTimer {
+    deadline: Duration,
+    // The sleeping task receives a guard, so the system is busy until it takes over.
+    waker: Sender<BusyGuard>,
+}
+
+impl VirtualClock {
+    pub(crate) fn new(observer: Observer) -> VirtualClock {
+        VirtualClock {
+            start: Instant::now(),
+            observer,
+            state: Mutex::new(VirtualClockState::default()),
+        }
+    }
+
+    pub(crate) fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.lock_state().elapsed
+    }
+
+    /// Waits until the clock is advanced the duration.
+    pub(crate) async fn sleep(&self, duration: Duration) {
+        let (waker, sleeper) = bounded(1);
+
+        {
+            let mut state = self.lock_state();
+            let deadline = state.elapsed + duration;
+
+            // Sleeps that were abandoned don't need to be woken up
+            state.timers.retain(|timer| !timer.waker.is_closed());
+            state.timers.push(VirtualTimer { deadline, waker });
+        }
+
+        // If the clock is dropped, nothing will ever wake this up, same as the real time in
+        // a stopped runtime.
+        if sleeper.recv().await.is_err() {
+            futures::future::pending::<()>().await;
+        }
+    }
+
+    pub(crate) async fn timeout<F: Future>(
+        &self,
+        duration: Duration,
+        future: F,
+    ) -> Option<F::Output> {
+        let sleep = self.sleep(duration);
+
+        pin_mut!(future);
+        pin_mut!(sleep);
+
+        match select(future, sleep).await {
+            Either::Left((output, _)) => Some(output),
+            Either::Right(_) => None,
+        }
+    }
+
+    /// Moves the clock to the next deadline, if it is not after `until`, and wakes up everything
+    /// waiting for it. Returns false, after moving the clock to `until`, if there are no more
+    /// deadlines until then.
+    pub(crate) fn advance_to_next_deadline(&self, until: Duration) -> bool {
+        let mut state = self.lock_state();
+
+        state.timers.retain(|timer| !timer.waker.is_closed());
+
+        let deadline = match state.timers.iter().map(|timer| timer.deadline).min() {
+            Some(deadline) if deadline <= until => deadline,
+            _ => {
+                state.elapsed = state.elapsed.max(until);
+                return false;
+            }
+        };
+
+        state.elapsed = state.elapsed.max(deadline);
+
+        let elapsed = state.elapsed;
+        let (expired, pending) = state
+            .timers
+            .drain(..)
+            .partition::<Vec<_>, _>(|timer| timer.deadline <= elapsed);
+
+        state.timers = pending;
+
+        for timer in expired {
+            // If the task stopped waiting meanwhile, the guard is dropped right away.
+            let _ = timer.waker.try_send(self.observer.busy());
+        }
+
+        true
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, VirtualClockState> {
+        // The state is always updated in one step, so it is consistent even after a panic
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
@@ -0,0 +1,467 @@
+use crate::actors::actor::Actor;
+use crate::error::CallError;
+use crate::services::handle::Serve;
+use crate::services::service::Service;
+use crate::system_director::{SystemConfiguration, SystemDirector};
+use crate::testkit::outbox::Outbox;
+use crate::{ActorAssistant, Respond, ServiceAssistant};
+use dashmap::DashMap;
+use std::any::{type_name, Any, TypeId};
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+// The stubs are stored by the types of the target and the message, so each one can be downcast
+// back to the closure type of its call.
+type ActorStub<A, M> =
+    Arc<dyn Fn(&<A as Actor>::Id, &M) -> <A as Respond<M>>::Response + Send + Sync>;
+type ServiceStub<S, M> = Arc<dyn Fn(&M) -> <S as Serve<M>>::Response + Send + Sync>;
+
+/// What an assistant was asked to do with a [CapturedMessage](./struct.CapturedMessage.html).
+/// The variants are named after the methods of the assistants. `call_actor_with_timeout` is
+/// captured as `CallActor`, with its timeout in `CapturedMessage::timeout`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    SendToActor,
+    SendToActorChecked,
+    TrySendToActor,
+    SendToAllActors,
+    CallActor,
+    SendToService,
+    SendToServiceChecked,
+    CallService,
+    Publish,
+    ScheduleSendToActor(Duration),
+    ScheduleIntervalToActor(Duration),
+    ScheduleSendToAllActors(Duration),
+    ScheduleIntervalToAllActors(Duration),
+    ScheduleSendToService(Duration),
+    ScheduleIntervalToService(Duration),
+}
+
+/// Assistants for calling the handlers of an actor or service directly, without a running system.
+/// Only available with the `testkit` feature.
+///
+/// The assistants created with `actor_assistant` and `service_assistant` don't send anything.
+/// Every message sent, scheduled, called or published through them is captured instead, so the
+/// test can check them with `take_messages`. The responses of `call_actor` and `call_service` are
+/// given by the stubs registered for the target and message types. Calls without a stub return
+/// `CallError::ActorStopped` or `CallError::ServiceStopped`. With `delay_actor_call` and
+/// `delay_service_call`, calls with a shorter timeout return `CallError::Timeout`.
+///
+/// Subscriptions and `stop` have no effect outside of the mock.
+///
+/// ```rust,no_run
+/// use acteur::testkit::{MockSystem, Operation};
+/// use acteur::{Actor, ActorAssistant, Receive, Respond};
+/// use async_trait::async_trait;
+///
+/// #[derive(Debug)]
+/// struct Bank;
+///
+/// #[async_trait]
+/// impl Actor for Bank {
+///     type Id = u32;
+///
+///     async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+///         Bank
+///     }
+/// }
+///
+/// #[derive(Debug)]
+/// struct GetBalance;
+///
+/// #[async_trait]
+/// impl Respond<GetBalance> for Bank {
+///     type Response = u32;
+///
+///     async fn handle(&mut self, _: GetBalance, _: &ActorAssistant<Bank>) -> u32 {
+///         0
+///     }
+/// }
+///
+/// #[derive(Debug)]
+/// struct Wallet {
+///     bank_id: u32,
+/// }
+///
+/// #[async_trait]
+/// impl Actor for Wallet {
+///     type Id = u32;
+///
+///     async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+///         Wallet { bank_id: 7 }
+///     }
+/// }
+///
+/// #[derive(Debug, Clone, PartialEq)]
+/// struct Pay(u32);
+///
+/// #[derive(Debug, Clone, PartialEq)]
+/// struct Rejected(u32);
+///
+/// #[async_trait]
+/// impl Receive<Pay> for Wallet {
+///     async fn handle(&mut self, payment: Pay, assistant: &ActorAssistant<Wallet>) {
+///         let balance = assistant
+///             .call_actor::<Bank, GetBalance>(self.bank_id, GetBalance)
+///             .await
+///             .unwrap_or(0);
+///
+///         if balance < payment.0 {
+///             assistant.publish(Rejected(payment.0)).await;
+///         }
+///     }
+/// }
+///
+/// let mock = MockSystem::new();
+/// mock.stub_actor_call::<Bank, GetBalance, _>(|_bank_id, _| 5);
+///
+/// let assistant = mock.actor_assistant::<Wallet>(1);
+/// let mut wallet = Wallet { bank_id: 7 };
+///
+/// futures::executor::block_on(wallet.handle(Pay(10), &assistant));
+///
+/// let messages = mock.take_messages();
+///
+/// assert_eq!(messages.len(), 2);
+/// assert_eq!(messages[0].operation(), Operation::CallActor);
+/// assert_eq!(messages[0].actor_id::<Bank>(), Some(&7));
+/// assert_eq!(messages[1].operation(), Operation::Publish);
+/// assert_eq!(messages[1].message::<Rejected>(), Some(&Rejected(10)));
+/// ```
+#[derive(Debug)]
+pub struct MockSystem {
+    state: Arc<MockState>,
+    system_director: SystemDirector,
+}
+
+impl Default for MockSystem {
+    fn default() -> Self {
+        MockSystem::new()
+    }
+}
+
+impl MockSystem {
+    pub fn new() -> MockSystem {
+        MockSystem {
+            state: Arc::new(MockState::default()),
+            system_director: SystemDirector::new(SystemConfiguration::default()),
+        }
+    }
+
+    /// Creates an assistant for calling the handlers of the actor A with the Id directly.
+    pub fn actor_assistant<A: Actor>(&self, actor_id: A::Id) -> ActorAssistant<A> {
+        ActorAssistant::new(
+            self.system_director.clone(),
+            self.system_director.get_actors_director(),
+            actor_id,
+        )
+        .with_outbox(self.outbox())
+    }
+
+    /// Creates an assistant for calling the handlers of the service S directly.
+    pub fn service_assistant<S: Service>(&self) -> ServiceAssistant<S> {
+        ServiceAssistant::new(
+            self.system_director.clone(),
+            self.system_director.get_broker(),
+        )
+        .with_outbox(self.outbox())
+    }
+
+    /// Sets the response of the calls with the message M to the actors of type A. The stub
+    /// receives the Id of the actor and the message. It replaces any previous stub for the same
+    /// actor and message types.
+    pub fn stub_actor_call<A, M, F>(&self, stub: F)
+    where
+        A: Actor + Respond<M>,
+        M: Debug + Send + 'static,
+        F: Fn(&A::Id, &M) -> <A as Respond<M>>::Response + Send + Sync + 'static,
+    {
+        let stub: ActorStub<A, M> = Arc::new(stub);
+
+        self.state
+            .stubs
+            .insert((TypeId::of::<A>(), TypeId::of::<M>()), Box::new(stub));
+    }
+
+    /// Sets the response of the calls with the message M to the service S. It replaces any
+    /// previous stub for the same service and message types.
+    pub fn stub_service_call<S, M, F>(&self, stub: F)
+    where
+        S: Service + Serve<M>,
+        M: Debug + Send + 'static,
+        F: Fn(&M) -> <S as Serve<M>>::Response + Send + Sync + 'static,
+    {
+        let stub: ServiceStub<S, M> = Arc::new(stub);
+
+        self.state
+            .stubs
+            .insert((TypeId::of::<S>(), TypeId::of::<M>()), Box::new(stub));
+    }
+
+    /// Makes the calls with the message M to the actors of type A take the delay to respond.
+    /// Calls with a timeout not longer than the delay return `CallError::Timeout` without
+    /// running the stub. The others are answered right away, nothing waits for the delay.
+    pub fn delay_actor_call<A: Actor + Respond<M>, M: Debug + Send + 'static>(
+        &self,
+        delay: Duration,
+    ) {
+        self.state
+            .delays
+            .insert((TypeId::of::<A>(), TypeId::of::<M>()), delay);
+    }
+
+    /// Same as `delay_actor_call` for the calls with the message M to the service S.
+    pub fn delay_service_call<S: Service + Serve<M>, M: Debug + Send + 'static>(
+        &self,
+        delay: Duration,
+    ) {
+        self.state
+            .delays
+            .insert((TypeId::of::<S>(), TypeId::of::<M>()), delay);
+    }
+
+    /// Returns, in order, the messages captured until now and forgets them.
+    pub fn take_messages(&self) -> Vec<CapturedMessage> {
+        self.state.lock_messages().drain(..).collect()
+    }
+
+    /// Returns how many of the messages captured until now are of type M.
+    pub fn count_of<M: 'static>(&self) -> usize {
+        self.state
+            .lock_messages()
+            .iter()
+            .filter(|message| message.is::<M>())
+            .count()
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock_messages().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.state.lock_messages().is_empty()
+    }
+
+    fn outbox(&self) -> Outbox {
+        Outbox::Mock(self.state.clone())
+    }
+}
+
+/// A message sent through a mock assistant. See [MockSystem](./struct.MockSystem.html).
+pub struct CapturedMessage {
+    operation: Operation,
+    target: Option<(TypeId, &'static str)>,
+    actor_id: Option<Box<dyn Any + Send>>,
+    topic: Option<String>,
+    timeout: Option<Duration>,
+    message_type: &'static str,
+    message: Box<dyn Any + Send>,
+}
+
+impl CapturedMessage {
+    pub(crate) fn for_actor<A: Actor, M: Send + 'static>(
+        operation: Operation,
+        actor_id: Option<A::Id>,
+        message: M,
+    ) -> CapturedMessage {
+        CapturedMessage {
+            operation,
+            target: Some((TypeId::of::<A>(), type_name::<A>())),
+            actor_id: actor_id.map(|actor_id| Box::new(actor_id) as Box<dyn Any + Send>),
+            topic: None,
+            timeout: None,
+            message_type: type_name::<M>(),
+            message: Box::new(message),
+        }
+    }
+
+    pub(crate) fn for_service<S: Service, M: Send + 'static>(
+        operation: Operation,
+        message: M,
+    ) -> CapturedMessage {
+        CapturedMessage {
+            operation,
+            target: Some((TypeId::of::<S>(), type_name::<S>())),
+            actor_id: None,
+            topic: None,
+            timeout: None,
+            message_type: type_name::<M>(),
+            message: Box::new(message),
+        }
+    }
+
+    pub(crate) fn published<M: Send + 'static>(topic: Option<&str>, message: M) -> CapturedMessage {
+        CapturedMessage {
+            operation: Operation::Publish,
+            target: None,
+            actor_id: None,
+            topic: topic.map(str::to_string),
+            timeout: None,
+            message_type: type_name::<M>(),
+            message: Box::new(message),
+        }
+    }
+
+    fn with_timeout(mut self, timeout: Option<Duration>) -> CapturedMessage {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn operation(&self) -> Operation {
+        self.operation
+    }
+
+    /// Type name of the actor or service the message was for, as returned by
+    /// `std::any::type_name`. None for published messages.
+    pub fn target_type(&self) -> Option<&'static str> {
+        self.target.map(|(_, name)| name)
+    }
+
+    /// Returns true if the message was for the actor or service T.
+    pub fn is_for<T: 'static>(&self) -> bool {
+        matches!(self.target, Some((target, _)) if target == TypeId::of::<T>())
+    }
+
+    /// Type name of the message, as returned by `std::any::type_name`.
+    pub fn message_type(&self) -> &'static str {
+        self.message_type
+    }
+
+    /// Returns true if the message is of type M.
+    pub fn is<M: 'static>(&self) -> bool {
+        self.message.is::<M>()
+    }
+
+    /// The Id of the actor the message was for, if it was for one actor of type A.
+    pub fn actor_id<A: Actor>(&self) -> Option<&A::Id> {
+        if !self.is_for::<A>() {
+            return None;
+        }
+
+        self.actor_id.as_ref()?.downcast_ref::<A::Id>()
+    }
+
+    /// The topic of the messages published with `publish_to_topic`.
+    pub fn topic(&self) -> Option<&str> {
+        self.topic.as_deref()
+    }
+
+    /// The timeout of the calls made with `call_actor_with_timeout` or
+    /// `call_service_with_timeout`.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// The message, if it is of type M.
+    pub fn message<M: 'static>(&self) -> Option<&M> {
+        self.message.downcast_ref::<M>()
+    }
+
+    /// Same as `message` but it takes the message.
+    pub fn into_message<M: 'static>(self) -> Option<M> {
+        self.message.downcast::<M>().ok().map(|message| *message)
+    }
+}
+
+impl Debug for CapturedMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CapturedMessage")
+            .field("operation", &self.operation)
+            .field("target_type", &self.target_type())
+            .field("topic", &self.topic)
+            .field("timeout", &self.timeout)
+            .field("message_type", &self.message_type)
+            .finish()
+    }
+}
+
+// The state shared by a `MockSystem` and its assistants. It captures all the outgoing messages
+// instead of sending them.
+#[derive(Default)]
+pub(crate) struct MockState {
+    messages: Mutex<Vec<CapturedMessage>>,
+    // By target and message type. The stubs are an `ActorStub` or a `ServiceStub`.
+    stubs: DashMap<(TypeId, TypeId), Box<dyn Any + Send + Sync>>,
+    delays: DashMap<(TypeId, TypeId), Duration>,
+}
+
+impl MockState {
+    pub(crate) fn capture(&self, message: CapturedMessage) {
+        self.lock_messages().push(message);
+    }
+
+    pub(crate) fn call_actor<A: Actor + Respond<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        message: M,
+        timeout: Option<Duration>,
+    ) -> Result<<A as Respond<M>>::Response, CallError> {
+        let key = (TypeId::of::<A>(), TypeId::of::<M>());
+
+        let response = match self.is_timed_out(&key, timeout) {
+            true => Err(CallError::Timeout),
+            false => match self.stub::<ActorStub<A, M>>(&key) {
+                Some(stub) => Ok(stub(&actor_id, &message)),
+                None => Err(CallError::ActorStopped),
+            },
+        };
+
+        self.capture(
+            CapturedMessage::for_actor::<A, M>(Operation::CallActor, Some(actor_id), message)
+                .with_timeout(timeout),
+        );
+
+        response
+    }
+
+    pub(crate) fn call_service<S: Service + Serve<M>, M: Debug + Send + 'static>(
+        &self,
+        message: M,
+        timeout: Option<Duration>,
+    ) -> Result<<S as Serve<M>>::Response, CallError> {
+        let key = (TypeId::of::<S>(), TypeId::of::<M>());
+
+        let response = match self.is_timed_out(&key, timeout) {
+            true => Err(CallError::Timeout),
+            false => match self.stub::<ServiceStub<S, M>>(&key) {
+                Some(stub) => Ok(stub(&message)),
+                None => Err(CallError::ServiceStopped),
+            },
+        };
+
+        self.capture(
+            CapturedMessage::for_service::<S, M>(Operation::CallService, message)
+                .with_timeout(timeout),
+        );
+
+        response
+    }
+
+    fn is_timed_out(&self, key: &(TypeId, TypeId), timeout: Option<Duration>) -> bool {
+        match (timeout, self.delays.get(key)) {
+            (Some(timeout), Some(delay)) => timeout <= *delay,
+            _ => false,
+        }
+    }
+
+    // Cloned, so the map is not locked while the stub runs
+    fn stub<T: Clone + 'static>(&self, key: &(TypeId, TypeId)) -> Option<T> {
+        self.stubs.get(key)?.downcast_ref::<T>().cloned()
+    }
+
+    fn lock_messages(&self) -> MutexGuard<'_, Vec<CapturedMessage>> {
+        // Messages are pushed in one step, a panic cannot leave the list half updated
+        match self.messages.lock() {
+            Ok(messages) => messages,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl Debug for MockState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MockState ()")
+    }
+}
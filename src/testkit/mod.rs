@@ -0,0 +1,20 @@
+//! Tools for testing actors and services without real time. Only available with the `testkit`
+//! feature, which is meant to be enabled only in the dev-dependencies:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! acteur = { version = "*", features = ["testkit"] }
+//! ```
+//!
+//! See [TestSystem](./struct.TestSystem.html) for testing a whole system and
+//! [MockSystem](./struct.MockSystem.html) for testing the handlers of one actor or service.
+
+pub(crate) mod clock;
+pub(crate) mod mock;
+pub(crate) mod outbox;
+mod recorder;
+mod system;
+
+pub use mock::{CapturedMessage, MockSystem, Operation};
+pub use recorder::{MessageRecorder, RecordedMessage};
+pub use system::{BlockingTestSystem, TestSystem};
@@ -0,0 +1,313 @@
+use crate::actors::actor::Actor;
+use crate::actors::handle::{Receive, Respond};
+use crate::error::{CallError, SendError, TrySendError};
+use crate::scheduler::ScheduleHandle;
+use crate::services::handle::{Listen, Serve};
+use crate::services::service::Service;
+use crate::system_director::SystemDirector;
+use crate::testkit::mock::{CapturedMessage, MockState, Operation};
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Where the assistants send their messages when the `testkit` feature is enabled. It has the
+/// same methods as the `SystemDirector`, which the assistants use directly without the feature.
+#[derive(Debug, Clone)]
+pub(crate) enum Outbox {
+    System(SystemDirector),
+    // The messages are captured by a `MockSystem` instead of being sent
+    Mock(Arc<MockState>),
+}
+
+impl Outbox {
+    pub(crate) async fn send_to_actor<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        message: M,
+    ) {
+        match self {
+            Outbox::System(system) => system.send_to_actor::<A, M>(actor_id, message).await,
+            Outbox::Mock(mock) => mock.capture(CapturedMessage::for_actor::<A, M>(
+                Operation::SendToActor,
+                Some(actor_id),
+                message,
+            )),
+        }
+    }
+
+    pub(crate) async fn send_to_actor_checked<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        message: M,
+    ) -> Result<(), SendError> {
+        match self {
+            Outbox::System(system) => {
+                system
+                    .send_to_actor_checked::<A, M>(actor_id, message)
+                    .await
+            }
+            Outbox::Mock(mock) => {
+                mock.capture(CapturedMessage::for_actor::<A, M>(
+                    Operation::SendToActorChecked,
+                    Some(actor_id),
+                    message,
+                ));
+                Ok(())
+            }
+        }
+    }
+
+    pub(crate) async fn try_send_to_actor<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        message: M,
+    ) -> Result<(), TrySendError> {
+        match self {
+            Outbox::System(system) => system.try_send_to_actor::<A, M>(actor_id, message).await,
+            Outbox::Mock(mock) => {
+                mock.capture(CapturedMessage::for_actor::<A, M>(
+                    Operation::TrySendToActor,
+                    Some(actor_id),
+                    message,
+                ));
+                Ok(())
+            }
+        }
+    }
+
+    pub(crate) fn schedule_send_to_actor<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        duration: Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        match self {
+            Outbox::System(system) => {
+                system.schedule_send_to_actor::<A, M>(actor_id, duration, message)
+            }
+            Outbox::Mock(mock) => {
+                mock.capture(CapturedMessage::for_actor::<A, M>(
+                    Operation::ScheduleSendToActor(duration),
+                    Some(actor_id),
+                    message,
+                ));
+                ScheduleHandle::new()
+            }
+        }
+    }
+
+    pub(crate) fn schedule_interval_to_actor<
+        A: Actor + Receive<M>,
+        M: Debug + Send + Clone + 'static,
+    >(
+        &self,
+        actor_id: A::Id,
+        interval: Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        match self {
+            Outbox::System(system) => {
+                system.schedule_interval_to_actor::<A, M>(actor_id, interval, message)
+            }
+            Outbox::Mock(mock) => {
+                mock.capture(CapturedMessage::for_actor::<A, M>(
+                    Operation::ScheduleIntervalToActor(interval),
+                    Some(actor_id),
+                    message,
+                ));
+                ScheduleHandle::new()
+            }
+        }
+    }
+
+    pub(crate) async fn send_to_all_actors<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        message: M,
+    ) {
+        match self {
+            Outbox::System(system) => system.send_to_all_actors::<A, M>(message).await,
+            Outbox::Mock(mock) => mock.capture(CapturedMessage::for_actor::<A, M>(
+                Operation::SendToAllActors,
+                None,
+                message,
+            )),
+        }
+    }
+
+    pub(crate) fn schedule_send_to_all_actors<A: Actor + Receive<M>, M: Debug + Send + 'static>(
+        &self,
+        duration: Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        match self {
+            Outbox::System(system) => system.schedule_send_to_all_actors::<A, M>(duration, message),
+            Outbox::Mock(mock) => {
+                mock.capture(CapturedMessage::for_actor::<A, M>(
+                    Operation::ScheduleSendToAllActors(duration),
+                    None,
+                    message,
+                ));
+                ScheduleHandle::new()
+            }
+        }
+    }
+
+    pub(crate) fn schedule_interval_to_all_actors<
+        A: Actor + Receive<M>,
+        M: Debug + Send + Clone + 'static,
+    >(
+        &self,
+        interval: Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        match self {
+            Outbox::System(system) => {
+                system.schedule_interval_to_all_actors::<A, M>(interval, message)
+            }
+            Outbox::Mock(mock) => {
+                mock.capture(CapturedMessage::for_actor::<A, M>(
+                    Operation::ScheduleIntervalToAllActors(interval),
+                    None,
+                    message,
+                ));
+                ScheduleHandle::new()
+            }
+        }
+    }
+
+    pub(crate) async fn call_actor<A: Actor + Respond<M>, M: Debug + Send + 'static>(
+        &self,
+        actor_id: A::Id,
+        message: M,
+    ) -> Result<<A as Respond<M>>::Response, CallError> {
+        match self {
+            Outbox::System(system) => system.call_actor::<A, M>(actor_id, message).await,
+            Outbox::Mock(mock) => mock.call_actor::<A, M>(actor_id, message, None),
+        }
+    }
+
+    pub(crate) async fn call_actor_with_timeout<
+        A: Actor + Respond<M>,
+        M: Debug + Send + 'static,
+    >(
+        &self,
+        actor_id: A::Id,
+        message: M,
+        timeout: Duration,
+    ) -> Result<<A as Respond<M>>::Response, CallError> {
+        match self {
+            Outbox::System(system) => {
+                system
+                    .call_actor_with_timeout::<A, M>(actor_id, message, timeout)
+                    .await
+            }
+            Outbox::Mock(mock) => mock.call_actor::<A, M>(actor_id, message, Some(timeout)),
+        }
+    }
+
+    pub(crate) async fn send_to_service<S: Service + Listen<M>, M: Debug + Send + 'static>(
+        &self,
+        message: M,
+    ) {
+        match self {
+            Outbox::System(system) => system.send_to_service::<S, M>(message).await,
+            Outbox::Mock(mock) => mock.capture(CapturedMessage::for_service::<S, M>(
+                Operation::SendToService,
+                message,
+            )),
+        }
+    }
+
+    pub(crate) async fn send_to_service_checked<
+        S: Service + Listen<M>,
+        M: Debug + Send + 'static,
+    >(
+        &self,
+        message: M,
+    ) -> Result<(), SendError> {
+        match self {
+            Outbox::System(system) => system.send_to_service_checked::<S, M>(message).await,
+            Outbox::Mock(mock) => {
+                mock.capture(CapturedMessage::for_service::<S, M>(
+                    Operation::SendToServiceChecked,
+                    message,
+                ));
+                Ok(())
+            }
+        }
+    }
+
+    pub(crate) fn schedule_send_to_service<S: Service + Listen<M>, M: Debug + Send + 'static>(
+        &self,
+        duration: Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        match self {
+            Outbox::System(system) => system.schedule_send_to_service::<S, M>(duration, message),
+            Outbox::Mock(mock) => {
+                mock.capture(CapturedMessage::for_service::<S, M>(
+                    Operation::ScheduleSendToService(duration),
+                    message,
+                ));
+                ScheduleHandle::new()
+            }
+        }
+    }
+
+    pub(crate) fn schedule_interval_to_service<
+        S: Service + Listen<M>,
+        M: Debug + Send + Clone + 'static,
+    >(
+        &self,
+        interval: Duration,
+        message: M,
+    ) -> ScheduleHandle {
+        match self {
+            Outbox::System(system) => {
+                system.schedule_interval_to_service::<S, M>(interval, message)
+            }
+            Outbox::Mock(mock) => {
+                mock.capture(CapturedMessage::for_service::<S, M>(
+                    Operation::ScheduleIntervalToService(interval),
+                    message,
+                ));
+                ScheduleHandle::new()
+            }
+        }
+    }
+
+    pub(crate) async fn call_service<S: Service + Serve<M>, M: Debug + Send + 'static>(
+        &self,
+        message: M,
+    ) -> Result<<S as Serve<M>>::Response, CallError> {
+        match self {
+            Outbox::System(system) => system.call_service::<S, M>(message).await,
+            Outbox::Mock(mock) => mock.call_service::<S, M>(message, None),
+        }
+    }
+
+    pub(crate) async fn call_service_with_timeout<
+        S: Service + Serve<M>,
+        M: Debug + Send + 'static,
+    >(
+        &self,
+        message: M,
+        timeout: Duration,
+    ) -> Result<<S as Serve<M>>::Response, CallError> {
+        match self {
+            Outbox::System(system) => {
+                system
+                    .call_service_with_timeout::<S, M>(message, timeout)
+                    .await
+            }
+            Outbox::Mock(mock) => mock.call_service::<S, M>(message, Some(timeout)),
+        }
+    }
+
+    pub(crate) async fn publish<M: Send + Clone + 'static>(&self, topic: Option<&str>, message: M) {
+        match self {
+            Outbox::System(system) => system.publish(topic, message).await,
+            Outbox::Mock(mock) => mock.capture(CapturedMessage::published(topic, message)),
+        }
+    }
+}
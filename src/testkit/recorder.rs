@@ -0,0 +1,81 @@
+use std::any::type_name;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// A message delivered to a recorded actor. See [TestSystem::record](./struct.TestSystem.html#method.record).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedMessage {
+    /// Type name of the actor, as returned by `std::any::type_name`.
+    pub actor_type: &'static str,
+    /// The Id of the actor, formatted with `Debug`.
+    pub actor_id: String,
+    /// Type name of the message, as returned by `std::any::type_name`.
+    pub message_type: &'static str,
+    /// The message, formatted with `Debug`. None if a middleware already took it.
+    pub message: Option<String>,
+}
+
+impl RecordedMessage {
+    /// Returns true if the message is of type M.
+    pub fn is<M: 'static>(&self) -> bool {
+        self.message_type == type_name::<M>()
+    }
+}
+
+/// Keeps, in order, every message delivered to the handlers of an actor type since the recorder
+/// was created with [TestSystem::record](./struct.TestSystem.html#method.record). Messages rejected
+/// by a middleware are not delivered, so they are not recorded.
+///
+/// Clones share the same messages.
+#[derive(Debug, Clone, Default)]
+pub struct MessageRecorder {
+    messages: Arc<Mutex<Vec<RecordedMessage>>>,
+}
+
+impl MessageRecorder {
+    /// Returns all the messages recorded until now.
+    pub fn messages(&self) -> Vec<RecordedMessage> {
+        self.lock_messages().clone()
+    }
+
+    /// Returns the messages of type M recorded until now.
+    pub fn messages_of<M: 'static>(&self) -> Vec<RecordedMessage> {
+        self.lock_messages()
+            .iter()
+            .filter(|message| message.is::<M>())
+            .cloned()
+            .collect()
+    }
+
+    /// Returns how many messages of type M were recorded.
+    pub fn count_of<M: 'static>(&self) -> usize {
+        self.lock_messages()
+            .iter()
+            .filter(|message| message.is::<M>())
+            .count()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock_messages().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock_messages().is_empty()
+    }
+
+    /// Forgets the messages recorded until now. New messages are still recorded.
+    pub fn clear(&self) {
+        self.lock_messages().clear();
+    }
+
+    pub(crate) fn push(&self, message: RecordedMessage) {
+        self.lock_messages().push(message);
+    }
+
+    fn lock_messages(&self) -> MutexGuard<'_, Vec<RecordedMessage>> {
+        // Messages are pushed in one step, a panic cannot leave the list half updated
+        match self.messages.lock() {
+            Ok(messages) => messages,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use crate::blocking::BlockingActeur;
+use crate::builder::ActeurBuilder;
+use crate::clock::Clock;
+use crate::facade::Acteur;
+use crate::observer::Observer;
+use crate::runtime;
+use crate::system_director::SystemDirector;
+use crate::testkit::clock::VirtualClock;
+use crate::testkit::recorder::MessageRecorder;
+use crate::Actor;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use std::time::Duration;
+
+// How often `wait_until_idle` checks the system.
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_millis(1);
+
+/// An isolated system for tests, with a virtual clock. Only available with the `testkit` feature.
+///
+/// It dereferences to [Acteur](../struct.Acteur.html), so messages are sent as usual. The
+/// differences with a normal system are:
+///
+/// - Time doesn't pass by itself. Scheduled messages and the passivation of inactive actors only
+///   happen when the test calls `advance`, so tests never need to sleep.
+/// - `wait_until_idle` waits until all the messages sent were handled, including the ones sent by
+///   the handlers themselves.
+/// - `record` keeps every message delivered to an actor type, for making assertions on them.
+///
+/// Calls with a timeout (`call_actor_with_timeout`, `ActeurBuilder::call_timeout`, etc) use the
+/// virtual clock too, so a slow handler never makes a call time out in a test. On the other
+/// hand, a handler that never finishes makes the call wait forever, as the system is never idle
+/// for `advance` to move the clock.
+///
+/// ```rust,no_run
+/// use acteur::testkit::TestSystem;
+/// use acteur::{Actor, ActorAssistant, Receive};
+/// use async_trait::async_trait;
+/// use std::time::Duration;
+///
+/// #[derive(Debug)]
+/// struct Alarm;
+///
+/// #[async_trait]
+/// impl Actor for Alarm {
+///     type Id = u32;
+///
+///     async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+///         Alarm
+///     }
+/// }
+///
+/// #[derive(Debug, Clone)]
+/// struct Ring;
+///
+/// #[async_trait]
+/// impl Receive<Ring> for Alarm {
+///     async fn handle(&mut self, _: Ring, _: &ActorAssistant<Alarm>) {}
+/// }
+///
+/// let sys = TestSystem::new().blocking();
+/// let rings = sys.record::<Alarm>();
+///
+/// sys.schedule_interval_to_actor::<Alarm, Ring>(1, Duration::from_secs(60), Ring);
+///
+/// // Two hours pass in an instant
+/// sys.advance(Duration::from_secs(2 * 60 * 60));
+///
+/// assert_eq!(rings.count_of::<Ring>(), 120);
+///
+/// sys.stop();
+/// sys.wait_until_stopped();
+/// ```
+#[derive(Debug)]
+pub struct TestSystem {
+    acteur: Acteur,
+    system_director: SystemDirector,
+    clock: Arc<VirtualClock>,
+    observer: Observer,
+}
+
+impl Default for TestSystem {
+    fn default() -> Self {
+        TestSystem::new()
+    }
+}
+
+impl TestSystem {
+    /// Creates a test system with the default configuration.
+    pub fn new() -> TestSystem {
+        TestSystem::from_builder(ActeurBuilder::new())
+    }
+
+    /// Creates a test system with the configuration of the builder.
+    ///
+    /// ```rust,no_run
+    /// use acteur::testkit::TestSystem;
+    /// use acteur::Acteur;
+    /// use std::time::Duration;
+    ///
+    /// let sys = TestSystem::from_builder(
+    ///     Acteur::builder().actors_inactivity_timeout(Duration::from_secs(60)),
+    /// );
+    /// ```
+    pub fn from_builder(builder: ActeurBuilder) -> TestSystem {
+        let observer = Observer::new();
+        let clock = Arc::new(VirtualClock::new(observer.clone()));
+
+        let mut configuration = builder.into_configuration();
+        configuration.clock = Clock::Virtual(clock.clone());
+        configuration.observer = observer.clone();
+
+        let system_director = SystemDirector::new(configuration);
+
+        TestSystem {
+            acteur: Acteur::from_system_director(system_director.clone()),
+            system_director,
+            clock,
+            observer,
+        }
+    }
+
+    /// Virtual time passed since the system was created.
+    pub fn elapsed(&self) -> Duration {
+        self.clock.elapsed()
+    }
+
+    /// Moves the virtual clock forward. Everything that was waiting for a moment in between, like
+    /// scheduled messages or inactive actors waiting to be deactivated, happens in order, and
+    /// the system is idle again before moving to the next moment.
+    pub async fn advance(&self, duration: Duration) {
+        let until = self.clock.elapsed() + duration;
+
+        loop {
+            self.wait_until_idle().await;
+
+            if !self.clock.advance_to_next_deadline(until) {
+                break;
+            }
+        }
+    }
+
+    /// Waits until there are no messages in any queue and no actor or service is handling a
+    /// message, activating or deactivating. Messages scheduled in the future don't count, as
+    /// they are not sent until the clock is advanced.
+    pub async fn wait_until_idle(&self) {
+        loop {
+            let generation = self.observer.generation();
+            let was_idle = self.system_director.is_idle();
+
+            runtime::sleep(IDLE_CHECK_INTERVAL).await;
+
+            // A message may have moved from a queue to a handler between the two checks, but that
+            // would have changed the generation.
+            if was_idle
+                && self.observer.generation() == generation
+                && self.system_director.is_idle()
+            {
+                return;
+            }
+        }
+    }
+
+    /// Records, from now on, every message delivered to the actors of type A.
+    pub fn record<A: Actor>(&self) -> MessageRecorder {
+        let recorder = MessageRecorder::default();
+
+        self.observer.add_recorder::<A>(recorder.clone());
+
+        recorder
+    }
+
+    /// Returns a handle to this same system with sync methods that block the current thread
+    /// until they finish. See [BlockingTestSystem](./struct.BlockingTestSystem.html).
+    pub fn blocking(&self) -> BlockingTestSystem {
+        BlockingTestSystem {
+            system: TestSystem {
+                acteur: self.acteur.clone(),
+                system_director: self.system_director.clone(),
+                clock: self.clock.clone(),
+                observer: self.observer.clone(),
+            },
+            acteur: self.acteur.blocking(),
+        }
+    }
+}
+
+impl Deref for TestSystem {
+    type Target = Acteur;
+
+    fn deref(&self) -> &Acteur {
+        &self.acteur
+    }
+}
+
+/// Same as [TestSystem](./struct.TestSystem.html), but with sync methods that block the current
+/// thread until they finish. It dereferences to [BlockingActeur](../struct.BlockingActeur.html),
+/// so messages are sent with its sync methods. Same as them, its methods panic if they are
+/// called from async code.
+#[derive(Debug)]
+pub struct BlockingTestSystem {
+    system: TestSystem,
+    acteur: BlockingActeur,
+}
+
+impl BlockingTestSystem {
+    /// Same as `TestSystem::elapsed`.
+    pub fn elapsed(&self) -> Duration {
+        self.system.elapsed()
+    }
+
+    /// Same as `TestSystem::advance`.
+    pub fn advance(&self, duration: Duration) {
+        runtime::block_on(self.system.advance(duration))
+    }
+
+    /// Same as `TestSystem::wait_until_idle`.
+    pub fn wait_until_idle(&self) {
+        runtime::block_on(self.system.wait_until_idle())
+    }
+
+    /// Same as `TestSystem::record`.
+    pub fn record<A: Actor>(&self) -> MessageRecorder {
+        self.system.record::<A>()
+    }
+}
+
+impl Deref for BlockingTestSystem {
+    type Target = BlockingActeur;
+
+    fn deref(&self) -> &BlockingActeur {
+        &self.acteur
+    }
+}
+
+impl DerefMut for BlockingTestSystem {
+    fn deref_mut(&mut self) -> &mut BlockingActeur {
+        &mut self.acteur
+    }
+}
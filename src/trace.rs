@@ -0,0 +1,103 @@
+use crate::{Actor, Service};
+use std::future::Future;
+
+#[cfg(feature = "tracing")]
+use std::any::type_name;
+#[cfg(feature = "tracing")]
+use tracing::{Instrument, Span};
+
+// Letters capture the `tracing` span that is current when they are created and the handlers run
+// inside a child of that span. As handlers send new messages from inside their span, traces show
+// which message caused which one, even across actors and services.
+//
+// Without the `tracing` feature this structure is empty and the futures are just awaited.
+
+/// The span of the code that created a letter.
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone)]
+pub(crate) struct TraceContext {
+    span: Span,
+}
+
+#[cfg(not(feature = "tracing"))]
+#[derive(Debug, Clone)]
+pub(crate) struct TraceContext;
+
+#[cfg(feature = "tracing")]
+impl TraceContext {
+    pub(crate) fn current() -> TraceContext {
+        TraceContext {
+            span: Span::current(),
+        }
+    }
+
+    /// Runs the future inside the captured span. Letters created by the future capture
+    /// the same span.
+    pub(crate) async fn in_scope<F: Future>(&self, future: F) -> F::Output {
+        future.instrument(self.span.clone()).await
+    }
+
+    /// Runs an actor handler inside a child span of the captured one.
+    pub(crate) async fn in_actor_handler<A: Actor, F: Future>(
+        &self,
+        actor_id: &A::Id,
+        message_type_name: &'static str,
+        handler: F,
+    ) -> F::Output {
+        let span = tracing::info_span!(
+            parent: &self.span,
+            "actor_handler",
+            actor_type = type_name::<A>(),
+            actor_id = ?actor_id,
+            message_type = message_type_name,
+        );
+
+        handler.instrument(span).await
+    }
+
+    /// Runs a service handler inside a child span of the captured one.
+    pub(crate) async fn in_service_handler<S: Service, F: Future>(
+        &self,
+        message_type_name: &'static str,
+        handler: F,
+    ) -> F::Output {
+        let span = tracing::info_span!(
+            parent: &self.span,
+            "service_handler",
+            service_type = type_name::<S>(),
+            message_type = message_type_name,
+        );
+
+        handler.instrument(span).await
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+impl TraceContext {
+    pub(crate) fn current() -> TraceContext {
+        TraceContext
+    }
+
+    pub(crate) async fn in_scope<F: Future>(&self, future: F) -> F::Output {
+        future.await
+    }
+
+    pub(crate) async fn in_actor_handler<A: Actor, F: Future>(
+        &self,
+        _: &A::Id,
+        _: &'static str,
+        handler: F,
+    ) -> F::Output {
+        handler.await
+    }
+
+    // S is only needed for the span fields
+    #[allow(clippy::extra_unused_type_parameters)]
+    pub(crate) async fn in_service_handler<S: Service, F: Future>(
+        &self,
+        _: &'static str,
+        handler: F,
+    ) -> F::Output {
+        handler.await
+    }
+}
@@ -0,0 +1,61 @@
+use acteur::{Acteur, Actor, ActorAssistant, Receive, Respond};
+use async_trait::async_trait;
+use std::time::Duration;
+
+#[derive(Debug)]
+struct Counter(u32);
+
+#[async_trait]
+impl Actor for Counter {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        Counter(0)
+    }
+}
+
+#[derive(Debug)]
+struct Increment;
+
+#[async_trait]
+impl Receive<Increment> for Counter {
+    async fn handle(&mut self, _: Increment, _: &ActorAssistant<Self>) {
+        self.0 += 1;
+    }
+}
+
+#[derive(Debug)]
+struct Get;
+
+#[async_trait]
+impl Respond<Get> for Counter {
+    type Response = u32;
+
+    async fn handle(&mut self, _: Get, _: &ActorAssistant<Self>) -> u32 {
+        self.0
+    }
+}
+
+#[test]
+fn blocking_and_async_references_reach_the_same_actor() {
+    let sys = Acteur::builder().build().blocking();
+    let counter = sys.actor_ref::<Counter>(42);
+
+    assert_eq!(counter.id(), &42);
+
+    counter.send(Increment);
+    assert_eq!(counter.send_checked(Increment), Ok(()));
+    assert_eq!(counter.try_send(Increment), Ok(()));
+
+    let counter_async = counter.as_async();
+    async_std::task::block_on(counter_async.send(Increment));
+
+    assert_eq!(counter.call(Get), Ok(4));
+    assert_eq!(
+        counter.call_with_timeout(Get, Duration::from_secs(5)),
+        Ok(4)
+    );
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
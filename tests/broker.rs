@@ -0,0 +1,180 @@
+use acteur::testkit::TestSystem;
+use acteur::{Actor, ActorAssistant, Receive};
+use async_trait::async_trait;
+
+#[derive(Debug)]
+struct Listener;
+
+#[async_trait]
+impl Actor for Listener {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        Listener
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Event;
+
+#[async_trait]
+impl Receive<Event> for Listener {
+    async fn handle(&mut self, _: Event, _: &ActorAssistant<Self>) {}
+}
+
+// Subscribes twice to the messages published without topic, or in topics matching the pattern.
+#[derive(Debug)]
+struct Join(Option<&'static str>);
+
+#[async_trait]
+impl Receive<Join> for Listener {
+    async fn handle(&mut self, message: Join, assistant: &ActorAssistant<Self>) {
+        for _ in 0..2 {
+            match message.0 {
+                Some(pattern) => assistant.subscribe_to_topic::<Event>(pattern).await,
+                None => assistant.subscribe::<Event>().await,
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Leave(Option<&'static str>);
+
+#[async_trait]
+impl Receive<Leave> for Listener {
+    async fn handle(&mut self, message: Leave, assistant: &ActorAssistant<Self>) {
+        match message.0 {
+            Some(pattern) => assistant.unsubscribe_from_topic::<Event>(pattern).await,
+            None => assistant.unsubscribe::<Event>().await,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Quit;
+
+#[async_trait]
+impl Receive<Quit> for Listener {
+    async fn handle(&mut self, _: Quit, assistant: &ActorAssistant<Self>) {
+        assistant.stop().await;
+    }
+}
+
+#[test]
+fn subscribing_twice_delivers_each_message_once() {
+    let mut sys = TestSystem::new().blocking();
+    let events = sys.record::<Listener>();
+
+    sys.send_to_actor::<Listener, Join>(1, Join(None));
+    sys.wait_until_idle();
+
+    sys.publish(Event);
+    sys.wait_until_idle();
+
+    assert_eq!(events.count_of::<Event>(), 1);
+
+    sys.send_to_actor::<Listener, Leave>(1, Leave(None));
+    sys.wait_until_idle();
+
+    sys.publish(Event);
+    sys.wait_until_idle();
+
+    assert_eq!(events.count_of::<Event>(), 1);
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn subscribing_twice_to_a_topic_delivers_each_message_once() {
+    let mut sys = TestSystem::new().blocking();
+    let events = sys.record::<Listener>();
+
+    sys.send_to_actor::<Listener, Join>(1, Join(Some("orders.*")));
+    sys.wait_until_idle();
+
+    sys.publish_to_topic("orders.created", Event);
+    sys.publish_to_topic("invoices.created", Event);
+    sys.wait_until_idle();
+
+    assert_eq!(events.count_of::<Event>(), 1);
+
+    // Only the subscription with the same pattern is removed
+    sys.send_to_actor::<Listener, Leave>(1, Leave(Some("orders.#")));
+    sys.wait_until_idle();
+
+    sys.publish_to_topic("orders.created", Event);
+    sys.wait_until_idle();
+
+    assert_eq!(events.count_of::<Event>(), 2);
+
+    sys.send_to_actor::<Listener, Leave>(1, Leave(Some("orders.*")));
+    sys.wait_until_idle();
+
+    sys.publish_to_topic("orders.created", Event);
+    sys.wait_until_idle();
+
+    assert_eq!(events.count_of::<Event>(), 2);
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn subscribing_all_actors_twice_delivers_one_copy_to_each() {
+    let mut sys = TestSystem::new().blocking();
+
+    for id in 1..=2 {
+        sys.send_to_actor::<Listener, Leave>(id, Leave(None));
+    }
+    sys.wait_until_idle();
+
+    let events = sys.record::<Listener>();
+
+    sys.subscribe_all_actors::<Listener, Event>();
+    sys.subscribe_all_actors::<Listener, Event>();
+
+    sys.publish(Event);
+    sys.wait_until_idle();
+
+    assert_eq!(events.count_of::<Event>(), 2);
+
+    sys.unsubscribe_all_actors::<Listener, Event>();
+
+    sys.publish(Event);
+    sys.wait_until_idle();
+
+    assert_eq!(events.count_of::<Event>(), 2);
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn subscriptions_end_when_the_actor_deactivates() {
+    let mut sys = TestSystem::new().blocking();
+    let events = sys.record::<Listener>();
+
+    sys.send_to_actor::<Listener, Join>(1, Join(None));
+    sys.send_to_actor::<Listener, Join>(1, Join(Some("orders.#")));
+    sys.send_to_actor::<Listener, Join>(2, Join(None));
+    sys.wait_until_idle();
+
+    sys.send_to_actor::<Listener, Quit>(1, Quit);
+    sys.wait_until_idle();
+
+    // The actor 1 would be activated again if it were still subscribed
+    sys.publish(Event);
+    sys.publish_to_topic("orders.created", Event);
+    sys.wait_until_idle();
+
+    assert_eq!(events.count_of::<Event>(), 2);
+    assert_eq!(
+        sys.get_statistics().actors[std::any::type_name::<Listener>()].len(),
+        1
+    );
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
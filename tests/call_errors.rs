@@ -0,0 +1,326 @@
+use acteur::{
+    Acteur, Actor, ActorAssistant, ActorConfiguration, BlockingActeur, CallError, Listen,
+    MailboxOverflow, MessageContext, Middleware, MiddlewareDecision, Receive, Respond, Serve,
+    Service, ServiceAssistant, ServiceConcurrency, ServiceConfiguration,
+};
+use async_channel::{unbounded, Receiver, Sender};
+use async_trait::async_trait;
+use std::any::type_name;
+use std::thread;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// Services handle all their enqueued messages before ending, so `ServiceStopped` only reaches
+// callers through the mocks of the testkit (see the mock tests).
+
+// Lets the test know that it started and waits until the test releases it.
+#[derive(Debug, Clone)]
+struct Hold {
+    started: Sender<()>,
+    release: Receiver<()>,
+}
+
+impl Hold {
+    async fn handle(self) {
+        let _ = self.started.send(()).await;
+        let _ = self.release.recv().await;
+    }
+}
+
+fn hold() -> (Hold, Receiver<()>, Sender<()>) {
+    let (started_sender, started) = unbounded();
+    let (release_sender, release) = unbounded();
+
+    let hold = Hold {
+        started: started_sender,
+        release,
+    };
+
+    (hold, started, release_sender)
+}
+
+#[derive(Debug)]
+struct Balance;
+
+#[derive(Debug)]
+struct Explode;
+
+// Rejected by the `Frozen` middleware.
+#[derive(Debug)]
+struct Withdraw;
+
+#[derive(Debug)]
+struct Account;
+
+#[async_trait]
+impl Actor for Account {
+    type Id = u32;
+
+    async fn activate(id: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        if id == 0 {
+            panic!("There is no account 0");
+        }
+
+        Account
+    }
+
+    fn configuration() -> ActorConfiguration {
+        ActorConfiguration {
+            mailbox_capacity: Some(1),
+            mailbox_overflow: MailboxOverflow::Reject,
+            ..ActorConfiguration::default()
+        }
+    }
+}
+
+#[async_trait]
+impl Receive<Hold> for Account {
+    async fn handle(&mut self, message: Hold, _: &ActorAssistant<Self>) {
+        message.handle().await;
+    }
+}
+
+#[async_trait]
+impl Respond<Balance> for Account {
+    type Response = u32;
+
+    async fn handle(&mut self, _: Balance, _: &ActorAssistant<Self>) -> u32 {
+        100
+    }
+}
+
+#[async_trait]
+impl Respond<Explode> for Account {
+    type Response = ();
+
+    async fn handle(&mut self, _: Explode, _: &ActorAssistant<Self>) {
+        panic!("The account exploded");
+    }
+}
+
+#[async_trait]
+impl Respond<Withdraw> for Account {
+    type Response = ();
+
+    async fn handle(&mut self, _: Withdraw, _: &ActorAssistant<Self>) {}
+}
+
+#[derive(Debug)]
+struct Ledger;
+
+#[async_trait]
+impl Service for Ledger {
+    async fn initialize(_: &ServiceAssistant<Self>) -> (Self, ServiceConfiguration) {
+        let configuration = ServiceConfiguration {
+            concurrency: ServiceConcurrency::Fixed(1),
+            ..ServiceConfiguration::default()
+        };
+
+        (Ledger, configuration)
+    }
+}
+
+#[async_trait]
+impl Listen<Hold> for Ledger {
+    async fn handle(&self, message: Hold, _: &ServiceAssistant<Self>) {
+        message.handle().await;
+    }
+}
+
+#[async_trait]
+impl Serve<Balance> for Ledger {
+    type Response = u32;
+
+    async fn handle(&self, _: Balance, _: &ServiceAssistant<Self>) -> u32 {
+        1000
+    }
+}
+
+#[async_trait]
+impl Serve<Explode> for Ledger {
+    type Response = ();
+
+    async fn handle(&self, _: Explode, _: &ServiceAssistant<Self>) {
+        panic!("The ledger exploded");
+    }
+}
+
+#[async_trait]
+impl Serve<Withdraw> for Ledger {
+    type Response = ();
+
+    async fn handle(&self, _: Withdraw, _: &ServiceAssistant<Self>) {}
+}
+
+#[derive(Debug)]
+struct Frozen;
+
+#[async_trait]
+impl Middleware for Frozen {
+    async fn before(&self, context: &mut MessageContext<'_>) -> MiddlewareDecision {
+        if context.message_type_name() == type_name::<Withdraw>() {
+            MiddlewareDecision::Reject
+        } else {
+            MiddlewareDecision::Continue
+        }
+    }
+}
+
+// Asks from a handler, so the errors are the ones the assistants get.
+#[derive(Debug)]
+struct Audit;
+
+#[async_trait]
+impl Respond<Audit> for Account {
+    type Response = Vec<Result<(), CallError>>;
+
+    async fn handle(&mut self, _: Audit, assistant: &ActorAssistant<Self>) -> Self::Response {
+        vec![
+            assistant.call_actor::<Account, Explode>(2, Explode).await,
+            assistant.call_actor::<Account, Withdraw>(2, Withdraw).await,
+            assistant.call_service::<Ledger, Explode>(Explode).await,
+            assistant.call_service::<Ledger, Withdraw>(Withdraw).await,
+        ]
+    }
+}
+
+#[test]
+fn actor_calls_tell_why_they_failed() {
+    let sys = Acteur::builder().middleware(Frozen).build().blocking();
+
+    assert_eq!(
+        sys.call_actor::<Account, Balance>(0, Balance),
+        Err(CallError::ActorStopped)
+    );
+    assert_eq!(
+        sys.call_actor::<Account, Explode>(1, Explode),
+        Err(CallError::HandlerPanicked)
+    );
+    assert_eq!(
+        sys.call_actor::<Account, Withdraw>(1, Withdraw),
+        Err(CallError::Rejected)
+    );
+    assert_eq!(sys.call_actor::<Account, Balance>(1, Balance), Ok(100));
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+// The room appears once the actor skips the expired call.
+fn balance_once_there_is_room(sys: &BlockingActeur) -> u32 {
+    for _ in 0..100 {
+        match sys.call_actor_with_timeout::<Account, Balance>(1, Balance, TIMEOUT) {
+            Err(CallError::MailboxFull) => thread::sleep(Duration::from_millis(1)),
+            result => return result.unwrap(),
+        }
+    }
+
+    panic!("The mailbox is still full");
+}
+
+#[test]
+fn actor_calls_fail_when_the_actor_is_busy() {
+    let sys = Acteur::builder().build().blocking();
+    let (hold, started, release) = hold();
+
+    sys.send_to_actor::<Account, Hold>(1, hold);
+    started.recv_blocking().unwrap();
+
+    // The expired call stays in the mailbox, so there is no room for the next one
+    assert_eq!(
+        sys.call_actor_with_timeout::<Account, Balance>(1, Balance, Duration::from_millis(50)),
+        Err(CallError::Timeout)
+    );
+    assert_eq!(
+        sys.call_actor::<Account, Balance>(1, Balance),
+        Err(CallError::MailboxFull)
+    );
+
+    drop(release);
+
+    assert_eq!(balance_once_there_is_room(&sys), 100);
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn service_calls_tell_why_they_failed() {
+    let sys = Acteur::builder().middleware(Frozen).build().blocking();
+
+    assert_eq!(
+        sys.call_service::<Ledger, Explode>(Explode),
+        Err(CallError::HandlerPanicked)
+    );
+    assert_eq!(
+        sys.call_service::<Ledger, Withdraw>(Withdraw),
+        Err(CallError::Rejected)
+    );
+    assert_eq!(sys.call_service::<Ledger, Balance>(Balance), Ok(1000));
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn service_calls_fail_when_the_service_is_busy() {
+    let sys = Acteur::builder().build().blocking();
+    let (hold, started, release) = hold();
+
+    sys.send_to_service::<Ledger, Hold>(hold);
+    started.recv_blocking().unwrap();
+
+    assert_eq!(
+        sys.call_service_with_timeout::<Ledger, Balance>(Balance, Duration::from_millis(50)),
+        Err(CallError::Timeout)
+    );
+
+    drop(release);
+
+    assert_eq!(
+        sys.call_service_with_timeout::<Ledger, Balance>(Balance, TIMEOUT),
+        Ok(1000)
+    );
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn calls_from_handlers_tell_why_they_failed() {
+    let sys = Acteur::builder().middleware(Frozen).build().blocking();
+
+    assert_eq!(
+        sys.call_actor::<Account, Audit>(1, Audit),
+        Ok(vec![
+            Err(CallError::HandlerPanicked),
+            Err(CallError::Rejected),
+            Err(CallError::HandlerPanicked),
+            Err(CallError::Rejected),
+        ])
+    );
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn calls_after_stopping_fail_with_system_stopping() {
+    let sys = Acteur::builder().build().blocking();
+
+    assert_eq!(sys.call_actor::<Account, Balance>(1, Balance), Ok(100));
+    assert_eq!(sys.call_service::<Ledger, Balance>(Balance), Ok(1000));
+
+    sys.stop();
+    sys.wait_until_stopped();
+
+    assert_eq!(
+        sys.call_actor::<Account, Balance>(1, Balance),
+        Err(CallError::SystemStopping)
+    );
+    assert_eq!(
+        sys.call_service::<Ledger, Balance>(Balance),
+        Err(CallError::SystemStopping)
+    );
+}
@@ -0,0 +1,325 @@
+use acteur::{
+    Acteur, Actor, ActorAssistant, BlockingActeur, CallError, Cluster, Receive, Respond, SendError,
+    TrySendError,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::any::type_name;
+use std::net::TcpListener;
+use std::time::Duration;
+
+#[derive(Debug)]
+struct Log(Vec<u32>);
+
+#[async_trait]
+impl Actor for Log {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        Log(vec![])
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Push(u32);
+
+#[async_trait]
+impl Receive<Push> for Log {
+    async fn handle(&mut self, message: Push, _: &ActorAssistant<Self>) {
+        self.0.push(message.0);
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entries;
+
+#[async_trait]
+impl Respond<Entries> for Log {
+    type Response = Vec<u32>;
+
+    async fn handle(&mut self, _: Entries, _: &ActorAssistant<Self>) -> Vec<u32> {
+        self.0.clone()
+    }
+}
+
+// Responds after the milliseconds given
+#[derive(Debug, Serialize, Deserialize)]
+struct Wait(u64);
+
+#[async_trait]
+impl Respond<Wait> for Log {
+    type Response = ();
+
+    async fn handle(&mut self, message: Wait, _: &ActorAssistant<Self>) {
+        async_std::task::sleep(Duration::from_millis(message.0)).await;
+    }
+}
+
+// Same as `Push`, but the caller can wait for it
+#[derive(Debug, Serialize, Deserialize)]
+struct Append(u32);
+
+#[async_trait]
+impl Respond<Append> for Log {
+    type Response = ();
+
+    async fn handle(&mut self, message: Append, _: &ActorAssistant<Self>) {
+        self.0.push(message.0);
+    }
+}
+
+// Not registered in the cluster
+#[derive(Debug, Serialize, Deserialize)]
+struct Len;
+
+#[async_trait]
+impl Respond<Len> for Log {
+    type Response = usize;
+
+    async fn handle(&mut self, _: Len, _: &ActorAssistant<Self>) -> usize {
+        self.0.len()
+    }
+}
+
+fn register(cluster: Cluster) -> Cluster {
+    cluster
+        .actor::<Log>("log")
+        .receive::<Log, Push>("push")
+        .respond::<Log, Entries>("entries")
+        .respond::<Log, Wait>("wait")
+        .respond::<Log, Append>("append")
+}
+
+// Starts a system for each node, all of them knowing each other.
+fn start_cluster(names: &[&str]) -> Vec<BlockingActeur> {
+    let nodes: Vec<Cluster> = names
+        .iter()
+        .map(|name| Cluster::bind(name, "127.0.0.1:0").unwrap())
+        .collect();
+    let addresses: Vec<_> = nodes.iter().map(Cluster::local_addr).collect();
+
+    nodes
+        .into_iter()
+        .map(|mut node| {
+            for (name, address) in names.iter().zip(&addresses) {
+                node = node.node(name, *address);
+            }
+
+            Acteur::builder().cluster(register(node)).build().blocking()
+        })
+        .collect()
+}
+
+fn stop(systems: &[BlockingActeur]) {
+    for sys in systems {
+        sys.stop();
+    }
+
+    for sys in systems {
+        sys.wait_until_stopped();
+    }
+}
+
+fn active_logs(sys: &BlockingActeur) -> usize {
+    sys.get_statistics()
+        .actors
+        .get(type_name::<Log>())
+        .map_or(0, Vec::len)
+}
+
+#[test]
+fn sends_and_calls_reach_the_node_owning_the_actor() {
+    let systems = start_cluster(&["a", "b"]);
+    let (a, b) = (&systems[0], &systems[1]);
+
+    for id in 0..10 {
+        a.send_to_actor::<Log, Push>(id, Push(id));
+        assert_eq!(a.call_actor::<Log, Entries>(id, Entries), Ok(vec![id]));
+
+        b.send_to_actor::<Log, Push>(id, Push(id + 100));
+        assert_eq!(
+            b.call_actor::<Log, Entries>(id, Entries),
+            Ok(vec![id, id + 100])
+        );
+    }
+
+    // Each actor lives in only one of the nodes, and both nodes own some
+    assert_eq!(active_logs(a) + active_logs(b), 10);
+    assert!(active_logs(a) > 0);
+    assert!(active_logs(b) > 0);
+
+    stop(&systems);
+}
+
+#[test]
+fn calls_not_registered_fail_when_the_actor_is_remote() {
+    let systems = start_cluster(&["a", "b"]);
+    let (a, b) = (&systems[0], &systems[1]);
+
+    for id in 0..10 {
+        let from_a = a.call_actor::<Log, Len>(id, Len);
+        let from_b = b.call_actor::<Log, Len>(id, Len);
+
+        // Only the node owning the actor can call it
+        assert!(
+            matches!(
+                (from_a, from_b),
+                (Ok(0), Err(CallError::Serialization)) | (Err(CallError::Serialization), Ok(0))
+            ),
+            "{:?}",
+            (from_a, from_b)
+        );
+    }
+
+    stop(&systems);
+}
+
+#[test]
+fn remote_calls_time_out_and_their_late_responses_are_ignored() {
+    let systems = start_cluster(&["a", "b"]);
+    let (a, b) = (&systems[0], &systems[1]);
+
+    // Every actor is remote for one of the nodes
+    for id in 0..4 {
+        for sys in [a, b] {
+            assert_eq!(
+                sys.call_actor_with_timeout::<Log, Wait>(id, Wait(200), Duration::from_millis(20)),
+                Err(CallError::Timeout)
+            );
+        }
+    }
+
+    for id in 0..4 {
+        for sys in [a, b] {
+            assert_eq!(sys.call_actor::<Log, Entries>(id, Entries), Ok(vec![]));
+        }
+    }
+
+    stop(&systems);
+}
+
+#[test]
+fn expired_remote_calls_are_skipped() {
+    let systems = start_cluster(&["a", "b"]);
+    let (a, b) = (&systems[0], &systems[1]);
+
+    for id in 0..4 {
+        for (sys, entry) in [(a, 1), (b, 2)] {
+            // Keeps the actor busy until the next call expires
+            assert_eq!(
+                sys.call_actor_with_timeout::<Log, Wait>(id, Wait(200), Duration::from_millis(20)),
+                Err(CallError::Timeout)
+            );
+            assert_eq!(
+                sys.call_actor_with_timeout::<Log, Append>(
+                    id,
+                    Append(entry),
+                    Duration::from_millis(20)
+                ),
+                Err(CallError::Timeout)
+            );
+        }
+    }
+
+    // Neither the node owning the actor nor the other one handled the expired calls
+    for id in 0..4 {
+        for sys in [a, b] {
+            assert_eq!(sys.call_actor::<Log, Entries>(id, Entries), Ok(vec![]));
+        }
+    }
+
+    stop(&systems);
+}
+
+#[test]
+fn calls_to_an_unreachable_node_fail() {
+    // Nobody listens in this address once the listener is dropped
+    let unreachable = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let node = Cluster::bind("a", "127.0.0.1:0")
+        .unwrap()
+        .node("b", unreachable)
+        .connect_timeout(Duration::from_secs(1));
+
+    let sys = Acteur::builder().cluster(register(node)).build().blocking();
+
+    let responses: Vec<_> = (0..10)
+        .map(|id| sys.call_actor::<Log, Entries>(id, Entries))
+        .collect();
+
+    assert!(responses.contains(&Err(CallError::NodeUnreachable)));
+    assert!(responses
+        .iter()
+        .all(|response| response == &Ok(vec![]) || response == &Err(CallError::NodeUnreachable)));
+
+    stop(&[sys]);
+}
+
+#[test]
+fn sends_to_an_unreachable_node_fail() {
+    let unreachable = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let node = Cluster::bind("a", "127.0.0.1:0")
+        .unwrap()
+        .node("b", unreachable)
+        .connect_timeout(Duration::from_secs(1));
+
+    let sys = Acteur::builder().cluster(register(node)).build().blocking();
+
+    let checked: Vec<_> = (0..10)
+        .map(|id| sys.send_to_actor_checked::<Log, Push>(id, Push(id)))
+        .collect();
+    let tried: Vec<_> = (0..10)
+        .map(|id| sys.try_send_to_actor::<Log, Push>(id, Push(id)))
+        .collect();
+
+    // The actors owned by this node get the messages, the rest are not reported as sent
+    for id in 0..10 {
+        match sys.call_actor::<Log, Entries>(id, Entries) {
+            Ok(entries) => {
+                assert_eq!(entries, vec![id, id]);
+                assert_eq!(checked[id as usize], Ok(()));
+                assert_eq!(tried[id as usize], Ok(()));
+            }
+            Err(error) => {
+                assert_eq!(error, CallError::NodeUnreachable);
+                assert_eq!(checked[id as usize], Err(SendError::NodeUnreachable));
+                assert_eq!(tried[id as usize], Err(TrySendError::NodeUnreachable));
+            }
+        }
+    }
+
+    assert!(checked.contains(&Err(SendError::NodeUnreachable)));
+
+    stop(&[sys]);
+}
+
+#[test]
+fn messages_from_one_node_arrive_in_order() {
+    let systems = start_cluster(&["a", "b", "c"]);
+    let a = &systems[0];
+
+    for entry in 0..100 {
+        for id in 0..6 {
+            a.send_to_actor::<Log, Push>(id, Push(entry));
+        }
+    }
+
+    for id in 0..6 {
+        assert_eq!(
+            a.call_actor::<Log, Entries>(id, Entries),
+            Ok((0..100).collect())
+        );
+    }
+
+    // Some of them live in other nodes
+    assert!(active_logs(a) < 6);
+
+    stop(&systems);
+}
@@ -0,0 +1,99 @@
+use acteur::testkit::TestSystem;
+use acteur::{
+    Acteur, Actor, ActorAssistant, Listen, Receive, Respond, Service, ServiceAssistant,
+    ServiceConfiguration,
+};
+use async_trait::async_trait;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug)]
+struct Relay;
+
+#[async_trait]
+impl Actor for Relay {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        Relay
+    }
+}
+
+// Each relay forwards the message to the next one until the hops run out.
+#[derive(Debug)]
+struct Forward(u32);
+
+#[async_trait]
+impl Receive<Forward> for Relay {
+    async fn handle(&mut self, message: Forward, assistant: &ActorAssistant<Self>) {
+        if message.0 > 0 {
+            let next = assistant.get_id().await + 1;
+
+            assistant
+                .send_to_actor::<Relay, Forward>(next, Forward(message.0 - 1))
+                .await;
+        }
+    }
+}
+
+// Takes some real time before responding.
+#[derive(Debug)]
+struct Slow;
+
+#[async_trait]
+impl Respond<Slow> for Relay {
+    type Response = ();
+
+    async fn handle(&mut self, _: Slow, _: &ActorAssistant<Self>) {
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[derive(Debug)]
+struct Dispatcher;
+
+#[async_trait]
+impl Service for Dispatcher {
+    async fn initialize(_: &ServiceAssistant<Self>) -> (Self, ServiceConfiguration) {
+        (Dispatcher, ServiceConfiguration::default())
+    }
+}
+
+#[async_trait]
+impl Listen<Forward> for Dispatcher {
+    async fn handle(&self, message: Forward, assistant: &ServiceAssistant<Self>) {
+        assistant.send_to_actor::<Relay, Forward>(1, message).await;
+    }
+}
+
+#[test]
+fn wait_until_idle_waits_for_the_messages_sent_by_handlers() {
+    let sys = TestSystem::new().blocking();
+    let forwards = sys.record::<Relay>();
+
+    sys.send_to_service::<Dispatcher, Forward>(Forward(5));
+    sys.wait_until_idle();
+
+    // The first relay and the five hops
+    assert_eq!(forwards.count_of::<Forward>(), 6);
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn call_timeouts_use_the_virtual_clock() {
+    let sys = TestSystem::from_builder(Acteur::builder().call_timeout(Duration::from_millis(1)))
+        .blocking();
+
+    // The handlers take more real time than the timeouts, but the virtual time doesn't pass
+    assert_eq!(sys.call_actor::<Relay, Slow>(1, Slow), Ok(()));
+    assert_eq!(
+        sys.call_actor_with_timeout::<Relay, Slow>(1, Slow, Duration::from_millis(1)),
+        Ok(())
+    );
+    assert_eq!(sys.elapsed(), Duration::from_secs(0));
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
@@ -0,0 +1,200 @@
+use acteur::{FileJournal, InMemoryJournal, Journal, JournalEntry, JournalError};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+fn entry(sequence_nr: u64) -> JournalEntry {
+    JournalEntry {
+        sequence_nr,
+        payload: format!("event {}", sequence_nr).into_bytes(),
+    }
+}
+
+// A new directory for each test, removed when dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> TempDir {
+        let path =
+            std::env::temp_dir().join(format!("acteur-journal-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&path);
+        TempDir(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[async_std::test]
+async fn file_journal_reads_what_was_appended() {
+    let dir = TempDir::new("round-trip");
+    let journal = FileJournal::new(&dir.0).unwrap();
+
+    journal
+        .append("user/1", 0, vec![entry(1), entry(2)])
+        .await
+        .unwrap();
+    journal.append("user/1", 2, vec![entry(3)]).await.unwrap();
+    journal.append("user/2", 0, vec![entry(1)]).await.unwrap();
+
+    assert_eq!(
+        journal.read("user/1", 1).await,
+        Ok(vec![entry(1), entry(2), entry(3)])
+    );
+    assert_eq!(journal.read("user/1", 3).await, Ok(vec![entry(3)]));
+    assert_eq!(journal.read("user/2", 1).await, Ok(vec![entry(1)]));
+    assert_eq!(journal.read("user/3", 1).await, Ok(vec![]));
+
+    // The events are still there for a new journal in the same directory
+    let journal = FileJournal::new(&dir.0).unwrap();
+
+    assert_eq!(
+        journal.read("user/1", 1).await,
+        Ok(vec![entry(1), entry(2), entry(3)])
+    );
+    journal.append("user/1", 3, vec![entry(4)]).await.unwrap();
+    assert_eq!(journal.read("user/1", 4).await, Ok(vec![entry(4)]));
+}
+
+#[async_std::test]
+async fn file_journal_removes_an_incomplete_last_entry() {
+    let dir = TempDir::new("torn-tail");
+    let journal = FileJournal::new(&dir.0).unwrap();
+
+    journal
+        .append("user-1", 0, vec![entry(1), entry(2)])
+        .await
+        .unwrap();
+
+    // A crash in the middle of an append leaves the header and part of the payload
+    let path = dir.0.join("user-1.journal");
+    let length = fs::metadata(&path).unwrap().len();
+    let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(&3u64.to_le_bytes()).unwrap();
+    file.write_all(&100u32.to_le_bytes()).unwrap();
+    file.write_all(b"event").unwrap();
+    drop(file);
+
+    // Appending after restarting doesn't leave the broken entry in the middle
+    let journal = FileJournal::new(&dir.0).unwrap();
+    journal.append("user-1", 2, vec![entry(3)]).await.unwrap();
+
+    assert_eq!(
+        journal.read("user-1", 1).await,
+        Ok(vec![entry(1), entry(2), entry(3)])
+    );
+
+    // Reading skips the broken entry but leaves the file as it is
+    let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(&4u64.to_le_bytes()).unwrap();
+    drop(file);
+
+    let journal = FileJournal::new(&dir.0).unwrap();
+
+    assert_eq!(
+        journal.read("user-1", 1).await,
+        Ok(vec![entry(1), entry(2), entry(3)])
+    );
+    // The three complete entries, each one with a 12 bytes header and a 7 bytes payload, and the
+    // 8 bytes of the broken one
+    assert_eq!(fs::metadata(&path).unwrap().len(), length + 19 + 8);
+
+    journal.append("user-1", 3, vec![entry(4)]).await.unwrap();
+    assert_eq!(fs::metadata(&path).unwrap().len(), length + 19 + 19);
+    assert_eq!(journal.read("user-1", 4).await, Ok(vec![entry(4)]));
+}
+
+#[async_std::test]
+async fn file_journal_reads_while_appending() {
+    let dir = TempDir::new("concurrent");
+    let journal = FileJournal::new(&dir.0).unwrap();
+
+    // Big payloads, so the reads often find an append half written
+    let big_entry = |sequence_nr: u64| JournalEntry {
+        sequence_nr,
+        payload: vec![sequence_nr as u8; 1024 * 1024],
+    };
+
+    let appender = {
+        let journal = journal.clone();
+        async_std::task::spawn(async move {
+            for sequence_nr in 1..=50 {
+                journal
+                    .append("user-1", sequence_nr - 1, vec![big_entry(sequence_nr)])
+                    .await
+                    .unwrap();
+            }
+        })
+    };
+
+    let reader = {
+        let journal = journal.clone();
+        async_std::task::spawn(async move {
+            loop {
+                let entries = journal.read("user-1", 1).await.unwrap();
+
+                // Every read sees the complete entries appended so far, in order
+                for (index, entry) in entries.iter().enumerate() {
+                    assert_eq!(entry, &big_entry(index as u64 + 1));
+                }
+
+                if entries.len() == 50 {
+                    break;
+                }
+            }
+        })
+    };
+
+    appender.await;
+    reader.await;
+
+    // Nothing was removed by the reads
+    let journal = FileJournal::new(&dir.0).unwrap();
+    let entries = journal.read("user-1", 1).await.unwrap();
+    assert_eq!(entries.len(), 50);
+    assert_eq!(entries.last(), Some(&big_entry(50)));
+    journal.append("user-1", 50, vec![entry(51)]).await.unwrap();
+}
+
+async fn rejects_a_sequence_mismatch(journal: &dyn Journal) {
+    journal.append("user-1", 0, vec![entry(1)]).await.unwrap();
+
+    assert_eq!(
+        journal.append("user-1", 0, vec![entry(1)]).await,
+        Err(JournalError::SequenceMismatch {
+            expected: 0,
+            actual: 1
+        })
+    );
+    assert_eq!(
+        journal.append("user-1", 2, vec![entry(3)]).await,
+        Err(JournalError::SequenceMismatch {
+            expected: 2,
+            actual: 1
+        })
+    );
+
+    // Nothing was stored by the rejected appends
+    assert_eq!(journal.read("user-1", 1).await, Ok(vec![entry(1)]));
+
+    journal.append("user-1", 1, vec![entry(2)]).await.unwrap();
+    assert_eq!(
+        journal.read("user-1", 1).await,
+        Ok(vec![entry(1), entry(2)])
+    );
+}
+
+#[async_std::test]
+async fn in_memory_journal_rejects_a_sequence_mismatch() {
+    rejects_a_sequence_mismatch(&InMemoryJournal::new()).await;
+}
+
+#[async_std::test]
+async fn file_journal_rejects_a_sequence_mismatch() {
+    let dir = TempDir::new("mismatch");
+
+    rejects_a_sequence_mismatch(&FileJournal::new(&dir.0).unwrap()).await;
+}
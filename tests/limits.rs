@@ -0,0 +1,131 @@
+use acteur::testkit::TestSystem;
+use acteur::{Acteur, Actor, ActorAssistant, ActorConfiguration, Passivation, Receive};
+use async_trait::async_trait;
+use std::any::type_name;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static BIG_DEACTIVATIONS: AtomicUsize = AtomicUsize::new(0);
+static PINNED_DEACTIVATIONS: AtomicUsize = AtomicUsize::new(0);
+static EVICTABLE_DEACTIVATIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug)]
+struct Load(usize);
+
+// Keeps its data in the heap, so only `estimated_memory` knows how big it is.
+#[derive(Debug)]
+struct Big {
+    data: Vec<u8>,
+}
+
+#[async_trait]
+impl Actor for Big {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        Big { data: vec![] }
+    }
+
+    async fn deactivate(&mut self) {
+        BIG_DEACTIVATIONS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn estimated_memory(&self) -> usize {
+        std::mem::size_of::<Self>() + self.data.capacity()
+    }
+}
+
+#[async_trait]
+impl Receive<Load> for Big {
+    async fn handle(&mut self, message: Load, _: &ActorAssistant<Self>) {
+        self.data = vec![0; message.0];
+    }
+}
+
+#[derive(Debug)]
+struct Pinned;
+
+#[async_trait]
+impl Actor for Pinned {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        Pinned
+    }
+
+    async fn deactivate(&mut self) {
+        PINNED_DEACTIVATIONS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn configuration() -> ActorConfiguration {
+        ActorConfiguration {
+            passivation: Passivation::Never,
+            ..ActorConfiguration::default()
+        }
+    }
+}
+
+#[async_trait]
+impl Receive<Load> for Pinned {
+    async fn handle(&mut self, _: Load, _: &ActorAssistant<Self>) {}
+}
+
+#[derive(Debug)]
+struct Evictable;
+
+#[async_trait]
+impl Actor for Evictable {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        Evictable
+    }
+
+    async fn deactivate(&mut self) {
+        EVICTABLE_DEACTIVATIONS.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[async_trait]
+impl Receive<Load> for Evictable {
+    async fn handle(&mut self, _: Load, _: &ActorAssistant<Self>) {}
+}
+
+#[test]
+fn memory_budget_counts_the_estimated_memory() {
+    let sys = TestSystem::from_builder(Acteur::builder().actors_memory_budget(2000)).blocking();
+
+    sys.send_to_actor::<Big, Load>(1, Load(1000));
+    sys.send_to_actor::<Big, Load>(2, Load(1000));
+    sys.wait_until_idle();
+
+    assert_eq!(BIG_DEACTIVATIONS.load(Ordering::SeqCst), 0);
+
+    // Two actors with 1000 bytes in the heap don't leave room for a third one.
+    sys.send_to_actor::<Big, Load>(3, Load(1000));
+    sys.wait_until_idle();
+
+    assert_eq!(BIG_DEACTIVATIONS.load(Ordering::SeqCst), 1);
+    assert_eq!(sys.get_statistics().actors[type_name::<Big>()].len(), 2);
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn never_passivated_actors_are_not_evicted() {
+    let sys = TestSystem::from_builder(Acteur::builder().max_actors(2)).blocking();
+
+    sys.send_to_actor::<Pinned, Load>(1, Load(0));
+    sys.send_to_actor::<Evictable, Load>(1, Load(0));
+    sys.wait_until_idle();
+
+    sys.send_to_actor::<Evictable, Load>(2, Load(0));
+    sys.wait_until_idle();
+
+    // The pinned actor is the least recently used one, but it is never evicted.
+    assert_eq!(PINNED_DEACTIVATIONS.load(Ordering::SeqCst), 0);
+    assert_eq!(EVICTABLE_DEACTIVATIONS.load(Ordering::SeqCst), 1);
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
@@ -0,0 +1,94 @@
+use acteur::{
+    Acteur, Actor, ActorAssistant, ActorConfiguration, MailboxOverflow, Receive, Respond,
+};
+use async_channel::{unbounded, Receiver};
+use async_trait::async_trait;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+// The handlers of `Block` wait until the sender of this channel is dropped.
+static GATE: Mutex<Option<Receiver<()>>> = Mutex::new(None);
+
+#[derive(Debug)]
+struct Worker(u32);
+
+#[async_trait]
+impl Actor for Worker {
+    type Id = u32;
+
+    async fn activate(id: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        Worker(id)
+    }
+
+    fn configuration() -> ActorConfiguration {
+        ActorConfiguration {
+            mailbox_capacity: Some(1),
+            mailbox_overflow: MailboxOverflow::Await,
+            ..ActorConfiguration::default()
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Block;
+
+#[async_trait]
+impl Receive<Block> for Worker {
+    async fn handle(&mut self, _: Block, _: &ActorAssistant<Self>) {
+        let gate = GATE.lock().unwrap().clone();
+
+        if let Some(gate) = gate {
+            let _ = gate.recv().await;
+        }
+    }
+}
+
+#[derive(Debug)]
+struct GetId;
+
+#[async_trait]
+impl Respond<GetId> for Worker {
+    type Response = u32;
+
+    async fn handle(&mut self, _: GetId, _: &ActorAssistant<Self>) -> u32 {
+        self.0
+    }
+}
+
+#[test]
+fn full_mailbox_only_blocks_its_senders() {
+    let (gate, receiver) = unbounded();
+    GATE.lock().unwrap().replace(receiver);
+
+    let sys = Acteur::builder().build().blocking();
+
+    sys.send_to_actor::<Worker, Block>(1, Block);
+
+    // One message fills the mailbox of the actor 1 and the next one waits for room.
+    let acteur = sys.to_async();
+    let sender = thread::spawn(move || {
+        let sys = acteur.blocking();
+        sys.send_to_actor::<Worker, Block>(1, Block);
+        sys.send_to_actor::<Worker, Block>(1, Block);
+    });
+
+    thread::sleep(Duration::from_millis(100));
+
+    assert_eq!(
+        sys.call_actor_with_timeout::<Worker, GetId>(2, GetId, Duration::from_secs(5)),
+        Ok(2)
+    );
+    assert!(!sender.is_finished());
+
+    drop(gate);
+    sender.join().unwrap();
+
+    assert_eq!(
+        sys.call_actor_with_timeout::<Worker, GetId>(1, GetId, Duration::from_secs(5)),
+        Ok(1)
+    );
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
@@ -0,0 +1,208 @@
+use acteur::testkit::TestSystem;
+use acteur::{
+    Actor, ActorAssistant, Receive, Respond, Serve, Service, ServiceAssistant, ServiceConfiguration,
+};
+use async_trait::async_trait;
+use std::any::type_name;
+
+#[derive(Debug)]
+struct Greeter;
+
+#[async_trait]
+impl Actor for Greeter {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        Greeter
+    }
+}
+
+#[derive(Debug)]
+struct Hello;
+
+#[async_trait]
+impl Receive<Hello> for Greeter {
+    async fn handle(&mut self, _: Hello, _: &ActorAssistant<Self>) {}
+}
+
+#[derive(Debug)]
+struct Ask;
+
+#[async_trait]
+impl Respond<Ask> for Greeter {
+    type Response = ();
+
+    async fn handle(&mut self, _: Ask, _: &ActorAssistant<Self>) {}
+}
+
+#[derive(Debug)]
+struct Echo;
+
+#[async_trait]
+impl Service for Echo {
+    async fn initialize(_: &ServiceAssistant<Self>) -> (Self, ServiceConfiguration) {
+        (Echo, Default::default())
+    }
+}
+
+#[async_trait]
+impl Serve<Ask> for Echo {
+    type Response = ();
+
+    async fn handle(&self, _: Ask, _: &ServiceAssistant<Self>) {}
+}
+
+fn actor_labels<M>() -> String {
+    format!(
+        "actor=\"{}\",message=\"{}\"",
+        type_name::<Greeter>(),
+        type_name::<M>()
+    )
+}
+
+fn service_labels<M>() -> String {
+    format!(
+        "service=\"{}\",message=\"{}\"",
+        type_name::<Echo>(),
+        type_name::<M>()
+    )
+}
+
+// The metric families declared with `# TYPE`, and their type.
+fn families(metrics: &str) -> Vec<(&str, &str)> {
+    metrics
+        .lines()
+        .filter_map(|line| line.strip_prefix("# TYPE "))
+        .filter_map(|line| line.split_once(' '))
+        .collect()
+}
+
+#[async_std::test]
+async fn metrics_are_rendered_in_openmetrics_format() {
+    let sys = TestSystem::new();
+
+    sys.send_to_actor::<Greeter, Hello>(1, Hello).await;
+    sys.send_to_actor::<Greeter, Hello>(1, Hello).await;
+    sys.call_actor::<Greeter, Ask>(2, Ask).await.unwrap();
+    sys.call_service::<Echo, Ask>(Ask).await.unwrap();
+
+    sys.wait_until_idle().await;
+
+    let metrics = sys.render_metrics();
+    let lines: Vec<&str> = metrics.lines().collect();
+
+    for line in [
+        format!(
+            "acteur_actor_messages_sent_total{{{}}} 2",
+            actor_labels::<Hello>()
+        ),
+        format!(
+            "acteur_actor_messages_sent_total{{{}}} 1",
+            actor_labels::<Ask>()
+        ),
+        format!(
+            "acteur_actor_messages_handled_total{{{}}} 2",
+            actor_labels::<Hello>()
+        ),
+        format!(
+            "acteur_actor_messages_handled_total{{{}}} 1",
+            actor_labels::<Ask>()
+        ),
+        format!(
+            "acteur_actor_handler_duration_seconds_bucket{{{},le=\"+Inf\"}} 2",
+            actor_labels::<Hello>()
+        ),
+        format!(
+            "acteur_actor_handler_duration_seconds_count{{{}}} 2",
+            actor_labels::<Hello>()
+        ),
+        format!(
+            "acteur_actor_activations_total{{actor=\"{}\"}} 2",
+            type_name::<Greeter>()
+        ),
+        format!(
+            "acteur_actors_live{{actor=\"{}\"}} 2",
+            type_name::<Greeter>()
+        ),
+        format!(
+            "acteur_actor_mailbox_depth{{actor=\"{}\"}} 0",
+            type_name::<Greeter>()
+        ),
+        format!(
+            "acteur_service_messages_sent_total{{{}}} 1",
+            service_labels::<Ask>()
+        ),
+        format!(
+            "acteur_service_messages_handled_total{{{}}} 1",
+            service_labels::<Ask>()
+        ),
+        format!(
+            "acteur_service_handler_duration_seconds_count{{{}}} 1",
+            service_labels::<Ask>()
+        ),
+        format!(
+            "acteur_service_mailbox_depth{{service=\"{}\"}} 0",
+            type_name::<Echo>()
+        ),
+    ] {
+        assert!(lines.contains(&line.as_str()), "{} in:\n{}", line, metrics);
+    }
+
+    // Histogram buckets are cumulative and their bounds always have a decimal point
+    let buckets: Vec<u64> = lines
+        .iter()
+        .filter(|line| {
+            line.starts_with(&format!(
+                "acteur_actor_handler_duration_seconds_bucket{{{},",
+                actor_labels::<Hello>()
+            ))
+        })
+        .map(|line| line.rsplit(' ').next().unwrap().parse().unwrap())
+        .collect();
+    assert_eq!(buckets.len(), 12);
+    assert!(buckets.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert!(metrics.contains(",le=\"1.0\"}"));
+
+    // Every sample belongs to a family declared before it, and the output ends with `# EOF`
+    let families = families(&metrics);
+    assert_eq!(
+        families,
+        vec![
+            ("acteur_actor_messages_sent", "counter"),
+            ("acteur_actor_messages_handled", "counter"),
+            ("acteur_actor_handler_duration_seconds", "histogram"),
+            ("acteur_actor_activations", "counter"),
+            ("acteur_actor_deactivations", "counter"),
+            ("acteur_actors_live", "gauge"),
+            ("acteur_actor_mailbox_depth", "gauge"),
+            ("acteur_service_messages_sent", "counter"),
+            ("acteur_service_messages_handled", "counter"),
+            ("acteur_service_handler_duration_seconds", "histogram"),
+            ("acteur_service_mailbox_depth", "gauge"),
+        ]
+    );
+
+    let mut declared = vec![];
+
+    for line in lines.iter() {
+        if let Some(family) = line.strip_prefix("# TYPE ") {
+            declared.push(family.split(' ').next().unwrap());
+        } else if !line.starts_with('#') {
+            let family = declared.last().unwrap();
+            assert!(line.starts_with(family), "{} is not in {}", line, family);
+        }
+    }
+
+    assert_eq!(lines.last(), Some(&"# EOF"));
+    assert!(metrics.ends_with("# EOF\n"));
+
+    sys.stop();
+    sys.wait_until_stopped().await;
+
+    let metrics = sys.render_metrics();
+    assert!(metrics.lines().any(|line| line
+        == format!(
+            "acteur_actor_deactivations_total{{actor=\"{}\"}} 2",
+            type_name::<Greeter>()
+        )));
+}
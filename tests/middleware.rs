@@ -0,0 +1,201 @@
+use acteur::{
+    Acteur, Actor, ActorAssistant, Listen, MessageContext, Middleware, MiddlewareDecision, Receive,
+    Respond, Serve, Service, ServiceAssistant, ServiceConcurrency, ServiceConfiguration,
+};
+use async_channel::{unbounded, Receiver, Sender};
+use async_trait::async_trait;
+use std::any::type_name;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// The handlers of `Block` wait until the sender of these channels is dropped.
+static ACTOR_GATE: Mutex<Option<Receiver<()>>> = Mutex::new(None);
+static SERVICE_GATE: Mutex<Option<Receiver<()>>> = Mutex::new(None);
+
+async fn wait_for(gate: &Mutex<Option<Receiver<()>>>) {
+    let gate = gate.lock().unwrap().clone();
+
+    if let Some(gate) = gate {
+        let _ = gate.recv().await;
+    }
+}
+
+// Records the hooks that ran, with the type name of the message. The type names of the messages
+// whose after hooks ran are also sent to a channel, as they run after the response is sent.
+#[derive(Clone)]
+struct Hooks {
+    ran: Arc<Mutex<Vec<(&'static str, &'static str)>>>,
+    afters: (Sender<&'static str>, Receiver<&'static str>),
+}
+
+impl Hooks {
+    fn new() -> Hooks {
+        Hooks {
+            ran: Arc::default(),
+            afters: unbounded(),
+        }
+    }
+
+    fn wait_for_after<M>(&self) {
+        async_std::task::block_on(async_std::future::timeout(Duration::from_secs(5), async {
+            while self.afters.1.recv().await != Ok(type_name::<M>()) {}
+        }))
+        .expect("The after hook didn't run");
+    }
+
+    fn of<M>(&self) -> Vec<&'static str> {
+        self.ran
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, message)| *message == type_name::<M>())
+            .map(|(hook, _)| *hook)
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Middleware for Hooks {
+    async fn before(&self, context: &mut MessageContext<'_>) -> MiddlewareDecision {
+        self.ran
+            .lock()
+            .unwrap()
+            .push(("before", context.message_type_name()));
+        MiddlewareDecision::Continue
+    }
+
+    async fn after(&self, context: &mut MessageContext<'_>, _: Duration) {
+        self.ran
+            .lock()
+            .unwrap()
+            .push(("after", context.message_type_name()));
+        let _ = self.afters.0.send(context.message_type_name()).await;
+    }
+}
+
+#[derive(Debug)]
+struct Block;
+
+#[derive(Debug)]
+struct Get;
+
+#[derive(Debug)]
+struct Worker;
+
+#[async_trait]
+impl Actor for Worker {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        Worker
+    }
+}
+
+#[async_trait]
+impl Receive<Block> for Worker {
+    async fn handle(&mut self, _: Block, _: &ActorAssistant<Self>) {
+        wait_for(&ACTOR_GATE).await;
+    }
+}
+
+#[async_trait]
+impl Respond<Get> for Worker {
+    type Response = u32;
+
+    async fn handle(&mut self, _: Get, _: &ActorAssistant<Self>) -> u32 {
+        1
+    }
+}
+
+#[derive(Debug)]
+struct Single;
+
+#[async_trait]
+impl Service for Single {
+    async fn initialize(_: &ServiceAssistant<Self>) -> (Self, ServiceConfiguration) {
+        let configuration = ServiceConfiguration {
+            concurrency: ServiceConcurrency::None,
+            ..ServiceConfiguration::default()
+        };
+
+        (Single, configuration)
+    }
+}
+
+#[async_trait]
+impl Listen<Block> for Single {
+    async fn handle(&self, _: Block, _: &ServiceAssistant<Self>) {
+        wait_for(&SERVICE_GATE).await;
+    }
+}
+
+#[async_trait]
+impl Serve<Get> for Single {
+    type Response = u32;
+
+    async fn handle(&self, _: Get, _: &ServiceAssistant<Self>) -> u32 {
+        1
+    }
+}
+
+#[test]
+fn expired_actor_calls_skip_the_middlewares() {
+    let (gate, receiver) = unbounded();
+    ACTOR_GATE.lock().unwrap().replace(receiver);
+
+    let hooks = Hooks::new();
+    let sys = Acteur::builder()
+        .middleware(hooks.clone())
+        .build()
+        .blocking();
+
+    sys.send_to_actor::<Worker, Block>(1, Block);
+
+    assert_eq!(
+        sys.call_actor_with_timeout::<Worker, Get>(1, Get, Duration::from_millis(50)),
+        Err(acteur::CallError::Timeout)
+    );
+
+    drop(gate);
+
+    assert_eq!(
+        sys.call_actor_with_timeout::<Worker, Get>(1, Get, Duration::from_secs(5)),
+        Ok(1)
+    );
+    hooks.wait_for_after::<Get>();
+    assert_eq!(hooks.of::<Get>(), vec!["before", "after"]);
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn expired_service_calls_skip_the_middlewares() {
+    let (gate, receiver) = unbounded();
+    SERVICE_GATE.lock().unwrap().replace(receiver);
+
+    let hooks = Hooks::new();
+    let sys = Acteur::builder()
+        .middleware(hooks.clone())
+        .build()
+        .blocking();
+
+    sys.send_to_service::<Single, Block>(Block);
+
+    assert_eq!(
+        sys.call_service_with_timeout::<Single, Get>(Get, Duration::from_millis(50)),
+        Err(acteur::CallError::Timeout)
+    );
+
+    drop(gate);
+
+    assert_eq!(
+        sys.call_service_with_timeout::<Single, Get>(Get, Duration::from_secs(5)),
+        Ok(1)
+    );
+    hooks.wait_for_after::<Get>();
+    assert_eq!(hooks.of::<Get>(), vec!["before", "after"]);
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
@@ -0,0 +1,146 @@
+use acteur::testkit::{MockSystem, Operation};
+use acteur::{
+    Actor, ActorAssistant, CallError, Respond, Serve, Service, ServiceAssistant,
+    ServiceConfiguration,
+};
+use async_trait::async_trait;
+use futures::executor::block_on;
+use std::time::Duration;
+
+#[derive(Debug)]
+struct Bank;
+
+#[async_trait]
+impl Actor for Bank {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        Bank
+    }
+}
+
+#[derive(Debug)]
+struct GetBalance;
+
+#[async_trait]
+impl Respond<GetBalance> for Bank {
+    type Response = u32;
+
+    async fn handle(&mut self, _: GetBalance, _: &ActorAssistant<Self>) -> u32 {
+        0
+    }
+}
+
+#[derive(Debug)]
+struct Rates;
+
+#[async_trait]
+impl Service for Rates {
+    async fn initialize(_: &ServiceAssistant<Self>) -> (Self, ServiceConfiguration) {
+        (Rates, ServiceConfiguration::default())
+    }
+}
+
+#[derive(Debug)]
+struct GetRate;
+
+#[async_trait]
+impl Serve<GetRate> for Rates {
+    type Response = u32;
+
+    async fn handle(&self, _: GetRate, _: &ServiceAssistant<Self>) -> u32 {
+        0
+    }
+}
+
+// Asks the bank for the balance and the rates service for the rate, with the timeout of the message
+#[derive(Debug)]
+struct Check(Duration);
+
+#[derive(Debug)]
+struct Wallet;
+
+#[async_trait]
+impl Actor for Wallet {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        Wallet
+    }
+}
+
+#[async_trait]
+impl Respond<Check> for Wallet {
+    type Response = (Result<u32, CallError>, Result<u32, CallError>);
+
+    async fn handle(&mut self, check: Check, assistant: &ActorAssistant<Self>) -> Self::Response {
+        let balance = assistant
+            .call_actor_with_timeout::<Bank, GetBalance>(7, GetBalance, check.0)
+            .await;
+        let rate = assistant
+            .call_service_with_timeout::<Rates, GetRate>(GetRate, check.0)
+            .await;
+
+        (balance, rate)
+    }
+}
+
+fn mock() -> MockSystem {
+    let mock = MockSystem::new();
+    mock.stub_actor_call::<Bank, GetBalance, _>(|bank_id, _| *bank_id);
+    mock.stub_service_call::<Rates, GetRate, _>(|_| 3);
+    mock.delay_actor_call::<Bank, GetBalance>(Duration::from_secs(5));
+    mock.delay_service_call::<Rates, GetRate>(Duration::from_secs(5));
+    mock
+}
+
+#[test]
+fn delayed_calls_time_out_when_the_timeout_is_shorter() {
+    let mock = mock();
+    let assistant = mock.actor_assistant::<Wallet>(1);
+
+    let response = block_on(Wallet.handle(Check(Duration::from_secs(1)), &assistant));
+
+    assert_eq!(response, (Err(CallError::Timeout), Err(CallError::Timeout)));
+}
+
+#[test]
+fn delayed_calls_are_answered_when_the_timeout_is_longer() {
+    let mock = mock();
+    let assistant = mock.actor_assistant::<Wallet>(1);
+
+    let response = block_on(Wallet.handle(Check(Duration::from_secs(10)), &assistant));
+
+    assert_eq!(response, (Ok(7), Ok(3)));
+}
+
+#[test]
+fn calls_are_captured_with_their_timeout() {
+    let mock = mock();
+    let assistant = mock.actor_assistant::<Wallet>(1);
+
+    let _ = block_on(Wallet.handle(Check(Duration::from_secs(1)), &assistant));
+
+    let messages = mock.take_messages();
+
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[0].operation(), Operation::CallActor);
+    assert_eq!(messages[0].actor_id::<Bank>(), Some(&7));
+    assert_eq!(messages[0].timeout(), Some(Duration::from_secs(1)));
+    assert_eq!(messages[1].operation(), Operation::CallService);
+    assert!(messages[1].is_for::<Rates>());
+    assert_eq!(messages[1].timeout(), Some(Duration::from_secs(1)));
+}
+
+#[test]
+fn calls_without_a_stub_are_stopped() {
+    let mock = MockSystem::new();
+    let assistant = mock.actor_assistant::<Wallet>(1);
+
+    let response = block_on(Wallet.handle(Check(Duration::from_secs(1)), &assistant));
+
+    assert_eq!(
+        response,
+        (Err(CallError::ActorStopped), Err(CallError::ServiceStopped))
+    );
+}
@@ -0,0 +1,154 @@
+use acteur::testkit::TestSystem;
+use acteur::{Actor, ActorAssistant, ActorConfiguration, Passivation, Receive};
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+static NEVER_IDLE_DEACTIVATIONS: AtomicUsize = AtomicUsize::new(0);
+static SINGLE_USE_DEACTIVATIONS: AtomicUsize = AtomicUsize::new(0);
+static IDLE_DEACTIVATIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug)]
+struct Ping;
+
+// Passivated after every message, as `AfterMessages(0)` behaves as `AfterMessages(1)`.
+#[derive(Debug)]
+struct SingleUse;
+
+#[async_trait]
+impl Actor for SingleUse {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        SingleUse
+    }
+
+    async fn deactivate(&mut self) {
+        SINGLE_USE_DEACTIVATIONS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn configuration() -> ActorConfiguration {
+        ActorConfiguration {
+            passivation: Passivation::AfterMessages(0),
+            ..ActorConfiguration::default()
+        }
+    }
+}
+
+#[async_trait]
+impl Receive<Ping> for SingleUse {
+    async fn handle(&mut self, _: Ping, _: &ActorAssistant<Self>) {}
+}
+
+#[derive(Debug)]
+struct Idle;
+
+#[async_trait]
+impl Actor for Idle {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        Idle
+    }
+
+    async fn deactivate(&mut self) {
+        IDLE_DEACTIVATIONS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn configuration() -> ActorConfiguration {
+        ActorConfiguration {
+            passivation: Passivation::AfterInactivity(Duration::from_secs(60)),
+            ..ActorConfiguration::default()
+        }
+    }
+}
+
+#[async_trait]
+impl Receive<Ping> for Idle {
+    async fn handle(&mut self, _: Ping, _: &ActorAssistant<Self>) {}
+}
+
+#[derive(Debug)]
+struct NeverIdle;
+
+#[async_trait]
+impl Actor for NeverIdle {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        NeverIdle
+    }
+
+    async fn deactivate(&mut self) {
+        NEVER_IDLE_DEACTIVATIONS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn configuration() -> ActorConfiguration {
+        ActorConfiguration {
+            passivation: Passivation::Never,
+            ..ActorConfiguration::default()
+        }
+    }
+}
+
+#[async_trait]
+impl Receive<Ping> for NeverIdle {
+    async fn handle(&mut self, _: Ping, _: &ActorAssistant<Self>) {}
+}
+
+#[test]
+fn after_zero_messages_passivates_after_each_message() {
+    let sys = TestSystem::new().blocking();
+
+    sys.send_to_actor::<SingleUse, Ping>(1, Ping);
+    sys.wait_until_idle();
+
+    assert_eq!(SINGLE_USE_DEACTIVATIONS.load(Ordering::SeqCst), 1);
+
+    sys.send_to_actor::<SingleUse, Ping>(1, Ping);
+    sys.wait_until_idle();
+
+    assert_eq!(SINGLE_USE_DEACTIVATIONS.load(Ordering::SeqCst), 2);
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn after_inactivity_passivates_only_once_the_time_passes() {
+    let sys = TestSystem::new().blocking();
+
+    sys.send_to_actor::<Idle, Ping>(1, Ping);
+    sys.advance(Duration::from_secs(59));
+
+    assert_eq!(IDLE_DEACTIVATIONS.load(Ordering::SeqCst), 0);
+
+    // A new message restarts the inactivity period
+    sys.send_to_actor::<Idle, Ping>(1, Ping);
+    sys.advance(Duration::from_secs(59));
+
+    assert_eq!(IDLE_DEACTIVATIONS.load(Ordering::SeqCst), 0);
+
+    sys.advance(Duration::from_secs(1));
+
+    assert_eq!(IDLE_DEACTIVATIONS.load(Ordering::SeqCst), 1);
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn never_doesnt_passivate() {
+    let sys = TestSystem::from_builder(
+        acteur::Acteur::builder().actors_inactivity_timeout(Duration::from_secs(1)),
+    )
+    .blocking();
+
+    sys.send_to_actor::<NeverIdle, Ping>(1, Ping);
+    sys.advance(Duration::from_secs(24 * 60 * 60));
+
+    assert_eq!(NEVER_IDLE_DEACTIVATIONS.load(Ordering::SeqCst), 0);
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
@@ -0,0 +1,184 @@
+use acteur::testkit::TestSystem;
+use acteur::{
+    Actor, ActorAssistant, Listen, Receive, Respond, Service, ServiceAssistant, ServiceConcurrency,
+    ServiceConfiguration, ServiceReport,
+};
+use async_channel::{unbounded, Receiver, Sender};
+use async_trait::async_trait;
+use std::any::type_name;
+
+#[derive(Debug)]
+struct Counter;
+
+#[async_trait]
+impl Service for Counter {
+    async fn initialize(_: &ServiceAssistant<Self>) -> (Self, ServiceConfiguration) {
+        let configuration = ServiceConfiguration {
+            concurrency: ServiceConcurrency::Fixed(2),
+            ..ServiceConfiguration::default()
+        };
+
+        (Counter, configuration)
+    }
+}
+
+#[derive(Debug)]
+struct Ping;
+
+#[async_trait]
+impl Listen<Ping> for Counter {
+    async fn handle(&self, _: Ping, _: &ServiceAssistant<Self>) {}
+}
+
+// Lets the test know that it started and waits until the test releases it.
+#[derive(Debug, Clone)]
+struct Hold {
+    started: Sender<()>,
+    release: Receiver<()>,
+}
+
+#[async_trait]
+impl Listen<Hold> for Counter {
+    async fn handle(&self, message: Hold, _: &ServiceAssistant<Self>) {
+        let _ = message.started.send(()).await;
+        let _ = message.release.recv().await;
+    }
+}
+
+fn report_of_counter(sys: &TestSystem) -> Option<ServiceReport> {
+    sys.get_statistics()
+        .services
+        .get(type_name::<Counter>())
+        .cloned()
+}
+
+#[async_std::test]
+async fn service_report_counts_the_handled_messages() {
+    let sys = TestSystem::new();
+
+    // Services not initialized yet are not in the report
+    assert!(report_of_counter(&sys).is_none());
+
+    for _ in 0..3 {
+        sys.send_to_service::<Counter, Ping>(Ping).await;
+    }
+
+    sys.wait_until_idle().await;
+
+    let report = report_of_counter(&sys).unwrap();
+    assert_eq!(report.handled_messages, 3);
+    assert_eq!(report.in_flight_messages, 0);
+    assert_eq!(report.enqueued_messages, vec![0, 0]);
+    assert!(report.last_message_on.is_some());
+
+    sys.stop();
+    sys.wait_until_stopped().await;
+}
+
+#[async_std::test]
+async fn service_report_counts_the_messages_in_flight_and_enqueued() {
+    let sys = TestSystem::new();
+    let (started_sender, started) = unbounded();
+    let (release_sender, release) = unbounded();
+
+    let hold = Hold {
+        started: started_sender,
+        release,
+    };
+
+    // Messages go to each lane in turn, so each one handles one and keeps the other enqueued
+    for _ in 0..4 {
+        sys.send_to_service::<Counter, Hold>(hold.clone()).await;
+    }
+
+    for _ in 0..2 {
+        started.recv().await.unwrap();
+    }
+
+    let report = report_of_counter(&sys).unwrap();
+    assert_eq!(report.handled_messages, 0);
+    assert_eq!(report.in_flight_messages, 2);
+    assert_eq!(report.enqueued_messages, vec![1, 1]);
+
+    drop(release_sender);
+    sys.wait_until_idle().await;
+
+    let report = report_of_counter(&sys).unwrap();
+    assert_eq!(report.handled_messages, 4);
+    assert_eq!(report.in_flight_messages, 0);
+    assert_eq!(report.enqueued_messages, vec![0, 0]);
+
+    sys.stop();
+    sys.wait_until_stopped().await;
+}
+
+#[derive(Debug)]
+struct Visitor;
+
+#[async_trait]
+impl Actor for Visitor {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        Visitor
+    }
+}
+
+#[async_trait]
+impl Receive<Hold> for Visitor {
+    async fn handle(&mut self, message: Hold, _: &ActorAssistant<Self>) {
+        let _ = message.started.send(()).await;
+        let _ = message.release.recv().await;
+    }
+}
+
+#[async_trait]
+impl Respond<Ping> for Visitor {
+    type Response = ();
+
+    async fn handle(&mut self, _: Ping, _: &ActorAssistant<Self>) {}
+}
+
+#[async_std::test]
+async fn actor_report_lists_the_loaded_actors_and_their_mailboxes() {
+    let sys = TestSystem::new();
+    let (started_sender, started) = unbounded();
+    let (release_sender, release) = unbounded();
+
+    let hold = Hold {
+        started: started_sender,
+        release,
+    };
+
+    for id in 0..2 {
+        for _ in 0..3 {
+            sys.send_to_actor::<Visitor, Hold>(id, hold.clone()).await;
+        }
+    }
+
+    for _ in 0..2 {
+        started.recv().await.unwrap();
+    }
+
+    // Once another actor responds, the messages sent before are in the mailboxes
+    sys.call_actor::<Visitor, Ping>(2, Ping).await.unwrap();
+
+    let mut enqueued: Vec<_> = sys.get_statistics().actors[type_name::<Visitor>()]
+        .iter()
+        .map(|actor| actor.enqueued_messages)
+        .collect();
+    enqueued.sort_unstable();
+    assert_eq!(enqueued, vec![0, 2, 2]);
+
+    drop(release_sender);
+    sys.wait_until_idle().await;
+
+    let enqueued: Vec<_> = sys.get_statistics().actors[type_name::<Visitor>()]
+        .iter()
+        .map(|actor| actor.enqueued_messages)
+        .collect();
+    assert_eq!(enqueued, vec![0, 0, 0]);
+
+    sys.stop();
+    sys.wait_until_stopped().await;
+}
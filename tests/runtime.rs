@@ -0,0 +1,89 @@
+use acteur::{Acteur, Actor, ActorAssistant, Receive, Respond};
+use async_trait::async_trait;
+use std::time::Duration;
+
+#[derive(Debug)]
+struct Counter(u32);
+
+#[async_trait]
+impl Actor for Counter {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        Counter(0)
+    }
+}
+
+#[derive(Debug)]
+struct Add;
+
+#[async_trait]
+impl Receive<Add> for Counter {
+    async fn handle(&mut self, _: Add, _: &ActorAssistant<Self>) {
+        self.0 += 1;
+    }
+}
+
+#[derive(Debug)]
+struct Get;
+
+#[async_trait]
+impl Respond<Get> for Counter {
+    type Response = u32;
+
+    async fn handle(&mut self, _: Get, _: &ActorAssistant<Self>) -> u32 {
+        self.0
+    }
+}
+
+#[test]
+fn actors_outlive_the_runtime_that_created_them() {
+    // Built outside of any tokio runtime
+    let sys = Acteur::builder().build();
+
+    // The manager and the actor are created while running in this runtime, which is dropped
+    // afterwards.
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(sys.send_to_actor::<Counter, Add>(1, Add));
+    drop(runtime);
+
+    let count =
+        sys.blocking()
+            .call_actor_with_timeout::<Counter, Get>(1, Get, Duration::from_secs(5));
+
+    assert_eq!(count, Ok(1));
+
+    sys.stop();
+    sys.blocking().wait_until_stopped();
+}
+
+#[test]
+fn systems_built_in_a_runtime_keep_running_in_it() {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let sys = runtime.block_on(async { Acteur::builder().build() });
+
+    // Used from another runtime that stops before the system does
+    let other = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    other.block_on(sys.send_to_actor::<Counter, Add>(1, Add));
+    drop(other);
+
+    let count = runtime.block_on(sys.call_actor_with_timeout::<Counter, Get>(
+        1,
+        Get,
+        Duration::from_secs(5),
+    ));
+
+    assert_eq!(count, Ok(1));
+
+    sys.stop();
+    runtime.block_on(sys.wait_until_stopped());
+}
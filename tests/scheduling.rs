@@ -0,0 +1,137 @@
+use acteur::testkit::{BlockingTestSystem, TestSystem};
+use acteur::{Actor, ActorAssistant, Receive};
+use async_trait::async_trait;
+use std::time::Duration;
+
+#[derive(Debug)]
+struct Clock;
+
+#[async_trait]
+impl Actor for Clock {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        Clock
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Load;
+
+#[async_trait]
+impl Receive<Load> for Clock {
+    async fn handle(&mut self, _: Load, _: &ActorAssistant<Self>) {}
+}
+
+#[derive(Debug, Clone)]
+struct Tick;
+
+#[async_trait]
+impl Receive<Tick> for Clock {
+    async fn handle(&mut self, _: Tick, _: &ActorAssistant<Self>) {}
+}
+
+// Doesn't implement Clone, as the messages sent to all actors don't need to.
+#[derive(Debug)]
+struct Alarm;
+
+#[async_trait]
+impl Receive<Alarm> for Clock {
+    async fn handle(&mut self, _: Alarm, _: &ActorAssistant<Self>) {}
+}
+
+fn load_actor(sys: &BlockingTestSystem) {
+    sys.send_to_actor::<Clock, Load>(1, Load);
+    sys.wait_until_idle();
+}
+
+#[test]
+fn send_to_all_actors_reaches_the_loaded_actor() {
+    let sys = TestSystem::new().blocking();
+    load_actor(&sys);
+    let alarms = sys.record::<Clock>();
+
+    sys.send_to_all_actors::<Clock, Alarm>(Alarm);
+    sys.wait_until_idle();
+
+    assert_eq!(alarms.count_of::<Alarm>(), 1);
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn scheduled_send_to_all_actors_reaches_the_loaded_actor() {
+    let sys = TestSystem::new().blocking();
+    load_actor(&sys);
+    let alarms = sys.record::<Clock>();
+
+    sys.schedule_send_to_all_actors::<Clock, Alarm>(Duration::from_secs(1), Alarm);
+    sys.advance(Duration::from_secs(1));
+    sys.wait_until_idle();
+
+    assert_eq!(alarms.count_of::<Alarm>(), 1);
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn every_interval_tick_reaches_the_loaded_actor() {
+    let sys = TestSystem::new().blocking();
+    load_actor(&sys);
+    let ticks = sys.record::<Clock>();
+
+    let handle = sys.schedule_interval_to_all_actors::<Clock, Tick>(Duration::from_secs(1), Tick);
+    sys.advance(Duration::from_secs(2));
+    sys.wait_until_idle();
+
+    assert_eq!(ticks.count_of::<Tick>(), 2);
+
+    // No more ticks once cancelled
+    handle.cancel();
+    sys.advance(Duration::from_secs(2));
+
+    assert_eq!(ticks.count_of::<Tick>(), 2);
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn scheduled_message_arrives_only_when_its_time_comes() {
+    let sys = TestSystem::new().blocking();
+    let ticks = sys.record::<Clock>();
+
+    sys.schedule_send_to_actor::<Clock, Tick>(1, Duration::from_secs(10), Tick);
+    sys.advance(Duration::from_secs(9));
+
+    assert_eq!(ticks.count_of::<Tick>(), 0);
+
+    sys.advance(Duration::from_secs(1));
+
+    assert_eq!(ticks.count_of::<Tick>(), 1);
+    assert_eq!(sys.elapsed(), Duration::from_secs(10));
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn interval_repeats_until_cancelled() {
+    let sys = TestSystem::new().blocking();
+    let ticks = sys.record::<Clock>();
+
+    let handle = sys.schedule_interval_to_actor::<Clock, Tick>(1, Duration::from_secs(1), Tick);
+    sys.advance(Duration::from_secs(3));
+
+    assert_eq!(ticks.count_of::<Tick>(), 3);
+
+    handle.cancel();
+    sys.advance(Duration::from_secs(3));
+
+    assert_eq!(ticks.count_of::<Tick>(), 3);
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
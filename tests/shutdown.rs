@@ -0,0 +1,262 @@
+use acteur::{
+    Acteur, Actor, ActorAssistant, CallError, Listen, Receive, Respond, SendError, Serve, Service,
+    ServiceAssistant, ServiceConcurrency, ServiceConfiguration, ShutdownOptions, TrySendError,
+};
+use async_channel::{unbounded, Receiver};
+use async_trait::async_trait;
+use std::any::type_name;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+// Jobs are handled by a `Worker`, which forwards them to the `Notes` service, which forwards
+// them to an `Archive` actor.
+#[derive(Debug, Clone)]
+struct Job;
+
+#[derive(Debug)]
+struct Worker;
+
+#[async_trait]
+impl Actor for Worker {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        Worker
+    }
+}
+
+#[async_trait]
+impl Receive<Job> for Worker {
+    async fn handle(&mut self, job: Job, assistant: &ActorAssistant<Self>) {
+        async_std::task::sleep(Duration::from_millis(10)).await;
+        assistant.send_to_service::<Notes, Job>(job).await;
+    }
+}
+
+#[derive(Debug)]
+struct Notes;
+
+#[async_trait]
+impl Service for Notes {
+    async fn initialize(_: &ServiceAssistant<Self>) -> (Self, ServiceConfiguration) {
+        // With unlimited concurrency the shutdown wouldn't wait for the handlers
+        let configuration = ServiceConfiguration {
+            concurrency: ServiceConcurrency::Fixed(2),
+            ..ServiceConfiguration::default()
+        };
+
+        (Notes, configuration)
+    }
+}
+
+#[async_trait]
+impl Listen<Job> for Notes {
+    async fn handle(&self, job: Job, assistant: &ServiceAssistant<Self>) {
+        async_std::task::sleep(Duration::from_millis(10)).await;
+        assistant.send_to_actor::<Archive, Job>(1, job).await;
+    }
+}
+
+#[derive(Debug)]
+struct Archive;
+
+#[async_trait]
+impl Actor for Archive {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        Archive
+    }
+}
+
+#[async_trait]
+impl Receive<Job> for Archive {
+    async fn handle(&mut self, _: Job, _: &ActorAssistant<Self>) {}
+}
+
+#[derive(Debug)]
+struct Ping;
+
+#[async_trait]
+impl Respond<Ping> for Archive {
+    type Response = ();
+
+    async fn handle(&mut self, _: Ping, _: &ActorAssistant<Self>) {}
+}
+
+#[async_trait]
+impl Listen<Ping> for Notes {
+    async fn handle(&self, _: Ping, _: &ServiceAssistant<Self>) {}
+}
+
+#[async_trait]
+impl Serve<Ping> for Notes {
+    type Response = ();
+
+    async fn handle(&self, _: Ping, _: &ServiceAssistant<Self>) {}
+}
+
+// The first `Blocked` message waits until the sender of the gate is dropped.
+static GATE: Mutex<Option<Receiver<()>>> = Mutex::new(None);
+static UNBLOCKED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug)]
+struct Blocked;
+
+#[derive(Debug)]
+struct Stuck;
+
+#[async_trait]
+impl Actor for Stuck {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        Stuck
+    }
+}
+
+#[async_trait]
+impl Receive<Blocked> for Stuck {
+    async fn handle(&mut self, _: Blocked, _: &ActorAssistant<Self>) {
+        let gate = GATE.lock().unwrap().take();
+
+        if let Some(gate) = gate {
+            let _ = gate.recv().await;
+            UNBLOCKED.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+#[test]
+fn shutdown_drains_actors_then_services_and_the_actors_they_message() {
+    let sys = Acteur::builder().build();
+
+    let summary = async_std::task::block_on(async {
+        for _ in 0..3 {
+            sys.send_to_actor::<Worker, Job>(1, Job).await;
+        }
+
+        let shutdown = sys.shutdown(ShutdownOptions::default());
+        let late = async {
+            async_std::task::sleep(Duration::from_millis(5)).await;
+            sys.send_to_actor::<Worker, Job>(1, Job).await;
+        };
+
+        futures::join!(shutdown, late).0
+    });
+
+    assert!(!summary.timed_out);
+    assert!(summary.discarded.is_empty());
+    assert_eq!(summary.rejected, 1);
+    assert_eq!(summary.drained.get(type_name::<Notes>()), Some(&3));
+    assert_eq!(summary.drained.get(type_name::<Archive>()), Some(&3));
+    // The first job may have been handled before the shutdown started
+    assert!(summary.drained.get(type_name::<Worker>()) >= Some(&2));
+}
+
+#[test]
+fn messages_are_refused_once_the_system_is_stopped() {
+    let sys = Acteur::builder().build().blocking();
+
+    assert_eq!(sys.send_to_actor_checked::<Archive, Job>(1, Job), Ok(()));
+    assert_eq!(sys.try_send_to_actor::<Archive, Job>(1, Job), Ok(()));
+    assert_eq!(sys.call_actor::<Archive, Ping>(1, Ping), Ok(()));
+    // Jobs would be sent to the `Archive` while it stops
+    assert_eq!(sys.send_to_service_checked::<Notes, Ping>(Ping), Ok(()));
+    assert_eq!(sys.call_service::<Notes, Ping>(Ping), Ok(()));
+
+    sys.stop();
+    sys.wait_until_stopped();
+
+    // Both for the actor that was loaded and for one that wasn't
+    for id in 1..3 {
+        assert_eq!(
+            sys.send_to_actor_checked::<Archive, Job>(id, Job),
+            Err(SendError::SystemStopped)
+        );
+        assert_eq!(
+            sys.try_send_to_actor::<Archive, Job>(id, Job),
+            Err(TrySendError::SystemStopping)
+        );
+        assert_eq!(
+            sys.call_actor::<Archive, Ping>(id, Ping),
+            Err(CallError::SystemStopping)
+        );
+        assert_eq!(
+            sys.call_actor_with_timeout::<Archive, Ping>(id, Ping, Duration::from_secs(5)),
+            Err(CallError::SystemStopping)
+        );
+    }
+
+    // And for an actor type that never ran
+    assert_eq!(
+        sys.send_to_actor_checked::<Worker, Job>(1, Job),
+        Err(SendError::SystemStopped)
+    );
+    assert_eq!(
+        sys.try_send_to_actor::<Worker, Job>(1, Job),
+        Err(TrySendError::SystemStopping)
+    );
+
+    assert_eq!(
+        sys.send_to_service_checked::<Notes, Ping>(Ping),
+        Err(SendError::SystemStopped)
+    );
+    assert_eq!(
+        sys.call_service::<Notes, Ping>(Ping),
+        Err(CallError::SystemStopping)
+    );
+    assert_eq!(
+        sys.call_service_with_timeout::<Notes, Ping>(Ping, Duration::from_secs(5)),
+        Err(CallError::SystemStopping)
+    );
+}
+
+#[test]
+fn shutdown_cancels_the_pending_schedules() {
+    let sys = Acteur::builder().build().blocking();
+
+    let once = sys.schedule_send_to_actor::<Worker, Job>(1, Duration::from_secs(60), Job);
+    let interval = sys.schedule_interval_to_actor::<Worker, Job>(1, Duration::from_secs(60), Job);
+    let cancelled = sys.schedule_send_to_actor::<Worker, Job>(1, Duration::from_secs(60), Job);
+    cancelled.cancel();
+
+    let summary = sys.shutdown(ShutdownOptions::default());
+
+    assert_eq!(summary.cancelled_schedules, 2);
+    assert!(once.is_cancelled());
+    assert!(interval.is_cancelled());
+}
+
+#[test]
+fn shutdown_discards_what_is_enqueued_at_the_deadline() {
+    let (gate, receiver) = unbounded();
+    GATE.lock().unwrap().replace(receiver);
+
+    let sys = Acteur::builder().build().blocking();
+
+    for _ in 0..4 {
+        sys.send_to_actor::<Stuck, Blocked>(1, Blocked);
+    }
+
+    let started = Instant::now();
+    let summary = sys.shutdown(ShutdownOptions {
+        deadline: Duration::from_millis(100),
+    });
+
+    assert!(summary.timed_out);
+    assert!(started.elapsed() >= Duration::from_millis(100));
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(summary.discarded.get(type_name::<Stuck>()), Some(&3));
+
+    // The handler that was running when the deadline expired keeps running
+    assert!(!UNBLOCKED.load(Ordering::SeqCst));
+
+    drop(gate);
+
+    while !UNBLOCKED.load(Ordering::SeqCst) {
+        sleep(Duration::from_millis(1));
+    }
+}
@@ -0,0 +1,244 @@
+use acteur::testkit::{BlockingTestSystem, TestSystem};
+use acteur::{
+    Acteur, Actor, ActorAssistant, ActorConfiguration, InMemorySnapshotStore, JournalError,
+    Passivation, PersistentActor, Receive, Respond, Snapshot, SnapshotActor, SnapshotPolicy,
+    SnapshotStore,
+};
+use async_std::task::block_on;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+const INACTIVITY: Duration = Duration::from_secs(60);
+
+trait Policy: Debug + Send + Sync + 'static {
+    const NAME: &'static str;
+    const POLICY: SnapshotPolicy;
+}
+
+#[derive(Debug)]
+struct EveryTwoMessages;
+
+impl Policy for EveryTwoMessages {
+    const NAME: &'static str = "every-two-messages";
+    const POLICY: SnapshotPolicy = SnapshotPolicy::EveryMessages(2);
+}
+
+#[derive(Debug)]
+struct OnDeactivate;
+
+impl Policy for OnDeactivate {
+    const NAME: &'static str = "on-deactivate";
+    const POLICY: SnapshotPolicy = SnapshotPolicy::OnDeactivate;
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+struct Tally<P> {
+    total: u64,
+    // Events applied since the actor was activated, including the replayed ones
+    #[serde(skip)]
+    applied: u64,
+    #[serde(skip)]
+    policy: PhantomData<P>,
+}
+
+#[async_trait]
+impl<P: Policy> Actor for Tally<P> {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, assistant: &ActorAssistant<Self>) -> Self {
+        let initial_state = Tally {
+            total: 0,
+            applied: 0,
+            policy: PhantomData,
+        };
+
+        assistant
+            .recover_from_snapshot(initial_state)
+            .await
+            .unwrap()
+    }
+
+    fn configuration() -> ActorConfiguration {
+        ActorConfiguration {
+            passivation: Passivation::AfterInactivity(INACTIVITY),
+            ..ActorConfiguration::default()
+        }
+    }
+}
+
+impl<P: Policy> PersistentActor for Tally<P> {
+    type Event = u64;
+
+    fn apply(&mut self, event: &u64) {
+        self.total += event;
+        self.applied += 1;
+    }
+
+    fn persistence_id(id: &u32) -> String {
+        format!("tally-{}-{}", P::NAME, id)
+    }
+}
+
+impl<P: Policy> SnapshotActor for Tally<P> {
+    fn snapshot_policy() -> SnapshotPolicy {
+        P::POLICY
+    }
+}
+
+#[derive(Debug)]
+struct Add(u64);
+
+#[async_trait]
+impl<P: Policy> Receive<Add> for Tally<P> {
+    async fn handle(&mut self, message: Add, assistant: &ActorAssistant<Self>) {
+        assistant.persist(self, message.0).await.unwrap();
+    }
+}
+
+// Returns the total and the events applied since the activation.
+#[derive(Debug)]
+struct Get;
+
+#[async_trait]
+impl<P: Policy> Respond<Get> for Tally<P> {
+    type Response = (u64, u64);
+
+    async fn handle(&mut self, _: Get, _: &ActorAssistant<Self>) -> (u64, u64) {
+        (self.total, self.applied)
+    }
+}
+
+// Counts the snapshots saved.
+#[derive(Debug, Clone, Default)]
+struct CountingStore {
+    store: InMemorySnapshotStore,
+    saves: Arc<AtomicUsize>,
+}
+
+impl CountingStore {
+    fn saves(&self) -> usize {
+        self.saves.load(Ordering::SeqCst)
+    }
+
+    fn sequence_nr_of<P: Policy>(&self, id: u32) -> Option<u64> {
+        let persistence_id = Tally::<P>::persistence_id(&id);
+
+        block_on(self.store.load(&persistence_id))
+            .unwrap()
+            .map(|snapshot| snapshot.sequence_nr)
+    }
+}
+
+#[async_trait]
+impl SnapshotStore for CountingStore {
+    async fn save(&self, persistence_id: &str, snapshot: Snapshot) -> Result<(), JournalError> {
+        self.saves.fetch_add(1, Ordering::SeqCst);
+        self.store.save(persistence_id, snapshot).await
+    }
+
+    async fn load(&self, persistence_id: &str) -> Result<Option<Snapshot>, JournalError> {
+        self.store.load(persistence_id).await
+    }
+}
+
+fn system(store: &CountingStore) -> BlockingTestSystem {
+    TestSystem::from_builder(Acteur::builder().snapshot_store(store.clone())).blocking()
+}
+
+#[test]
+fn every_messages_saves_a_snapshot_after_that_many_messages() {
+    let store = CountingStore::default();
+    let sys = system(&store);
+
+    for amount in 1..=3 {
+        sys.send_to_actor::<Tally<EveryTwoMessages>, Add>(1, Add(amount));
+    }
+    sys.wait_until_idle();
+
+    assert_eq!(store.sequence_nr_of::<EveryTwoMessages>(1), Some(2));
+
+    // This policy doesn't save a snapshot on deactivation
+    sys.advance(INACTIVITY);
+
+    assert_eq!(store.saves(), 1);
+
+    // Only the event after the snapshot is replayed
+    assert_eq!(
+        sys.call_actor::<Tally<EveryTwoMessages>, Get>(1, Get),
+        Ok((6, 1))
+    );
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn on_deactivate_saves_a_snapshot_before_deactivating() {
+    let store = CountingStore::default();
+    let sys = system(&store);
+
+    for amount in 1..=3 {
+        sys.send_to_actor::<Tally<OnDeactivate>, Add>(1, Add(amount));
+    }
+    sys.wait_until_idle();
+
+    assert_eq!(store.sequence_nr_of::<OnDeactivate>(1), None);
+
+    sys.advance(INACTIVITY);
+
+    assert_eq!(store.sequence_nr_of::<OnDeactivate>(1), Some(3));
+
+    // Nothing is replayed, the snapshot has all the events
+    assert_eq!(
+        sys.call_actor::<Tally<OnDeactivate>, Get>(1, Get),
+        Ok((6, 0))
+    );
+
+    sys.send_to_actor::<Tally<OnDeactivate>, Add>(1, Add(4));
+    sys.advance(INACTIVITY);
+
+    assert_eq!(store.sequence_nr_of::<OnDeactivate>(1), Some(4));
+    assert_eq!(
+        sys.call_actor::<Tally<OnDeactivate>, Get>(1, Get),
+        Ok((10, 0))
+    );
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn no_snapshot_is_saved_when_nothing_changed() {
+    let store = CountingStore::default();
+    let sys = system(&store);
+
+    // Without events there is nothing to save
+    for _ in 0..2 {
+        sys.call_actor::<Tally<EveryTwoMessages>, Get>(1, Get)
+            .unwrap();
+    }
+    sys.call_actor::<Tally<OnDeactivate>, Get>(1, Get).unwrap();
+    sys.advance(INACTIVITY);
+
+    assert_eq!(store.saves(), 0);
+
+    // Neither after recovering from a snapshot, until a new event is persisted
+    sys.send_to_actor::<Tally<OnDeactivate>, Add>(1, Add(1));
+    sys.advance(INACTIVITY);
+
+    assert_eq!(store.saves(), 1);
+
+    sys.call_actor::<Tally<OnDeactivate>, Get>(1, Get).unwrap();
+    sys.advance(INACTIVITY);
+
+    assert_eq!(store.saves(), 1);
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
@@ -0,0 +1,512 @@
+use acteur::{
+    Acteur, Actor, ActorAssistant, ActorConfiguration, BlockingActeur, CallError, Failure,
+    Passivation, Receive, Respond, Serve, Service, ServiceAssistant, ServiceConcurrency,
+    ServiceConfiguration, SupervisionStrategy, Supervisor, SupervisorDecision,
+};
+use async_channel::{unbounded, Receiver, Sender};
+use async_trait::async_trait;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+static ACTIVATIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug)]
+struct FailsOnFirstActivation;
+
+#[async_trait]
+impl Actor for FailsOnFirstActivation {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        if ACTIVATIONS.fetch_add(1, Ordering::SeqCst) == 0 {
+            panic!("The first activation fails");
+        }
+
+        FailsOnFirstActivation
+    }
+}
+
+#[derive(Debug)]
+struct Ping;
+
+#[async_trait]
+impl Respond<Ping> for FailsOnFirstActivation {
+    type Response = ();
+
+    async fn handle(&mut self, _: Ping, _: &ActorAssistant<Self>) {}
+}
+
+#[test]
+fn actor_failing_on_activation_is_removed() {
+    let sys = Acteur::builder().build().blocking();
+
+    assert_eq!(
+        sys.call_actor::<FailsOnFirstActivation, Ping>(1, Ping),
+        Err(CallError::ActorStopped)
+    );
+
+    // The failed proxy is not kept, so the next message activates the actor again.
+    assert_eq!(
+        sys.call_actor::<FailsOnFirstActivation, Ping>(1, Ping),
+        Ok(())
+    );
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+// The configuration of each actor or service type used by the tests, which run in parallel, and
+// how many instances of it were created.
+trait Setup: Debug + Send + Sync + 'static {
+    fn supervision() -> SupervisionStrategy;
+
+    fn max_restarts() -> Option<usize> {
+        None
+    }
+
+    fn instances() -> &'static AtomicUsize;
+}
+
+macro_rules! setup {
+    ($name:ident, $supervision:expr, $max_restarts:expr) => {
+        #[derive(Debug)]
+        struct $name;
+
+        impl Setup for $name {
+            fn supervision() -> SupervisionStrategy {
+                $supervision
+            }
+
+            fn max_restarts() -> Option<usize> {
+                $max_restarts
+            }
+
+            fn instances() -> &'static AtomicUsize {
+                static INSTANCES: AtomicUsize = AtomicUsize::new(0);
+                &INSTANCES
+            }
+        }
+    };
+}
+
+// Adds to its value. Exploding enqueues an `Add(5)` behind the failing message.
+#[derive(Debug)]
+struct Counter<T: Setup> {
+    id: u32,
+    value: u32,
+    instance: usize,
+    phantom: PhantomData<T>,
+}
+
+#[async_trait]
+impl<T: Setup> Actor for Counter<T> {
+    type Id = u32;
+
+    async fn activate(id: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        Counter {
+            id,
+            value: 0,
+            instance: T::instances().fetch_add(1, Ordering::SeqCst) + 1,
+            phantom: PhantomData,
+        }
+    }
+
+    fn configuration() -> ActorConfiguration {
+        ActorConfiguration {
+            supervision: T::supervision(),
+            max_restarts: T::max_restarts(),
+            ..ActorConfiguration::default()
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Add(u32);
+
+#[async_trait]
+impl<T: Setup> Receive<Add> for Counter<T> {
+    async fn handle(&mut self, message: Add, _: &ActorAssistant<Self>) {
+        self.value += message.0;
+    }
+}
+
+#[derive(Debug)]
+struct Explode;
+
+#[async_trait]
+impl<T: Setup> Respond<Explode> for Counter<T> {
+    type Response = ();
+
+    async fn handle(&mut self, _: Explode, assistant: &ActorAssistant<Self>) {
+        assistant
+            .send_to_actor_checked::<Self, Add>(self.id, Add(5))
+            .await
+            .unwrap();
+
+        panic!("Counter {} exploded", self.id);
+    }
+}
+
+#[derive(Debug)]
+struct Get;
+
+#[async_trait]
+impl<T: Setup> Respond<Get> for Counter<T> {
+    // The value and which instance has it
+    type Response = (u32, usize);
+
+    async fn handle(&mut self, _: Get, _: &ActorAssistant<Self>) -> (u32, usize) {
+        (self.value, self.instance)
+    }
+}
+
+fn explode<T: Setup>(sys: &BlockingActeur) {
+    assert_eq!(
+        sys.call_actor_with_timeout::<Counter<T>, Explode>(1, Explode, TIMEOUT),
+        Err(CallError::HandlerPanicked)
+    );
+}
+
+// Right after a stop, a message can still reach the mailbox of the stopped instance, which
+// discards it. Then the message is sent again.
+fn get<T: Setup>(sys: &BlockingActeur) -> (u32, usize) {
+    for _ in 0..100 {
+        match sys.call_actor_with_timeout::<Counter<T>, Get>(1, Get, TIMEOUT) {
+            Err(CallError::ActorStopped) => continue,
+            result => return result.unwrap(),
+        }
+    }
+
+    panic!("The actor didn't respond");
+}
+
+#[test]
+fn restart_activates_the_actor_again_and_keeps_its_mailbox() {
+    setup!(Restarted, SupervisionStrategy::Restart, None);
+    let sys = Acteur::builder().build().blocking();
+
+    sys.send_to_actor::<Counter<Restarted>, Add>(1, Add(1));
+    explode::<Restarted>(&sys);
+
+    // The state was discarded, but not the message enqueued before the panic
+    assert_eq!(get::<Restarted>(&sys), (5, 2));
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn stop_removes_the_actor_and_discards_its_mailbox() {
+    setup!(Stopped, SupervisionStrategy::Stop, None);
+    let sys = Acteur::builder().build().blocking();
+
+    sys.send_to_actor::<Counter<Stopped>, Add>(1, Add(1));
+    explode::<Stopped>(&sys);
+
+    // The next message activates a new instance
+    assert_eq!(get::<Stopped>(&sys), (0, 2));
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+// Records the failures and answers with the decision.
+#[derive(Clone)]
+struct Recorder {
+    decision: SupervisorDecision,
+    failures: Arc<Mutex<Vec<Failure>>>,
+}
+
+impl Recorder {
+    fn new(decision: SupervisorDecision) -> Recorder {
+        Recorder {
+            decision,
+            failures: Arc::default(),
+        }
+    }
+
+    fn failures(&self) -> Vec<(&'static str, Option<String>, String)> {
+        self.failures
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|failure| {
+                (
+                    failure.type_name,
+                    failure.actor_id.clone(),
+                    failure.panic_message.clone(),
+                )
+            })
+            .collect()
+    }
+}
+
+impl Supervisor for Recorder {
+    fn on_failure(&self, failure: &Failure) -> SupervisorDecision {
+        self.failures.lock().unwrap().push(failure.clone());
+        self.decision
+    }
+}
+
+#[test]
+fn escalate_asks_the_supervisor() {
+    setup!(Escalated, SupervisionStrategy::Escalate, None);
+    let supervisor = Recorder::new(SupervisorDecision::Restart);
+    let sys = Acteur::builder()
+        .supervisor(supervisor.clone())
+        .build()
+        .blocking();
+
+    explode::<Escalated>(&sys);
+
+    assert_eq!(get::<Escalated>(&sys), (5, 2));
+    assert_eq!(
+        supervisor.failures(),
+        vec![(
+            std::any::type_name::<Counter<Escalated>>(),
+            Some("1".to_string()),
+            "Counter 1 exploded".to_string()
+        )]
+    );
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn escalate_without_supervisor_stops_the_actor() {
+    setup!(Unsupervised, SupervisionStrategy::Escalate, None);
+    let sys = Acteur::builder().build().blocking();
+
+    explode::<Unsupervised>(&sys);
+
+    assert_eq!(get::<Unsupervised>(&sys), (0, 2));
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn actor_is_stopped_once_it_reaches_the_restart_limit() {
+    setup!(Limited, SupervisionStrategy::Restart, Some(1));
+    let sys = Acteur::builder().build().blocking();
+
+    explode::<Limited>(&sys);
+    assert_eq!(get::<Limited>(&sys), (5, 2));
+
+    // The second failure stops it, discarding the enqueued `Add(5)`
+    explode::<Limited>(&sys);
+    assert_eq!(get::<Limited>(&sys), (0, 3));
+
+    // The limit is counted again for the new instance
+    explode::<Limited>(&sys);
+    assert_eq!(get::<Limited>(&sys), (5, 4));
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+// Lets the test know when `deactivate` starts, and then panics.
+static DEACTIVATIONS: Mutex<Option<Sender<()>>> = Mutex::new(None);
+
+#[derive(Debug)]
+struct FailsOnDeactivation(usize);
+
+static FAILING_DEACTIVATION_INSTANCES: AtomicUsize = AtomicUsize::new(0);
+
+#[async_trait]
+impl Actor for FailsOnDeactivation {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        FailsOnDeactivation(FAILING_DEACTIVATION_INSTANCES.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+
+    async fn deactivate(&mut self) {
+        let deactivations = DEACTIVATIONS.lock().unwrap().clone();
+
+        if let Some(deactivations) = deactivations {
+            let _ = deactivations.send(()).await;
+        }
+
+        panic!("Deactivation fails");
+    }
+
+    fn configuration() -> ActorConfiguration {
+        ActorConfiguration {
+            passivation: Passivation::AfterMessages(1),
+            ..ActorConfiguration::default()
+        }
+    }
+}
+
+#[async_trait]
+impl Respond<Get> for FailsOnDeactivation {
+    type Response = usize;
+
+    async fn handle(&mut self, _: Get, _: &ActorAssistant<Self>) -> usize {
+        self.0
+    }
+}
+
+#[test]
+fn actor_failing_on_deactivation_is_removed() {
+    let (sender, deactivations): (Sender<()>, Receiver<()>) = unbounded();
+    DEACTIVATIONS.lock().unwrap().replace(sender);
+    let sys = Acteur::builder().build().blocking();
+
+    assert_eq!(
+        sys.call_actor_with_timeout::<FailsOnDeactivation, Get>(1, Get, TIMEOUT),
+        Ok(1)
+    );
+
+    futures::executor::block_on(deactivations.recv()).unwrap();
+
+    // The failed instance is not kept, so the message goes to a new one
+    assert_eq!(
+        sys.call_actor_with_timeout::<FailsOnDeactivation, Get>(1, Get, TIMEOUT),
+        Ok(2)
+    );
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+// A service with one loop that knows which instance it is.
+#[derive(Debug)]
+struct Fragile<T: Setup> {
+    instance: usize,
+    phantom: PhantomData<T>,
+}
+
+#[async_trait]
+impl<T: Setup> Service for Fragile<T> {
+    async fn initialize(_: &ServiceAssistant<Self>) -> (Self, ServiceConfiguration) {
+        let service = Fragile {
+            instance: T::instances().fetch_add(1, Ordering::SeqCst) + 1,
+            phantom: PhantomData,
+        };
+
+        let configuration = ServiceConfiguration {
+            concurrency: ServiceConcurrency::None,
+            supervision: T::supervision(),
+            max_restarts: T::max_restarts(),
+        };
+
+        (service, configuration)
+    }
+}
+
+#[async_trait]
+impl<T: Setup> Serve<Explode> for Fragile<T> {
+    type Response = ();
+
+    async fn handle(&self, _: Explode, _: &ServiceAssistant<Self>) {
+        panic!("Service {} exploded", self.instance);
+    }
+}
+
+#[async_trait]
+impl<T: Setup> Serve<Get> for Fragile<T> {
+    type Response = usize;
+
+    async fn handle(&self, _: Get, _: &ServiceAssistant<Self>) -> usize {
+        self.instance
+    }
+}
+
+fn explode_service<T: Setup>(sys: &BlockingActeur) {
+    assert_eq!(
+        sys.call_service_with_timeout::<Fragile<T>, Explode>(Explode, TIMEOUT),
+        Err(CallError::HandlerPanicked)
+    );
+}
+
+fn get_service<T: Setup>(sys: &BlockingActeur) -> usize {
+    sys.call_service_with_timeout::<Fragile<T>, Get>(Get, TIMEOUT)
+        .unwrap()
+}
+
+// A stopped service handles the messages enqueued before it ends, so it is asked until the
+// instance changes.
+fn wait_for_service_instance<T: Setup>(sys: &BlockingActeur, instance: usize) {
+    for _ in 0..100 {
+        if get_service::<T>(sys) == instance {
+            return;
+        }
+    }
+
+    panic!("The service was not initialized again");
+}
+
+#[test]
+fn restart_keeps_the_service_running() {
+    setup!(RestartedService, SupervisionStrategy::Restart, None);
+    let sys = Acteur::builder().build().blocking();
+
+    explode_service::<RestartedService>(&sys);
+    explode_service::<RestartedService>(&sys);
+
+    assert_eq!(get_service::<RestartedService>(&sys), 1);
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn stop_initializes_the_service_again() {
+    setup!(StoppedService, SupervisionStrategy::Stop, None);
+    let sys = Acteur::builder().build().blocking();
+
+    explode_service::<StoppedService>(&sys);
+
+    wait_for_service_instance::<StoppedService>(&sys, 2);
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn escalated_service_failures_are_decided_by_the_supervisor() {
+    setup!(EscalatedService, SupervisionStrategy::Escalate, None);
+    let supervisor = Recorder::new(SupervisorDecision::Stop);
+    let sys = Acteur::builder()
+        .supervisor(supervisor.clone())
+        .build()
+        .blocking();
+
+    explode_service::<EscalatedService>(&sys);
+
+    wait_for_service_instance::<EscalatedService>(&sys, 2);
+    assert_eq!(
+        supervisor.failures(),
+        vec![(
+            std::any::type_name::<Fragile<EscalatedService>>(),
+            None,
+            "Service 1 exploded".to_string()
+        )]
+    );
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn service_is_stopped_once_it_reaches_the_restart_limit() {
+    setup!(LimitedService, SupervisionStrategy::Restart, Some(1));
+    let sys = Acteur::builder().build().blocking();
+
+    explode_service::<LimitedService>(&sys);
+    assert_eq!(get_service::<LimitedService>(&sys), 1);
+
+    explode_service::<LimitedService>(&sys);
+    wait_for_service_instance::<LimitedService>(&sys, 2);
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
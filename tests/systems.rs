@@ -0,0 +1,189 @@
+use acteur::{
+    Acteur, Actor, ActorAssistant, BlockingActeur, CallError, Middleware, Receive, Respond, Serve,
+    Service, ServiceAssistant, ServiceConfiguration,
+};
+use async_trait::async_trait;
+use std::any::type_name;
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug)]
+struct Counter {
+    value: u32,
+}
+
+#[async_trait]
+impl Actor for Counter {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        Counter { value: 0 }
+    }
+}
+
+#[derive(Debug)]
+struct Increment;
+
+#[async_trait]
+impl Receive<Increment> for Counter {
+    async fn handle(&mut self, _: Increment, _: &ActorAssistant<Self>) {
+        self.value += 1;
+    }
+}
+
+#[derive(Debug)]
+struct Get;
+
+#[async_trait]
+impl Respond<Get> for Counter {
+    type Response = u32;
+
+    async fn handle(&mut self, _: Get, _: &ActorAssistant<Self>) -> u32 {
+        self.value
+    }
+}
+
+#[derive(Debug)]
+struct Total {
+    value: Mutex<u32>,
+}
+
+#[async_trait]
+impl Service for Total {
+    async fn initialize(_: &ServiceAssistant<Self>) -> (Self, ServiceConfiguration) {
+        let service = Total {
+            value: Mutex::new(0),
+        };
+
+        (service, ServiceConfiguration::default())
+    }
+}
+
+#[derive(Debug)]
+struct Add(u32);
+
+#[async_trait]
+impl Serve<Add> for Total {
+    type Response = u32;
+
+    async fn handle(&self, message: Add, _: &ServiceAssistant<Self>) -> u32 {
+        let mut value = self.value.lock().unwrap();
+        *value += message.0;
+        *value
+    }
+}
+
+fn loaded_counters(sys: &BlockingActeur) -> usize {
+    sys.get_statistics()
+        .actors
+        .get(type_name::<Counter>())
+        .map_or(0, Vec::len)
+}
+
+#[test]
+fn systems_created_with_the_builder_have_their_own_actors_and_services() {
+    let first = Acteur::builder().build().blocking();
+    let second = Acteur::builder().build().blocking();
+
+    first.send_to_actor::<Counter, Increment>(1, Increment);
+    first.send_to_actor::<Counter, Increment>(1, Increment);
+    first.send_to_actor::<Counter, Increment>(2, Increment);
+    second.send_to_actor::<Counter, Increment>(1, Increment);
+
+    assert_eq!(first.call_actor::<Counter, Get>(1, Get), Ok(2));
+    assert_eq!(second.call_actor::<Counter, Get>(1, Get), Ok(1));
+
+    assert_eq!(first.call_service::<Total, Add>(Add(5)), Ok(5));
+    assert_eq!(second.call_service::<Total, Add>(Add(1)), Ok(1));
+    assert_eq!(first.call_service::<Total, Add>(Add(5)), Ok(10));
+
+    assert_eq!(loaded_counters(&first), 2);
+    assert_eq!(loaded_counters(&second), 1);
+
+    first.stop();
+    first.wait_until_stopped();
+    second.stop();
+    second.wait_until_stopped();
+}
+
+#[test]
+fn stopping_a_system_leaves_the_others_running() {
+    let first = Acteur::builder().build().blocking();
+    let second = Acteur::builder().build().blocking();
+    let global = Acteur::new().blocking();
+
+    for sys in [&first, &second, &global] {
+        sys.send_to_actor::<Counter, Increment>(1, Increment);
+    }
+
+    first.stop();
+    first.wait_until_stopped();
+
+    assert_eq!(
+        first.call_actor::<Counter, Get>(1, Get),
+        Err(CallError::SystemStopping)
+    );
+    assert_eq!(
+        first.call_service::<Total, Add>(Add(1)),
+        Err(CallError::SystemStopping)
+    );
+
+    assert_eq!(second.call_actor::<Counter, Get>(1, Get), Ok(1));
+    assert_eq!(second.call_service::<Total, Add>(Add(1)), Ok(1));
+    assert_eq!(global.call_actor::<Counter, Get>(1, Get), Ok(1));
+    assert_eq!(global.call_service::<Total, Add>(Add(1)), Ok(1));
+
+    // A new system can be created after stopping one
+    let third = Acteur::builder().build().blocking();
+    assert_eq!(third.call_actor::<Counter, Get>(1, Get), Ok(0));
+
+    second.stop();
+    second.wait_until_stopped();
+    third.stop();
+    third.wait_until_stopped();
+}
+
+// The system keeps its middlewares until it is freed.
+#[derive(Debug)]
+struct Probe {
+    _alive: Arc<()>,
+}
+
+impl Middleware for Probe {}
+
+fn is_freed(probe: &Weak<()>) -> bool {
+    // The tasks of the runtime may still be dropping their handles to the system
+    for _ in 0..100 {
+        if probe.upgrade().is_none() {
+            return true;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    false
+}
+
+#[test]
+fn stopped_systems_are_freed_once_they_are_dropped() {
+    let probe = Arc::new(());
+    let weak_probe = Arc::downgrade(&probe);
+    let sys = Acteur::builder()
+        .middleware(Probe { _alive: probe })
+        .build()
+        .blocking();
+
+    sys.send_to_actor::<Counter, Increment>(1, Increment);
+    assert_eq!(sys.call_actor::<Counter, Get>(1, Get), Ok(1));
+    assert_eq!(sys.call_service::<Total, Add>(Add(1)), Ok(1));
+
+    sys.stop();
+    sys.wait_until_stopped();
+
+    assert!(weak_probe.upgrade().is_some());
+
+    drop(sys);
+
+    assert!(is_freed(&weak_probe));
+}
@@ -0,0 +1,253 @@
+use acteur::{
+    Acteur, Actor, ActorAssistant, Respond, Serve, Service, ServiceAssistant, ServiceConfiguration,
+};
+use async_trait::async_trait;
+use std::any::type_name;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, Once};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+use tracing_core::span::Current;
+
+// A request goes from a `Front` actor to the `Audit` service and from it to a `Store` actor.
+
+#[derive(Debug)]
+struct Front;
+
+#[async_trait]
+impl Actor for Front {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        Front
+    }
+}
+
+#[derive(Debug)]
+struct Request;
+
+#[async_trait]
+impl Respond<Request> for Front {
+    type Response = ();
+
+    async fn handle(&mut self, _: Request, assistant: &ActorAssistant<Self>) {
+        assistant
+            .call_service::<Audit, Request>(Request)
+            .await
+            .unwrap();
+    }
+}
+
+#[derive(Debug)]
+struct Audit;
+
+#[async_trait]
+impl Service for Audit {
+    async fn initialize(_: &ServiceAssistant<Self>) -> (Self, ServiceConfiguration) {
+        (Audit, Default::default())
+    }
+}
+
+#[async_trait]
+impl Serve<Request> for Audit {
+    type Response = ();
+
+    async fn handle(&self, _: Request, assistant: &ServiceAssistant<Self>) {
+        assistant
+            .call_actor::<Store, Request>(7, Request)
+            .await
+            .unwrap();
+    }
+}
+
+#[derive(Debug)]
+struct Store;
+
+#[async_trait]
+impl Actor for Store {
+    type Id = u32;
+
+    async fn activate(_: Self::Id, _: &ActorAssistant<Self>) -> Self {
+        Store
+    }
+}
+
+#[async_trait]
+impl Respond<Request> for Store {
+    type Response = ();
+
+    async fn handle(&mut self, _: Request, _: &ActorAssistant<Self>) {}
+}
+
+#[derive(Debug, Clone)]
+struct RecordedSpan {
+    id: u64,
+    name: &'static str,
+    parent: Option<u64>,
+    fields: HashMap<&'static str, String>,
+    metadata: &'static Metadata<'static>,
+}
+
+impl Visit for RecordedSpan {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.fields.insert(field.name(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.fields.insert(field.name(), format!("{:?}", value));
+    }
+}
+
+thread_local! {
+    // The spans entered in this thread, the current one last
+    static ENTERED: RefCell<Vec<u64>> = const { RefCell::new(vec![]) };
+}
+
+// Keeps every span created, with its parent. The handlers run in the threads of the runtime, so
+// it is the global subscriber of the tests.
+struct Recorder {
+    next_id: AtomicU64,
+    spans: Mutex<Vec<RecordedSpan>>,
+}
+
+static RECORDER: Recorder = Recorder {
+    next_id: AtomicU64::new(1),
+    spans: Mutex::new(vec![]),
+};
+
+impl Recorder {
+    fn install() -> &'static Recorder {
+        static INSTALL: Once = Once::new();
+
+        INSTALL.call_once(|| tracing::subscriber::set_global_default(&RECORDER).unwrap());
+
+        &RECORDER
+    }
+
+    fn spans(&self) -> Vec<RecordedSpan> {
+        self.spans.lock().unwrap().clone()
+    }
+
+    fn span(&self, id: u64) -> Option<RecordedSpan> {
+        self.spans().into_iter().find(|span| span.id == id)
+    }
+
+    /// The only span with the name whose parent is the given one.
+    fn child(&self, parent: u64, name: &str) -> RecordedSpan {
+        let children: Vec<_> = self
+            .spans()
+            .into_iter()
+            .filter(|span| span.parent == Some(parent) && span.name == name)
+            .collect();
+
+        assert_eq!(children.len(), 1, "{:?}", children);
+        children[0].clone()
+    }
+}
+
+impl Subscriber for &'static Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+
+        let parent = if attributes.is_contextual() {
+            ENTERED.with(|entered| entered.borrow().last().copied())
+        } else {
+            attributes.parent().map(Id::into_u64)
+        };
+
+        let mut span = RecordedSpan {
+            id,
+            name: attributes.metadata().name(),
+            parent,
+            fields: HashMap::new(),
+            metadata: attributes.metadata(),
+        };
+        attributes.record(&mut span);
+
+        self.spans.lock().unwrap().push(span);
+
+        Id::from_u64(id)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, _: &Event<'_>) {}
+
+    fn enter(&self, span: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, _: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().pop());
+    }
+
+    fn current_span(&self) -> Current {
+        let current = ENTERED.with(|entered| entered.borrow().last().copied());
+
+        match current.and_then(|id| self.span(id)) {
+            Some(span) => Current::new(Id::from_u64(span.id), span.metadata),
+            None => Current::none(),
+        }
+    }
+}
+
+#[test]
+fn handlers_run_in_children_of_the_span_that_sent_the_message() {
+    let recorder = Recorder::install();
+    let sys = Acteur::builder().build().blocking();
+
+    let request = tracing::info_span!("request");
+    let request_id = request.id().unwrap().into_u64();
+
+    request.in_scope(|| sys.call_actor::<Front, Request>(1, Request).unwrap());
+
+    let front = recorder.child(request_id, "actor_handler");
+    assert_eq!(front.fields["actor_type"], type_name::<Front>());
+    assert_eq!(front.fields["actor_id"], "1");
+    assert_eq!(front.fields["message_type"], type_name::<Request>());
+
+    let audit = recorder.child(front.id, "service_handler");
+    assert_eq!(audit.fields["service_type"], type_name::<Audit>());
+    assert_eq!(audit.fields["message_type"], type_name::<Request>());
+
+    let store = recorder.child(audit.id, "actor_handler");
+    assert_eq!(store.fields["actor_type"], type_name::<Store>());
+    assert_eq!(store.fields["actor_id"], "7");
+
+    sys.stop();
+    sys.wait_until_stopped();
+}
+
+#[test]
+fn handlers_of_messages_sent_outside_of_any_span_have_no_parent() {
+    let recorder = Recorder::install();
+    let sys = Acteur::builder().build().blocking();
+
+    sys.call_actor::<Front, Request>(2, Request).unwrap();
+
+    let front: Vec<_> = recorder
+        .spans()
+        .into_iter()
+        .filter(|span| span.fields.get("actor_id").map(String::as_str) == Some("2"))
+        .collect();
+
+    assert_eq!(front.len(), 1);
+    assert_eq!(front[0].parent, None);
+    assert_eq!(
+        recorder.child(front[0].id, "service_handler").name,
+        "service_handler"
+    );
+
+    sys.stop();
+    sys.wait_until_stopped();
+}